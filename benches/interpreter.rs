@@ -0,0 +1,80 @@
+//! Wall-clock benchmarks for the tree-walking interpreter.
+//!
+//! Run with `cargo bench`. Each workload is run a few times and the fastest
+//! run is reported, which is stable enough to compare two builds.
+
+use std::io::sink;
+use std::time::{Duration, Instant};
+
+use rox_rust::Rox;
+
+const RUNS: usize = 5;
+
+const LOOP: &str = "
+var i = 0;
+var total = 0;
+while (i < 200000) {
+    total = total + i * 2 - 1;
+    i = i + 1;
+}
+";
+
+const NESTED_BLOCKS: &str = "
+var x = 0;
+for (var i = 0; i < 50000; i = i + 1) {
+    {
+        {
+            if (x > 10) { x = 0; } else { x = x + 1; }
+        }
+    }
+}
+";
+
+const STRING_VALUES: &str = "
+var s = \"a string long enough to be worth copying around\";
+var t = nil;
+for (var i = 0; i < 100000; i = i + 1) {
+    t = s;
+    s = t;
+}
+";
+
+const STRINGS: &str = "
+var s = \"";
+
+const STRINGS_TAIL: &str = "\";
+var n = 0;
+for (var i = 0; i < 20000; i = i + 1) {
+    n = n + len(upper(s));
+}
+";
+
+fn run(source: &str) -> Duration {
+    let mut best = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let mut rox = Rox::new();
+        rox.set_output(Box::new(sink()), Box::new(sink()));
+
+        let start = Instant::now();
+        rox.eval(source).expect("benchmark script failed");
+        best = best.min(start.elapsed());
+    }
+
+    best
+}
+
+fn main() {
+    let strings = format!("{}{}{}", STRINGS, "lorem ipsum ".repeat(8), STRINGS_TAIL);
+
+    let workloads = [
+        ("while loop", LOOP.to_string()),
+        ("nested blocks", NESTED_BLOCKS.to_string()),
+        ("string values", STRING_VALUES.to_string()),
+        ("string natives", strings),
+    ];
+
+    for (name, source) in workloads.iter() {
+        println!("{:<16} {:>10.2?}", name, run(source));
+    }
+}
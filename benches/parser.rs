@@ -0,0 +1,80 @@
+//! Wall-clock benchmark for scanning, parsing and resolving a large file.
+//!
+//! Run with `cargo bench --bench parser`. The script is generated so the
+//! benchmark needs no fixtures; it mixes the statements and expressions a
+//! typical program is made of, a hundred thousand lines in all.
+
+use std::time::{Duration, Instant};
+
+use rox_rust::{Log, LogLevel, Rox};
+
+const RUNS: usize = 5;
+const LINES: usize = 100_000;
+
+/// A block of code that declares and uses its own names, so blocks can be
+/// repeated without redeclaring anything. Each is `BLOCK_LINES` long.
+fn block(n: usize) -> String {
+    format!(
+        "{{
+    var a{n} = {n} * 2 + (3 - 1) / 4;
+    var (b{n}, c{n}) = (\"text\", 1.5);
+    const d{n}: int = a{n} & 255;
+    if (a{n} > d{n} and b{n} != nil) {{
+        a{n} = a{n} - 1;
+    }} else {{
+        print b{n}, c{n};
+    }}
+    for (var i = 0; i < 3; i = i + 1) {{
+        a{n} = a{n} + len(list((i, a{n}, c{n})));
+    }}
+    for (x in 0..2) {{
+        if (x == 1) continue;
+        print upper(b{n}), x;
+    }}
+    match (a{n}) {{
+        0 -> print \"zero\";
+        1, 2 -> print \"small\";
+        else -> print d{n};
+    }}
+    try {{
+        throw a{n};
+    }} catch (e) {{
+        print e;
+    }}
+}}
+"
+    )
+}
+
+const BLOCK_LINES: usize = 27;
+
+/// The fastest times to parse `source` and to drop the tree it parses to.
+fn run(source: &str) -> (Duration, Duration) {
+    let mut parse = Duration::MAX;
+    let mut drop_tree = Duration::MAX;
+
+    for _ in 0..RUNS {
+        // Warnings are not what is being measured.
+        let rox = Rox::with_logger(Log::new(LogLevel::Off));
+
+        let start = Instant::now();
+        let stmts = rox.parse(source).expect("benchmark script failed to parse");
+        parse = parse.min(start.elapsed());
+
+        let start = Instant::now();
+        drop(stmts);
+        drop_tree = drop_tree.min(start.elapsed());
+    }
+
+    (parse, drop_tree)
+}
+
+fn main() {
+    let source: String = (0..LINES / BLOCK_LINES).map(block).collect();
+
+    let (parse, drop_tree) = run(&source);
+
+    println!("{}k lines", source.lines().count() / 1000);
+    println!("{:<16} {:>10.2?}", "parse", parse);
+    println!("{:<16} {:>10.2?}", "drop", drop_tree);
+}
@@ -0,0 +1,39 @@
+fn main() {
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Writes `rox.h` from the `extern "C"` functions in `src/ffi.rs` into
+/// `OUT_DIR`. Set `ROX_HEADER_DIR` to also write it somewhere else, as in
+/// `ROX_HEADER_DIR=include cargo build --features ffi` to update the
+/// checked-in copy; a plain build never touches the source tree.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    use std::{env, path::PathBuf};
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-env-changed=ROX_HEADER_DIR");
+
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let mut config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("ROX_H".to_string()),
+        header: Some("/* Generated from src/ffi.rs by cbindgen. Do not edit. */".to_string()),
+        cpp_compat: true,
+        ..Default::default()
+    };
+    // `RoxStatus::SyntaxError` becomes `ROX_STATUS_SYNTAX_ERROR`.
+    config.enumeration.prefix_with_name = true;
+    config.enumeration.rename_variants = cbindgen::RenameRule::ScreamingSnakeCase;
+
+    let header = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/ffi.rs", crate_dir))
+        .generate()
+        .expect("Unable to generate rox.h");
+
+    header.write_to_file(PathBuf::from(env::var("OUT_DIR").unwrap()).join("rox.h"));
+    if let Some(dir) = env::var_os("ROX_HEADER_DIR") {
+        header.write_to_file(PathBuf::from(crate_dir).join(dir).join("rox.h"));
+    }
+}
@@ -0,0 +1,108 @@
+/* Generated from src/ffi.rs by cbindgen. Do not edit. */
+
+#ifndef ROX_H
+#define ROX_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The outcome of `rox_eval` and `rox_load_prelude`.
+ */
+typedef enum RoxStatus {
+  /**
+   * The result string holds the value of the final expression statement.
+   */
+  ROX_STATUS_OK = 0,
+  /**
+   * The source could not be read, scanned or parsed. The result string
+   * holds the errors.
+   */
+  ROX_STATUS_SYNTAX_ERROR = 1,
+  /**
+   * The script raised an error or exceeded a limit, or its result
+   * contains a NUL character and cannot be passed back. The result
+   * string holds the message.
+   */
+  ROX_STATUS_RUNTIME_ERROR = 2,
+  /**
+   * The script called `exit`. The result string holds the exit code.
+   */
+  ROX_STATUS_EXIT = 3,
+  /**
+   * A null pointer or a source that is not valid UTF-8 was passed in.
+   */
+  ROX_STATUS_INVALID_ARGUMENT = 4,
+  /**
+   * The interpreter panicked. The result string holds the panic message.
+   * The interpreter may be left in any state and should be freed.
+   */
+  ROX_STATUS_INTERNAL_ERROR = 5,
+} RoxStatus;
+
+/**
+ * An interpreter together with the text of its last result.
+ */
+typedef struct RoxHandle RoxHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an interpreter. Free it with `rox_free`.
+ */
+struct RoxHandle *rox_new(void);
+
+/**
+ * Runs `source`. Globals persist between calls, as with `Rox::eval`.
+ * Output goes to the process's stdout and errors are also reported on
+ * stderr.
+ *
+ * # Safety
+ *
+ * `rox` must come from `rox_new` and not have been freed, and `source`
+ * must be a NUL-terminated string.
+ */
+enum RoxStatus rox_eval(struct RoxHandle *rox, const char *source);
+
+/**
+ * Runs `source` in the global scope, so that what it declares is visible to
+ * every later `rox_eval` and to imported modules, as with
+ * `Rox::load_prelude`. On success the result string is empty.
+ *
+ * # Safety
+ *
+ * `rox` must come from `rox_new` and not have been freed, and `source`
+ * must be a NUL-terminated string.
+ */
+enum RoxStatus rox_load_prelude(struct RoxHandle *rox, const char *source);
+
+/**
+ * The text of the last `rox_eval` or `rox_load_prelude` result, as
+ * described by its status, or an empty string before the first call. The
+ * pointer stays valid until the next of those calls or `rox_free` on the
+ * same interpreter.
+ *
+ * # Safety
+ *
+ * `rox` must come from `rox_new` and not have been freed.
+ */
+const char *rox_get_string_result(const struct RoxHandle *rox);
+
+/**
+ * Destroys an interpreter. Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `rox` must come from `rox_new` and must not be used again afterwards.
+ */
+void rox_free(struct RoxHandle *rox);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ROX_H */
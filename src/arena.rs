@@ -0,0 +1,280 @@
+//! Storage for the nodes of a syntax tree.
+//!
+//! Nested expressions and statements are not boxed one by one: the parser
+//! pushes them into an `Arena` and links them by index, so a `Binary`
+//! holds the `ExprId`s of its operands and an `If` the `StmtId`s of its
+//! branches. Lists, like a call's arguments or a block's statements, stay
+//! inline in their parent. A `Program` is the top-level statements plus
+//! the arena their children live in, which makes parsing, walking and
+//! dropping a large script a matter of a few vectors rather than one
+//! allocation per node.
+
+use std::ops::{Index, IndexMut};
+
+use crate::{
+    expression::Expr,
+    statement::Stmt,
+    visitor::{ExprVisitorMut, StmtVisitorMut},
+};
+
+/// The index of an expression in its `Arena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// The index of a statement in its `Arena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StmtId(u32);
+
+/// The nested nodes of one or more programs. Ids are only meaningful in
+/// the arena that handed them out.
+#[derive(Debug, Clone, Default)]
+pub struct Arena {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_expr(&mut self, expr: Expr) -> ExprId {
+        let id = u32::try_from(self.exprs.len()).expect("too many expressions");
+        self.exprs.push(expr);
+        ExprId(id)
+    }
+
+    pub fn add_stmt(&mut self, stmt: Stmt) -> StmtId {
+        let id = u32::try_from(self.stmts.len()).expect("too many statements");
+        self.stmts.push(stmt);
+        StmtId(id)
+    }
+
+    /// How many nodes the arena holds, reachable or not.
+    pub fn len(&self) -> usize {
+        self.exprs.len() + self.stmts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls `Expr::accept_mut` on the expression at `id`. The node is
+    /// moved out of the arena while it is visited, so the visitor can take
+    /// the arena along to reach its children.
+    pub fn accept_expr_mut<R, V: ExprVisitorMut<R> + ?Sized>(
+        &mut self,
+        id: ExprId,
+        visitor: &mut V,
+    ) -> R {
+        let mut expr = std::mem::replace(&mut self[id], Expr::Grouping(id));
+        let result = expr.accept_mut(self, visitor);
+        self[id] = expr;
+        result
+    }
+
+    /// Like `accept_expr_mut`, for the statement at `id`.
+    pub fn accept_stmt_mut<R, V: StmtVisitorMut<R> + ?Sized>(
+        &mut self,
+        id: StmtId,
+        visitor: &mut V,
+    ) -> R {
+        let mut stmt = std::mem::replace(&mut self[id], Stmt::Block(Vec::new()));
+        let result = stmt.accept_mut(self, visitor);
+        self[id] = stmt;
+        result
+    }
+}
+
+impl Index<ExprId> for Arena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
+    }
+}
+
+impl IndexMut<ExprId> for Arena {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0 as usize]
+    }
+}
+
+impl Index<StmtId> for Arena {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.0 as usize]
+    }
+}
+
+impl IndexMut<StmtId> for Arena {
+    fn index_mut(&mut self, id: StmtId) -> &mut Stmt {
+        &mut self.stmts[id.0 as usize]
+    }
+}
+
+/// A parsed program: its top-level statements and the arena holding
+/// everything nested in them.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+    pub arena: Arena,
+}
+
+/// Two programs are equal when they have the same shape and tokens,
+/// wherever in the source, and wherever in their arenas, the nodes are.
+impl PartialEq for Program {
+    fn eq(&self, other: &Program) -> bool {
+        same_stmts(&self.arena, &self.stmts, &other.arena, &other.stmts)
+    }
+}
+
+fn same_exprs(a: &Arena, xs: &[Expr], b: &Arena, ys: &[Expr]) -> bool {
+    xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_expr(a, x, b, y))
+}
+
+fn same_expr(a: &Arena, x: &Expr, b: &Arena, y: &Expr) -> bool {
+    let same = |x: &ExprId, y: &ExprId| same_expr(a, &a[*x], b, &b[*y]);
+    match (x, y) {
+        (Expr::Unary(op1, right1), Expr::Unary(op2, right2))
+        | (Expr::Spread(op1, right1), Expr::Spread(op2, right2)) => {
+            op1 == op2 && same(right1, right2)
+        }
+        (Expr::Binary(left1, op1, right1), Expr::Binary(left2, op2, right2))
+        | (Expr::Logical(left1, op1, right1), Expr::Logical(left2, op2, right2)) => {
+            same(left1, left2) && op1 == op2 && same(right1, right2)
+        }
+        (Expr::Grouping(inner1), Expr::Grouping(inner2)) => same(inner1, inner2),
+        (Expr::Literal(token1, literal1), Expr::Literal(token2, literal2)) => {
+            token1 == token2 && literal1 == literal2
+        }
+        (Expr::Var(name1, local1), Expr::Var(name2, local2)) => name1 == name2 && local1 == local2,
+        (Expr::Assign(name1, value1, local1), Expr::Assign(name2, value2, local2)) => {
+            name1 == name2 && same(value1, value2) && local1 == local2
+        }
+        (Expr::Call(callee1, paren1, args1), Expr::Call(callee2, paren2, args2)) => {
+            same(callee1, callee2) && paren1 == paren2 && same_exprs(a, args1, b, args2)
+        }
+        (Expr::Get(object1, name1), Expr::Get(object2, name2)) => {
+            same(object1, object2) && name1 == name2
+        }
+        (Expr::Tuple(open1, items1, close1), Expr::Tuple(open2, items2, close2)) => {
+            open1 == open2 && same_exprs(a, items1, b, items2) && close1 == close2
+        }
+        _ => false,
+    }
+}
+
+fn same_stmts(a: &Arena, xs: &[Stmt], b: &Arena, ys: &[Stmt]) -> bool {
+    xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_stmt(a, x, b, y))
+}
+
+pub(crate) fn same_stmt(a: &Arena, x: &Stmt, b: &Arena, y: &Stmt) -> bool {
+    let same = |x: &StmtId, y: &StmtId| same_stmt(a, &a[*x], b, &b[*y]);
+    let same_optional = |x: &Option<StmtId>, y: &Option<StmtId>| match (x, y) {
+        (Some(x), Some(y)) => same(x, y),
+        (x, y) => x.is_none() && y.is_none(),
+    };
+    let same_expr = |x: &Expr, y: &Expr| same_expr(a, x, b, y);
+    match (x, y) {
+        (Stmt::Print(keyword1, values1), Stmt::Print(keyword2, values2)) => {
+            keyword1 == keyword2 && same_exprs(a, values1, b, values2)
+        }
+        (Stmt::Expression(expr1), Stmt::Expression(expr2)) => same_expr(expr1, expr2),
+        (Stmt::Var(pattern1, annotation1, init1), Stmt::Var(pattern2, annotation2, init2)) => {
+            pattern1 == pattern2
+                && annotation1 == annotation2
+                && match (init1, init2) {
+                    (Some(init1), Some(init2)) => same_expr(init1, init2),
+                    (init1, init2) => init1.is_none() && init2.is_none(),
+                }
+        }
+        (
+            Stmt::Const(name1, slot1, annotation1, init1),
+            Stmt::Const(name2, slot2, annotation2, init2),
+        ) => {
+            name1 == name2
+                && slot1 == slot2
+                && annotation1 == annotation2
+                && same_expr(init1, init2)
+        }
+        (Stmt::Block(stmts1), Stmt::Block(stmts2)) => same_stmts(a, stmts1, b, stmts2),
+        (Stmt::If(keyword1, cond1, then1, else1), Stmt::If(keyword2, cond2, then2, else2)) => {
+            keyword1 == keyword2
+                && same_expr(cond1, cond2)
+                && same(then1, then2)
+                && same_optional(else1, else2)
+        }
+        (
+            Stmt::While(keyword1, label1, cond1, body1, incr1),
+            Stmt::While(keyword2, label2, cond2, body2, incr2),
+        ) => {
+            keyword1 == keyword2
+                && label1 == label2
+                && same_expr(cond1, cond2)
+                && same(body1, body2)
+                && match (incr1, incr2) {
+                    (Some(incr1), Some(incr2)) => same_expr(incr1, incr2),
+                    (incr1, incr2) => incr1.is_none() && incr2.is_none(),
+                }
+        }
+        (
+            Stmt::ForEach(keyword1, label1, pattern1, iterable1, body1),
+            Stmt::ForEach(keyword2, label2, pattern2, iterable2, body2),
+        ) => {
+            keyword1 == keyword2
+                && label1 == label2
+                && pattern1 == pattern2
+                && same_expr(iterable1, iterable2)
+                && same(body1, body2)
+        }
+        (Stmt::Break(keyword1, label1), Stmt::Break(keyword2, label2))
+        | (Stmt::Continue(keyword1, label1), Stmt::Continue(keyword2, label2)) => {
+            keyword1 == keyword2 && label1 == label2
+        }
+        (Stmt::Throw(keyword1, value1), Stmt::Throw(keyword2, value2)) => {
+            keyword1 == keyword2 && same_expr(value1, value2)
+        }
+        (Stmt::Defer(keyword1, stmt1), Stmt::Defer(keyword2, stmt2))
+        | (Stmt::Pub(keyword1, stmt1), Stmt::Pub(keyword2, stmt2)) => {
+            keyword1 == keyword2 && same(stmt1, stmt2)
+        }
+        (
+            Stmt::Try(keyword1, try1, catch1, finally1),
+            Stmt::Try(keyword2, try2, catch2, finally2),
+        ) => {
+            keyword1 == keyword2
+                && same_stmts(a, try1, b, try2)
+                && match (catch1, catch2) {
+                    (Some((name1, catch1)), Some((name2, catch2))) => {
+                        name1 == name2 && same_stmts(a, catch1, b, catch2)
+                    }
+                    (catch1, catch2) => catch1.is_none() && catch2.is_none(),
+                }
+                && match (finally1, finally2) {
+                    (Some(finally1), Some(finally2)) => same_stmts(a, finally1, b, finally2),
+                    (finally1, finally2) => finally1.is_none() && finally2.is_none(),
+                }
+        }
+        (Stmt::Import(keyword1, path1, alias1), Stmt::Import(keyword2, path2, alias2)) => {
+            keyword1 == keyword2 && path1 == path2 && alias1 == alias2
+        }
+        (
+            Stmt::Match(keyword1, subject1, arms1, default1),
+            Stmt::Match(keyword2, subject2, arms2, default2),
+        ) => {
+            keyword1 == keyword2
+                && same_expr(subject1, subject2)
+                && arms1.len() == arms2.len()
+                && arms1
+                    .iter()
+                    .zip(arms2)
+                    .all(|((patterns1, body1), (patterns2, body2))| {
+                        same_exprs(a, patterns1, b, patterns2) && same_stmt(a, body1, b, body2)
+                    })
+                && same_optional(default1, default2)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,781 @@
+use crate::{
+    arena::{Arena, ExprId, Program, StmtId},
+    diagnostic::json_string,
+    expression::Expr,
+    statement::{Pattern, Stmt},
+    token::{Literal, Span, Token, TokenType},
+};
+
+/// Bumped whenever the shape of the JSON below changes.
+pub const AST_JSON_VERSION: u32 = 5;
+
+/// Renders a parsed program as a JSON document for external tools.
+///
+/// Every node is an object whose `kind` names the `Stmt`, `Expr` or
+/// `Pattern` variant. Tokens keep their type, lexeme and position, and
+/// literals map onto the matching JSON value. Int literals are written
+/// without a decimal point and number literals always with one or with an
+/// exponent, so the two read back as they were. Variable slots are left out;
+/// they are recomputed when the program is resolved again.
+pub fn ast_to_json(program: &Program) -> String {
+    let arena = &program.arena;
+    object(&[
+        ("version", AST_JSON_VERSION.to_string()),
+        (
+            "statements",
+            array(&program.stmts, |stmt| stmt_json(arena, stmt)),
+        ),
+    ])
+}
+
+pub(crate) fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+pub(crate) fn node(kind: &str, fields: &[(&str, String)]) -> String {
+    let mut all = vec![("kind", json_string(kind))];
+    all.extend_from_slice(fields);
+    object(&all)
+}
+
+fn array<T>(items: &[T], render: impl Fn(&T) -> String) -> String {
+    let items: Vec<String> = items.iter().map(render).collect();
+    format!("[{}]", items.join(","))
+}
+
+fn optional<T>(value: Option<&T>, render: impl Fn(&T) -> String) -> String {
+    value.map_or_else(|| "null".to_string(), render)
+}
+
+fn literal_json(literal: &Literal) -> String {
+    match literal {
+        Literal::Int(n) => n.to_string(),
+        Literal::Number(n) if n.is_finite() => format!("{:?}", n),
+        Literal::Number(_) | Literal::Nil => "null".to_string(),
+        Literal::String(s) => json_string(s),
+        Literal::Bool(b) => b.to_string(),
+    }
+}
+
+fn token_json(token: &Token) -> String {
+    object(&[
+        ("type", json_string(&format!("{:?}", token.token_type))),
+        ("lexeme", json_string(&token.lexeme)),
+        ("line", token.line.to_string()),
+        ("column", token.column.to_string()),
+        (
+            "span",
+            format!(
+                "{{\"start\":{},\"end\":{}}}",
+                token.span.start, token.span.end
+            ),
+        ),
+        ("literal", literal_json(&token.literal)),
+    ])
+}
+
+fn pattern_json(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Name(name, _) => node("Name", &[("name", token_json(name))]),
+        Pattern::Tuple(open, items, close) => node(
+            "Tuple",
+            &[
+                ("open", token_json(open)),
+                ("items", array(items, pattern_json)),
+                ("close", token_json(close)),
+            ],
+        ),
+    }
+}
+
+fn stmt_json(arena: &Arena, stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Print(keyword, exprs) => node(
+            "Print",
+            &[
+                ("keyword", token_json(keyword)),
+                ("exprs", array(exprs, |expr| expr_json(arena, expr))),
+            ],
+        ),
+        Stmt::Expression(expr) => node("Expression", &[("expr", expr_json(arena, expr))]),
+        Stmt::Var(target, annotation, initializer) => node(
+            "Var",
+            &[
+                ("pattern", pattern_json(target)),
+                ("annotation", optional(annotation.as_ref(), token_json)),
+                (
+                    "initializer",
+                    optional(initializer.as_ref(), |expr| expr_json(arena, expr)),
+                ),
+            ],
+        ),
+        Stmt::Const(name, _, annotation, initializer) => node(
+            "Const",
+            &[
+                ("name", token_json(name)),
+                ("annotation", optional(annotation.as_ref(), token_json)),
+                ("initializer", expr_json(arena, initializer)),
+            ],
+        ),
+        Stmt::Block(body) => node(
+            "Block",
+            &[("stmts", array(body, |stmt| stmt_json(arena, stmt)))],
+        ),
+        Stmt::If(keyword, condition, then_branch, else_branch) => node(
+            "If",
+            &[
+                ("keyword", token_json(keyword)),
+                ("condition", expr_json(arena, condition)),
+                ("then", nested_stmt_json(arena, *then_branch)),
+                (
+                    "else",
+                    optional(else_branch.as_ref(), |stmt| nested_stmt_json(arena, *stmt)),
+                ),
+            ],
+        ),
+        Stmt::While(keyword, label, condition, body, increment) => node(
+            "While",
+            &[
+                ("keyword", token_json(keyword)),
+                ("label", optional(label.as_ref(), token_json)),
+                ("condition", expr_json(arena, condition)),
+                ("body", nested_stmt_json(arena, *body)),
+                (
+                    "increment",
+                    optional(increment.as_ref(), |expr| expr_json(arena, expr)),
+                ),
+            ],
+        ),
+        Stmt::ForEach(keyword, label, target, iterable, body) => node(
+            "ForEach",
+            &[
+                ("keyword", token_json(keyword)),
+                ("label", optional(label.as_ref(), token_json)),
+                ("pattern", pattern_json(target)),
+                ("iterable", expr_json(arena, iterable)),
+                ("body", nested_stmt_json(arena, *body)),
+            ],
+        ),
+        Stmt::Break(keyword, label) => node(
+            "Break",
+            &[
+                ("keyword", token_json(keyword)),
+                ("label", optional(label.as_ref(), token_json)),
+            ],
+        ),
+        Stmt::Continue(keyword, label) => node(
+            "Continue",
+            &[
+                ("keyword", token_json(keyword)),
+                ("label", optional(label.as_ref(), token_json)),
+            ],
+        ),
+        Stmt::Throw(keyword, value) => node(
+            "Throw",
+            &[
+                ("keyword", token_json(keyword)),
+                ("value", expr_json(arena, value)),
+            ],
+        ),
+        Stmt::Defer(keyword, deferred) => node(
+            "Defer",
+            &[
+                ("keyword", token_json(keyword)),
+                ("stmt", nested_stmt_json(arena, *deferred)),
+            ],
+        ),
+        Stmt::Try(keyword, body, catch_clause, finally_block) => node(
+            "Try",
+            &[
+                ("keyword", token_json(keyword)),
+                ("body", array(body, |stmt| stmt_json(arena, stmt))),
+                (
+                    "catch",
+                    optional(catch_clause.as_ref(), |(name, body)| {
+                        object(&[
+                            ("name", token_json(name)),
+                            ("body", array(body, |stmt| stmt_json(arena, stmt))),
+                        ])
+                    }),
+                ),
+                (
+                    "finally",
+                    optional(finally_block.as_ref(), |body| {
+                        array(body, |stmt| stmt_json(arena, stmt))
+                    }),
+                ),
+            ],
+        ),
+        Stmt::Import(keyword, path, alias) => node(
+            "Import",
+            &[
+                ("keyword", token_json(keyword)),
+                ("path", json_string(path)),
+                ("alias", optional(alias.as_ref(), token_json)),
+            ],
+        ),
+        Stmt::Pub(keyword, stmt) => node(
+            "Pub",
+            &[
+                ("keyword", token_json(keyword)),
+                ("stmt", nested_stmt_json(arena, *stmt)),
+            ],
+        ),
+        Stmt::Match(keyword, subject, arms, default) => node(
+            "Match",
+            &[
+                ("keyword", token_json(keyword)),
+                ("subject", expr_json(arena, subject)),
+                (
+                    "arms",
+                    array(arms, |(patterns, body)| {
+                        object(&[
+                            ("patterns", array(patterns, |expr| expr_json(arena, expr))),
+                            ("body", stmt_json(arena, body)),
+                        ])
+                    }),
+                ),
+                (
+                    "else",
+                    optional(default.as_ref(), |stmt| nested_stmt_json(arena, *stmt)),
+                ),
+            ],
+        ),
+    }
+}
+
+fn nested_stmt_json(arena: &Arena, stmt: StmtId) -> String {
+    stmt_json(arena, &arena[stmt])
+}
+
+fn expr_json(arena: &Arena, expr: &Expr) -> String {
+    match expr {
+        Expr::Unary(op, right) => node(
+            "Unary",
+            &[
+                ("op", token_json(op)),
+                ("right", nested_expr_json(arena, *right)),
+            ],
+        ),
+        Expr::Binary(left, op, right) => node(
+            "Binary",
+            &[
+                ("left", nested_expr_json(arena, *left)),
+                ("op", token_json(op)),
+                ("right", nested_expr_json(arena, *right)),
+            ],
+        ),
+        Expr::Logical(left, op, right) => node(
+            "Logical",
+            &[
+                ("left", nested_expr_json(arena, *left)),
+                ("op", token_json(op)),
+                ("right", nested_expr_json(arena, *right)),
+            ],
+        ),
+        Expr::Grouping(inner) => node("Grouping", &[("expr", nested_expr_json(arena, *inner))]),
+        Expr::Literal(token, value) => node(
+            "Literal",
+            &[("token", token_json(token)), ("value", literal_json(value))],
+        ),
+        Expr::Var(name, _) => node("Var", &[("name", token_json(name))]),
+        Expr::Assign(name, value, _) => node(
+            "Assign",
+            &[
+                ("name", token_json(name)),
+                ("value", nested_expr_json(arena, *value)),
+            ],
+        ),
+        Expr::Call(callee, paren, args) => node(
+            "Call",
+            &[
+                ("callee", nested_expr_json(arena, *callee)),
+                ("paren", token_json(paren)),
+                ("args", array(args, |expr| expr_json(arena, expr))),
+            ],
+        ),
+        Expr::Get(object, name) => node(
+            "Get",
+            &[
+                ("object", nested_expr_json(arena, *object)),
+                ("name", token_json(name)),
+            ],
+        ),
+        Expr::Tuple(open, items, close) => node(
+            "Tuple",
+            &[
+                ("open", token_json(open)),
+                ("items", array(items, |expr| expr_json(arena, expr))),
+                ("close", token_json(close)),
+            ],
+        ),
+        Expr::Spread(op, inner) => node(
+            "Spread",
+            &[
+                ("op", token_json(op)),
+                ("expr", nested_expr_json(arena, *inner)),
+            ],
+        ),
+    }
+}
+
+fn nested_expr_json(arena: &Arena, expr: ExprId) -> String {
+    expr_json(arena, &arena[expr])
+}
+
+/// Reads a program back from the output of `ast_to_json`, so tools that
+/// generate rox programs can hand them to `Rox::run_ast` without source.
+pub fn ast_from_json(json: &str) -> Result<Program, String> {
+    let document = JsonReader::new(json).document()?;
+
+    let version = document.get("version")?.as_number()?;
+    if version != AST_JSON_VERSION as f64 {
+        return Err(format!(
+            "Unsupported AST version {} (expected {})",
+            version, AST_JSON_VERSION
+        ));
+    }
+
+    let mut arena = Arena::new();
+    let stmts = decode_list(document.get("statements")?, |json| {
+        decode_stmt(json, &mut arena)
+    })?;
+    Ok(Program { stmts, arena })
+}
+
+/// Just enough of JSON to read the AST and snapshot formats back.
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    /// A number written without a fraction or exponent that fits in an i64.
+    Integer(i64),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn get(&self, name: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("Missing field '{}'", name)),
+            _ => Err(format!("Expected an object with a '{}' field", name)),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str, String> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err("Expected a string".to_string()),
+        }
+    }
+
+    pub(crate) fn as_number(&self) -> Result<f64, String> {
+        match self {
+            Json::Integer(n) => Ok(*n as f64),
+            Json::Number(n) => Ok(*n),
+            _ => Err("Expected a number".to_string()),
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(items) => Ok(items),
+            _ => Err("Expected an array".to_string()),
+        }
+    }
+}
+
+pub(crate) struct JsonReader<'a> {
+    source: &'a str,
+    current: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    pub(crate) fn new(source: &'a str) -> JsonReader<'a> {
+        JsonReader { source, current: 0 }
+    }
+
+    pub(crate) fn document(&mut self) -> Result<Json, String> {
+        let value = self.value()?;
+        self.skip_whitespace();
+
+        if self.current < self.source.len() {
+            return Err(self.error("Unexpected data after the document"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}", message, self.current)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.current..].chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.advance() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("Expected '{}'", expected))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if self.source[self.current..].starts_with(word) {
+            self.current += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("Unexpected character"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.advance() != Some('"') {
+            return Err(self.error("Expected a string"));
+        }
+
+        let mut out = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(out),
+                Some('\\') => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("Invalid escape")),
+                    };
+                    out.push(escaped);
+                }
+                Some(c) => out.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    /// Decodes the digits after `\u`, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.source[self.current..].starts_with("\\u") {
+                return Err(self.error("Unpaired surrogate"));
+            }
+            self.current += 2;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .source
+            .get(self.current..self.current + 4)
+            .ok_or_else(|| self.error("Truncated unicode escape"))?;
+        let value =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("Invalid unicode escape"))?;
+        self.current += 4;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.current;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.advance();
+        }
+
+        let text = &self.source[start..self.current];
+        if !text.contains(['.', 'e', 'E']) {
+            if let Ok(n) = text.parse() {
+                return Ok(Json::Integer(n));
+            }
+        }
+
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("Invalid number"))
+    }
+}
+
+fn decode_list<T>(
+    json: &Json,
+    decode: impl FnMut(&Json) -> Result<T, String>,
+) -> Result<Vec<T>, String> {
+    json.as_array()?.iter().map(decode).collect()
+}
+
+fn decode_optional<T>(
+    json: &Json,
+    decode: impl FnOnce(&Json) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    match json {
+        Json::Null => Ok(None),
+        json => decode(json).map(Some),
+    }
+}
+
+fn decode_literal(json: &Json) -> Result<Literal, String> {
+    match json {
+        Json::Null => Ok(Literal::Nil),
+        Json::Bool(b) => Ok(Literal::Bool(*b)),
+        Json::Integer(n) => Ok(Literal::Int(*n)),
+        Json::Number(n) => Ok(Literal::Number(*n)),
+        Json::String(s) => Ok(Literal::String(s.as_str().into())),
+        _ => Err("Expected a literal value".to_string()),
+    }
+}
+
+fn decode_token(json: &Json) -> Result<Token, String> {
+    let type_name = json.get("type")?.as_str()?;
+    let token_type = TokenType::from_name(type_name)
+        .ok_or_else(|| format!("Unknown token type '{}'", type_name))?;
+    let span = json.get("span")?;
+
+    Ok(Token::new(
+        token_type,
+        json.get("lexeme")?.as_str()?.to_string(),
+        json.get("line")?.as_number()? as i32,
+        json.get("column")?.as_number()? as i32,
+        Span {
+            start: span.get("start")?.as_number()? as usize,
+            end: span.get("end")?.as_number()? as usize,
+        },
+        decode_literal(json.get("literal")?)?,
+    ))
+}
+
+fn decode_pattern(json: &Json) -> Result<Pattern, String> {
+    match json.get("kind")?.as_str()? {
+        "Name" => Ok(Pattern::Name(decode_token(json.get("name")?)?, None)),
+        "Tuple" => Ok(Pattern::Tuple(
+            decode_token(json.get("open")?)?,
+            decode_list(json.get("items")?, decode_pattern)?,
+            decode_token(json.get("close")?)?,
+        )),
+        kind => Err(format!("Unknown pattern kind '{}'", kind)),
+    }
+}
+
+fn decode_block(json: &Json, arena: &mut Arena) -> Result<Vec<Stmt>, String> {
+    decode_list(json, |json| decode_stmt(json, arena))
+}
+
+fn decode_catch(json: &Json, arena: &mut Arena) -> Result<(Token, Vec<Stmt>), String> {
+    Ok((
+        decode_token(json.get("name")?)?,
+        decode_block(json.get("body")?, arena)?,
+    ))
+}
+
+fn decode_arm(json: &Json, arena: &mut Arena) -> Result<(Vec<Expr>, Stmt), String> {
+    Ok((
+        decode_exprs(json.get("patterns")?, arena)?,
+        decode_stmt(json.get("body")?, arena)?,
+    ))
+}
+
+/// Decodes a nested statement into `arena`.
+fn decode_stmt_id(json: &Json, arena: &mut Arena) -> Result<StmtId, String> {
+    let stmt = decode_stmt(json, arena)?;
+    Ok(arena.add_stmt(stmt))
+}
+
+fn decode_stmt(json: &Json, arena: &mut Arena) -> Result<Stmt, String> {
+    let token = |name| decode_token(json.get(name)?);
+    let label = || decode_optional(json.get("label")?, decode_token);
+
+    let stmt = match json.get("kind")?.as_str()? {
+        "Print" => Stmt::Print(token("keyword")?, decode_exprs(json.get("exprs")?, arena)?),
+        "Expression" => Stmt::Expression(decode_expr(json.get("expr")?, arena)?),
+        "Var" => Stmt::Var(
+            decode_pattern(json.get("pattern")?)?,
+            decode_optional(json.get("annotation")?, decode_token)?,
+            decode_optional(json.get("initializer")?, |json| decode_expr(json, arena))?,
+        ),
+        "Const" => Stmt::Const(
+            token("name")?,
+            None,
+            decode_optional(json.get("annotation")?, decode_token)?,
+            decode_expr(json.get("initializer")?, arena)?,
+        ),
+        "Block" => Stmt::Block(decode_block(json.get("stmts")?, arena)?),
+        "If" => Stmt::If(
+            token("keyword")?,
+            decode_expr(json.get("condition")?, arena)?,
+            decode_stmt_id(json.get("then")?, arena)?,
+            decode_optional(json.get("else")?, |json| decode_stmt_id(json, arena))?,
+        ),
+        "While" => Stmt::While(
+            token("keyword")?,
+            label()?,
+            decode_expr(json.get("condition")?, arena)?,
+            decode_stmt_id(json.get("body")?, arena)?,
+            decode_optional(json.get("increment")?, |json| decode_expr(json, arena))?,
+        ),
+        "ForEach" => Stmt::ForEach(
+            token("keyword")?,
+            label()?,
+            decode_pattern(json.get("pattern")?)?,
+            decode_expr(json.get("iterable")?, arena)?,
+            decode_stmt_id(json.get("body")?, arena)?,
+        ),
+        "Break" => Stmt::Break(token("keyword")?, label()?),
+        "Continue" => Stmt::Continue(token("keyword")?, label()?),
+        "Throw" => Stmt::Throw(token("keyword")?, decode_expr(json.get("value")?, arena)?),
+        "Defer" => Stmt::Defer(token("keyword")?, decode_stmt_id(json.get("stmt")?, arena)?),
+        "Pub" => Stmt::Pub(token("keyword")?, decode_stmt_id(json.get("stmt")?, arena)?),
+        "Try" => Stmt::Try(
+            token("keyword")?,
+            decode_block(json.get("body")?, arena)?,
+            decode_optional(json.get("catch")?, |json| decode_catch(json, arena))?,
+            decode_optional(json.get("finally")?, |json| decode_block(json, arena))?,
+        ),
+        "Import" => Stmt::Import(
+            token("keyword")?,
+            json.get("path")?.as_str()?.to_string(),
+            decode_optional(json.get("alias")?, decode_token)?,
+        ),
+        "Match" => Stmt::Match(
+            token("keyword")?,
+            decode_expr(json.get("subject")?, arena)?,
+            decode_list(json.get("arms")?, |json| decode_arm(json, arena))?,
+            decode_optional(json.get("else")?, |json| decode_stmt_id(json, arena))?,
+        ),
+        kind => return Err(format!("Unknown statement kind '{}'", kind)),
+    };
+
+    Ok(stmt)
+}
+
+fn decode_exprs(json: &Json, arena: &mut Arena) -> Result<Vec<Expr>, String> {
+    decode_list(json, |json| decode_expr(json, arena))
+}
+
+/// Decodes a nested expression into `arena`.
+fn decode_expr_id(json: &Json, arena: &mut Arena) -> Result<ExprId, String> {
+    let expr = decode_expr(json, arena)?;
+    Ok(arena.add_expr(expr))
+}
+
+fn decode_expr(json: &Json, arena: &mut Arena) -> Result<Expr, String> {
+    let token = |name| decode_token(json.get(name)?);
+    let mut expr = |name| decode_expr_id(json.get(name)?, arena);
+
+    let expr = match json.get("kind")?.as_str()? {
+        "Unary" => Expr::Unary(token("op")?, expr("right")?),
+        "Binary" => Expr::Binary(expr("left")?, token("op")?, expr("right")?),
+        "Logical" => Expr::Logical(expr("left")?, token("op")?, expr("right")?),
+        "Grouping" => Expr::Grouping(expr("expr")?),
+        "Literal" => Expr::Literal(token("token")?, decode_literal(json.get("value")?)?),
+        "Var" => Expr::Var(token("name")?, None),
+        "Assign" => Expr::Assign(token("name")?, expr("value")?, None),
+        "Call" => Expr::Call(
+            expr("callee")?,
+            token("paren")?,
+            decode_exprs(json.get("args")?, arena)?,
+        ),
+        "Get" => Expr::Get(expr("object")?, token("name")?),
+        "Tuple" => Expr::Tuple(
+            token("open")?,
+            decode_exprs(json.get("items")?, arena)?,
+            token("close")?,
+        ),
+        "Spread" => Expr::Spread(token("op")?, expr("expr")?),
+        kind => return Err(format!("Unknown expression kind '{}'", kind)),
+    };
+
+    Ok(expr)
+}
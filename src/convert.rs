@@ -0,0 +1,231 @@
+use std::rc::Rc;
+
+use crate::{interpreter::RuntimeError, value::Value};
+
+/// Converts a Rust value into a rox value.
+pub trait IntoRox {
+    fn into_rox(self) -> Value;
+}
+
+/// Converts a rox value into a Rust value, failing with a runtime error when
+/// the rox value has the wrong type.
+pub trait FromRox: Sized {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError>;
+}
+
+fn type_error(expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::Generic(format!(
+        "Expected a {} but got a {} '{}'",
+        expected,
+        value.type_name(),
+        value
+    ))
+}
+
+impl IntoRox for Value {
+    fn into_rox(self) -> Value {
+        self
+    }
+}
+
+impl FromRox for Value {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        Ok(value)
+    }
+}
+
+impl IntoRox for f64 {
+    fn into_rox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromRox for f64 {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        value.as_f64().ok_or_else(|| type_error("number", &value))
+    }
+}
+
+impl IntoRox for i64 {
+    fn into_rox(self) -> Value {
+        Value::Int(self)
+    }
+}
+
+impl FromRox for i64 {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Int(n) => Ok(n),
+            _ => Err(type_error("int", &value)),
+        }
+    }
+}
+
+impl IntoRox for bool {
+    fn into_rox(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromRox for bool {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(type_error("bool", &value)),
+        }
+    }
+}
+
+impl IntoRox for String {
+    fn into_rox(self) -> Value {
+        Value::String(self.into())
+    }
+}
+
+impl IntoRox for &str {
+    fn into_rox(self) -> Value {
+        Value::String(self.into())
+    }
+}
+
+impl FromRox for String {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            _ => Err(type_error("string", &value)),
+        }
+    }
+}
+
+impl IntoRox for () {
+    fn into_rox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl<T: IntoRox> IntoRox for Option<T> {
+    fn into_rox(self) -> Value {
+        match self {
+            Some(value) => value.into_rox(),
+            None => ().into_rox(),
+        }
+    }
+}
+
+impl<T: FromRox> FromRox for Option<T> {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Nil => Ok(None),
+            _ => T::from_rox(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoRox> IntoRox for Vec<T> {
+    fn into_rox(self) -> Value {
+        Value::List(Rc::new(self.into_iter().map(T::into_rox).collect()))
+    }
+}
+
+impl<T: FromRox> FromRox for Vec<T> {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match &value {
+            Value::List(items) => items.iter().cloned().map(T::from_rox).collect(),
+            _ => Err(type_error("list", &value)),
+        }
+    }
+}
+
+impl<A: IntoRox, B: IntoRox> IntoRox for (A, B) {
+    fn into_rox(self) -> Value {
+        Value::Tuple(Rc::new(vec![self.0.into_rox(), self.1.into_rox()]))
+    }
+}
+
+impl<A: FromRox, B: FromRox> FromRox for (A, B) {
+    fn from_rox(value: Value) -> Result<Self, RuntimeError> {
+        match &value {
+            Value::Tuple(items) if items.len() == 2 => Ok((
+                A::from_rox(items[0].clone())?,
+                B::from_rox(items[1].clone())?,
+            )),
+            _ => Err(type_error("pair", &value)),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        value.into_rox()
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        value.into_rox()
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        value.into_rox()
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        value.into_rox()
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        value.into_rox()
+    }
+}
+
+impl<T: IntoRox> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        value.into_rox()
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        f64::from_rox(value)
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        i64::from_rox(value)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        bool::from_rox(value)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        String::from_rox(value)
+    }
+}
+
+impl<T: FromRox> TryFrom<Value> for Vec<T> {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Vec::<T>::from_rox(value)
+    }
+}
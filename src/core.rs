@@ -1 +0,0 @@
-use std::io::{Stdout, Write};
@@ -0,0 +1,179 @@
+use std::rc::Rc;
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{items_arg, string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Natives for comma-separated values as described by RFC 4180: fields
+/// containing commas, quotes or line breaks are wrapped in double quotes,
+/// and a quote inside them is doubled.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "csv_parse",
+            arity: 1..=2,
+            func: csv_parse,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "csv_write",
+            arity: 1..=1,
+            func: csv_write,
+            deterministic: true,
+        },
+    ]
+}
+
+fn list_value(items: Vec<Value>) -> Value {
+    Value::List(Rc::new(items))
+}
+
+/// `csv_parse(text)` returns a list of rows, each a list of strings. With
+/// `csv_parse(text, true)` the first row is taken as a header and the
+/// result is a `(header, rows)` tuple.
+fn csv_parse(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string_arg("csv_parse", &args, 0)?;
+    let header = match args.get(1) {
+        None => false,
+        Some(Value::Bool(header)) => *header,
+        Some(x) => {
+            return Err(RuntimeError::Generic(format!(
+                "csv_parse() expects a bool for the header option, got '{}'",
+                x
+            )))
+        }
+    };
+
+    let mut rows = parse(&text)?
+        .into_iter()
+        .map(|row| list_value(row.into_iter().map(string_value).collect()));
+
+    if !header {
+        return Ok(list_value(rows.collect()));
+    }
+
+    let names = rows.next().unwrap_or_else(|| list_value(Vec::new()));
+    Ok(Value::Tuple(Rc::new(vec![
+        names,
+        list_value(rows.collect()),
+    ])))
+}
+
+fn parse(text: &str) -> Result<Vec<Vec<String>>, RuntimeError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    // Set once the current field was quoted, so that `""` alone on a line
+    // is a row with one empty field rather than a blank line.
+    let mut quoted = false;
+    let mut line = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => {
+                            return Err(RuntimeError::Generic(format!(
+                                "csv_parse() found an unterminated quote on line {}",
+                                start
+                            )))
+                        }
+                    }
+                }
+
+                if !matches!(chars.peek(), None | Some(',' | '\r' | '\n')) {
+                    return Err(RuntimeError::Generic(format!(
+                        "csv_parse() expects a ',' or line break after a quoted field on line {}",
+                        line
+                    )));
+                }
+            }
+            ',' => {
+                row.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                // Blank lines are skipped.
+                if !row.is_empty() || !field.is_empty() || quoted {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                quoted = false;
+                line += 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    // A final line break does not start another row.
+    if !row.is_empty() || !field.is_empty() || quoted {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// `csv_write(rows)` renders a collection of rows, each a collection of
+/// values, as CSV text with a line break after every row. Strings are
+/// written as they are, nil as an empty field, and other values as `print`
+/// shows them.
+fn csv_write(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut out = String::new();
+
+    for row in items_arg("csv_write", &args, 0)? {
+        // Strings are iterable, but a string row is almost certainly a bug.
+        let fields = match &row {
+            Value::String(_) => None,
+            row => row.iter(),
+        };
+        let Some(fields) = fields else {
+            return Err(RuntimeError::Generic(format!(
+                "csv_write() expects each row to be a list or tuple, got '{}'",
+                row
+            )));
+        };
+
+        for (index, value) in fields.enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            match value {
+                Value::Nil => {}
+                value => write_field(&mut out, &value.to_string()),
+            }
+        }
+        out.push('\n');
+    }
+
+    Ok(string_value(out))
+}
+
+fn write_field(out: &mut String, field: &str) {
+    if !field.contains([',', '"', '\n', '\r']) {
+        out.push_str(field);
+        return;
+    }
+
+    out.push('"');
+    out.push_str(&field.replace('"', "\"\""));
+    out.push('"');
+}
@@ -0,0 +1,694 @@
+//! A Debug Adapter Protocol server, so editors can run a script with
+//! breakpoints, stepping and a view of its variables.
+//!
+//! Messages are JSON bodies behind a `Content-Length` header, requests from
+//! the editor on the input and responses and events on the output. The
+//! script runs on the calling thread and is stopped from inside
+//! `InterpreterHooks::inspect`, which answers requests until the editor
+//! resumes it. A second thread reads requests, so that `pause` and new
+//! breakpoints reach a script that is running.
+//!
+//! There is one thread and, without user functions, one stack frame. Steps
+//! move by line: `next` stays out of imported modules, `stepIn` follows
+//! them and `stepOut` runs until the current module returns.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, ErrorKind, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+};
+
+use crate::{
+    ast_json::{object, Json, JsonReader},
+    diagnostic::json_string,
+    error::RoxError,
+    hooks::{Frame, InterpreterHooks},
+    interpreter::RuntimeError,
+    statement::Stmt,
+    value::Value,
+    Rox,
+};
+
+/// The only thread there is, as the editor knows it.
+const THREAD_ID: i64 = 1;
+
+/// Serves one debugging session: waits for the editor to `launch` a script
+/// and finish configuring, runs it with `rox` and returns once the editor
+/// disconnects. Disconnecting while the script runs ends the process, as
+/// nothing else can stop a script from inside a hook.
+pub fn serve_dap<R, W>(mut rox: Rox, input: R, output: W)
+where
+    R: BufRead + Send + 'static,
+    W: Write + 'static,
+{
+    let (sender, requests) = channel();
+    thread::spawn(move || {
+        let mut input = input;
+        // A broken or closed input ends the session like a disconnect.
+        while let Ok(Some(body)) = read_message(&mut input) {
+            if let Ok(message) = JsonReader::new(&body).document() {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let connection = Rc::new(RefCell::new(Connection {
+        out: Box::new(output),
+        seq: 0,
+    }));
+    let session = Rc::new(RefCell::new(Session {
+        connection: connection.clone(),
+        requests,
+        breakpoints: HashMap::new(),
+        program: None,
+        configured: false,
+        step: Step::Run,
+        stopped: false,
+        last: None,
+        stopped_at: None,
+        references: Vec::new(),
+    }));
+
+    let program = loop {
+        let mut session = session.borrow_mut();
+        if session.configured {
+            if let Some(program) = session.program.clone() {
+                break program;
+            }
+        }
+        let Some(request) = session.next_request() else {
+            return;
+        };
+        if let Flow::Disconnect = session.handle(&request, None) {
+            return;
+        }
+    };
+
+    rox.set_output(
+        Box::new(OutputEvents::new(connection.clone(), "stdout")),
+        Box::new(OutputEvents::new(connection.clone(), "stderr")),
+    );
+    rox.set_hooks(Box::new(Debugger(session.clone())));
+
+    let exit_code = match rox.run_file(&program) {
+        Ok(_) => 0,
+        Err(RoxError::Runtime(RuntimeError::Exit(code))) => code,
+        // Runtime errors were reported through the error output already.
+        Err(RoxError::Runtime(_)) | Err(RoxError::LimitExceeded(_)) => 1,
+        Err(err) => {
+            connection
+                .borrow_mut()
+                .output("stderr", &format!("{}\n", err));
+            1
+        }
+    };
+    drop(rox);
+
+    let mut connection = connection.borrow_mut();
+    connection.event("exited", object(&[("exitCode", exit_code.to_string())]));
+    connection.event("terminated", object(&[]));
+    drop(connection);
+
+    let mut session = session.borrow_mut();
+    while let Some(request) = session.next_request() {
+        if let Flow::Disconnect = session.handle(&request, None) {
+            break;
+        }
+    }
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Missing Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+struct Request {
+    seq: i64,
+    command: String,
+    arguments: Json,
+}
+
+impl Request {
+    fn parse(message: Json) -> Option<Request> {
+        if message.get("type").ok()?.as_str().ok()? != "request" {
+            return None;
+        }
+
+        let seq = message.get("seq").ok()?.as_number().ok()? as i64;
+        let command = message.get("command").ok()?.as_str().ok()?.to_string();
+        let arguments = match message {
+            Json::Object(fields) => fields
+                .into_iter()
+                .find(|(name, _)| name == "arguments")
+                .map_or(Json::Null, |(_, value)| value),
+            _ => Json::Null,
+        };
+
+        Some(Request {
+            seq,
+            command,
+            arguments,
+        })
+    }
+
+    fn argument(&self, name: &str) -> Option<&Json> {
+        self.arguments.get(name).ok()
+    }
+}
+
+struct Connection {
+    out: Box<dyn Write>,
+    seq: i64,
+}
+
+impl Connection {
+    // An editor that goes away also closes the input, which ends the
+    // session, so write errors are ignored here.
+    fn send(&mut self, kind: &str, fields: &[(&str, String)]) {
+        self.seq += 1;
+        let mut all = vec![("seq", self.seq.to_string()), ("type", json_string(kind))];
+        all.extend_from_slice(fields);
+        let body = object(&all);
+
+        let _ = write!(self.out, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = self.out.flush();
+    }
+
+    fn respond(&mut self, request: &Request, body: String) {
+        self.send(
+            "response",
+            &[
+                ("request_seq", request.seq.to_string()),
+                ("success", "true".to_string()),
+                ("command", json_string(&request.command)),
+                ("body", body),
+            ],
+        );
+    }
+
+    fn fail(&mut self, request: &Request, message: &str) {
+        self.send(
+            "response",
+            &[
+                ("request_seq", request.seq.to_string()),
+                ("success", "false".to_string()),
+                ("command", json_string(&request.command)),
+                ("message", json_string(message)),
+            ],
+        );
+    }
+
+    fn event(&mut self, event: &str, body: String) {
+        self.send("event", &[("event", json_string(event)), ("body", body)]);
+    }
+
+    fn output(&mut self, category: &str, text: &str) {
+        self.event(
+            "output",
+            object(&[
+                ("category", json_string(category)),
+                ("output", json_string(text)),
+            ]),
+        );
+    }
+}
+
+/// Sends what the script writes to the editor's debug console, a line at a
+/// time.
+struct OutputEvents {
+    connection: Rc<RefCell<Connection>>,
+    category: &'static str,
+    line: Vec<u8>,
+}
+
+impl OutputEvents {
+    fn new(connection: Rc<RefCell<Connection>>, category: &'static str) -> OutputEvents {
+        OutputEvents {
+            connection,
+            category,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if self.line.ends_with(b"\n") {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            let text = String::from_utf8_lossy(&self.line).to_string();
+            self.connection.borrow_mut().output(self.category, &text);
+            self.line.clear();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputEvents {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// What the script should do before it next stops.
+#[derive(Clone, Copy)]
+enum Step {
+    Run,
+    /// Stop at the first statement.
+    Entry,
+    Pause,
+    /// Stop on the next line, wherever it is.
+    In,
+    /// Stop on the next line at this import depth or above.
+    Over(usize),
+    /// Stop once the script is above this import depth.
+    Out(usize),
+}
+
+enum Flow {
+    Stay,
+    Resume,
+    Disconnect,
+}
+
+/// A line in a file, or in a script run without one.
+type Location = (Option<PathBuf>, i32);
+
+struct Session {
+    connection: Rc<RefCell<Connection>>,
+    requests: Receiver<Json>,
+    /// Breakpoint lines, by the canonical path of their file.
+    breakpoints: HashMap<PathBuf, HashSet<i32>>,
+    program: Option<PathBuf>,
+    configured: bool,
+    step: Step,
+    /// Whether the script is waiting for the editor to resume it.
+    stopped: bool,
+    /// Where the last statement was, so a line with several statements
+    /// hits its breakpoint once.
+    last: Option<Location>,
+    /// Where the script last stopped, so a step moves on to another line.
+    stopped_at: Option<Location>,
+    /// Variables the editor can expand while the script is stopped, by
+    /// `variablesReference` less one.
+    references: Vec<Vec<(String, Value)>>,
+}
+
+impl Session {
+    /// Waits for the next request, or `None` once the editor has gone.
+    fn next_request(&mut self) -> Option<Request> {
+        loop {
+            if let Some(request) = Request::parse(self.requests.recv().ok()?) {
+                return Some(request);
+            }
+        }
+    }
+
+    fn at_statement(&mut self, stmt: &Stmt, frame: &Frame<'_>) {
+        // A block would only repeat the position of its first statement.
+        if let Stmt::Block(_) = stmt {
+            return;
+        }
+
+        loop {
+            match self.requests.try_recv() {
+                Ok(message) => {
+                    if let Some(request) = Request::parse(message) {
+                        if let Flow::Disconnect = self.handle(&request, Some(frame)) {
+                            std::process::exit(0);
+                        }
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => std::process::exit(0),
+            }
+        }
+
+        let here = (frame.file().map(Path::to_path_buf), frame.line);
+        let moved = self.stopped_at.as_ref() != Some(&here);
+        let reason = match self.step {
+            Step::Entry => Some("entry"),
+            Step::Pause => Some("pause"),
+            Step::In if moved => Some("step"),
+            Step::Over(depth) if moved && frame.depth() <= depth => Some("step"),
+            Step::Out(depth) if frame.depth() < depth => Some("step"),
+            _ if self.last.as_ref() != Some(&here) && self.has_breakpoint(&here) => {
+                Some("breakpoint")
+            }
+            _ => None,
+        };
+        self.last = Some(here.clone());
+
+        if let Some(reason) = reason {
+            self.stop(reason, here, frame);
+        }
+    }
+
+    fn has_breakpoint(&self, (file, line): &Location) -> bool {
+        file.as_ref()
+            .and_then(|file| self.breakpoints.get(file))
+            .is_some_and(|lines| lines.contains(line))
+    }
+
+    fn stop(&mut self, reason: &str, here: Location, frame: &Frame<'_>) {
+        self.step = Step::Run;
+        self.stopped_at = Some(here);
+
+        let mut globals = frame.top_level();
+        globals.extend(
+            frame
+                .globals()
+                .into_iter()
+                .filter(|(name, _)| !globals.iter().any(|(seen, _)| seen == name))
+                .collect::<Vec<_>>(),
+        );
+        self.references = vec![frame.locals(), globals];
+
+        self.connection.borrow_mut().event(
+            "stopped",
+            object(&[
+                ("reason", json_string(reason)),
+                ("threadId", THREAD_ID.to_string()),
+                ("allThreadsStopped", "true".to_string()),
+            ]),
+        );
+
+        self.stopped = true;
+        while self.stopped {
+            let Some(request) = self.next_request() else {
+                std::process::exit(0);
+            };
+            match self.handle(&request, Some(frame)) {
+                Flow::Stay => {}
+                Flow::Resume => self.stopped = false,
+                Flow::Disconnect => std::process::exit(0),
+            }
+        }
+        self.references.clear();
+    }
+
+    /// Answers `request`. `frame` is where the script is while it runs or
+    /// is stopped, and `None` before it starts and after it ends.
+    fn handle(&mut self, request: &Request, frame: Option<&Frame<'_>>) -> Flow {
+        let stopped = self.stopped;
+        let connection = self.connection.clone();
+        let mut connection = connection.borrow_mut();
+
+        match request.command.as_str() {
+            "initialize" => {
+                connection.respond(
+                    request,
+                    object(&[
+                        ("supportsConfigurationDoneRequest", "true".to_string()),
+                        ("supportsEvaluateForHovers", "true".to_string()),
+                    ]),
+                );
+                connection.event("initialized", object(&[]));
+            }
+            "launch" => {
+                let Some(program) = request.argument("program").and_then(|p| p.as_str().ok())
+                else {
+                    connection.fail(request, "Missing 'program' to launch");
+                    return Flow::Stay;
+                };
+                self.program = Some(PathBuf::from(program));
+                if let Some(Json::Bool(true)) = request.argument("stopOnEntry") {
+                    self.step = Step::Entry;
+                }
+                connection.respond(request, object(&[]));
+            }
+            "configurationDone" => {
+                self.configured = true;
+                connection.respond(request, object(&[]));
+            }
+            "setBreakpoints" => {
+                let path = request
+                    .argument("source")
+                    .and_then(|source| source.get("path").ok())
+                    .and_then(|path| path.as_str().ok());
+                let Some(path) = path else {
+                    connection.fail(request, "Breakpoints need a source path");
+                    return Flow::Stay;
+                };
+
+                let lines: Vec<i32> = request
+                    .argument("breakpoints")
+                    .and_then(|breakpoints| breakpoints.as_array().ok())
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint.get("line").ok()?.as_number().ok())
+                    .map(|line| line as i32)
+                    .collect();
+                let path = Path::new(path);
+                let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                self.breakpoints
+                    .insert(path, lines.iter().copied().collect());
+
+                let breakpoints: Vec<String> = lines
+                    .iter()
+                    .map(|line| {
+                        object(&[("verified", "true".to_string()), ("line", line.to_string())])
+                    })
+                    .collect();
+                connection.respond(
+                    request,
+                    object(&[("breakpoints", format!("[{}]", breakpoints.join(",")))]),
+                );
+            }
+            "setExceptionBreakpoints" => connection.respond(request, object(&[])),
+            "threads" => {
+                let thread =
+                    object(&[("id", THREAD_ID.to_string()), ("name", json_string("main"))]);
+                connection.respond(request, object(&[("threads", format!("[{}]", thread))]));
+            }
+            "stackTrace" => {
+                let Some(frame) = frame.filter(|_| stopped) else {
+                    connection.fail(request, "The script is not stopped");
+                    return Flow::Stay;
+                };
+
+                let mut fields = vec![
+                    ("id", "1".to_string()),
+                    ("line", frame.line.to_string()),
+                    ("column", frame.column.to_string()),
+                ];
+                match frame.file() {
+                    Some(file) => {
+                        let name = file
+                            .file_name()
+                            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+                        fields.push(("name", json_string(&name)));
+                        fields.push((
+                            "source",
+                            object(&[
+                                ("name", json_string(&name)),
+                                ("path", json_string(&file.display().to_string())),
+                            ]),
+                        ));
+                    }
+                    None => fields.push(("name", json_string("script"))),
+                }
+                connection.respond(
+                    request,
+                    object(&[
+                        ("stackFrames", format!("[{}]", object(&fields))),
+                        ("totalFrames", "1".to_string()),
+                    ]),
+                );
+            }
+            "scopes" => {
+                if !stopped {
+                    connection.fail(request, "The script is not stopped");
+                    return Flow::Stay;
+                }
+
+                let scope = |name: &str, reference: usize| {
+                    object(&[
+                        ("name", json_string(name)),
+                        ("variablesReference", reference.to_string()),
+                        ("expensive", "false".to_string()),
+                    ])
+                };
+                connection.respond(
+                    request,
+                    object(&[(
+                        "scopes",
+                        format!("[{},{}]", scope("Locals", 1), scope("Globals", 2)),
+                    )]),
+                );
+            }
+            "variables" => {
+                let reference = request
+                    .argument("variablesReference")
+                    .and_then(|reference| reference.as_number().ok())
+                    .unwrap_or(0.0) as usize;
+                let Some(variables) = reference
+                    .checked_sub(1)
+                    .and_then(|index| self.references.get(index))
+                    .filter(|_| stopped)
+                    .cloned()
+                else {
+                    connection.fail(request, "No such variables");
+                    return Flow::Stay;
+                };
+
+                let variables: Vec<String> = variables
+                    .into_iter()
+                    .map(|(name, value)| {
+                        object(&[
+                            ("name", json_string(&name)),
+                            ("value", json_string(&display(&value))),
+                            ("type", json_string(value.type_name())),
+                            ("variablesReference", self.reference(&value).to_string()),
+                        ])
+                    })
+                    .collect();
+                connection.respond(
+                    request,
+                    object(&[("variables", format!("[{}]", variables.join(",")))]),
+                );
+            }
+            "evaluate" => {
+                let expression = request
+                    .argument("expression")
+                    .and_then(|expression| expression.as_str().ok())
+                    .unwrap_or_default()
+                    .trim();
+                let value = frame
+                    .filter(|_| stopped)
+                    .and_then(|frame| frame.lookup(expression));
+                let Some(value) = value else {
+                    connection.fail(
+                        request,
+                        &format!(
+                            "Cannot evaluate '{}': only variables in scope can be shown",
+                            expression
+                        ),
+                    );
+                    return Flow::Stay;
+                };
+
+                connection.respond(
+                    request,
+                    object(&[
+                        ("result", json_string(&display(&value))),
+                        ("type", json_string(value.type_name())),
+                        ("variablesReference", self.reference(&value).to_string()),
+                    ]),
+                );
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                let Some(frame) = frame.filter(|_| stopped) else {
+                    connection.fail(request, "The script is not stopped");
+                    return Flow::Stay;
+                };
+
+                self.step = match request.command.as_str() {
+                    "next" => Step::Over(frame.depth()),
+                    "stepIn" => Step::In,
+                    "stepOut" => Step::Out(frame.depth()),
+                    _ => Step::Run,
+                };
+                let body = match request.command.as_str() {
+                    "continue" => object(&[("allThreadsContinued", "true".to_string())]),
+                    _ => object(&[]),
+                };
+                connection.respond(request, body);
+                return Flow::Resume;
+            }
+            "pause" => {
+                if !stopped && frame.is_some() {
+                    self.step = Step::Pause;
+                }
+                connection.respond(request, object(&[]));
+            }
+            "disconnect" | "terminate" => {
+                connection.respond(request, object(&[]));
+                return Flow::Disconnect;
+            }
+            command => connection.fail(request, &format!("Unsupported request '{}'", command)),
+        }
+
+        Flow::Stay
+    }
+
+    /// The `variablesReference` that expands `value`, or 0 when it has no
+    /// parts to show.
+    fn reference(&mut self, value: &Value) -> usize {
+        let children: Vec<(String, Value)> = match value {
+            Value::List(items) | Value::Tuple(items) if !items.is_empty() => items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| (index.to_string(), item.clone()))
+                .collect(),
+            Value::Module(module) if !module.exports.is_empty() => {
+                let mut exports: Vec<(String, Value)> = module
+                    .exports
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                exports.sort_by(|a, b| a.0.cmp(&b.0));
+                exports
+            }
+            _ => return 0,
+        };
+
+        self.references.push(children);
+        self.references.len()
+    }
+}
+
+/// A value as the editor shows it. Strings are quoted to tell them apart
+/// from the numbers and names they may hold.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        value => value.to_string(),
+    }
+}
+
+/// The hooks that let a `Session` stop the script.
+struct Debugger(Rc<RefCell<Session>>);
+
+impl InterpreterHooks for Debugger {
+    fn inspect(&mut self, stmt: &Stmt, frame: &Frame<'_>) {
+        self.0.borrow_mut().at_statement(stmt, frame);
+    }
+}
@@ -0,0 +1,263 @@
+//! Calendar arithmetic for the date and time natives. Timestamps are seconds
+//! since the Unix epoch and are always read and written in UTC.
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// A timestamp split into its calendar fields.
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    /// Days since January 1st, counting from zero.
+    yday: u32,
+    /// Days since Sunday.
+    weekday: u32,
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: i64) -> DateTime {
+        let days = timestamp.div_euclid(SECONDS_PER_DAY);
+        let seconds = timestamp.rem_euclid(SECONDS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(days);
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            yday: (days - days_from_civil(year, 1, 1)) as u32,
+            // 1970-01-01 was a Thursday.
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+}
+
+/// Days since 1970-01-01 for a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Renders `timestamp` with strftime-style directives: `%Y`, `%y`, `%m`,
+/// `%d`, `%H`, `%M`, `%S`, `%j`, `%a`, `%A`, `%b`, `%B`, `%s`, the
+/// shorthands `%F` (`%Y-%m-%d`) and `%T` (`%H:%M:%S`), and `%%`.
+pub fn format_timestamp(timestamp: f64, format: &str) -> Result<String, String> {
+    let whole = timestamp.floor() as i64;
+    let time = DateTime::from_timestamp(whole);
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('Y') => out.push_str(&time.year.to_string()),
+            Some('y') => out.push_str(&format!("{:02}", time.year.rem_euclid(100))),
+            Some('m') => out.push_str(&format!("{:02}", time.month)),
+            Some('d') => out.push_str(&format!("{:02}", time.day)),
+            Some('H') => out.push_str(&format!("{:02}", time.hour)),
+            Some('M') => out.push_str(&format!("{:02}", time.minute)),
+            Some('S') => out.push_str(&format!("{:02}", time.second)),
+            Some('j') => out.push_str(&format!("{:03}", time.yday + 1)),
+            Some('a') => out.push_str(&WEEKDAYS[time.weekday as usize][..3]),
+            Some('A') => out.push_str(WEEKDAYS[time.weekday as usize]),
+            Some('b') => out.push_str(&MONTHS[time.month as usize - 1][..3]),
+            Some('B') => out.push_str(MONTHS[time.month as usize - 1]),
+            Some('s') => out.push_str(&whole.to_string()),
+            Some('F') => out.push_str(&format!("{}-{:02}-{:02}", time.year, time.month, time.day)),
+            Some('T') => out.push_str(&format!(
+                "{:02}:{:02}:{:02}",
+                time.hour, time.minute, time.second
+            )),
+            Some('%') => out.push('%'),
+            Some(other) => return Err(format!("unknown directive '%{}'", other)),
+            None => return Err("the format ends with a lone '%'".to_string()),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Reads a timestamp written in `format`, which uses the same directives as
+/// `format_timestamp` except for the weekday names. Fields the format leaves
+/// out default to 1970-01-01 00:00:00. Returns `Ok(None)` when `text` does
+/// not match the format, and an error when the format itself is invalid.
+pub fn parse_timestamp(text: &str, format: &str) -> Result<Option<f64>, String> {
+    let format = format.replace("%F", "%Y-%m-%d").replace("%T", "%H:%M:%S");
+    let mut input = text;
+
+    let (mut year, mut month, mut day) = (1970, 1, 1);
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    let mut yday = None;
+
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            match input.strip_prefix(c) {
+                Some(rest) => input = rest,
+                None => return Ok(None),
+            }
+            continue;
+        }
+
+        let directive = chars
+            .next()
+            .ok_or_else(|| "the format ends with a lone '%'".to_string())?;
+        let field = match directive {
+            'Y' => take_number(&mut input, 4, true),
+            'y' => {
+                take_number(&mut input, 2, false).map(|y| if y < 69 { 2000 + y } else { 1900 + y })
+            }
+            'm' | 'd' | 'H' | 'M' | 'S' => take_number(&mut input, 2, false),
+            'j' => take_number(&mut input, 3, false),
+            's' => {
+                let Some(seconds) = take_number(&mut input, 20, true) else {
+                    return Ok(None);
+                };
+                let time = DateTime::from_timestamp(seconds);
+                (year, month, day) = (time.year, time.month, time.day);
+                (hour, minute, second) = (time.hour, time.minute, time.second);
+                continue;
+            }
+            'b' | 'B' => take_month(&mut input, directive == 'B'),
+            '%' => input.strip_prefix('%').map(|rest| {
+                input = rest;
+                0
+            }),
+            other => return Err(format!("unknown directive '%{}'", other)),
+        };
+
+        let Some(value) = field else {
+            return Ok(None);
+        };
+        match directive {
+            'Y' | 'y' => year = value,
+            'm' | 'b' | 'B' => month = value as u32,
+            'd' => day = value as u32,
+            'H' => hour = value as u32,
+            'M' => minute = value as u32,
+            'S' => second = value as u32,
+            'j' => yday = Some(value),
+            _ => {}
+        }
+    }
+
+    if !input.is_empty() || !(1..=12).contains(&month) || hour > 23 || minute > 59 || second > 60 {
+        return Ok(None);
+    }
+    if day < 1 || day > days_in_month(year, month) {
+        return Ok(None);
+    }
+
+    let days = match yday {
+        Some(yday) if (1..=366).contains(&yday) => days_from_civil(year, 1, 1) + yday - 1,
+        Some(_) => return Ok(None),
+        None => days_from_civil(year, month, day),
+    };
+    let seconds = hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+
+    Ok(Some((days * SECONDS_PER_DAY + seconds) as f64))
+}
+
+/// Consumes up to `max` digits, with a leading minus sign when `signed`.
+fn take_number(input: &mut &str, max: usize, signed: bool) -> Option<i64> {
+    let sign = if signed && input.starts_with('-') {
+        1
+    } else {
+        0
+    };
+    let digits = input[sign..]
+        .bytes()
+        .take(max)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    if digits == 0 {
+        return None;
+    }
+
+    let (number, rest) = input.split_at(sign + digits);
+    *input = rest;
+    number.parse().ok()
+}
+
+/// Consumes a month name, abbreviated to three letters unless `full`.
+fn take_month(input: &mut &str, full: bool) -> Option<i64> {
+    MONTHS.iter().enumerate().find_map(|(index, name)| {
+        let name = if full { name } else { &name[..3] };
+        let head = input.get(..name.len())?;
+        if !head.eq_ignore_ascii_case(name) {
+            return None;
+        }
+
+        *input = &input[name.len()..];
+        Some(index as i64 + 1)
+    })
+}
@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use crate::{error::LogLevel, token::Span};
+
+pub const E_UNEXPECTED_CHARACTER: &str = "E1001";
+pub const E_UNTERMINATED_STRING: &str = "E1002";
+pub const E_UNTERMINATED_COMMENT: &str = "E1003";
+pub const E_INTEGER_TOO_LARGE: &str = "E1004";
+pub const E_INVALID_NUMBER: &str = "E1005";
+pub const E_PARSE: &str = "E2001";
+pub const E_RESOLVE: &str = "E2002";
+pub const E_TYPE: &str = "E2003";
+pub const W_UNREACHABLE_CODE: &str = "W2101";
+pub const W_CONSTANT_CONDITION: &str = "W2102";
+pub const W_UNUSED_VARIABLE: &str = "W2103";
+pub const W_SHADOWED_VARIABLE: &str = "W2104";
+pub const W_REDECLARED_VARIABLE: &str = "W2105";
+pub const W_UNUSED_EXPRESSION: &str = "W2106";
+pub const E_RUNTIME: &str = "E3001";
+pub const E_UNCAUGHT_EXCEPTION: &str = "E3002";
+pub const E_LIMIT_EXCEEDED: &str = "E3003";
+
+/// A single message about the program, with as much location information as
+/// the reporter has available.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: LogLevel,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<i32>,
+    pub column: Option<i32>,
+    pub span: Option<Span>,
+    /// Other places in the source the message is about, such as an earlier
+    /// declaration that conflicts with this one.
+    pub related: Vec<Related>,
+}
+
+/// A secondary location of a `Diagnostic`.
+#[derive(Debug, Clone)]
+pub struct Related {
+    pub message: String,
+    pub line: i32,
+    pub column: i32,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(severity: LogLevel, message: String) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code: None,
+            message,
+            file: None,
+            line: None,
+            column: None,
+            span: None,
+            related: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            code: Some(code),
+            ..Diagnostic::new(LogLevel::Error, message)
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            code: Some(code),
+            ..Diagnostic::new(LogLevel::Warning, message)
+        }
+    }
+
+    pub fn at(mut self, line: i32, column: i32, span: Span) -> Diagnostic {
+        self.line = Some(line);
+        self.column = Some(column);
+        self.span = Some(span);
+        self
+    }
+
+    pub fn related(mut self, message: String, line: i32, column: i32, span: Span) -> Diagnostic {
+        self.related.push(Related {
+            message,
+            line,
+            column,
+            span,
+        });
+        self
+    }
+
+    pub fn in_file(mut self, file: Option<String>) -> Diagnostic {
+        self.file = file;
+        self
+    }
+
+    /// Renders the diagnostic as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(json, "{{\"severity\":\"{}\"", self.severity.name());
+        let _ = write!(
+            json,
+            ",\"code\":{}",
+            json_option(self.code.map(json_string))
+        );
+        let _ = write!(json, ",\"message\":{}", json_string(&self.message));
+        let _ = write!(
+            json,
+            ",\"file\":{}",
+            json_option(self.file.as_deref().map(json_string))
+        );
+        let _ = write!(
+            json,
+            ",\"line\":{}",
+            json_option(self.line.map(|l| l.to_string()))
+        );
+        let _ = write!(
+            json,
+            ",\"column\":{}",
+            json_option(self.column.map(|c| c.to_string()))
+        );
+        let _ = write!(json, ",\"span\":{}", json_option(self.span.map(span_json)));
+        let related: Vec<String> = self
+            .related
+            .iter()
+            .map(|related| {
+                format!(
+                    "{{\"message\":{},\"line\":{},\"column\":{},\"span\":{}}}",
+                    json_string(&related.message),
+                    related.line,
+                    related.column,
+                    span_json(related.span)
+                )
+            })
+            .collect();
+        let _ = write!(json, ",\"related\":[{}]}}", related.join(","));
+
+        json
+    }
+}
+
+fn span_json(span: Span) -> String {
+    format!("{{\"start\":{},\"end\":{}}}", span.start, span.end)
+}
+
+fn json_option(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,301 @@
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Checksum and encoding natives. Strings are hashed and encoded as their
+/// UTF-8 bytes, and decoding fails if the bytes are not valid UTF-8.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "sha256",
+            arity: 1..=1,
+            func: sha256_native,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "md5",
+            arity: 1..=1,
+            func: md5_native,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "base64_encode",
+            arity: 1..=1,
+            func: base64_encode,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "base64_decode",
+            arity: 1..=1,
+            func: base64_decode,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "hex_encode",
+            arity: 1..=1,
+            func: hex_encode,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "hex_decode",
+            arity: 1..=1,
+            func: hex_decode,
+            deterministic: true,
+        },
+    ]
+}
+
+fn decoded_string(name: &str, bytes: Vec<u8>) -> Result<Value, RuntimeError> {
+    String::from_utf8(bytes).map(string_value).map_err(|_| {
+        RuntimeError::Generic(format!("{}() decoded bytes that are not valid UTF-8", name))
+    })
+}
+
+/// `sha256(s)` as 64 lowercase hex digits.
+fn sha256_native(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("sha256", &args, 0)?;
+
+    Ok(string_value(to_hex(&sha256(s.as_bytes()))))
+}
+
+/// `md5(s)` as 32 lowercase hex digits. Only suitable as a checksum.
+fn md5_native(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("md5", &args, 0)?;
+
+    Ok(string_value(to_hex(&md5(s.as_bytes()))))
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `base64_encode(s)` with the standard alphabet and `=` padding.
+fn base64_encode(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("base64_encode", &args, 0)?;
+
+    let mut out = String::new();
+    for chunk in s.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0x3f;
+                out.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    Ok(string_value(out))
+}
+
+/// `base64_decode(s)` accepts the standard and URL-safe alphabets, with or
+/// without padding. Whitespace is ignored.
+fn base64_decode(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("base64_decode", &args, 0)?;
+    let invalid = || RuntimeError::Generic("base64_decode() got invalid base64".to_string());
+
+    let trimmed = s.trim_end_matches(|c: char| c == '=' || c.is_whitespace());
+    let mut bytes = Vec::new();
+    let mut group = 0u32;
+    let mut bits = 0;
+    for c in trimmed.chars().filter(|c| !c.is_whitespace()) {
+        let sextet = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            _ => return Err(invalid()),
+        };
+
+        group = (group << 6) | sextet;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+
+    // Six leftover bits cannot come from a whole byte.
+    if bits >= 6 {
+        return Err(invalid());
+    }
+
+    decoded_string("base64_decode", bytes)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// `hex_encode(s)` as lowercase hex digits.
+fn hex_encode(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("hex_encode", &args, 0)?;
+
+    Ok(string_value(to_hex(s.as_bytes())))
+}
+
+/// `hex_decode(s)` accepts upper and lower case digits.
+fn hex_decode(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("hex_decode", &args, 0)?;
+    let invalid = || RuntimeError::Generic("hex_decode() got invalid hex".to_string());
+
+    if s.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    let bytes = s
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<u8>, RuntimeError>>()?;
+
+    decoded_string("hex_decode", bytes)
+}
+
+/// Appends the padding both MD5 and SHA-256 use: a 1 bit, zeros up to 56
+/// bytes into the last block, and the message length in bits.
+fn pad(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    if big_endian {
+        message.extend_from_slice(&bit_len.to_be_bytes());
+    } else {
+        message.extend_from_slice(&bit_len.to_le_bytes());
+    }
+    message
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    for block in pad(data, true).chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (chunk, word) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+fn md5(data: &[u8]) -> [u8; 16] {
+    // The sines of 1 to 64, scaled to 32 bits, as the algorithm specifies.
+    let constants: Vec<u32> = (1..=64)
+        .map(|i| ((i as f64).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    for block in pad(data, false).chunks(64) {
+        let mut m = [0u32; 16];
+        for (i, word) in block.chunks(4).enumerate() {
+            m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(m[g])
+                .rotate_left(MD5_SHIFTS[i]);
+
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (chunk, word) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
@@ -1,21 +1,30 @@
-use std::{
-    collections::HashMap,
-    ops::{Deref, DerefMut},
-};
+use std::collections::{HashMap, HashSet};
 
-use crate::interpreter::Object;
+use crate::{expression::Local, value::Value};
 
+/// One scope of bindings and the scopes around it. The interpreter keeps
+/// the natives in a separate global `Env` that is consulted after the
+/// whole chain, so it can be shared by every script and module.
 #[derive(Clone)]
 pub struct Env {
     enclosing: Box<Option<Env>>,
-    values: HashMap<String, Object>,
+    values: HashMap<String, Value>,
+    constants: HashSet<String>,
+    /// Locals the resolver gave a slot, by slot. A slot is empty until its
+    /// declaration runs.
+    slots: Vec<Option<Value>>,
+    /// The names of the locals in `slots`, by slot, and whether each is a
+    /// constant. Only recorded while hooks are installed, for debuggers to
+    /// show, or once code that calls `eval` has run, for it to resolve
+    /// names against.
+    slot_names: Vec<Option<(String, bool)>>,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone)]
 pub enum EnvError {
-    VarNotFound(String),
-    VarDefine(String),
     VarAssign(String),
+    ConstAssign(String),
 }
 
 impl Env {
@@ -23,43 +32,188 @@ impl Env {
         Env {
             enclosing: Box::new(enclosing),
             values: HashMap::new(),
+            constants: HashSet::new(),
+            slots: Vec::new(),
+            slot_names: Vec::new(),
         }
     }
 
-    pub fn define(&mut self, ident: String, value: Object) -> Result<(), EnvError> {
+    pub fn define(&mut self, ident: String, value: Value) -> Result<(), EnvError> {
+        if self.constants.contains(&ident) {
+            return Err(EnvError::ConstAssign(format!(
+                "Cannot redeclare constant '{}'",
+                ident
+            )));
+        }
+
         self.values.insert(ident, value);
         Ok(())
     }
 
-    pub fn assign(&mut self, ident: String, value: Object) -> Result<(), EnvError> {
-        if self.values.contains_key(&ident[..]) {
-            self.define(ident, value).unwrap();
-        } else {
-            match self.enclosing.as_mut() {
-                Some(env) => {
-                    env.define(ident, value).unwrap();
-                }
-                None => return Err(EnvError::VarAssign(format!("Undefined variable {ident}."))),
+    /// Defines a binding that can never be assigned to or redeclared.
+    pub fn define_const(&mut self, ident: String, value: Value) -> Result<(), EnvError> {
+        self.define(ident.clone(), value)?;
+        self.constants.insert(ident);
+        Ok(())
+    }
+
+    /// Assigns to the innermost binding of `ident`, searching outwards
+    /// through every enclosing scope.
+    pub fn assign(&mut self, ident: &str, value: Value) -> Result<(), EnvError> {
+        if let Some(slot) = self.values.get_mut(ident) {
+            if self.constants.contains(ident) {
+                return Err(EnvError::ConstAssign(format!(
+                    "Cannot assign to constant '{}'",
+                    ident
+                )));
             }
+
+            *slot = value;
+            return Ok(());
         }
 
-        Ok(())
+        match self.enclosing.as_mut() {
+            Some(env) => env.assign(ident, value),
+            None => Err(EnvError::VarAssign(format!(
+                "Undefined variable '{}'",
+                ident
+            ))),
+        }
     }
 
-    pub fn get(&self, ident: String) -> Result<Object, EnvError> {
-        match self.values.get(&ident[..]) {
-            Some(val) => Ok(val.clone()),
-            None => match *self.enclosing.clone() {
-                Some(env) => env.get(ident),
-                None => Err(EnvError::VarNotFound(format!(
-                    "Cannot find the variable '{}' in the scope",
-                    ident
-                ))),
-            },
+    pub fn get(&self, ident: &str) -> Option<Value> {
+        match self.values.get(ident) {
+            Some(val) => Some(val.clone()),
+            None => self.enclosing.as_ref().as_ref()?.get(ident),
+        }
+    }
+
+    /// Whether this scope or any enclosing one binds `ident` by name.
+    pub fn defines(&self, ident: &str) -> bool {
+        self.values.contains_key(ident)
+            || self
+                .enclosing
+                .as_ref()
+                .as_ref()
+                .is_some_and(|env| env.defines(ident))
+    }
+
+    /// Defines the local in `slot` of this scope. Constness is checked by
+    /// the resolver, so locals need no bookkeeping for it here.
+    pub fn define_at(&mut self, slot: usize, value: Value) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(value);
+    }
+
+    /// Records `ident` as the name of the local in `slot`.
+    pub fn name_slot(&mut self, slot: usize, ident: &str, constant: bool) {
+        if slot >= self.slot_names.len() {
+            self.slot_names.resize(slot + 1, None);
+        }
+        self.slot_names[slot] = Some((ident.to_string(), constant));
+    }
+
+    /// The locals of this scope whose names are known, with their slots and
+    /// whether each is a constant.
+    pub fn named_slots(&self) -> Vec<(String, usize, bool)> {
+        self.slot_names
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, name)| {
+                let (name, constant) = name.as_ref()?;
+                Some((name.clone(), slot, *constant))
+            })
+            .collect()
+    }
+
+    pub fn get_at(&self, local: Local) -> Option<Value> {
+        self.ancestor(local.depth)?.slots.get(local.slot)?.clone()
+    }
+
+    /// Assigns to the local at `local`, returning false if its declaration
+    /// has not run yet.
+    pub fn assign_at(&mut self, local: Local, value: Value) -> bool {
+        match self
+            .ancestor_mut(local.depth)
+            .and_then(|env| env.slots.get_mut(local.slot))
+        {
+            Some(Some(slot)) => {
+                *slot = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ancestor(&self, depth: usize) -> Option<&Env> {
+        let mut env = self;
+        for _ in 0..depth {
+            env = env.enclosing.as_ref().as_ref()?;
+        }
+        Some(env)
+    }
+
+    fn ancestor_mut(&mut self, depth: usize) -> Option<&mut Env> {
+        let mut env = self;
+        for _ in 0..depth {
+            env = env.enclosing.as_mut().as_mut()?;
+        }
+        Some(env)
+    }
+
+    pub fn bindings(&self) -> HashMap<String, Value> {
+        self.values.clone()
+    }
+
+    /// The bindings of this scope alone, sorted by name. Locals in slots
+    /// are included once their declaration has run and their name is known.
+    pub fn scope_bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for (name, value) in self.slot_names.iter().zip(self.slots.iter()) {
+            if let (Some((name, _)), Some(value)) = (name, value) {
+                bindings.push((name.clone(), value.clone()));
+            }
+        }
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    pub fn enclosing(&self) -> Option<&Env> {
+        self.enclosing.as_ref().as_ref()
+    }
+
+    pub fn is_const(&self, ident: &str) -> bool {
+        self.constants.contains(ident)
+    }
+
+    /// Binds `ident` even if it is already a constant, for restoring saved
+    /// state over the current one.
+    pub fn replace(&mut self, ident: String, value: Value, constant: bool) {
+        if constant {
+            self.constants.insert(ident.clone());
+        } else {
+            self.constants.remove(&ident);
+        }
+        self.values.insert(ident, value);
+    }
+
+    /// Every name visible from this scope that is bound by name, innermost
+    /// first. Locals held in slots have no names at runtime and are left out.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        if let Some(env) = self.enclosing.as_ref() {
+            names.extend(env.names());
         }
+        names
     }
 
-    pub fn get_enclosing(&self) -> Option<Env> {
-        *self.enclosing.clone()
+    pub fn into_enclosing(self) -> Option<Env> {
+        *self.enclosing
     }
 }
@@ -1,17 +1,320 @@
-#[derive(Clone, Copy)]
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    fs::File,
+    io::{stderr, IsTerminal, LineWriter, Write},
+    path::Path,
+    rc::Rc,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    ast_json::object,
+    datetime::format_timestamp,
+    diagnostic::{json_string, Diagnostic},
+    interpreter::RuntimeError,
+    parser::ParseError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     Debug,
+    Info,
     Warning,
     Error,
+    /// Above every severity, so a logger set to it reports nothing.
+    Off,
+}
+
+impl LogLevel {
+    /// The name a diagnostic at this level is reported under. Nothing is
+    /// reported at `Off`, so it reads as an error.
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Error | LogLevel::Off => "error",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warning),
+            "error" => Ok(LogLevel::Error),
+            "off" => Ok(LogLevel::Off),
+            _ => Err(format!("Unknown log level '{}'", s)),
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format '{}'", s)),
+        }
+    }
+}
+
+/// Writes diagnostics at or above `level` to stderr, and copies them to
+/// `file` if one is set. Clones share the file and the count of problems.
+#[derive(Clone)]
 pub struct Log {
     pub level: LogLevel,
+    pub color: bool,
+    pub format: ErrorFormat,
+    pub file: Option<Rc<LogFile>>,
+    /// How many warnings and errors were reported, shown or not.
+    pub(crate) problems: Rc<Cell<usize>>,
 }
 
 impl Log {
+    /// Creates a logger that colors its output only when stderr is a
+    /// terminal and `NO_COLOR` is not set.
+    pub fn new(level: LogLevel) -> Log {
+        let color = stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+
+        Log {
+            level,
+            color,
+            format: ErrorFormat::Text,
+            file: None,
+            problems: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Also writes every diagnostic reported from now on to a new file at
+    /// `path`, and the script's `print` output too if `prints` is set.
+    pub fn log_to_file<P: AsRef<Path>>(&mut self, path: P, prints: bool) -> std::io::Result<()> {
+        self.file = Some(Rc::new(LogFile::create(path, prints)?));
+        Ok(())
+    }
+
+    /// Reports a diagnostic on stderr if its severity is enabled. Warnings
+    /// and errors are counted either way.
+    pub fn report(&self, diagnostic: &Diagnostic) {
+        if diagnostic.severity >= LogLevel::Warning {
+            self.problems.set(self.problems.get() + 1);
+        }
+        if diagnostic.severity >= self.level {
+            eprintln!("{}", self.render(diagnostic));
+            self.record(diagnostic);
+        }
+    }
+
+    /// How many warnings and errors this logger and its clones have been
+    /// given, including those `level` kept from being shown.
+    pub fn problems(&self) -> usize {
+        self.problems.get()
+    }
+
+    /// Copies a diagnostic to the log file, for reporters that write it
+    /// somewhere other than stderr themselves.
+    pub fn record(&self, diagnostic: &Diagnostic) {
+        if let Some(file) = &self.file {
+            file.diagnostic(diagnostic);
+        }
+    }
+
+    /// Whether `print` output is copied to a log file.
+    pub fn logs_prints(&self) -> bool {
+        self.file.as_ref().is_some_and(|file| file.prints)
+    }
+
+    /// Copies a line of `print` output to the log file, if it takes them.
+    pub fn record_print(&self, file: Option<&str>, line: i32, text: &str) {
+        if let Some(log_file) = self.file.as_ref().filter(|log_file| log_file.prints) {
+            log_file.entry(&[
+                ("level", json_string("print")),
+                ("file", file.map_or("null".to_string(), json_string)),
+                ("line", line.to_string()),
+                ("message", json_string(text)),
+            ]);
+        }
+    }
+
+    /// Formats a diagnostic as colored text or JSON, depending on `format`.
+    /// Text starts with `file:line:column: ` for as much of the location as
+    /// the diagnostic has.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        match self.format {
+            ErrorFormat::Text => {
+                let message = match location(diagnostic) {
+                    Some(location) => format!("{}: {}", location, diagnostic.message),
+                    None => diagnostic.message.clone(),
+                };
+                self.format(diagnostic.severity, &message)
+            }
+            ErrorFormat::Json => diagnostic.to_json(),
+        }
+    }
+
+    pub fn debug(&self, message: String) {
+        self.log(LogLevel::Debug, &message);
+    }
+
+    pub fn info(&self, message: String) {
+        self.log(LogLevel::Info, &message);
+    }
+
+    pub fn warn(&self, message: String) {
+        self.log(LogLevel::Warning, &message);
+    }
+
     pub fn error(&self, message: String) {
-        println!("\x1b[31m{}\x1b[0m", message);
+        self.log(LogLevel::Error, &message);
     }
+
+    fn log(&self, level: LogLevel, message: &str) {
+        self.report(&Diagnostic::new(level, message.to_string()));
+    }
+
+    fn format(&self, level: LogLevel, message: &str) -> String {
+        if !self.color {
+            return message.to_string();
+        }
+
+        let color = match level {
+            LogLevel::Debug => "2",
+            LogLevel::Info => "36",
+            LogLevel::Warning => "33",
+            LogLevel::Error | LogLevel::Off => "31",
+        };
+
+        format!("\x1b[{}m{}\x1b[0m", color, message)
+    }
+}
+
+/// Where `diagnostic` points, as `file:line:column` with the parts it does
+/// not know left out, or `None` when it has no line.
+fn location(diagnostic: &Diagnostic) -> Option<String> {
+    let line = diagnostic.line?;
+    let mut location = match &diagnostic.file {
+        Some(file) => format!("{}:{}", file, line),
+        None => line.to_string(),
+    };
+    if let Some(column) = diagnostic.column {
+        location.push_str(&format!(":{}", column));
+    }
+    Some(location)
 }
+
+/// A log file with one JSON object per line. Each entry has a
+/// `timestamp` in UTC, a `level` (a severity, or `print` for script
+/// output), the `file` and `line` it came from when known, and the
+/// `message`; diagnostics also carry their `code`. Lines are written out as
+/// they are logged, so the file is complete even if the process exits.
+pub struct LogFile {
+    out: RefCell<LineWriter<File>>,
+    /// Whether `print` output is logged as well as diagnostics.
+    pub prints: bool,
+}
+
+impl LogFile {
+    pub fn create<P: AsRef<Path>>(path: P, prints: bool) -> std::io::Result<LogFile> {
+        Ok(LogFile {
+            out: RefCell::new(LineWriter::new(File::create(path)?)),
+            prints,
+        })
+    }
+
+    fn diagnostic(&self, diagnostic: &Diagnostic) {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        self.entry(&[
+            ("level", json_string(diagnostic.severity.name())),
+            (
+                "file",
+                optional(diagnostic.file.as_deref().map(json_string)),
+            ),
+            (
+                "line",
+                optional(diagnostic.line.map(|line| line.to_string())),
+            ),
+            ("code", optional(diagnostic.code.map(json_string))),
+            ("message", json_string(&diagnostic.message)),
+        ]);
+    }
+
+    // Logging must not fail the program it is watching, so write errors
+    // are ignored.
+    fn entry(&self, fields: &[(&str, String)]) {
+        let mut entry = vec![("timestamp", json_string(&timestamp()))];
+        entry.extend_from_slice(fields);
+        let _ = writeln!(self.out.borrow_mut(), "{}", object(&entry));
+    }
+}
+
+/// The current time as `2024-01-31T09:30:00.250Z`.
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = format_timestamp(now.as_secs() as f64, "%FT%T").unwrap_or_default();
+    format!("{}.{:03}Z", seconds, now.subsec_millis())
+}
+
+#[derive(Debug, Clone)]
+pub enum RoxError {
+    Io(String),
+    /// Every lexical error in the source, in order.
+    Scan(Vec<Diagnostic>),
+    Parse(Vec<ParseError>),
+    Runtime(RuntimeError),
+    LimitExceeded(String),
+}
+
+impl From<RuntimeError> for RoxError {
+    fn from(err: RuntimeError) -> Self {
+        match err {
+            RuntimeError::LimitExceeded(message) => RoxError::LimitExceeded(message),
+            err => RoxError::Runtime(err),
+        }
+    }
+}
+
+impl Display for RoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoxError::Io(message) => write!(f, "{}", message),
+            RoxError::Scan(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", err.message)?;
+                }
+                Ok(())
+            }
+            RoxError::Parse(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", err)?;
+                }
+                Ok(())
+            }
+            RoxError::Runtime(err) => write!(f, "{}", err),
+            RoxError::LimitExceeded(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RoxError {}
@@ -1,41 +1,126 @@
-use std::fmt::Display;
-
 use crate::{
-    interpreter::Object,
-    token::{self, Literal, Token},
+    arena::{Arena, ExprId},
+    token::{Literal, Span, Token},
+    visitor::{ExprVisitor, ExprVisitorMut},
 };
 
+/// Where the resolver found a local variable: `depth` scopes out from the
+/// one the reference is in, at index `slot` of that scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Local {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+/// An expression. Nested expressions live in the program's `Arena` and
+/// are linked by `ExprId`; argument and item lists are kept inline.
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Unary(Token, Box<Expr>),
-    Binary(Box<Expr>, Token, Box<Expr>),
-    Logical(Box<Expr>, Token, Box<Expr>),
-    Grouping(Box<Expr>),
-    Literal(Literal),
-    Var(Token),
-    Assign(Token, Box<Expr>),
+    Unary(Token, ExprId),
+    Binary(ExprId, Token, ExprId),
+    Logical(ExprId, Token, ExprId),
+    Grouping(ExprId),
+    Literal(Token, Literal),
+    /// A variable read. The resolver fills in where it lives; `None` means
+    /// a top-level or native name, which is looked up by name.
+    Var(Token, Option<Local>),
+    Assign(Token, ExprId, Option<Local>),
+    Call(ExprId, Token, Vec<Expr>),
+    Get(ExprId, Token),
+    Tuple(Token, Vec<Expr>, Token),
+    /// `...xs` in a call's argument list.
+    Spread(Token, ExprId),
 }
 
-impl Display for Expr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Expr {
+    /// The first token in this expression.
+    pub fn first_token<'a>(&'a self, arena: &'a Arena) -> &'a Token {
+        match self {
+            Expr::Unary(op, _) => op,
+            Expr::Binary(left, _, _) => arena[*left].first_token(arena),
+            Expr::Logical(left, _, _) => arena[*left].first_token(arena),
+            Expr::Grouping(expr) => arena[*expr].first_token(arena),
+            Expr::Literal(token, _) => token,
+            Expr::Var(token, _) => token,
+            Expr::Assign(token, _, _) => token,
+            Expr::Call(callee, _, _) => arena[*callee].first_token(arena),
+            Expr::Get(object, _) => arena[*object].first_token(arena),
+            Expr::Tuple(paren, _, _) => paren,
+            Expr::Spread(op, _) => op,
+        }
+    }
+
+    /// The line of the first token in this expression.
+    pub fn line(&self, arena: &Arena) -> i32 {
+        self.first_token(arena).line
+    }
+
+    /// The source range covered by this expression's tokens.
+    pub fn span(&self, arena: &Arena) -> Span {
+        let span = |id: &ExprId| arena[*id].span(arena);
         match self {
+            Expr::Unary(op, right) => op.span.to(span(right)),
+            Expr::Binary(left, _, right) => span(left).to(span(right)),
+            Expr::Logical(left, _, right) => span(left).to(span(right)),
+            Expr::Grouping(expr) => span(expr),
+            Expr::Literal(token, _) => token.span,
+            Expr::Var(token, _) => token.span,
+            Expr::Assign(token, value, _) => token.span.to(span(value)),
+            Expr::Call(callee, paren, _) => span(callee).to(paren.span),
+            Expr::Get(object, name) => span(object).to(name.span),
+            Expr::Tuple(open, _, close) => open.span.to(close.span),
+            Expr::Spread(op, expr) => op.span.to(span(expr)),
+        }
+    }
+
+    /// Calls the method of `visitor` for this kind of expression, with the
+    /// children looked up in `arena`.
+    pub fn accept<R, V: ExprVisitor<R> + ?Sized>(&self, arena: &Arena, visitor: &mut V) -> R {
+        match self {
+            Expr::Unary(op, right) => visitor.visit_unary_expr(arena, op, &arena[*right]),
             Expr::Binary(left, op, right) => {
-                write!(f, "({} {} {})", op, left, right)
+                visitor.visit_binary_expr(arena, &arena[*left], op, &arena[*right])
             }
-            Expr::Unary(op, value) => {
-                write!(f, "({}{})", op, value)
+            Expr::Logical(left, op, right) => {
+                visitor.visit_logical_expr(arena, &arena[*left], op, &arena[*right])
             }
-            Expr::Grouping(expr) => {
-                write!(f, "(group {})", expr)
+            Expr::Grouping(inner) => visitor.visit_grouping_expr(arena, &arena[*inner]),
+            Expr::Literal(token, literal) => visitor.visit_literal_expr(arena, token, literal),
+            Expr::Var(name, local) => visitor.visit_var_expr(arena, name, *local),
+            Expr::Assign(name, value, local) => {
+                visitor.visit_assign_expr(arena, name, &arena[*value], *local)
             }
-            Expr::Literal(value) => {
-                write!(f, "{}", value)
+            Expr::Call(callee, paren, args) => {
+                visitor.visit_call_expr(arena, &arena[*callee], paren, args)
             }
-            Expr::Var(token) => write!(f, "(var {})", token.lexeme),
-            Expr::Assign(_token, value) => write!(f, "(= {})", value),
-            Expr::Logical(left, op, right) => {
-                write!(f, "({} {} {})", op, left, right)
+            Expr::Get(object, name) => visitor.visit_get_expr(arena, &arena[*object], name),
+            Expr::Tuple(open, items, close) => visitor.visit_tuple_expr(arena, open, items, close),
+            Expr::Spread(op, expr) => visitor.visit_spread_expr(arena, op, &arena[*expr]),
+        }
+    }
+
+    /// Like `accept`, for visitors that modify the tree. This expression
+    /// must not be in `arena` itself; `Arena::accept_expr_mut` visits one
+    /// that is.
+    pub fn accept_mut<R, V: ExprVisitorMut<R> + ?Sized>(
+        &mut self,
+        arena: &mut Arena,
+        visitor: &mut V,
+    ) -> R {
+        match self {
+            Expr::Unary(op, right) => visitor.visit_unary_expr(arena, op, *right),
+            Expr::Binary(left, op, right) => visitor.visit_binary_expr(arena, *left, op, *right),
+            Expr::Logical(left, op, right) => visitor.visit_logical_expr(arena, *left, op, *right),
+            Expr::Grouping(inner) => visitor.visit_grouping_expr(arena, *inner),
+            Expr::Literal(token, literal) => visitor.visit_literal_expr(arena, token, literal),
+            Expr::Var(name, local) => visitor.visit_var_expr(arena, name, local),
+            Expr::Assign(name, value, local) => {
+                visitor.visit_assign_expr(arena, name, *value, local)
             }
+            Expr::Call(callee, paren, args) => visitor.visit_call_expr(arena, *callee, paren, args),
+            Expr::Get(object, name) => visitor.visit_get_expr(arena, *object, name),
+            Expr::Tuple(open, items, close) => visitor.visit_tuple_expr(arena, open, items, close),
+            Expr::Spread(op, expr) => visitor.visit_spread_expr(arena, op, *expr),
         }
     }
 }
@@ -0,0 +1,168 @@
+//! A C API for hosts that cannot use the Rust one. Strings cross the
+//! boundary as NUL-terminated UTF-8.
+
+use std::{
+    any::Any,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{error::RoxError, interpreter::RuntimeError, Rox};
+
+/// An interpreter together with the text of its last result.
+pub struct RoxHandle {
+    rox: Rox,
+    result: CString,
+}
+
+/// The outcome of `rox_eval` and `rox_load_prelude`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoxStatus {
+    /// The result string holds the value of the final expression statement.
+    Ok = 0,
+    /// The source could not be read, scanned or parsed. The result string
+    /// holds the errors.
+    SyntaxError = 1,
+    /// The script raised an error or exceeded a limit, or its result
+    /// contains a NUL character and cannot be passed back. The result
+    /// string holds the message.
+    RuntimeError = 2,
+    /// The script called `exit`. The result string holds the exit code.
+    Exit = 3,
+    /// A null pointer or a source that is not valid UTF-8 was passed in.
+    InvalidArgument = 4,
+    /// The interpreter panicked. The result string holds the panic message.
+    /// The interpreter may be left in any state and should be freed.
+    InternalError = 5,
+}
+
+/// Creates an interpreter. Free it with `rox_free`.
+#[no_mangle]
+pub extern "C" fn rox_new() -> *mut RoxHandle {
+    Box::into_raw(Box::new(RoxHandle {
+        rox: Rox::new(),
+        result: CString::default(),
+    }))
+}
+
+/// Runs `source`. Globals persist between calls, as with `Rox::eval`.
+/// Output goes to the process's stdout and errors are also reported on
+/// stderr.
+///
+/// # Safety
+///
+/// `rox` must come from `rox_new` and not have been freed, and `source`
+/// must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rox_eval(rox: *mut RoxHandle, source: *const c_char) -> RoxStatus {
+    run(rox, source, |rox, source| {
+        rox.eval(source).map(|value| value.to_string())
+    })
+}
+
+/// Runs `source` in the global scope, so that what it declares is visible to
+/// every later `rox_eval` and to imported modules, as with
+/// `Rox::load_prelude`. On success the result string is empty.
+///
+/// # Safety
+///
+/// `rox` must come from `rox_new` and not have been freed, and `source`
+/// must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rox_load_prelude(rox: *mut RoxHandle, source: *const c_char) -> RoxStatus {
+    run(rox, source, |rox, source| {
+        rox.load_prelude(source).map(|_| String::new())
+    })
+}
+
+/// Checks the arguments shared by the entry points that run source, then
+/// runs it and stores the result text.
+unsafe fn run(
+    rox: *mut RoxHandle,
+    source: *const c_char,
+    f: impl FnOnce(&mut Rox, &str) -> Result<String, RoxError>,
+) -> RoxStatus {
+    let Some(handle) = rox.as_mut() else {
+        return RoxStatus::InvalidArgument;
+    };
+    if source.is_null() {
+        return RoxStatus::InvalidArgument;
+    }
+    let Ok(source) = CStr::from_ptr(source).to_str() else {
+        return RoxStatus::InvalidArgument;
+    };
+
+    // Unwinding across `extern "C"` would abort the host, so a panic is
+    // caught here and reported like any other failure.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(&mut handle.rox, source)));
+    let (status, text) = match result {
+        Err(payload) => (RoxStatus::InternalError, panic_message(payload)),
+        Ok(result) => status_and_text(result),
+    };
+
+    let (result, status) = match CString::new(text) {
+        Ok(text) => (text, status),
+        // `hex_decode("00")`, for one, makes a string holding NUL.
+        Err(_) => (
+            CString::new("The result contains a NUL character").unwrap(),
+            RoxStatus::RuntimeError,
+        ),
+    };
+    handle.result = result;
+    status
+}
+
+fn status_and_text(result: Result<String, RoxError>) -> (RoxStatus, String) {
+    match result {
+        Ok(text) => (RoxStatus::Ok, text),
+        Err(RoxError::Runtime(RuntimeError::Exit(code))) => (RoxStatus::Exit, code.to_string()),
+        Err(err @ (RoxError::Io(_) | RoxError::Scan(_) | RoxError::Parse(_))) => {
+            (RoxStatus::SyntaxError, err.to_string())
+        }
+        Err(err @ (RoxError::Runtime(_) | RoxError::LimitExceeded(_))) => {
+            (RoxStatus::RuntimeError, err.to_string())
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown cause".to_string(),
+        },
+    };
+    // The message must itself be a valid C string.
+    format!("The interpreter panicked: {}", message.replace('\0', ""))
+}
+
+/// The text of the last `rox_eval` or `rox_load_prelude` result, as
+/// described by its status, or an empty string before the first call. The
+/// pointer stays valid until the next of those calls or `rox_free` on the
+/// same interpreter.
+///
+/// # Safety
+///
+/// `rox` must come from `rox_new` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn rox_get_string_result(rox: *const RoxHandle) -> *const c_char {
+    match rox.as_ref() {
+        Some(handle) => handle.result.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Destroys an interpreter. Passing null does nothing.
+///
+/// # Safety
+///
+/// `rox` must come from `rox_new` and must not be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rox_free(rox: *mut RoxHandle) {
+    if !rox.is_null() {
+        drop(Box::from_raw(rox));
+    }
+}
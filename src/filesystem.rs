@@ -0,0 +1,185 @@
+use std::{fs, path::Path, rc::Rc};
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Filesystem natives and path helpers. The path helpers only look at the
+/// text of a path, so they work in sandbox mode.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "list_dir",
+            arity: 1..=1,
+            func: list_dir,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "mkdir",
+            arity: 1..=1,
+            func: mkdir,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "remove",
+            arity: 1..=1,
+            func: remove,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "copy",
+            arity: 2..=2,
+            func: copy,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "rename",
+            arity: 2..=2,
+            func: rename,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "is_dir",
+            arity: 1..=1,
+            func: is_dir,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "join_path",
+            arity: 1..=usize::MAX,
+            func: join_path,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "basename",
+            arity: 1..=1,
+            func: basename,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "dirname",
+            arity: 1..=1,
+            func: dirname,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "extension",
+            arity: 1..=1,
+            func: extension,
+            deterministic: true,
+        },
+    ]
+}
+
+fn fs_error(name: &str, path: &str, err: std::io::Error) -> RuntimeError {
+    RuntimeError::Generic(format!("{}() failed for '{}': {}", name, path, err))
+}
+
+fn optional_string(s: Option<&std::ffi::OsStr>) -> Value {
+    match s {
+        Some(s) => string_value(s.to_string_lossy().into_owned()),
+        None => Value::Nil,
+    }
+}
+
+/// `list_dir(path)` returns the names of the entries in a directory, sorted.
+fn list_dir(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("list_dir", &args, 0)?;
+    let entries = fs::read_dir(&*path).map_err(|err| fs_error("list_dir", &path, err))?;
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| fs_error("list_dir", &path, err))?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+
+    Ok(Value::List(Rc::new(
+        names.into_iter().map(string_value).collect(),
+    )))
+}
+
+/// `mkdir(path)` creates a directory along with any missing parents.
+fn mkdir(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("mkdir", &args, 0)?;
+    fs::create_dir_all(&*path).map_err(|err| fs_error("mkdir", &path, err))?;
+
+    Ok(Value::Nil)
+}
+
+/// `remove(path)` deletes a file or an empty directory. Directories with
+/// contents have to be emptied first, so a typo cannot wipe out a tree.
+fn remove(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("remove", &args, 0)?;
+    let metadata = fs::symlink_metadata(&*path).map_err(|err| fs_error("remove", &path, err))?;
+
+    if metadata.is_dir() {
+        fs::remove_dir(&*path)
+    } else {
+        fs::remove_file(&*path)
+    }
+    .map_err(|err| fs_error("remove", &path, err))?;
+
+    Ok(Value::Nil)
+}
+
+/// `copy(from, to)` copies a file and returns the number of bytes copied.
+fn copy(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let from = string_arg("copy", &args, 0)?;
+    let to = string_arg("copy", &args, 1)?;
+    let bytes = fs::copy(&*from, &*to).map_err(|err| fs_error("copy", &from, err))?;
+
+    Ok(Value::Int(bytes as i64))
+}
+
+/// `rename(from, to)` moves a file or directory, replacing a file at `to`.
+fn rename(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let from = string_arg("rename", &args, 0)?;
+    let to = string_arg("rename", &args, 1)?;
+    fs::rename(&*from, &*to).map_err(|err| fs_error("rename", &from, err))?;
+
+    Ok(Value::Nil)
+}
+
+/// `is_dir(path)` is false for missing paths as well as for files.
+fn is_dir(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("is_dir", &args, 0)?;
+
+    Ok(Value::Bool(Path::new(&*path).is_dir()))
+}
+
+/// `join_path("a", "b", "c.txt")`. An absolute part replaces everything
+/// before it.
+fn join_path(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut path = std::path::PathBuf::new();
+    for index in 0..args.len() {
+        path.push(&*string_arg("join_path", &args, index)?);
+    }
+
+    Ok(string_value(path.to_string_lossy().into_owned()))
+}
+
+/// `basename("a/b.txt")` is `"b.txt"`, or nil for paths such as `/`.
+fn basename(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("basename", &args, 0)?;
+
+    Ok(optional_string(Path::new(&*path).file_name()))
+}
+
+/// `dirname("a/b.txt")` is `"a"`, `""` for a bare name, and nil for `/`.
+fn dirname(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("dirname", &args, 0)?;
+
+    Ok(optional_string(
+        Path::new(&*path).parent().map(Path::as_os_str),
+    ))
+}
+
+/// `extension("a/b.tar.gz")` is `"gz"`, or nil when there is none.
+fn extension(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = string_arg("extension", &args, 0)?;
+
+    Ok(optional_string(Path::new(&*path).extension()))
+}
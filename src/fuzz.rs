@@ -0,0 +1,44 @@
+//! Entry points for coverage-guided fuzzing, driven by the targets under
+//! `fuzz/`. They take raw bytes so that every input a fuzzer produces is
+//! tried. Scanning and parsing must never panic, whatever those bytes are;
+//! the round trip panics exactly when it finds a mismatch.
+
+#[cfg(feature = "fuzzing")]
+use crate::roundtrip::{check_round_trip, random_ast};
+use crate::{
+    error::{Log, LogLevel},
+    highlight::{highlight, HighlightFormat},
+    scanner::{is_incomplete, Scanner},
+    Rox,
+};
+
+/// Scans `data` as source, along with the other tools built on the scanner.
+pub fn fuzz_scan(data: &[u8]) {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    Scanner::quiet(source.to_string()).scan_tokens();
+    is_incomplete(source);
+    highlight(source, HighlightFormat::Html);
+}
+
+/// Scans, parses, resolves and type checks `data` as source, without
+/// running it.
+pub fn fuzz_parse(data: &[u8]) {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut rox = Rox::with_logger(Log::new(LogLevel::Off));
+    let _ = rox.check(source);
+}
+
+/// Builds a random program from `data` and panics if printing it and
+/// parsing the result does not give the same program back.
+#[cfg(feature = "fuzzing")]
+pub fn fuzz_round_trip(data: &[u8]) {
+    if let Err(message) = check_round_trip(&random_ast(data)) {
+        panic!("{}", message);
+    }
+}
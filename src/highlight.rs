@@ -0,0 +1,97 @@
+use crate::{keywords::keyword, scanner::Scanner, token::TokenType};
+
+/// The markup `highlight` produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightFormat {
+    /// Terminal color escape codes.
+    Ansi,
+    /// A `<pre class="rox">` block of `<span>`s with classes such as
+    /// `rox-keyword`, `rox-string` and `rox-comment`, to be styled with CSS.
+    Html,
+}
+
+/// Renders `source` with its tokens colored. Whitespace, comments and any
+/// text the scanner cannot make sense of are kept exactly as written, so
+/// the output reads the same as the input.
+pub fn highlight(source: &str, format: HighlightFormat) -> String {
+    let mut scanner = Scanner::quiet(source.to_string());
+    scanner.scan_tokens();
+
+    let mut out = String::new();
+    if format == HighlightFormat::Html {
+        out.push_str("<pre class=\"rox\">");
+    }
+
+    let mut end = 0;
+    for token in &scanner.tokens {
+        let span = token.span;
+        if span.start < end || span.end > source.len() {
+            continue;
+        }
+
+        push_text(&mut out, &source[end..span.start], None, format);
+        push_text(
+            &mut out,
+            &source[span.start..span.end],
+            class(token.token_type, &token.lexeme),
+            format,
+        );
+        end = span.end;
+    }
+    push_text(&mut out, &source[end..], None, format);
+
+    if format == HighlightFormat::Html {
+        out.push_str("</pre>\n");
+    }
+    out
+}
+
+fn class(token_type: TokenType, lexeme: &str) -> Option<&'static str> {
+    match token_type {
+        TokenType::STRING => Some("string"),
+        TokenType::NUMBER => Some("number"),
+        TokenType::COMMENT | TokenType::DOC_COMMENT => Some("comment"),
+        TokenType::TRUE | TokenType::FALSE | TokenType::NIL => Some("constant"),
+        _ if keyword(lexeme).is_some() => Some("keyword"),
+        _ => None,
+    }
+}
+
+fn push_text(out: &mut String, text: &str, class: Option<&str>, format: HighlightFormat) {
+    if text.is_empty() {
+        return;
+    }
+
+    match format {
+        HighlightFormat::Ansi => match class {
+            Some(class) => {
+                let color = match class {
+                    "keyword" => "35",
+                    "constant" => "36",
+                    "string" => "32",
+                    "number" => "33",
+                    _ => "90",
+                };
+                out.push_str(&format!("\x1b[{}m{}\x1b[0m", color, text));
+            }
+            None => out.push_str(text),
+        },
+        HighlightFormat::Html => {
+            if let Some(class) = class {
+                out.push_str(&format!("<span class=\"rox-{}\">", class));
+            }
+            for c in text.chars() {
+                match c {
+                    '&' => out.push_str("&amp;"),
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    '"' => out.push_str("&quot;"),
+                    c => out.push(c),
+                }
+            }
+            if class.is_some() {
+                out.push_str("</span>");
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use crate::{arena::Arena, env::Env, statement::Stmt, value::Value};
+
+/// Callbacks an embedder can install to observe execution, e.g. to build a
+/// debugger, profiler or audit log. Every method defaults to doing nothing.
+pub trait InterpreterHooks {
+    /// Called before each statement runs. `arena` holds the statement's
+    /// nested nodes.
+    fn on_statement(&mut self, _arena: &Arena, _stmt: &Stmt, _line: i32) {}
+
+    /// Called before each statement runs, right after `on_statement`, with
+    /// the variables in scope. It may block, e.g. while a debugger is
+    /// stopped at a breakpoint.
+    fn inspect(&mut self, _stmt: &Stmt, _frame: &Frame<'_>) {}
+
+    /// Called before a function is invoked.
+    fn on_call(&mut self, _name: &str) {}
+
+    /// Called whenever a variable is defined or assigned.
+    fn on_var_write(&mut self, _name: &str, _value: &Value) {}
+}
+
+/// Where the interpreter is and what it can see from there.
+pub struct Frame<'a> {
+    env: &'a Env,
+    globals: &'a Env,
+    file: Option<&'a Path>,
+    depth: usize,
+    pub line: i32,
+    pub column: i32,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(
+        env: &'a Env,
+        globals: &'a Env,
+        file: Option<&'a Path>,
+        depth: usize,
+        line: i32,
+        column: i32,
+    ) -> Frame<'a> {
+        Frame {
+            env,
+            globals,
+            file,
+            depth,
+            line,
+            column,
+        }
+    }
+
+    /// The script or module being run, if it came from a file.
+    pub fn file(&self) -> Option<&Path> {
+        self.file
+    }
+
+    /// How many scripts and modules are being run, one importing the next.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The variables of the blocks around the statement, innermost first,
+    /// leaving out those shadowed by an inner one.
+    pub fn locals(&self) -> Vec<(String, Value)> {
+        let mut locals: Vec<(String, Value)> = Vec::new();
+        let mut env = self.env;
+        while let Some(enclosing) = env.enclosing() {
+            for (name, value) in env.scope_bindings() {
+                if !locals.iter().any(|(seen, _)| *seen == name) {
+                    locals.push((name, value));
+                }
+            }
+            env = enclosing;
+        }
+        locals
+    }
+
+    /// The top-level variables of the script or module being run.
+    pub fn top_level(&self) -> Vec<(String, Value)> {
+        let mut env = self.env;
+        while let Some(enclosing) = env.enclosing() {
+            env = enclosing;
+        }
+        env.scope_bindings()
+    }
+
+    /// Globals defined by the host or a prelude. Natives are left out.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.globals
+            .scope_bindings()
+            .into_iter()
+            .filter(|(_, value)| !matches!(value, Value::Native(_)))
+            .collect()
+    }
+
+    /// The value `name` refers to at this point, as the script would see it.
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        let mut env = Some(self.env);
+        while let Some(scope) = env {
+            if let Some((_, value)) = scope.scope_bindings().into_iter().find(|(n, _)| n == name) {
+                return Some(value);
+            }
+            env = scope.enclosing();
+        }
+        self.globals.get(name)
+    }
+}
@@ -0,0 +1,108 @@
+use std::{io::Read, rc::Rc};
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{items_arg, string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Responses larger than this are refused rather than read into memory.
+const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// HTTP client natives. Both return a `(status, body)` tuple for any
+/// response the server sends, including error statuses, and raise a runtime
+/// error only when no response arrives at all.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "http_get",
+            arity: 1..=2,
+            func: http_get,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "http_post",
+            arity: 2..=3,
+            func: http_post,
+            deterministic: false,
+        },
+    ]
+}
+
+/// `http_get(url, headers)`, where the optional headers are a collection of
+/// `(name, value)` pairs.
+fn http_get(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let url = string_arg("http_get", &args, 0)?;
+    let request = with_headers("http_get", ureq::get(&url), &args, 1)?;
+
+    respond("http_get", request.call())
+}
+
+/// `http_post(url, body, headers)`, like `http_get` with a string body.
+fn http_post(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let url = string_arg("http_post", &args, 0)?;
+    let body = string_arg("http_post", &args, 1)?;
+    let request = with_headers("http_post", ureq::post(&url), &args, 2)?;
+
+    respond("http_post", request.send_string(&body))
+}
+
+fn with_headers(
+    name: &str,
+    mut request: ureq::Request,
+    args: &[Value],
+    index: usize,
+) -> Result<ureq::Request, RuntimeError> {
+    if args.len() <= index {
+        return Ok(request);
+    }
+
+    for header in items_arg(name, args, index)? {
+        let pair = match &header {
+            Value::Tuple(items) | Value::List(items) if items.len() == 2 => {
+                match (&items[0], &items[1]) {
+                    (Value::String(key), Value::String(value)) => Some((key, value)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let Some((key, value)) = pair else {
+            return Err(RuntimeError::Generic(format!(
+                "{}() expects headers as (name, value) string pairs, got '{}'",
+                name, header
+            )));
+        };
+
+        request = request.set(key, value);
+    }
+
+    Ok(request)
+}
+
+fn respond(name: &str, result: Result<ureq::Response, ureq::Error>) -> Result<Value, RuntimeError> {
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => {
+            return Err(RuntimeError::Generic(format!(
+                "{}() request failed: {}",
+                name, err
+            )))
+        }
+    };
+
+    let status = response.status();
+    let mut body = String::new();
+    response
+        .into_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|err| {
+            RuntimeError::Generic(format!("{}() could not read the response: {}", name, err))
+        })?;
+
+    Ok(Value::Tuple(Rc::new(vec![
+        Value::Int(status as i64),
+        string_value(body),
+    ])))
+}
@@ -0,0 +1,615 @@
+//! Parsing a file again after an edit without starting from scratch.
+//!
+//! A `Document` keeps the tokens and top-level declarations of the last
+//! version of a file. Given the next version, it finds the bytes that
+//! changed, rescans and reparses only the declarations they touch, and
+//! moves the declarations after them to their new positions. The
+//! boundaries between top-level declarations can be relied on because every
+//! declaration ends with a `;` or a `}` and no declaration starts with a
+//! token that could continue the one before it.
+//!
+//! Whenever the changed region does not scan and parse cleanly on its own,
+//! the whole file is parsed again, so errors are reported exactly as a
+//! full parse reports them.
+//!
+//! Reparsed declarations add their nodes to the document's arena, and the
+//! nodes of the ones they replace stay behind unused. Once the arena has
+//! grown to `MAX_GROWTH` times its size after the last full parse, the
+//! file is parsed from scratch to let them go.
+
+use crate::{
+    arena::{Arena, ExprId, Program, StmtId},
+    error::{Log, LogLevel, RoxError},
+    expression::{Expr, Local},
+    parser::Parser,
+    scanner::Scanner,
+    statement::{Pattern, Stmt},
+    token::{Literal, Span, Token, TokenType},
+    visitor::{ExprVisitorMut, StmtVisitorMut},
+};
+
+/// How many times its size after a full parse the arena may grow to
+/// through reparsed regions before the file is parsed from scratch.
+const MAX_GROWTH: usize = 2;
+
+/// A source file kept parsed across edits, for editors and `--watch`.
+/// Hand it to `Rox::reparse` with each new version of the file.
+#[derive(Debug, Default)]
+pub struct Document {
+    source: String,
+    /// Every token of `source`, comments included, ending with EOF.
+    tokens: Vec<Token>,
+    program: Program,
+    declarations: Vec<Declaration>,
+    /// How many nodes the arena held after the last full parse.
+    parsed_nodes: usize,
+}
+
+/// A top-level declaration: where its last token ends and how many
+/// statements it parsed to, as a grouped `var` gives several.
+#[derive(Debug, Clone, Copy)]
+struct Declaration {
+    end: usize,
+    stmts: usize,
+}
+
+/// The part of the old source that is scanned and parsed again.
+struct Region {
+    /// The first and one past the last declaration it covers.
+    first: usize,
+    last: usize,
+    /// Byte offsets in the old source.
+    start: usize,
+    end: usize,
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    /// The version of the file that was last parsed successfully.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The tokens of `source`, comments included, ending with EOF.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub(crate) fn program_mut(&mut self) -> &mut Program {
+        &mut self.program
+    }
+
+    /// Brings the document up to `source`, reusing what the edit left
+    /// unchanged. Statements are left unresolved.
+    pub(crate) fn update(
+        &mut self,
+        source: &str,
+        file: Option<String>,
+        logger: &Log,
+    ) -> Result<(), RoxError> {
+        if source == self.source && !self.tokens.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(region) = self.changed_region(source) {
+            if self.reparse_region(source, region)
+                && self.program.arena.len() <= MAX_GROWTH * self.parsed_nodes
+            {
+                return Ok(());
+            }
+        }
+
+        self.parse_all(source, file, logger)
+    }
+
+    fn parse_all(
+        &mut self,
+        source: &str,
+        file: Option<String>,
+        logger: &Log,
+    ) -> Result<(), RoxError> {
+        // A failed parse leaves nothing to reuse for the next version.
+        *self = Document::new();
+
+        let mut scanner = Scanner::new(source.to_string(), file.clone(), logger.clone());
+        scanner.scan_tokens();
+        if scanner.had_error {
+            return Err(RoxError::Scan(scanner.errors));
+        }
+
+        let tokens = scanner.tokens.clone();
+        let mut parser = Parser::new(scanner.tokens, file, logger);
+        let (stmts, declarations) = parser.parse_declarations().map_err(RoxError::Parse)?;
+        let arena = parser.into_arena();
+
+        self.source = source.to_string();
+        self.tokens = tokens;
+        self.parsed_nodes = arena.len();
+        self.program = Program { stmts, arena };
+        self.declarations = declarations
+            .into_iter()
+            .map(|(end, stmts)| Declaration { end, stmts })
+            .collect();
+        Ok(())
+    }
+
+    /// The declarations that the change from the current source to `source`
+    /// touches, or `None` when there is nothing to reuse.
+    fn changed_region(&self, source: &str) -> Option<Region> {
+        let old = self.source.as_str();
+        if self.declarations.is_empty() {
+            return None;
+        }
+
+        let mut prefix = old
+            .bytes()
+            .zip(source.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(prefix) || !source.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let limit = old.len().min(source.len()) - prefix;
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(source.bytes().rev())
+            .take(limit)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) {
+            suffix -= 1;
+        }
+        let edit_end = old.len() - suffix;
+
+        // A declaration ending right where the edit starts is included, as
+        // the edit may extend its last token.
+        let first = self
+            .declarations
+            .partition_point(|declaration| declaration.end < prefix);
+        let mut last = first
+            + self.declarations[first.min(self.declarations.len())..]
+                .partition_point(|declaration| declaration.end <= edit_end);
+        if last < self.declarations.len() {
+            last += 1;
+        }
+
+        // Declarations after the region keep their columns only if they
+        // start on a later line than the region ends on.
+        while last < self.declarations.len() && !self.starts_on_new_line(last) {
+            last += 1;
+        }
+
+        let start = match first {
+            0 => 0,
+            first => self.declarations[first - 1].end,
+        };
+        let end = match last {
+            last if last == self.declarations.len() => old.len(),
+            last => self.declarations[last - 1].end,
+        };
+
+        Some(Region {
+            first,
+            last,
+            start,
+            end,
+        })
+    }
+
+    /// Whether a line break follows the declaration before `index` before
+    /// any other token, comments included.
+    fn starts_on_new_line(&self, index: usize) -> bool {
+        let end = self.declarations[index - 1].end;
+        match self
+            .tokens
+            .get(self.tokens.partition_point(|t| t.span.start < end))
+        {
+            Some(token) => self.source[end..token.span.start].contains('\n'),
+            None => false,
+        }
+    }
+
+    /// Scans and parses `region` of `source` on its own and splices it in.
+    /// Returns false, leaving the document as it was, if the region does
+    /// not stand on its own.
+    fn reparse_region(&mut self, source: &str, region: Region) -> bool {
+        let old = self.source.as_str();
+        let delta = source.len() as isize - old.len() as isize;
+        let end = region.end.checked_add_signed(delta).unwrap_or(0);
+        if end < region.start {
+            return false;
+        }
+        let line = 1 + old[..region.start].matches('\n').count() as i32;
+        let lines = source[region.start..end].matches('\n').count() as i32
+            - old[region.start..region.end].matches('\n').count() as i32;
+
+        let quiet = Log::new(LogLevel::Off);
+        let mut scanner = Scanner::new(source.to_string(), None, quiet.clone());
+        scanner.scan_range(region.start, end, line);
+        if !scanner.ended_cleanly_at(end) {
+            return false;
+        }
+
+        // A region that reaches the end of the file has its own EOF token.
+        let at_end = region.last == self.declarations.len();
+        let mut tokens = scanner.tokens;
+        let mut parser_tokens = tokens.clone();
+        if !at_end {
+            let eof = Token::new(
+                TokenType::EOF,
+                String::new(),
+                line,
+                1,
+                Span { start: end, end },
+                Literal::Nil,
+            );
+            parser_tokens.push(eof);
+        }
+        let arena = std::mem::take(&mut self.program.arena);
+        let mut parser = Parser::new(parser_tokens, None, &quiet).with_arena(arena);
+        let parsed = parser.parse_declarations();
+        self.program.arena = parser.into_arena();
+        let Ok((mut stmts, declarations)) = parsed else {
+            return false;
+        };
+
+        // Tokens: keep those before the region, replace those in it and
+        // move those after it.
+        let first_token = self.tokens.partition_point(|t| t.span.end <= region.start);
+        let last_token = match at_end {
+            true => self.tokens.len(),
+            false => self.tokens.partition_point(|t| t.span.start < region.end),
+        };
+        let mut shift = Shift {
+            bytes: delta,
+            lines,
+        };
+        shift.tokens(&mut self.tokens[last_token..]);
+        self.tokens
+            .splice(first_token..last_token, tokens.drain(..));
+
+        let first_stmt: usize = self.declarations[..region.first]
+            .iter()
+            .map(|d| d.stmts)
+            .sum();
+        let last_stmt: usize = first_stmt
+            + self.declarations[region.first..region.last]
+                .iter()
+                .map(|d| d.stmts)
+                .sum::<usize>();
+        let Program {
+            stmts: old_stmts,
+            arena,
+        } = &mut self.program;
+        shift.stmts(arena, &mut old_stmts[last_stmt..]);
+        old_stmts.splice(first_stmt..last_stmt, stmts.drain(..));
+
+        for declaration in &mut self.declarations[region.last..] {
+            declaration.end = declaration.end.saturating_add_signed(delta);
+        }
+        self.declarations.splice(
+            region.first..region.last,
+            declarations
+                .into_iter()
+                .map(|(end, stmts)| Declaration { end, stmts }),
+        );
+
+        self.source = source.to_string();
+        true
+    }
+}
+
+/// Moves the tokens it visits `bytes` further into the source and `lines`
+/// further down, for the declarations after a reparsed region.
+struct Shift {
+    bytes: isize,
+    lines: i32,
+}
+
+impl Shift {
+    fn token(&self, token: &mut Token) {
+        token.line += self.lines;
+        token.span.start = token.span.start.saturating_add_signed(self.bytes);
+        token.span.end = token.span.end.saturating_add_signed(self.bytes);
+    }
+
+    fn tokens<'a>(&self, tokens: impl IntoIterator<Item = &'a mut Token>) {
+        for token in tokens {
+            self.token(token);
+        }
+    }
+
+    fn pattern(&self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Name(name, _) => self.token(name),
+            Pattern::Tuple(open, patterns, close) => {
+                self.tokens([open, close]);
+                for pattern in patterns {
+                    self.pattern(pattern);
+                }
+            }
+        }
+    }
+
+    fn exprs<'a>(&mut self, arena: &mut Arena, exprs: impl IntoIterator<Item = &'a mut Expr>) {
+        for expr in exprs {
+            expr.accept_mut(arena, self);
+        }
+    }
+
+    fn stmts(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        for stmt in stmts {
+            stmt.accept_mut(arena, self);
+        }
+    }
+}
+
+impl ExprVisitorMut<()> for Shift {
+    fn visit_unary_expr(&mut self, arena: &mut Arena, op: &mut Token, right: ExprId) {
+        self.token(op);
+        arena.accept_expr_mut(right, self);
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) {
+        arena.accept_expr_mut(left, self);
+        self.token(op);
+        arena.accept_expr_mut(right, self);
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) {
+        self.visit_binary_expr(arena, left, op, right);
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &mut Arena, inner: ExprId) {
+        arena.accept_expr_mut(inner, self);
+    }
+
+    fn visit_literal_expr(
+        &mut self,
+        _arena: &mut Arena,
+        token: &mut Token,
+        _literal: &mut Literal,
+    ) {
+        self.token(token);
+    }
+
+    fn visit_var_expr(&mut self, _arena: &mut Arena, name: &mut Token, _local: &mut Option<Local>) {
+        self.token(name);
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        value: ExprId,
+        _local: &mut Option<Local>,
+    ) {
+        self.token(name);
+        arena.accept_expr_mut(value, self);
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        arena: &mut Arena,
+        callee: ExprId,
+        paren: &mut Token,
+        args: &mut [Expr],
+    ) {
+        arena.accept_expr_mut(callee, self);
+        self.token(paren);
+        self.exprs(arena, args);
+    }
+
+    fn visit_get_expr(&mut self, arena: &mut Arena, object: ExprId, name: &mut Token) {
+        arena.accept_expr_mut(object, self);
+        self.token(name);
+    }
+
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &mut Arena,
+        open: &mut Token,
+        items: &mut [Expr],
+        close: &mut Token,
+    ) {
+        self.tokens([open, close]);
+        self.exprs(arena, items);
+    }
+
+    fn visit_spread_expr(&mut self, arena: &mut Arena, op: &mut Token, expr: ExprId) {
+        self.token(op);
+        arena.accept_expr_mut(expr, self);
+    }
+}
+
+impl StmtVisitorMut<()> for Shift {
+    fn visit_print_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, values: &mut [Expr]) {
+        self.token(keyword);
+        self.exprs(arena, values);
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &mut Arena, expr: &mut Expr) {
+        expr.accept_mut(arena, self);
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &mut Arena,
+        pattern: &mut Pattern,
+        annotation: Option<&mut Token>,
+        initializer: Option<&mut Expr>,
+    ) {
+        self.pattern(pattern);
+        self.tokens(annotation);
+        self.exprs(arena, initializer);
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        _slot: &mut Option<usize>,
+        annotation: Option<&mut Token>,
+        initializer: &mut Expr,
+    ) {
+        self.token(name);
+        self.tokens(annotation);
+        initializer.accept_mut(arena, self);
+    }
+
+    fn visit_block_stmt(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        self.stmts(arena, stmts);
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        condition: &mut Expr,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) {
+        self.token(keyword);
+        condition.accept_mut(arena, self);
+        arena.accept_stmt_mut(then_branch, self);
+        if let Some(else_branch) = else_branch {
+            arena.accept_stmt_mut(else_branch, self);
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        condition: &mut Expr,
+        body: StmtId,
+        increment: Option<&mut Expr>,
+    ) {
+        self.token(keyword);
+        self.tokens(label);
+        condition.accept_mut(arena, self);
+        arena.accept_stmt_mut(body, self);
+        self.exprs(arena, increment);
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        pattern: &mut Pattern,
+        iterable: &mut Expr,
+        body: StmtId,
+    ) {
+        self.token(keyword);
+        self.tokens(label);
+        self.pattern(pattern);
+        iterable.accept_mut(arena, self);
+        arena.accept_stmt_mut(body, self);
+    }
+
+    fn visit_break_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        self.token(keyword);
+        self.tokens(label);
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        self.token(keyword);
+        self.tokens(label);
+    }
+
+    fn visit_throw_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, value: &mut Expr) {
+        self.token(keyword);
+        value.accept_mut(arena, self);
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) {
+        self.token(keyword);
+        arena.accept_stmt_mut(stmt, self);
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        try_block: &mut [Stmt],
+        catch_clause: Option<(&mut Token, &mut [Stmt])>,
+        finally_block: Option<&mut [Stmt]>,
+    ) {
+        self.token(keyword);
+        self.stmts(arena, try_block);
+        if let Some((name, catch_block)) = catch_clause {
+            self.token(name);
+            self.stmts(arena, catch_block);
+        }
+        if let Some(finally_block) = finally_block {
+            self.stmts(arena, finally_block);
+        }
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        keyword: &mut Token,
+        _path: &str,
+        alias: Option<&mut Token>,
+    ) {
+        self.token(keyword);
+        self.tokens(alias);
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) {
+        self.token(keyword);
+        arena.accept_stmt_mut(stmt, self);
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        subject: &mut Expr,
+        arms: &mut [(Vec<Expr>, Stmt)],
+        default: Option<StmtId>,
+    ) {
+        self.token(keyword);
+        subject.accept_mut(arena, self);
+        for (patterns, body) in arms {
+            self.exprs(arena, patterns);
+            body.accept_mut(arena, self);
+        }
+        if let Some(default) = default {
+            arena.accept_stmt_mut(default, self);
+        }
+    }
+}
@@ -1,361 +1,1791 @@
-use std::fmt::Display;
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt::Display,
+    io::{stderr, stdout, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
 
 use crate::{
-    env::Env,
-    error::Log,
-    expression::Expr,
-    statement::Stmt,
-    token::{Literal, Token, TokenType},
+    arena::{Arena, Program},
+    diagnostic::{Diagnostic, E_LIMIT_EXCEEDED, E_RUNTIME, E_UNCAUGHT_EXCEPTION},
+    env::{Env, EnvError},
+    error::{Log, LogLevel},
+    expression::{Expr, Local},
+    hooks::{Frame, InterpreterHooks},
+    limits::{Budget, Limits},
+    module::{Module, ModuleLoader},
+    native::get_natives,
+    parser::{ParseError, Parser},
+    resolver::{strict_pragma, Resolver},
+    scanner::Scanner,
+    snapshot::{self, Binding, Snapshot},
+    statement::{Pattern, Stmt},
+    stats::Stats,
+    suggest::closest,
+    token::{Literal, Span, Token, TokenType},
+    value::{compare_int_number, Value},
+    visitor::{ExprVisitor, StmtVisitor, Walk},
 };
 
 #[derive(Debug, Clone)]
-pub struct Object {
-    value: Literal,
+pub enum RuntimeError {
+    Generic(String),
+    Throw(Value),
+    Exit(i32),
+    LimitExceeded(String),
+    /// Unwinds to the loop a `break` targets, the innermost one when it has
+    /// no label.
+    Break(Option<String>),
+    /// Unwinds to the loop a `continue` targets.
+    Continue(Option<String>),
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::Generic(message) => write!(f, "{}", message),
+            RuntimeError::Throw(value) => write!(f, "Uncaught exception: {}", value),
+            RuntimeError::Exit(code) => write!(f, "Exited with code {}", code),
+            RuntimeError::LimitExceeded(message) => write!(f, "Limit exceeded: {}", message),
+            RuntimeError::Break(label) => {
+                write!(f, "'break{}' outside a loop", label_suffix(label))
+            }
+            RuntimeError::Continue(label) => {
+                write!(f, "'continue{}' outside a loop", label_suffix(label))
+            }
+        }
+    }
+}
+
+fn label_suffix(label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!(" {}", label),
+        None => String::new(),
+    }
+}
+
+impl From<EnvError> for RuntimeError {
+    fn from(err: EnvError) -> Self {
+        match err {
+            EnvError::VarAssign(message) => RuntimeError::Generic(message),
+            EnvError::ConstAssign(message) => RuntimeError::Generic(message),
+        }
+    }
 }
 
 pub struct Interpreter {
+    globals: Env,
     env: Env,
+    modules: ModuleLoader,
+    budget: Budget,
+    stats: Stats,
+    /// How many block scopes are open, for `stats.peak_scope_depth`.
+    scope_depth: usize,
+    sandbox: bool,
+    strict: bool,
+    /// Whether the code running now is strict, by the host's choice or its
+    /// own `"use strict";`, which makes `==` and `!=` between values of
+    /// different types an error.
+    strict_run: bool,
+    hooks: Option<Box<dyn InterpreterHooks>>,
+    /// Set once code that mentions `eval` has run, so that locals keep
+    /// their names for the code it is given to be resolved against.
+    name_locals: bool,
+    /// Statements queued by `defer`, one list per open scope.
+    deferred: Vec<Vec<Stmt>>,
+    /// Set while a changed script is run again over its old state.
+    reloading: bool,
+    line: i32,
+    column: i32,
+    span: Span,
+    out: Box<dyn Write>,
+    err: Box<dyn Write>,
     logger: Log,
 }
 
 impl Interpreter {
     pub fn new(logger: Log) -> Interpreter {
+        Interpreter::with_output(logger, Box::new(stdout()), Box::new(stderr()))
+    }
+
+    /// Creates an interpreter that writes `print` output to `out` and
+    /// runtime error reports to `err`.
+    pub fn with_output(logger: Log, out: Box<dyn Write>, err: Box<dyn Write>) -> Interpreter {
+        let mut globals = Env::new(None);
+
+        for native in get_natives() {
+            globals
+                .define(native.name.to_string(), Value::Native(native))
+                .unwrap();
+        }
+
         Interpreter {
             env: Env::new(None),
+            globals,
+            modules: ModuleLoader::new(),
+            budget: Budget::default(),
+            stats: Stats::default(),
+            scope_depth: 0,
+            sandbox: false,
+            strict: false,
+            strict_run: false,
+            hooks: None,
+            name_locals: false,
+            deferred: Vec::new(),
+            reloading: false,
+            line: 0,
+            column: 0,
+            span: Span::default(),
+            out,
+            err,
             logger,
         }
     }
 
-    fn is_truthy(&mut self, obj: Object) -> bool {
-        match obj.value {
-            Literal::Bool(x) => x,
-            Literal::Number(x) => x == 0.0,
-            Literal::String(x) => x.len() == 0,
-            Literal::Nil => false,
+    pub fn set_output(&mut self, out: Box<dyn Write>, err: Box<dyn Write>) {
+        self.out = out;
+        self.err = err;
+    }
+
+    /// The sink that `print` writes to.
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.out.as_mut()
+    }
+
+    /// The sink that `eprint` and runtime error reports write to.
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        self.err.as_mut()
+    }
+
+    /// Reports an error at the statement that was executing when it occurred.
+    fn report_error(&mut self, code: &'static str, message: String) {
+        let file = self.current_file();
+        let diagnostic = Diagnostic::error(code, message)
+            .at(self.line, self.column, self.span)
+            .in_file(file);
+
+        // Nothing sensible can be done if the error sink itself fails.
+        let _ = writeln!(self.err, "{}", self.logger.render(&diagnostic));
+        self.logger.record(&diagnostic);
+    }
+
+    pub fn set_hooks(&mut self, hooks: Box<dyn InterpreterHooks>) {
+        self.hooks = Some(hooks);
+    }
+
+    /// In sandbox mode natives that are not deterministic raise an error.
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.sandbox = sandbox;
+    }
+
+    /// In strict mode warnings are errors and more mistakes are caught
+    /// before code runs, in scripts and the modules they import alike.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
+
+    pub fn set_module_paths(&mut self, paths: Vec<PathBuf>) {
+        self.modules.set_search_paths(paths);
+    }
+
+    /// Pauses the script, cutting the pause short with an error if it would
+    /// run past the time limit.
+    pub fn sleep(&mut self, duration: Duration) -> Result<(), RuntimeError> {
+        self.budget.sleep(duration)
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Resets the resource counters at the start of a new run.
+    pub fn start_run(&mut self) {
+        self.budget.start();
+    }
+
+    /// Records the file being run so that imports resolve relative to it.
+    pub fn enter_script(&mut self, path: &Path) -> Result<(), RuntimeError> {
+        let path = path.canonicalize().map_err(|err| {
+            RuntimeError::Generic(format!("Cannot open '{}': {}", path.display(), err))
+        })?;
+
+        self.modules.enter(path)
+    }
+
+    pub fn leave_script(&mut self) {
+        self.modules.leave();
+    }
+
+    /// The script or module being run, as it should appear in diagnostics.
+    pub fn current_file(&self) -> Option<String> {
+        self.modules
+            .current()
+            .map(|path| path.display().to_string())
+    }
+
+    /// Binds `name` in the global scope, where every script and module can
+    /// see it. An existing binding of the same name is replaced.
+    pub fn define_global(&mut self, name: String, value: Value) -> Result<(), RuntimeError> {
+        Ok(self.globals.define(name, value)?)
+    }
+
+    /// Runs `program` with the global scope as the current one, so whatever
+    /// it declares becomes visible to every later script and module.
+    pub fn run_prelude(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        let globals = std::mem::replace(&mut self.globals, Env::new(None));
+        let saved_env = std::mem::replace(&mut self.env, globals);
+
+        let result = self.interpret(program);
+
+        let globals = std::mem::replace(&mut self.env, saved_env);
+        self.globals = globals;
+        result.map(|_| ())
+    }
+
+    /// Runs a new version of the script over the state the old one left.
+    /// Top-level variables that already exist keep their values and their
+    /// initializers are skipped, while constants take their new values and
+    /// every other statement runs again.
+    pub fn reload(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        self.reloading = true;
+        let result = self.interpret(program);
+        self.reloading = false;
+        result
+    }
+
+    /// Whether a reload leaves the variables `pattern` declares alone,
+    /// because they are all top-level and already defined.
+    fn kept_on_reload(&self, pattern: &Pattern) -> bool {
+        match pattern {
+            Pattern::Name(name, None) => self.env.defines(&name.lexeme),
+            Pattern::Name(_, Some(_)) => false,
+            Pattern::Tuple(_, patterns, _) => patterns.iter().all(|p| self.kept_on_reload(p)),
+        }
+    }
+
+    /// Serializes the top-level bindings, the globals added on top of the
+    /// natives and the loaded modules, as described in `snapshot`.
+    pub fn snapshot(&self) -> Result<String, RuntimeError> {
+        // Natives and host functions bound under their own name are set up
+        // again by whoever creates the interpreter that restores this.
+        let globals = env_bindings(&self.globals)
+            .into_iter()
+            .filter(|binding| match &binding.value {
+                Value::Native(native) => native.name != binding.name,
+                Value::Host(host) => host.name != binding.name,
+                _ => true,
+            })
+            .collect();
+
+        let snapshot = Snapshot {
+            globals,
+            bindings: env_bindings(&self.env),
+            modules: self.modules.loaded().cloned().collect(),
+        };
+        snapshot::encode(&snapshot).map_err(RuntimeError::Generic)
+    }
+
+    /// Restores state saved by `snapshot`. Saved bindings replace current
+    /// ones of the same name; everything else is left alone.
+    pub fn restore(&mut self, json: &str) -> Result<(), RuntimeError> {
+        let globals = &self.globals;
+        let native = |name: &str| match globals.get(name) {
+            Some(value @ (Value::Native(_) | Value::Host(_))) => Some(value),
+            _ => None,
+        };
+        let snapshot = snapshot::decode(json, &native).map_err(|message| {
+            RuntimeError::Generic(format!("Cannot restore snapshot: {}", message))
+        })?;
+
+        for module in snapshot.modules {
+            self.modules.insert(module.path.clone(), module);
+        }
+        for binding in snapshot.globals {
+            self.globals
+                .replace(binding.name, binding.value, binding.constant);
         }
+        for binding in snapshot.bindings {
+            self.env
+                .replace(binding.name, binding.value, binding.constant);
+        }
+
+        Ok(())
+    }
+
+    /// The names a statement run now could refer to: the current scope's
+    /// and the globals, sorted and without duplicates.
+    pub fn visible_names(&self) -> Vec<String> {
+        let mut names = self.env.names();
+        names.extend(self.globals.names());
+        names.sort();
+        names.dedup();
+        names
     }
 
-    fn eval_literal(&mut self, lit_val: Literal) -> Object {
-        Object { value: lit_val }
+    /// Locals held in slots have no names at runtime, so only names bound
+    /// by name are offered as suggestions.
+    fn undefined_variable(&self, name: &Token) -> RuntimeError {
+        let mut message = format!("Undefined variable '{}' at line {}", name.lexeme, name.line);
+        let names = self.visible_names();
+        if let Some(similar) = closest(&name.lexeme, names.iter().map(String::as_str)) {
+            message.push_str(&format!(". Did you mean '{}'?", similar));
+        }
+        RuntimeError::Generic(message)
     }
 
-    fn eval_group(&mut self, g_val: Expr) -> Object {
+    fn eval_literal(&mut self, lit_val: &Literal) -> Result<Value, RuntimeError> {
+        Ok(Value::from(lit_val))
+    }
+
+    fn eval_group(&mut self, arena: &Arena, g_val: &Expr) -> Result<Value, RuntimeError> {
         match g_val {
-            Expr::Literal(lit_val) => self.eval_literal(lit_val),
-            _ => self.eval_expr(g_val),
+            Expr::Literal(_token, lit_val) => self.eval_literal(lit_val),
+            _ => self.eval_expr(arena, g_val),
         }
     }
 
-    fn eval_unary(&mut self, op: Token, right: Box<Expr>) -> Object {
-        let right = self.eval_expr(*right);
+    fn eval_unary(
+        &mut self,
+        arena: &Arena,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let right = self.eval_expr(arena, right)?;
+        let result = apply_unary(op, right);
+        if result.is_err() {
+            self.point_at(op);
+        }
+        result
+    }
 
-        match op.token_type {
-            TokenType::MINUS => match right.value {
-                Literal::Number(x) => Object {
-                    value: Literal::Number(x * -1.0),
-                },
-                x => {
-                    panic!("Cannot apply {:?} to a non-number '{}'", op.token_type, x);
-                }
-            },
-            TokenType::BANG => {
-                let obj_val = match right.value {
-                    Literal::Bool(x) => !x,
-                    // Literal::Object => false,
-                    Literal::String(x) => x.len() > 0,
-                    Literal::Nil => false,
-                    Literal::Number(x) => x == 0.0,
+    fn eval_binary(
+        &mut self,
+        arena: &Arena,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let left = self.eval_expr(arena, left)?;
+        let right = self.eval_expr(arena, right)?;
+        let result = self.apply_binary(op, left, right);
+        if result.is_err() {
+            self.point_at(op);
+        }
+        result
+    }
+
+    fn apply_binary(&self, op: &Token, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        let value = match op.token_type {
+            TokenType::MINUS | TokenType::PLUS | TokenType::STAR | TokenType::SLASH => {
+                arithmetic(op.token_type, &left, &right)?
+            }
+            TokenType::AMPERSAND
+            | TokenType::PIPE
+            | TokenType::CARET
+            | TokenType::LESS_LESS
+            | TokenType::GREATER_GREATER => bitwise(op.token_type, &left, &right)?,
+            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL
+                if self.strict_run && !comparable(&left, &right) =>
+            {
+                return Err(RuntimeError::Generic(format!(
+                    "Comparing values of different types, {} '{}' and {} '{}', at line {}",
+                    left.type_name(),
+                    left,
+                    right.type_name(),
+                    right,
+                    op.line
+                )));
+            }
+            TokenType::EQUAL_EQUAL => Value::Bool(left.equals(&right)),
+            TokenType::BANG_EQUAL => Value::Bool(!left.equals(&right)),
+            TokenType::LESS
+            | TokenType::LESS_EQUAL
+            | TokenType::GREATER
+            | TokenType::GREATER_EQUAL => compare(op, &left, &right)?,
+            TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL => {
+                let bounds = match (left.as_i64(), right.as_i64()) {
+                    (Some(start), Some(end)) => (start, end),
+                    _ => {
+                        return Err(RuntimeError::Generic(format!(
+                            "Range bounds must be integers, got '{}' and '{}'",
+                            left, right
+                        )));
+                    }
                 };
 
-                Object {
-                    value: Literal::Bool(obj_val),
-                }
+                let inclusive = op.token_type == TokenType::DOT_DOT_EQUAL;
+                Value::Range(bounds.0, bounds.1, inclusive)
             }
+            TokenType::IS => match right {
+                // Ints are numbers too.
+                Value::String(ref name) => Value::Bool(
+                    left.type_name() == &**name
+                        || (&**name == "number" && matches!(left, Value::Int(_))),
+                ),
+                _ => {
+                    return Err(RuntimeError::Generic(format!(
+                        "The right side of 'is' must be a type name string, got '{}'",
+                        right
+                    )));
+                }
+            },
             x => {
-                panic!("Cannot apply {:?} to '{:?}'", x, right.value);
+                return Err(RuntimeError::Generic(format!(
+                    "No such operator as {:?}",
+                    x
+                )));
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn eval_logical(
+        &mut self,
+        arena: &Arena,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let left_val = self.eval_expr(arena, left)?;
+
+        if op.token_type == TokenType::QUESTION_QUESTION {
+            return match left_val {
+                Value::Nil => self.eval_expr(arena, right),
+                _ => Ok(left_val),
+            };
+        }
+
+        let is_op_or = matches!(op.token_type, TokenType::OR);
+
+        if is_op_or {
+            if left_val.is_truthy() {
+                return Ok(left_val);
             }
+        } else if !left_val.is_truthy() {
+            return Ok(left_val);
         }
+
+        self.eval_expr(arena, right)
     }
 
-    fn eval_binary(&mut self, left: Expr, op: Token, right: Expr) -> Object {
-        let left = self.eval_expr(left);
-        let right = self.eval_expr(right);
+    fn assign_expr(
+        &mut self,
+        arena: &Arena,
+        token: &Token,
+        expr: &Expr,
+        local: Option<Local>,
+    ) -> Result<Value, RuntimeError> {
+        let expr_val = self.eval_expr(arena, expr)?;
+        self.stats.lookups += 1;
 
-        let value = match op.token_type {
-            TokenType::MINUS => {
-                if let Literal::Number(lvalue) = left.value {
-                    if let Literal::Number(rvalue) = right.value {
-                        Literal::Number(lvalue - rvalue)
-                    } else {
-                        self.logger.error(format!(
-                            "Cannot apply - to '{}' and '{}'",
-                            left.value, right.value
-                        ));
-                        panic!();
-                    }
-                } else {
-                    self.logger.error(format!(
-                        "Cannot apply - to '{}' and '{}'",
-                        left.value, right.value
-                    ));
-                    panic!();
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_var_write(&token.lexeme, &expr_val);
+        }
+
+        match local {
+            Some(local) => {
+                if !self.env.assign_at(local, expr_val) {
+                    return Err(self.undefined_variable(token));
                 }
             }
-            TokenType::PLUS => {
-                if let Literal::Number(lvalue) = left.value {
-                    if let Literal::Number(rvalue) = right.value {
-                        Literal::Number(lvalue + rvalue)
-                    } else {
-                        self.logger.error(format!(
-                            "Cannot apply + to '{}' and '{}'",
-                            left.value, right.value
-                        ));
-                        panic!();
-                    }
-                } else {
-                    self.logger.error(format!(
-                        "Cannot apply + to '{}' and '{}'",
-                        left.value, right.value
-                    ));
-                    panic!();
-                }
+            None if self.env.defines(&token.lexeme) => self.env.assign(&token.lexeme, expr_val)?,
+            None if self.globals.defines(&token.lexeme) => {
+                self.globals.assign(&token.lexeme, expr_val)?
             }
-            TokenType::STAR => {
-                if let Literal::Number(lvalue) = left.value {
-                    if let Literal::Number(rvalue) = right.value {
-                        Literal::Number(lvalue * rvalue)
-                    } else {
-                        self.logger.error(format!(
-                            "Cannot apply * to '{}' and '{}'",
-                            left.value, right.value
-                        ));
-                        panic!();
+            None => return Err(self.undefined_variable(token)),
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_call(
+        &mut self,
+        arena: &Arena,
+        callee: &Expr,
+        paren: &Token,
+        args: &[Expr],
+    ) -> Result<Value, RuntimeError> {
+        let callee_value = self.eval_expr(arena, callee)?;
+
+        let mut arg_vals = Vec::new();
+        for arg in args {
+            match arg {
+                Expr::Spread(op, expr) => {
+                    let value = self.eval_expr(arena, &arena[*expr])?;
+                    match value.iter() {
+                        Some(items) => arg_vals.extend(items),
+                        None => {
+                            return Err(RuntimeError::Generic(format!(
+                                "Cannot spread a {} '{}' at line {}",
+                                value.type_name(),
+                                value,
+                                op.line
+                            )))
+                        }
                     }
-                } else {
-                    self.logger.error(format!(
-                        "Cannot apply * to '{}' and '{}'",
-                        left.value, right.value
-                    ));
-                    panic!();
                 }
+                _ => arg_vals.push(self.eval_expr(arena, arg)?),
             }
-            TokenType::SLASH => {
-                if let Literal::Number(lvalue) = left.value {
-                    if let Literal::Number(rvalue) = right.value {
-                        if rvalue == 0.0 {
-                            self.logger.error(format!("Cannot divide by zero"));
-                            panic!();
-                        }
-                        Literal::Number(lvalue / rvalue)
-                    } else {
-                        self.logger.error(format!(
-                            "Cannot apply / to '{}' and '{}'",
-                            left.value, right.value
-                        ));
-                        panic!();
-                    }
-                } else {
-                    self.logger.error(format!(
-                        "Cannot apply / to '{}' and '{}'",
-                        left.value, right.value
-                    ));
-                    panic!();
+        }
+
+        let result = self.call_value_at(callee_value, arg_vals, paren.line);
+        if result.is_err() {
+            self.locate(arena, callee);
+        }
+        result
+    }
+
+    /// Charges the memory budget for a value a function call returned. A
+    /// string counts as one heap object, and a list as one plus one per item.
+    fn account_result(&mut self, result: Value) -> Result<Value, RuntimeError> {
+        match &result {
+            Value::String(_) => self.allocate(1)?,
+            Value::List(items) => self.allocate(1 + items.len())?,
+            _ => {}
+        }
+
+        Ok(result)
+    }
+
+    /// Calls a function value from native code, such as the callback passed
+    /// to `map`. Errors are reported at the current statement.
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        self.call_value_at(callee, args, self.line)
+    }
+
+    fn call_value_at(
+        &mut self,
+        callee: Value,
+        arg_vals: Vec<Value>,
+        line: i32,
+    ) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Native(native) => {
+                check_arity(&native.arity, arg_vals.len(), line)?;
+
+                if self.sandbox && !native.deterministic {
+                    return Err(RuntimeError::Generic(format!(
+                        "{}() is not available in sandbox mode",
+                        native.name
+                    )));
+                }
+
+                if let Some(hooks) = self.hooks.as_mut() {
+                    hooks.on_call(native.name);
                 }
+
+                self.budget.enter_call()?;
+                let result = (native.func)(self, arg_vals);
+                self.budget.leave_call();
+
+                self.account_result(result?)
             }
-            TokenType::LESS => match left.value {
-                Literal::Number(x) => match right.value {
-                    Literal::Number(y) => Literal::Bool(x < y),
-                    _ => {
-                        self.logger.error("Cannot compare non-numbers.".into());
-                        panic!();
-                    }
-                },
-                _ => {
-                    self.logger.error("Cannot compare non-numbers.".into());
-                    panic!();
+            Value::Host(host) => {
+                check_arity(&host.arity, arg_vals.len(), line)?;
+
+                if let Some(hooks) = self.hooks.as_mut() {
+                    hooks.on_call(&host.name);
                 }
+
+                self.budget.enter_call()?;
+                let result = (host.func)(arg_vals);
+                self.budget.leave_call();
+
+                self.account_result(result?)
+            }
+            x => Err(RuntimeError::Generic(format!(
+                "Can only call functions, '{}' is not callable at line {}",
+                x, line
+            ))),
+        }
+    }
+
+    fn eval_get(
+        &mut self,
+        arena: &Arena,
+        object: &Expr,
+        name: &Token,
+    ) -> Result<Value, RuntimeError> {
+        let object = self.eval_expr(arena, object)?;
+
+        let result = match object {
+            Value::Module(module) => match module.exports.get(&name.lexeme) {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::Generic(format!(
+                    "Module '{}' has no member '{}' at line {}",
+                    module.name, name.lexeme, name.line
+                ))),
             },
-            TokenType::EQUAL_EQUAL => match left.value {
-                Literal::Number(x) => match right.value {
-                    Literal::Number(y) => Literal::Bool(x == y),
-                    _ => {
-                        self.logger.error("Cannot compare non-numbers.".into());
-                        panic!();
-                    }
-                },
-                _ => {
-                    self.logger.error("Cannot compare non-numbers.".into());
-                    panic!();
+            x => Err(RuntimeError::Generic(format!(
+                "Cannot access '{}' on '{}' at line {}",
+                name.lexeme, x, name.line
+            ))),
+        };
+        if result.is_err() {
+            self.point_at(name);
+        }
+        result
+    }
+
+    fn eval_expr(&mut self, arena: &Arena, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.stats.expressions += 1;
+        expr.accept(arena, self)
+    }
+
+    fn eval_tuple(&mut self, arena: &Arena, items: &[Expr]) -> Result<Value, RuntimeError> {
+        let values = items
+            .iter()
+            .map(|item| self.eval_expr(arena, item))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.allocate(1 + values.len())?;
+        Ok(Value::Tuple(Rc::new(values)))
+    }
+
+    /// Counts `count` new heap objects, failing once over the heap limit.
+    fn allocate(&mut self, count: usize) -> Result<(), RuntimeError> {
+        self.stats.allocations += count as u64;
+        self.budget.allocate(count)
+    }
+
+    /// Defines the names in `pattern`, destructuring tuples element by element.
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value) -> Result<(), RuntimeError> {
+        match pattern {
+            // Part of a tuple declaration whose other names are new.
+            Pattern::Name(name, None) if self.reloading && self.env.defines(&name.lexeme) => {}
+            Pattern::Name(name, slot) => {
+                if let Some(hooks) = self.hooks.as_mut() {
+                    hooks.on_var_write(&name.lexeme, &value);
                 }
-            },
-            TokenType::GREATER => match left.value {
-                Literal::Number(x) => match right.value {
-                    Literal::Number(y) => Literal::Bool(x > y),
-                    _ => {
-                        self.logger.error("Cannot compare non-numbers.".into());
-                        panic!();
-                    }
-                },
-                _ => {
-                    self.logger.error("Cannot compare non-numbers.".into());
-                    panic!();
+
+                match slot {
+                    Some(slot) => self.define_local(*slot, &name.lexeme, false, value),
+                    None => self.env.define(name.lexeme.clone(), value)?,
                 }
-            },
-            TokenType::GREATER_EQUAL => match left.value {
-                Literal::Number(x) => match right.value {
-                    Literal::Number(y) => Literal::Bool(x >= y),
+            }
+            Pattern::Tuple(paren, patterns, _close) => {
+                let items = match &value {
+                    Value::Tuple(items) if items.len() == patterns.len() => items.clone(),
+                    Value::Tuple(items) => {
+                        return Err(RuntimeError::Generic(format!(
+                            "Cannot destructure a tuple of {} values into {} names at line {}",
+                            items.len(),
+                            patterns.len(),
+                            paren.line
+                        )))
+                    }
                     _ => {
-                        self.logger.error("Cannot compare non-numbers.".into());
-                        panic!();
+                        return Err(RuntimeError::Generic(format!(
+                            "Cannot destructure a {} '{}' at line {}",
+                            value.type_name(),
+                            value,
+                            paren.line
+                        )))
                     }
-                },
-                _ => {
-                    self.logger.error("Cannot compare non-numbers.".into());
-                    panic!();
+                };
+
+                for (pattern, item) in patterns.iter().zip(items.iter()) {
+                    self.bind_pattern(pattern, item.clone())?;
                 }
-            },
-            x => {
-                panic!("No such operator as {:?}", x);
             }
-        };
+        }
 
-        Object { value }
+        Ok(())
     }
 
-    fn eval_logical(&mut self, left: Expr, op: Token, right: Expr) -> Object {
-        let left_val = self.eval_expr(left);
+    /// Defines the local in `slot`. Its name is only needed by hooks that
+    /// inspect scopes and by `eval`, so it is kept only while hooks are
+    /// installed or once code that calls `eval` has run.
+    fn define_local(&mut self, slot: usize, name: &str, constant: bool, value: Value) {
+        if self.hooks.is_some() || self.name_locals {
+            self.env.name_slot(slot, name, constant);
+        }
+        self.env.define_at(slot, value);
+    }
 
-        let is_op_or = match op.token_type {
-            TokenType::OR => true,
-            _ => false,
-        };
+    /// Enters a new innermost scope. The enclosing chain is moved rather
+    /// than copied, so entering a block costs the same at any depth.
+    fn push_scope(&mut self) {
+        let enclosing = std::mem::replace(&mut self.env, Env::new(None));
+        self.env = Env::new(Some(enclosing));
 
-        if is_op_or {
-            if self.is_truthy(left_val.clone()) {
-                return left_val;
+        self.scope_depth += 1;
+        self.stats.peak_scope_depth = self.stats.peak_scope_depth.max(self.scope_depth);
+    }
+
+    /// Leaves the innermost scope, dropping its bindings.
+    fn pop_scope(&mut self) {
+        let env = std::mem::replace(&mut self.env, Env::new(None));
+        self.env = env.into_enclosing().unwrap();
+        self.scope_depth -= 1;
+    }
+
+    fn exec_block(&mut self, arena: &Arena, statements: &[Stmt]) -> Result<Value, RuntimeError> {
+        self.push_scope();
+        self.deferred.push(Vec::new());
+
+        let mut result = Ok(());
+        for stmt in statements {
+            result = self.execute(arena, stmt);
+            if result.is_err() {
+                break;
             }
-        } else {
-            if !self.is_truthy(left_val.clone()) {
-                return left_val;
+        }
+
+        result = self.run_deferred(arena, result);
+        self.pop_scope();
+
+        result.map(|_| Value::Nil)
+    }
+
+    /// Closes the innermost scope's `defer` list, running it newest first.
+    /// Deferred code runs even when the scope is left by an error, and an
+    /// error it raises replaces the scope's result, as in a finally block.
+    /// Exiting the process or exceeding a limit skips it.
+    fn run_deferred(
+        &mut self,
+        arena: &Arena,
+        result: Result<(), RuntimeError>,
+    ) -> Result<(), RuntimeError> {
+        let deferred = self.deferred.pop().unwrap_or_default();
+
+        let mut result = result;
+        for stmt in deferred.iter().rev() {
+            if let Err(RuntimeError::Exit(_) | RuntimeError::LimitExceeded(_)) = result {
+                break;
+            }
+            if let Err(err) = self.execute(arena, stmt) {
+                result = Err(err);
+            }
+        }
+
+        result
+    }
+
+    fn eval_if(
+        &mut self,
+        arena: &Arena,
+        condition: &Expr,
+        then_block: &Stmt,
+        else_block: Option<&Stmt>,
+    ) -> Result<(), RuntimeError> {
+        let cond_val = self.eval_expr(arena, condition)?;
+
+        if cond_val.is_truthy() {
+            self.execute(arena, then_block)?;
+        } else if let Some(else_block) = else_block {
+            self.execute(arena, else_block)?;
+        }
+
+        Ok(())
+    }
+
+    fn exec_while(
+        &mut self,
+        arena: &Arena,
+        label: Option<&Token>,
+        cond: &Expr,
+        block: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Result<(), RuntimeError> {
+        loop {
+            // The body moved the position on, so point back at the condition
+            // before each evaluation.
+            self.locate(arena, cond);
+            if !self.eval_expr(arena, cond)?.is_truthy() {
+                break;
+            }
+
+            match self.execute(arena, block) {
+                Ok(()) => {}
+                Err(RuntimeError::Break(target)) if targets(&target, label) => break,
+                Err(RuntimeError::Continue(target)) if targets(&target, label) => {}
+                Err(err) => return Err(err),
+            }
+
+            if let Some(increment) = increment {
+                self.locate(arena, increment);
+                self.eval_expr(arena, increment)?;
             }
         }
 
-        return self.eval_expr(right);
+        Ok(())
     }
 
-    fn assign_expr(&mut self, token: Token, expr: Expr) -> Object {
-        let expr_val = self.eval_expr(expr);
+    fn exec_foreach(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        let value = self.eval_expr(arena, iterable)?;
+
+        let items = match value.iter() {
+            Some(items) => items,
+            None => {
+                self.locate(arena, iterable);
+                return Err(RuntimeError::Generic(format!(
+                    "Cannot iterate over a {} '{}' at line {}",
+                    value.type_name(),
+                    value,
+                    keyword.line
+                )));
+            }
+        };
 
-        self.env.assign(token.lexeme, expr_val).unwrap();
+        // The loop variable lives in its own scope around the body, and each
+        // iteration runs its own deferred statements while it is still bound.
+        self.push_scope();
 
-        Object {
-            value: Literal::Nil,
+        let mut result = Ok(());
+        for item in items {
+            self.deferred.push(Vec::new());
+            result = self
+                .bind_pattern(pattern, item)
+                .and_then(|_| self.execute(arena, body));
+            result = match self.run_deferred(arena, result) {
+                Err(RuntimeError::Break(target)) if targets(&target, label) => {
+                    result = Ok(());
+                    break;
+                }
+                Err(RuntimeError::Continue(target)) if targets(&target, label) => Ok(()),
+                result => result,
+            };
+            if result.is_err() {
+                break;
+            }
         }
+
+        self.pop_scope();
+
+        result
     }
 
-    fn eval_expr(&mut self, expr: Expr) -> Object {
-        match expr {
-            Expr::Literal(lit_val) => self.eval_literal(lit_val),
-            Expr::Grouping(inner) => self.eval_group(*inner),
-            Expr::Unary(op, right) => self.eval_unary(op, right),
-            Expr::Binary(left, op, right) => self.eval_binary(*left, op, *right),
-            Expr::Var(var) => self.env.get(var.lexeme).unwrap(),
-            Expr::Assign(token, expr) => self.assign_expr(token, *expr),
-            Expr::Logical(left, op, right) => self.eval_logical(*left, op, *right),
+    fn exec_match(
+        &mut self,
+        arena: &Arena,
+        subject: &Expr,
+        arms: &[(Vec<Expr>, Stmt)],
+        default: Option<&Stmt>,
+    ) -> Result<(), RuntimeError> {
+        let subject = self.eval_expr(arena, subject)?;
+
+        for (patterns, body) in arms {
+            for pattern in patterns {
+                if self.eval_expr(arena, pattern)?.equals(&subject) {
+                    return self.execute(arena, body);
+                }
+            }
         }
+
+        if let Some(default) = default {
+            self.execute(arena, default)?;
+        }
+
+        Ok(())
+    }
+
+    fn exec_catch(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        exception: Value,
+        statements: &[Stmt],
+    ) -> Result<(), RuntimeError> {
+        // The resolver gives the caught value the first slot of its scope.
+        self.push_scope();
+        self.define_local(0, &name.lexeme, false, exception);
+
+        let result = self.exec_block(arena, statements).map(|_| ());
+        self.pop_scope();
+
+        result
     }
 
-    fn eval_var_expr(&mut self, token: Token, initializer: Object) -> Object {
-        self.env.define(token.lexeme, initializer).unwrap();
+    fn exec_try(
+        &mut self,
+        arena: &Arena,
+        try_block: &[Stmt],
+        catch_clause: Option<(&Token, &[Stmt])>,
+        finally_block: Option<&[Stmt]>,
+    ) -> Result<(), RuntimeError> {
+        let mut result = self.exec_block(arena, try_block).map(|_| ());
 
-        Object {
-            value: Literal::Nil,
+        if let Some((ident, catch_block)) = catch_clause {
+            let exception = match result {
+                Err(RuntimeError::Generic(ref message)) => {
+                    Some(Value::String(message.as_str().into()))
+                }
+                Err(RuntimeError::Throw(ref value)) => Some(value.clone()),
+                _ => None,
+            };
+
+            if let Some(exception) = exception {
+                result = self.exec_catch(arena, ident, exception, catch_block);
+            }
         }
+
+        // The finally block always runs; an error raised inside it replaces
+        // whatever the try/catch produced.
+        if let Some(finally_block) = finally_block {
+            self.exec_block(arena, finally_block)?;
+        }
+
+        result
     }
 
-    fn exec_block(&mut self, statements: Vec<Stmt>) -> Object {
-        let local_env = Env::new(Some(self.env.clone()));
-        self.env = local_env;
+    fn load_module(&mut self, import_path: &str) -> Result<Rc<Module>, RuntimeError> {
+        let path = self.modules.resolve(import_path)?;
 
-        for stmt in statements.into_iter() {
-            self.execute(stmt);
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(module);
+        }
+
+        self.budget.enter_call()?;
+        if let Err(err) = self.modules.enter(path.clone()) {
+            self.budget.leave_call();
+            return Err(err);
+        }
+
+        // Every module runs in its own environment that only sees the globals.
+        let module_env = Env::new(None);
+        let saved_env = std::mem::replace(&mut self.env, module_env);
+        // Errors inside the module are reported at the import statement.
+        let saved_position = (self.line, self.column, self.span);
+
+        let globals = if self.strict {
+            self.globals.names()
+        } else {
+            Vec::new()
+        };
+        let mut result = self
+            .modules
+            .load(&path, self.logger.clone(), self.strict, globals);
+        if let Ok(program) = &result {
+            let arena = &program.arena;
+            if !self.name_locals {
+                self.name_locals = mentions(program, "eval");
+            }
+            let strict_run = self.strict_run;
+            self.strict_run = self.strict || strict_pragma(&program.stmts);
+            self.deferred.push(Vec::new());
+            let mut executed = Ok(());
+            for stmt in &program.stmts {
+                executed = self.execute(arena, stmt);
+                if executed.is_err() {
+                    break;
+                }
+            }
+            if let Err(err) = self.run_deferred(arena, executed) {
+                result = Err(err);
+            }
+            self.strict_run = strict_run;
         }
 
-        self.env = self.env.get_enclosing().unwrap();
+        let module_env = std::mem::replace(&mut self.env, saved_env);
+        (self.line, self.column, self.span) = saved_position;
+        self.modules.leave();
+        self.budget.leave_call();
 
-        Object {
-            value: Literal::Nil,
+        // A module that marks anything `pub` exports only those bindings.
+        // One that marks nothing exports everything, as modules always did.
+        let mut exports = module_env.bindings();
+        let program = result?;
+        let public = public_names(&program);
+        if !public.is_empty() {
+            exports.retain(|name, _| public.contains(name.as_str()));
         }
+
+        let constants = exports
+            .keys()
+            .filter(|name| module_env.is_const(name))
+            .cloned()
+            .collect();
+        let module = Rc::new(Module {
+            name: import_path.to_string(),
+            path: path.clone(),
+            exports,
+            constants,
+        });
+        self.modules.insert(path, module.clone());
+
+        Ok(module)
     }
 
-    fn eval_if(&mut self, condition: Expr, then_block: Stmt, else_block: Option<Stmt>) {
-        let cond_val = self.eval_expr(condition);
+    fn exec_import(&mut self, path: &str, alias: Option<&Token>) -> Result<(), RuntimeError> {
+        let module = self.load_module(path)?;
 
-        if self.is_truthy(cond_val) {
-            self.execute(then_block);
-        } else if else_block.is_some() {
-            self.execute(else_block.unwrap());
+        match alias {
+            Some(alias) => self
+                .env
+                .define(alias.lexeme.clone(), Value::Module(module))?,
+            None => {
+                for (name, value) in module.exports.iter() {
+                    if module.constants.contains(name) {
+                        self.env.define_const(name.clone(), value.clone())?;
+                    } else {
+                        self.env.define(name.clone(), value.clone())?;
+                    }
+                }
+            }
         }
+
+        Ok(())
     }
 
-    fn exec_while(&mut self, cond: Expr, block: Stmt) {
-        let mut cond_val = self.eval_expr(cond.clone());
+    /// Points runtime errors at `expr`, for expressions evaluated outside
+    /// the statement that holds them.
+    fn locate(&mut self, arena: &Arena, expr: &Expr) {
+        let token = expr.first_token(arena);
+        self.line = token.line;
+        self.column = token.column;
+        self.span = expr.span(arena);
+    }
 
-        while self.is_truthy(cond_val.clone()) {
-            self.execute(block.clone());
-            cond_val = self.eval_expr(cond.clone());
+    /// Points runtime errors at `token`, such as the operator that failed.
+    fn point_at(&mut self, token: &Token) {
+        self.line = token.line;
+        self.column = token.column;
+        self.span = token.span;
+    }
+
+    /// Bookkeeping shared by every statement: resource limits and hooks.
+    fn before_statement(&mut self, arena: &Arena, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.stats.statements += 1;
+        self.budget.tick_statement()?;
+
+        if let Some(token) = stmt.first_token(arena) {
+            self.line = token.line;
+            self.column = token.column;
         }
+        if let Some(span) = stmt.span(arena) {
+            self.span = span;
+        }
+
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_statement(arena, stmt, self.line);
+
+            let frame = Frame::new(
+                &self.env,
+                &self.globals,
+                self.modules.current(),
+                self.modules.depth(),
+                self.line,
+                self.column,
+            );
+            hooks.inspect(stmt, &frame);
+        }
+
+        Ok(())
     }
 
-    pub fn execute(&mut self, stmt: Stmt) {
-        match stmt {
-            Stmt::Expression(expr) => Some(self.eval_expr(expr)),
-            Stmt::Print(expr) => {
-                let value = self.eval_expr(expr);
-                println!("{}", value);
-                None
-            }
-            Stmt::Var(token, initializer) => {
-                let init = match initializer {
-                    Some(expr) => self.eval_expr(expr),
-                    None => Object {
-                        value: Literal::Nil,
-                    },
-                };
+    pub fn execute(&mut self, arena: &Arena, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.before_statement(arena, stmt)?;
+        self.exec_stmt(arena, stmt)
+    }
 
-                self.eval_var_expr(token, init);
+    fn exec_stmt(&mut self, arena: &Arena, stmt: &Stmt) -> Result<(), RuntimeError> {
+        stmt.accept(arena, self)
+    }
+
+    /// Executes `program` and returns the value of the last statement when
+    /// it is an expression statement, otherwise nil.
+    pub fn interpret(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        let result = self.run_program(&program.arena, &program.stmts);
 
-                None
+        if let Err(err) = &result {
+            match err {
+                RuntimeError::Generic(message) => {
+                    self.report_error(E_RUNTIME, format!("Runtime Error: {}", message));
+                }
+                RuntimeError::Throw(value) => {
+                    self.report_error(
+                        E_UNCAUGHT_EXCEPTION,
+                        format!("Uncaught exception: {}", value),
+                    );
+                }
+                RuntimeError::LimitExceeded(message) => {
+                    self.report_error(E_LIMIT_EXCEEDED, format!("Limit exceeded: {}", message));
+                }
+                RuntimeError::Break(_) | RuntimeError::Continue(_) => {
+                    self.report_error(E_RUNTIME, format!("Runtime Error: {}", err));
+                }
+                RuntimeError::Exit(_) => {}
             }
-            Stmt::Block(statements) => {
-                self.exec_block(statements);
-                None
+        }
+
+        result
+    }
+
+    /// Scans, parses and runs `source` in the current environment, for the
+    /// `eval` native. Problems with the source become runtime errors rather
+    /// than being reported, so the calling script can catch them. A final
+    /// expression does not need its semicolon, so `eval("1 + 2")` works.
+    ///
+    /// The code sees the local variables of the blocks `eval` was called
+    /// from, and what it declares goes in the innermost of them.
+    pub fn eval_source(&mut self, source: &str) -> Result<Value, RuntimeError> {
+        // Every scope but the outermost, which binds its names by name.
+        let mut scopes = Vec::new();
+        let mut env = Some(&self.env);
+        while let Some(scope) = env {
+            env = scope.enclosing();
+            if env.is_some() {
+                scopes.push(scope.named_slots());
             }
-            Stmt::If(condition, then_block, else_block) => {
-                self.eval_if(condition, *then_block, *else_block);
-                None
+        }
+        scopes.reverse();
+
+        let program = match compile_silently(source, &self.logger, scopes.clone()) {
+            Ok(program) => program,
+            Err(err) => compile_silently(&format!("{}\n;", source), &self.logger, scopes)
+                .map_err(|_| RuntimeError::Generic(format!("eval() {}", err)))?,
+        };
+
+        self.budget.enter_call()?;
+        let result = self.run_program(&program.arena, &program.stmts);
+        self.budget.leave_call();
+        result
+    }
+
+    /// Runs top-level statements and their defers, returning the value of
+    /// the last statement if it is an expression.
+    fn run_program(&mut self, arena: &Arena, stmts: &[Stmt]) -> Result<Value, RuntimeError> {
+        if !self.name_locals {
+            self.name_locals = mentions_in(arena, stmts, "eval");
+        }
+        // Code run by `eval` stays strict when the code calling it is.
+        let strict_run = self.strict_run;
+        self.strict_run = strict_run || self.strict || strict_pragma(stmts);
+        let mut last = Value::Nil;
+        let mut result = Ok(());
+
+        // Top-level defers run once the whole program has finished.
+        self.deferred.push(Vec::new());
+        for stmt in stmts.iter() {
+            result = self.before_statement(arena, stmt).and_then(|_| match stmt {
+                Stmt::Expression(expr) => self.eval_expr(arena, expr).map(|value| last = value),
+                stmt => self.exec_stmt(arena, stmt).map(|_| last = Value::Nil),
+            });
+
+            if result.is_err() {
+                break;
             }
-            Stmt::While(cond, block) => {
-                self.exec_while(cond, *block);
-                None
+        }
+
+        let result = self.run_deferred(arena, result);
+        self.strict_run = strict_run;
+        result.map(|_| last)
+    }
+}
+
+impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
+    fn visit_unary_expr(
+        &mut self,
+        arena: &Arena,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_unary(arena, op, right)
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        arena: &Arena,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_binary(arena, left, op, right)
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        arena: &Arena,
+        left: &Expr,
+        op: &Token,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_logical(arena, left, op, right)
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &Arena, inner: &Expr) -> Result<Value, RuntimeError> {
+        self.eval_group(arena, inner)
+    }
+
+    fn visit_literal_expr(
+        &mut self,
+        _arena: &Arena,
+        _token: &Token,
+        literal: &Literal,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_literal(literal)
+    }
+
+    fn visit_var_expr(
+        &mut self,
+        _arena: &Arena,
+        name: &Token,
+        local: Option<Local>,
+    ) -> Result<Value, RuntimeError> {
+        self.stats.lookups += 1;
+        let value = match local {
+            Some(local) => self.env.get_at(local),
+            None => self
+                .env
+                .get(&name.lexeme)
+                .or_else(|| self.globals.get(&name.lexeme)),
+        };
+        value.ok_or_else(|| self.undefined_variable(name))
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        value: &Expr,
+        local: Option<Local>,
+    ) -> Result<Value, RuntimeError> {
+        self.assign_expr(arena, name, value, local)
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        arena: &Arena,
+        callee: &Expr,
+        paren: &Token,
+        args: &[Expr],
+    ) -> Result<Value, RuntimeError> {
+        self.eval_call(arena, callee, paren, args)
+    }
+
+    fn visit_get_expr(
+        &mut self,
+        arena: &Arena,
+        object: &Expr,
+        name: &Token,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_get(arena, object, name)
+    }
+
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &Arena,
+        _open: &Token,
+        items: &[Expr],
+        _close: &Token,
+    ) -> Result<Value, RuntimeError> {
+        self.eval_tuple(arena, items)
+    }
+
+    /// Spreads are unpacked by `eval_call`, so any other one is misplaced.
+    fn visit_spread_expr(
+        &mut self,
+        _arena: &Arena,
+        op: &Token,
+        _expr: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        Err(RuntimeError::Generic(format!(
+            "'...' can only be used on call arguments at line {}",
+            op.line
+        )))
+    }
+}
+
+impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
+    fn visit_print_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        values: &[Expr],
+    ) -> Result<(), RuntimeError> {
+        let mut texts = Vec::with_capacity(values.len());
+        for expr in values {
+            texts.push(self.eval_expr(arena, expr)?.to_string());
+        }
+        let text = texts.join(" ");
+        writeln!(self.out, "{}", text)
+            .map_err(|err| RuntimeError::Generic(format!("Cannot write output: {}", err)))?;
+        if self.logger.logs_prints() {
+            let file = self.current_file();
+            self.logger.record_print(file.as_deref(), self.line, &text);
+        }
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &Arena, expr: &Expr) -> Result<(), RuntimeError> {
+        self.eval_expr(arena, expr)?;
+        Ok(())
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &Arena,
+        pattern: &Pattern,
+        _annotation: Option<&Token>,
+        initializer: Option<&Expr>,
+    ) -> Result<(), RuntimeError> {
+        if self.reloading && self.kept_on_reload(pattern) {
+            return Ok(());
+        }
+
+        let init = match initializer {
+            Some(expr) => self.eval_expr(arena, expr)?,
+            None => Value::Nil,
+        };
+
+        self.bind_pattern(pattern, init)
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        slot: Option<usize>,
+        _annotation: Option<&Token>,
+        initializer: &Expr,
+    ) -> Result<(), RuntimeError> {
+        let value = self.eval_expr(arena, initializer)?;
+
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_var_write(&name.lexeme, &value);
+        }
+
+        match slot {
+            Some(slot) => self.define_local(slot, &name.lexeme, true, value),
+            None if self.reloading => self.env.replace(name.lexeme.clone(), value, true),
+            None => self.env.define_const(name.lexeme.clone(), value)?,
+        }
+
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, arena: &Arena, stmts: &[Stmt]) -> Result<(), RuntimeError> {
+        self.exec_block(arena, stmts)?;
+        Ok(())
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Result<(), RuntimeError> {
+        self.eval_if(arena, condition, then_branch, else_branch)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        label: Option<&Token>,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Result<(), RuntimeError> {
+        self.exec_while(arena, label, condition, body, increment)
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        self.exec_foreach(arena, keyword, label, pattern, iterable, body)
+    }
+
+    fn visit_break_stmt(
+        &mut self,
+        _arena: &Arena,
+        _keyword: &Token,
+        label: Option<&Token>,
+    ) -> Result<(), RuntimeError> {
+        Err(RuntimeError::Break(label.map(|l| l.lexeme.clone())))
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _arena: &Arena,
+        _keyword: &Token,
+        label: Option<&Token>,
+    ) -> Result<(), RuntimeError> {
+        Err(RuntimeError::Continue(label.map(|l| l.lexeme.clone())))
+    }
+
+    fn visit_throw_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        value: &Expr,
+    ) -> Result<(), RuntimeError> {
+        let value = self.eval_expr(arena, value)?;
+        Err(RuntimeError::Throw(value))
+    }
+
+    fn visit_defer_stmt(
+        &mut self,
+        _arena: &Arena,
+        _keyword: &Token,
+        stmt: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        if let Some(deferred) = self.deferred.last_mut() {
+            deferred.push(stmt.clone());
+        }
+        Ok(())
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        try_block: &[Stmt],
+        catch_clause: Option<(&Token, &[Stmt])>,
+        finally_block: Option<&[Stmt]>,
+    ) -> Result<(), RuntimeError> {
+        self.exec_try(arena, try_block, catch_clause, finally_block)
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _arena: &Arena,
+        _keyword: &Token,
+        path: &str,
+        alias: Option<&Token>,
+    ) -> Result<(), RuntimeError> {
+        self.exec_import(path, alias)
+    }
+
+    fn visit_pub_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        stmt: &Stmt,
+    ) -> Result<(), RuntimeError> {
+        self.exec_stmt(arena, stmt)
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        subject: &Expr,
+        arms: &[(Vec<Expr>, Stmt)],
+        default: Option<&Stmt>,
+    ) -> Result<(), RuntimeError> {
+        self.exec_match(arena, subject, arms, default)
+    }
+}
+
+fn env_bindings(env: &Env) -> Vec<Binding> {
+    env.bindings()
+        .into_iter()
+        .map(|(name, value)| Binding {
+            constant: env.is_const(&name),
+            name,
+            value,
+        })
+        .collect()
+}
+
+fn check_arity(arity: &RangeInclusive<usize>, count: usize, line: i32) -> Result<(), RuntimeError> {
+    if arity.contains(&count) {
+        return Ok(());
+    }
+
+    let expected = match (arity.start(), arity.end()) {
+        (min, max) if min == max => min.to_string(),
+        (min, max) => format!("{} to {}", min, max),
+    };
+    Err(RuntimeError::Generic(format!(
+        "Expected {} arguments but got {} at line {}",
+        expected, count, line
+    )))
+}
+
+/// The names bound by the `pub` declarations among a module's top-level
+/// statements.
+fn public_names(program: &Program) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    for stmt in &program.stmts {
+        if let Stmt::Pub(_, stmt) = stmt {
+            match &program.arena[*stmt] {
+                Stmt::Var(pattern, _, _) => {
+                    names.extend(pattern.names().into_iter().map(|name| name.lexeme.as_str()));
+                }
+                Stmt::Const(name, _, _, _) => {
+                    names.insert(name.lexeme.as_str());
+                }
+                _ => {}
             }
+        }
+    }
+    names
+}
+
+/// Whether a `break` or `continue` aimed at `target` stops at the loop
+/// labeled `label`. An unlabeled jump stops at the innermost loop.
+fn targets(target: &Option<String>, label: Option<&Token>) -> bool {
+    match (target, label) {
+        (None, _) => true,
+        (Some(target), Some(label)) => *target == label.lexeme,
+        (Some(_), None) => false,
+    }
+}
+
+/// `+`, `-`, `*` and `/`. Two ints give an int, and `/` between them rounds
+/// toward negative infinity. Overflow is an error rather than wrapping. As
+/// soon as either side is a number the result is a number.
+fn arithmetic(op: TokenType, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+    let symbol = match op {
+        TokenType::PLUS => "+",
+        TokenType::MINUS => "-",
+        TokenType::STAR => "*",
+        _ => "/",
+    };
+
+    if let (Value::Int(a), Value::Int(b)) = (left, right) {
+        let (a, b) = (*a, *b);
+        let result = match op {
+            TokenType::PLUS => a.checked_add(b),
+            TokenType::MINUS => a.checked_sub(b),
+            TokenType::STAR => a.checked_mul(b),
+            _ if b == 0 => return Err(RuntimeError::Generic("Cannot divide by zero".into())),
+            _ => a.checked_div(b).map(|quotient| {
+                if a % b != 0 && (a < 0) != (b < 0) {
+                    quotient - 1
+                } else {
+                    quotient
+                }
+            }),
         };
+
+        return result.map(Value::Int).ok_or_else(|| {
+            RuntimeError::Generic(format!("Integer overflow in {} {} {}", a, symbol, b))
+        });
     }
 
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) {
-        for stmt in stmts.into_iter() {
-            self.execute(stmt);
+    let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) else {
+        return Err(RuntimeError::Generic(format!(
+            "Cannot apply {} to '{}' and '{}'",
+            symbol, left, right
+        )));
+    };
+
+    let result = match op {
+        TokenType::PLUS => a + b,
+        TokenType::MINUS => a - b,
+        TokenType::STAR => a * b,
+        _ if b == 0.0 => return Err(RuntimeError::Generic("Cannot divide by zero".into())),
+        _ => a / b,
+    };
+    Ok(Value::Number(result))
+}
+
+/// `&`, `|`, `^`, `<<` and `>>`, which only apply to ints. `>>` keeps the
+/// sign, and shifting by a negative amount or by 64 or more is an error.
+fn bitwise(op: TokenType, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+    let symbol = match op {
+        TokenType::AMPERSAND => "&",
+        TokenType::PIPE => "|",
+        TokenType::CARET => "^",
+        TokenType::LESS_LESS => "<<",
+        _ => ">>",
+    };
+
+    let (Value::Int(a), Value::Int(b)) = (left, right) else {
+        return Err(RuntimeError::Generic(format!(
+            "Cannot apply {} to '{}' and '{}', which only works on ints",
+            symbol, left, right
+        )));
+    };
+    let (a, b) = (*a, *b);
+
+    let result = match op {
+        TokenType::AMPERSAND => a & b,
+        TokenType::PIPE => a | b,
+        TokenType::CARET => a ^ b,
+        _ => {
+            if !(0..64).contains(&b) {
+                return Err(RuntimeError::Generic(format!(
+                    "Cannot shift by {}, the amount must be between 0 and 63",
+                    b
+                )));
+            }
+            match op {
+                TokenType::LESS_LESS => a << b,
+                _ => a >> b,
+            }
         }
+    };
+
+    Ok(Value::Int(result))
+}
+
+/// Whether strict mode lets `==` and `!=` compare these values: ones of
+/// the same type, an int and a number, or nil and anything, since that is
+/// how code checks for a missing value.
+fn comparable(left: &Value, right: &Value) -> bool {
+    matches!(left, Value::Nil)
+        || matches!(right, Value::Nil)
+        || left.type_name() == right.type_name()
+        || (left.as_f64().is_some() && right.as_f64().is_some())
+}
+
+/// `<`, `<=`, `>` and `>=`. Two ints are compared exactly, and an int
+/// and a number as floats. Strings can be ordered, character by character
+/// by Unicode code point, but not against anything else.
+fn apply_unary(op: &Token, right: Value) -> Result<Value, RuntimeError> {
+    match op.token_type {
+        TokenType::MINUS => match right {
+            Value::Int(x) => x
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::Generic(format!("Integer overflow in -{}", x))),
+            Value::Number(x) => Ok(Value::Number(-x)),
+            x => Err(RuntimeError::Generic(format!(
+                "Cannot apply {:?} to a non-number '{}'",
+                op.token_type, x
+            ))),
+        },
+        TokenType::TILDE => match right {
+            Value::Int(x) => Ok(Value::Int(!x)),
+            x => Err(RuntimeError::Generic(format!(
+                "Cannot apply ~ to a non-int '{}'",
+                x
+            ))),
+        },
+        TokenType::BANG => Ok(Value::Bool(!right.is_truthy())),
+        x => Err(RuntimeError::Generic(format!(
+            "Cannot apply {:?} to '{:?}'",
+            x, right
+        ))),
     }
 }
 
-impl Display for Object {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.value.clone() {
-            Literal::Bool(x) => write!(f, "{}", x),
-            Literal::Number(x) => write!(f, "{}", x),
-            Literal::Nil => write!(f, "nil"),
-            Literal::String(x) => write!(f, "{}", x),
+fn compare(op: &Token, left: &Value, right: &Value) -> Result<Value, RuntimeError> {
+    fn holds<T: PartialOrd + ?Sized>(op: TokenType, a: &T, b: &T) -> bool {
+        match op {
+            TokenType::LESS => a < b,
+            TokenType::LESS_EQUAL => a <= b,
+            TokenType::GREATER => a > b,
+            _ => a >= b,
         }
     }
+
+    let result = match (left, right) {
+        (Value::Int(a), Value::Int(b)) => holds(op.token_type, a, b),
+        (Value::String(a), Value::String(b)) => holds(op.token_type, &**a, &**b),
+        (Value::Int(a), Value::Number(b)) => compare_int_number(*a, *b)
+            .is_some_and(|order| holds(op.token_type, &order, &Ordering::Equal)),
+        (Value::Number(a), Value::Int(b)) => compare_int_number(*b, *a)
+            .is_some_and(|order| holds(op.token_type, &order.reverse(), &Ordering::Equal)),
+        _ => match (left.as_f64(), right.as_f64()) {
+            (Some(a), Some(b)) => holds(op.token_type, &a, &b),
+            _ => {
+                return Err(RuntimeError::Generic(format!(
+                    "Cannot compare {} '{}' with {} '{}' using {}",
+                    left.type_name(),
+                    left,
+                    right.type_name(),
+                    right,
+                    op.lexeme
+                )));
+            }
+        },
+    };
+
+    Ok(Value::Bool(result))
+}
+
+/// Scans, parses and resolves `source` without reporting anything, returning
+/// a description of the first problem found instead. The code is resolved
+/// inside `scopes`, as described in `Resolver::within`.
+fn compile_silently(
+    source: &str,
+    logger: &Log,
+    scopes: Vec<Vec<(String, usize, bool)>>,
+) -> Result<Program, String> {
+    // Problems in the source are the caller's to handle, so they are not
+    // counted with the ones the logger has seen.
+    let silent = Log {
+        level: LogLevel::Off,
+        problems: Rc::new(Cell::new(0)),
+        ..logger.clone()
+    };
+
+    let mut scanner = Scanner::new(source.to_string(), None, silent.clone());
+    scanner.scan_tokens();
+    if scanner.had_error {
+        return Err(format!(
+            "could not scan its source: {}",
+            scanner
+                .errors
+                .iter()
+                .map(|err| err.message.as_str())
+                .collect::<Vec<_>>()
+                .join("; ")
+        ));
+    }
+
+    let invalid = |errors: Vec<ParseError>| {
+        let messages: Vec<String> = errors.iter().map(ParseError::to_string).collect();
+        format!("could not parse its source: {}", messages.join("; "))
+    };
+    let mut program = Parser::new(scanner.tokens, None, &silent)
+        .parse()
+        .map_err(invalid)?;
+    Resolver::new(None, &silent)
+        .within(scopes)
+        .resolve(&mut program)
+        .map_err(invalid)?;
+
+    Ok(program)
+}
+
+/// Whether `program` refers to the variable `name` anywhere.
+fn mentions(program: &Program, name: &str) -> bool {
+    mentions_in(&program.arena, &program.stmts, name)
+}
+
+/// Like `mentions`, for statements whose nodes live in `arena`.
+fn mentions_in(arena: &Arena, stmts: &[Stmt], name: &str) -> bool {
+    let mut finder = Mentions { name, found: false };
+    for stmt in stmts {
+        stmt.accept(arena, &mut finder);
+    }
+    finder.found
+}
+
+struct Mentions<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl Walk for Mentions<'_> {
+    fn visit_var_expr(&mut self, _arena: &Arena, name: &Token, _local: Option<Local>) {
+        self.found |= name.lexeme == self.name;
+    }
 }
@@ -1,26 +1,48 @@
-use std::collections::HashMap;
-
 use crate::token::TokenType;
 
-pub fn get_keywords() -> Box<HashMap<String, TokenType>> {
-    let mut keywords = Box::new(HashMap::new());
+/// Every reserved word, sorted so that it can be binary searched.
+const KEYWORDS: &[(&str, TokenType)] = &[
+    ("and", TokenType::AND),
+    ("as", TokenType::AS),
+    ("break", TokenType::BREAK),
+    ("catch", TokenType::CATCH),
+    ("class", TokenType::CLASS),
+    ("const", TokenType::CONST),
+    ("continue", TokenType::CONTINUE),
+    ("defer", TokenType::DEFER),
+    ("else", TokenType::ELSE),
+    ("false", TokenType::FALSE),
+    ("finally", TokenType::FINALLY),
+    ("for", TokenType::FOR),
+    ("fun", TokenType::FUN),
+    ("if", TokenType::IF),
+    ("import", TokenType::IMPORT),
+    ("in", TokenType::IN),
+    ("is", TokenType::IS),
+    ("match", TokenType::MATCH),
+    ("nil", TokenType::NIL),
+    ("or", TokenType::OR),
+    ("print", TokenType::PRINT),
+    ("pub", TokenType::PUB),
+    ("return", TokenType::RETURN),
+    ("super", TokenType::SUPER),
+    ("this", TokenType::THIS),
+    ("throw", TokenType::THROW),
+    ("true", TokenType::TRUE),
+    ("try", TokenType::TRY),
+    ("var", TokenType::VAR),
+    ("while", TokenType::WHILE),
+];
 
-    keywords.insert("and".to_string(), TokenType::AND);
-    keywords.insert("class".to_string(), TokenType::CLASS);
-    keywords.insert("else".to_string(), TokenType::ELSE);
-    keywords.insert("false".to_string(), TokenType::FALSE);
-    keywords.insert("for".to_string(), TokenType::FOR);
-    keywords.insert("fun".to_string(), TokenType::FUN);
-    keywords.insert("if".to_string(), TokenType::IF);
-    keywords.insert("nil".to_string(), TokenType::NIL);
-    keywords.insert("or".to_string(), TokenType::OR);
-    keywords.insert("print".to_string(), TokenType::PRINT);
-    keywords.insert("return".to_string(), TokenType::RETURN);
-    keywords.insert("super".to_string(), TokenType::SUPER);
-    keywords.insert("this".to_string(), TokenType::THIS);
-    keywords.insert("true".to_string(), TokenType::TRUE);
-    keywords.insert("var".to_string(), TokenType::VAR);
-    keywords.insert("while".to_string(), TokenType::WHILE);
+/// The keyword spelled by `lexeme`, or `None` for an ordinary identifier.
+pub fn keyword(lexeme: &str) -> Option<TokenType> {
+    KEYWORDS
+        .binary_search_by_key(&lexeme, |&(name, _)| name)
+        .ok()
+        .map(|index| KEYWORDS[index].1)
+}
 
-    return keywords;
+/// The reserved words, in alphabetical order.
+pub fn keywords() -> impl Iterator<Item = &'static str> {
+    KEYWORDS.iter().map(|&(name, _)| name)
 }
@@ -0,0 +1,385 @@
+use std::{
+    fs,
+    io::Write,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use interpreter::Interpreter;
+use native::HostFn;
+use parser::Parser;
+use resolver::{analyze, strict_pragma};
+use scanner::Scanner;
+use typecheck::TypeChecker;
+
+pub use arena::{Arena, ExprId, Program, StmtId};
+pub use ast_json::{ast_from_json, ast_to_json, AST_JSON_VERSION};
+pub use convert::{FromRox, IntoRox};
+pub use dap::serve_dap;
+pub use diagnostic::{Diagnostic, Related};
+pub use error::{ErrorFormat, Log, LogFile, LogLevel, RoxError};
+pub use expression::{Expr, Local};
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use fuzz::fuzz_round_trip;
+pub use fuzz::{fuzz_parse, fuzz_scan};
+pub use highlight::{highlight, HighlightFormat};
+pub use hooks::{Frame, InterpreterHooks};
+pub use incremental::Document;
+pub use interpreter::RuntimeError;
+pub use keywords::keywords;
+pub use limits::{Limits, DEFAULT_MAX_DEPTH};
+pub use parser::ParseError;
+pub use printer::ast_to_source;
+pub use project::{Project, MANIFEST};
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use roundtrip::{check_round_trip, random_ast};
+pub use scanner::is_incomplete;
+pub use snapshot::SNAPSHOT_VERSION;
+pub use statement::{Pattern, Stmt};
+pub use stats::Stats;
+pub use token::{Literal, Span, Token, TokenType};
+pub use trace::{Trace, TraceRecorder, TraceStep, TRACE_VERSION};
+pub use value::Value;
+pub use visitor::{
+    walk_expr, walk_expr_mut, walk_stmt, walk_stmt_mut, ExprVisitor, ExprVisitorMut, StmtVisitor,
+    StmtVisitorMut, Walk, WalkMut,
+};
+
+mod arena;
+mod ast_json;
+mod convert;
+mod csv;
+mod dap;
+mod datetime;
+mod diagnostic;
+mod encoding;
+mod env;
+mod error;
+mod expression;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod filesystem;
+mod fuzz;
+mod highlight;
+mod hooks;
+#[cfg(feature = "http")]
+mod http;
+mod incremental;
+mod interpreter;
+mod keywords;
+mod limits;
+mod module;
+mod native;
+mod parser;
+mod printer;
+mod project;
+#[cfg(feature = "regex")]
+mod re;
+mod reflect;
+mod resolver;
+#[cfg(any(test, feature = "fuzzing"))]
+mod roundtrip;
+mod scanner;
+mod snapshot;
+mod socket;
+mod statement;
+mod stats;
+mod suggest;
+mod token;
+mod trace;
+mod typecheck;
+mod value;
+mod visitor;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+/// An embeddable rox interpreter. Global state persists between calls, so a
+/// series of `eval` calls behaves like consecutive lines of one script.
+pub struct Rox {
+    interpreter: Interpreter,
+    logger: Log,
+}
+
+impl Rox {
+    pub fn new() -> Rox {
+        Rox::with_logger(Log::new(LogLevel::Warning))
+    }
+
+    pub fn with_logger(logger: Log) -> Rox {
+        Rox {
+            interpreter: Interpreter::new(logger.clone()),
+            logger,
+        }
+    }
+
+    /// Runs `source` and returns the value of its final expression statement,
+    /// or nil when the source does not end with one.
+    pub fn eval(&mut self, source: &str) -> Result<Value, RoxError> {
+        let program = self.parse(source)?;
+
+        self.interpreter.start_run();
+        Ok(self.interpreter.interpret(&program)?)
+    }
+
+    /// Runs a program that was built or deserialized without going through
+    /// `parse`, e.g. with `ast_from_json`. It is resolved first, so the same
+    /// static errors are caught as for source code.
+    pub fn run_ast(&mut self, mut program: Program) -> Result<Value, RoxError> {
+        self.analyze(&mut program, self.interpreter.current_file())?;
+
+        self.interpreter.start_run();
+        Ok(self.interpreter.interpret(&program)?)
+    }
+
+    /// Runs `source` in the global scope, so the functions and values it
+    /// declares are available to every later `eval`, script and module.
+    /// Hosts use it to ship helper code to all the scripts they run.
+    pub fn load_prelude(&mut self, source: &str) -> Result<(), RoxError> {
+        let program = self.parse(source)?;
+
+        self.interpreter.start_run();
+        Ok(self.interpreter.run_prelude(&program)?)
+    }
+
+    /// Binds `name` to `value` in the global scope, replacing any native or
+    /// prelude binding of the same name.
+    pub fn define_global(&mut self, name: &str, value: impl IntoRox) -> Result<(), RoxError> {
+        Ok(self
+            .interpreter
+            .define_global(name.to_string(), value.into_rox())?)
+    }
+
+    /// Registers a Rust closure as a global function that scripts call like
+    /// any native. `arity` is how many arguments it accepts.
+    pub fn define_native<F>(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        func: F,
+    ) -> Result<(), RoxError>
+    where
+        F: Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static,
+    {
+        let host = HostFn {
+            name: name.to_string(),
+            arity,
+            func: Box::new(func),
+        };
+        self.define_global(name, Value::Host(Rc::new(host)))
+    }
+
+    /// Checks `source` for syntax, scoping and type annotation errors without
+    /// running it.
+    pub fn check(&mut self, source: &str) -> Result<(), RoxError> {
+        let program = self.parse(source)?;
+        // Strict code was type checked while it was parsed.
+        if self.interpreter.strict() || strict_pragma(&program.stmts) {
+            return Ok(());
+        }
+
+        TypeChecker::new(self.interpreter.current_file(), &self.logger)
+            .check(&program)
+            .map_err(RoxError::Parse)
+    }
+
+    /// Scans, parses and resolves `source` without running it.
+    pub fn parse(&self, source: &str) -> Result<Program, RoxError> {
+        let file = self.interpreter.current_file();
+        let mut scanner = Scanner::new(source.to_string(), file.clone(), self.logger.clone());
+        scanner.scan_tokens();
+
+        if scanner.had_error {
+            return Err(RoxError::Scan(scanner.errors));
+        }
+
+        let mut parser = Parser::new(scanner.tokens, file.clone(), &self.logger);
+        let mut program = parser.parse().map_err(RoxError::Parse)?;
+        self.analyze(&mut program, file)?;
+
+        Ok(program)
+    }
+
+    /// Parses and resolves a new version of a file like `parse`, reusing
+    /// the tokens and top-level statements of the version `document` held
+    /// wherever the edit left them unchanged. Resolution still covers the
+    /// whole file. Editors call it on every change to keep diagnostics
+    /// current on large files.
+    pub fn reparse<'d>(
+        &self,
+        document: &'d mut Document,
+        source: &str,
+    ) -> Result<&'d Program, RoxError> {
+        let file = self.interpreter.current_file();
+        document.update(source, file.clone(), &self.logger)?;
+        self.analyze(document.program_mut(), file)?;
+
+        Ok(document.program())
+    }
+
+    fn analyze(&self, program: &mut Program, file: Option<String>) -> Result<(), RoxError> {
+        let strict = self.interpreter.strict();
+        let globals = if strict {
+            self.interpreter.visible_names()
+        } else {
+            Vec::new()
+        };
+        analyze(program, file, &self.logger, strict, globals).map_err(RoxError::Parse)
+    }
+
+    /// The variables, constants, modules and natives defined so far, sorted.
+    /// Tools such as a REPL can use them to complete identifiers.
+    pub fn names(&self) -> Vec<String> {
+        self.interpreter.visible_names()
+    }
+
+    /// Serializes the session's top-level variables and constants, the
+    /// globals a prelude added and the modules loaded so far to JSON. Fails
+    /// if one of them holds an iterator or a socket.
+    pub fn snapshot(&self) -> Result<String, RoxError> {
+        Ok(self.interpreter.snapshot()?)
+    }
+
+    /// Restores a session saved with `snapshot`. Host functions it refers
+    /// to must have been registered again with `define_native` first.
+    pub fn restore(&mut self, snapshot: &str) -> Result<(), RoxError> {
+        Ok(self.interpreter.restore(snapshot)?)
+    }
+
+    /// Writes `snapshot` to a file.
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), RoxError> {
+        let path = path.as_ref();
+        fs::write(path, self.snapshot()?)
+            .map_err(|err| RoxError::Io(format!("Cannot write '{}': {}", path.display(), err)))
+    }
+
+    /// Restores a session from a file written by `save_snapshot`.
+    pub fn load_snapshot<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RoxError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|err| RoxError::Io(format!("Cannot read '{}': {}", path.display(), err)))?;
+        self.restore(&json)
+    }
+
+    /// What the interpreter has done since it was created: statements,
+    /// expressions, variable lookups, allocations and scope depth.
+    pub fn stats(&self) -> Stats {
+        self.interpreter.stats()
+    }
+
+    /// Applies resource limits to every subsequent `eval` or `run_file`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits);
+    }
+
+    /// Disables natives that read the clock, environment or filesystem, so
+    /// the same script and input always produce the same output.
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.interpreter.set_sandbox(sandbox);
+    }
+
+    /// Turns warnings into errors and catches more mistakes before code
+    /// runs: assigning to undeclared names, shadowing, reading variables
+    /// before they are assigned and comparing values of different types.
+    /// Comparisons whose types only show at runtime fail when they run.
+    /// Code can also opt in by starting with `"use strict";`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.interpreter.set_strict(strict);
+    }
+
+    /// Directories to look in, in order, for imports that are not found
+    /// relative to the importing file.
+    pub fn set_module_paths(&mut self, paths: Vec<PathBuf>) {
+        self.interpreter.set_module_paths(paths);
+    }
+
+    /// Applies the module paths, features and limits of a `rox.toml`.
+    /// Features it leaves off stay as they were.
+    pub fn set_project(&mut self, project: &Project) {
+        self.set_module_paths(project.paths.clone());
+        if project.sandbox {
+            self.set_sandbox(true);
+        }
+        if project.strict {
+            self.set_strict(true);
+        }
+        self.set_limits(project.limits);
+    }
+
+    /// Redirects `print` output and runtime error reports, which default to
+    /// stdout and stderr.
+    pub fn set_output(&mut self, out: Box<dyn Write>, err: Box<dyn Write>) {
+        self.interpreter.set_output(out, err);
+    }
+
+    /// Installs callbacks that observe statements, calls and variable writes.
+    pub fn set_hooks(&mut self, hooks: Box<dyn InterpreterHooks>) {
+        self.interpreter.set_hooks(hooks);
+    }
+
+    /// Runs a script file. Imports inside it resolve relative to its location.
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, RoxError> {
+        self.with_file(path.as_ref(), |rox, source| rox.eval(source))
+    }
+
+    /// Runs a new version of a script over the state an earlier run left,
+    /// for hot reloading. Top-level variables that already exist keep their
+    /// values, constants are redefined and every other statement runs
+    /// again. Modules that were already imported are not run again.
+    pub fn reload_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, RoxError> {
+        self.with_file(path.as_ref(), |rox, source| {
+            let program = rox.parse(source)?;
+            rox.interpreter.start_run();
+            Ok(rox.interpreter.reload(&program)?)
+        })
+    }
+
+    /// Like `reload_file`, parsing only what changed since `document` last
+    /// saw the file.
+    pub fn reload_document<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        document: &mut Document,
+    ) -> Result<Value, RoxError> {
+        self.with_file(path.as_ref(), |rox, source| {
+            let program = rox.reparse(document, source)?;
+            rox.interpreter.start_run();
+            Ok(rox.interpreter.reload(program)?)
+        })
+    }
+
+    /// Checks a script file like `check`, without running it.
+    pub fn check_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RoxError> {
+        self.with_file(path.as_ref(), |rox, source| rox.check(source))
+    }
+
+    /// Parses a script file like `parse`, without running it.
+    pub fn parse_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Program, RoxError> {
+        self.with_file(path.as_ref(), |rox, source| rox.parse(source))
+    }
+
+    /// Reads `path` and hands its source to `f` with the file recorded as
+    /// the current script, for diagnostics and relative imports.
+    fn with_file<T>(
+        &mut self,
+        path: &Path,
+        f: impl FnOnce(&mut Rox, &str) -> Result<T, RoxError>,
+    ) -> Result<T, RoxError> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| RoxError::Io(format!("Cannot read '{}': {}", path.display(), err)))?;
+
+        self.interpreter.enter_script(path)?;
+        let result = f(self, &source);
+        self.interpreter.leave_script();
+
+        result
+    }
+}
+
+impl Default for Rox {
+    fn default() -> Self {
+        Rox::new()
+    }
+}
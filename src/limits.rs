@@ -0,0 +1,135 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::interpreter::RuntimeError;
+
+/// How deeply calls, imports and `eval`s may nest unless the embedder says
+/// otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Caps on the work a single run may do. `None` means unlimited, except
+/// for the call depth, which always has a limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// Total number of statements executed.
+    pub max_statements: Option<u64>,
+    /// Depth of nested calls, imports and `eval`s. `None` means
+    /// `DEFAULT_MAX_DEPTH`, which keeps deep nesting from overflowing the
+    /// native stack.
+    pub max_call_depth: Option<usize>,
+    /// Number of strings and lists created by calls at runtime.
+    pub max_heap_objects: Option<usize>,
+    /// Wall-clock time since the run started, including the time spent
+    /// waiting in `sleep`, `after` and `every`.
+    pub timeout: Option<Duration>,
+}
+
+/// Tracks usage against a set of `Limits` for the current run.
+#[derive(Debug, Default)]
+pub struct Budget {
+    pub limits: Limits,
+    statements: u64,
+    call_depth: usize,
+    heap_objects: usize,
+    started: Option<Instant>,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.statements = 0;
+        self.call_depth = 0;
+        self.heap_objects = 0;
+        // Reading the clock is only needed for a timeout, and is not
+        // possible at all on some targets, such as WebAssembly.
+        self.started = self.limits.timeout.map(|_| Instant::now());
+    }
+
+    pub fn tick_statement(&mut self) -> Result<(), RuntimeError> {
+        self.statements += 1;
+
+        if let Some(max) = self.limits.max_statements {
+            if self.statements > max {
+                return Err(RuntimeError::LimitExceeded(format!(
+                    "Statement budget of {} exceeded",
+                    max
+                )));
+            }
+        }
+
+        if let (Some(timeout), Some(started)) = (self.limits.timeout, self.started) {
+            if started.elapsed() > timeout {
+                return Err(timed_out(timeout));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Blocks for `duration`, or only until the timeout if that comes first.
+    pub fn sleep(&self, duration: Duration) -> Result<(), RuntimeError> {
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            return Err(RuntimeError::Generic(
+                "Sleeping is not supported in a WebAssembly build".to_string(),
+            ));
+        }
+
+        if let (Some(timeout), Some(started)) = (self.limits.timeout, self.started) {
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if duration > remaining {
+                thread::sleep(remaining);
+                return Err(timed_out(timeout));
+            }
+        }
+
+        thread::sleep(duration);
+        Ok(())
+    }
+
+    /// Counts a call, import or `eval` being entered. Going too deep is an
+    /// ordinary runtime error that scripts can catch, unlike the other
+    /// limits, since unwinding the nesting is all it takes to recover.
+    pub fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        let max = self.limits.max_call_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if self.call_depth >= max {
+            return Err(RuntimeError::Generic(format!(
+                "Maximum call depth of {} exceeded",
+                max
+            )));
+        }
+
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    pub fn leave_call(&mut self) {
+        self.call_depth -= 1;
+    }
+
+    pub fn allocate(&mut self, count: usize) -> Result<(), RuntimeError> {
+        self.heap_objects += count;
+
+        if let Some(max) = self.limits.max_heap_objects {
+            if self.heap_objects > max {
+                return Err(RuntimeError::LimitExceeded(format!(
+                    "Heap object limit of {} exceeded",
+                    max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn timed_out(timeout: Duration) -> RuntimeError {
+    RuntimeError::LimitExceeded(format!("Timed out after {:?}", timeout))
+}
@@ -1,43 +1,248 @@
-use std::fs;
-use std::io::{stdin, stdout, Write};
-use std::str::from_utf8;
-
-use expression::Expr;
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
-use token::{Token, TokenType};
-
-use crate::error::{Log, LogLevel};
-use crate::token::Literal;
-
-mod core;
-mod env;
-mod error;
-mod expression;
-mod interpreter;
-mod keywords;
-mod parser;
-mod scanner;
-mod statement;
-mod token;
+use std::io::{stdin, stdout, BufReader};
+use std::process::exit;
+use std::time::Duration;
+use std::{env, fs, thread};
+
+mod repl;
+mod replay;
+
+use rox_rust::{
+    ast_to_json, highlight, serve_dap, Document, ErrorFormat, HighlightFormat, Limits, Log,
+    LogLevel, Project, Rox, RoxError, RuntimeError, TraceRecorder,
+};
+
+// Exit codes follow the BSD sysexits convention.
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+const EX_CANTCREAT: i32 = 73;
+const EX_CONFIG: i32 = 78;
+
+/// How often `--watch` looks at the script's modification time.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+const USAGE: &str =
+    "Usage: rox [run | check | repl | highlight | replay | dap] [--sandbox] [--strict] \
+                     [--watch] [--stats] [--record=trace] [--log-file=file [--log-prints]] \
+                     [--log-level=debug|info|warn|error|off] [--error-format=text|json] \
+                     [--max-depth=N] [--emit=ast-json] [--ansi | --html] [script]";
+
+#[derive(PartialEq, Eq)]
+enum Mode {
+    Run,
+    Check,
+    Repl,
+    Highlight,
+    Replay,
+    Dap,
+}
 
 fn main() {
-    let logger = Log {
-        level: LogLevel::Debug,
+    let mut path = None;
+    let mut mode = Mode::Run;
+    let mut highlight_format = HighlightFormat::Ansi;
+    let mut emit_ast = false;
+    let mut sandbox = false;
+    let mut strict = false;
+    let mut watch = false;
+    let mut stats = false;
+    let mut record = None;
+    let mut log_file = None;
+    let mut log_prints = false;
+    let mut log_level = LogLevel::Warning;
+    let mut error_format = ErrorFormat::Text;
+    let mut max_depth = None;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--sandbox" => sandbox = true,
+            "--strict" => strict = true,
+            "--watch" => watch = true,
+            "--stats" => stats = true,
+            "--log-prints" => log_prints = true,
+            "run" if path.is_none() && mode == Mode::Run => {}
+            "check" if path.is_none() && mode == Mode::Run => mode = Mode::Check,
+            "repl" if path.is_none() && mode == Mode::Run => mode = Mode::Repl,
+            "highlight" if path.is_none() && mode == Mode::Run => mode = Mode::Highlight,
+            "replay" if path.is_none() && mode == Mode::Run => mode = Mode::Replay,
+            "dap" if path.is_none() && mode == Mode::Run => mode = Mode::Dap,
+            "--ansi" => highlight_format = HighlightFormat::Ansi,
+            "--html" => highlight_format = HighlightFormat::Html,
+            flag if flag.starts_with("--log-level=") => {
+                log_level = match flag["--log-level=".len()..].parse() {
+                    Ok(level) => level,
+                    Err(message) => {
+                        eprintln!("{}\n{}", message, USAGE);
+                        exit(EX_USAGE);
+                    }
+                };
+            }
+            flag if flag.starts_with("--error-format=") => {
+                error_format = match flag["--error-format=".len()..].parse() {
+                    Ok(format) => format,
+                    Err(message) => {
+                        eprintln!("{}\n{}", message, USAGE);
+                        exit(EX_USAGE);
+                    }
+                };
+            }
+            flag if flag.starts_with("--max-depth=") => {
+                max_depth = match flag["--max-depth=".len()..].parse() {
+                    Ok(depth) => Some(depth),
+                    Err(_) => {
+                        eprintln!("Invalid maximum depth in '{}'\n{}", flag, USAGE);
+                        exit(EX_USAGE);
+                    }
+                };
+            }
+            flag if flag.starts_with("--record=") => {
+                record = Some(flag["--record=".len()..].to_string());
+            }
+            flag if flag.starts_with("--log-file=") => {
+                log_file = Some(flag["--log-file=".len()..].to_string());
+            }
+            "--emit=ast-json" => emit_ast = true,
+            flag if flag.starts_with("--emit=") => {
+                eprintln!("Unknown output '{}'\n{}", &flag["--emit=".len()..], USAGE);
+                exit(EX_USAGE);
+            }
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown option '{}'\n{}", flag, USAGE);
+                exit(EX_USAGE);
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                exit(EX_USAGE);
+            }
+        }
+    }
+
+    if ((mode == Mode::Repl || mode == Mode::Dap) && path.is_some())
+        || ((watch || stats || record.is_some()) && (mode != Mode::Run || emit_ast))
+        || (watch && stats)
+        || (mode == Mode::Replay && path.is_none())
+        || (log_prints && log_file.is_none())
+    {
+        eprintln!("{}", USAGE);
+        exit(EX_USAGE);
+    }
+
+    // Running or checking without a script runs the project: a rox.toml
+    // here or in a directory above says what to run, and sets up imports,
+    // features and limits. A script given by name runs on its own.
+    let runs_project = path.is_none() && matches!(mode, Mode::Run | Mode::Check);
+    let project = match runs_project.then(Project::find_current).transpose() {
+        Ok(project) => project.flatten(),
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(EX_CONFIG);
+        }
     };
+    let path = path
+        .or_else(|| {
+            let entry = project.as_ref()?.entry.as_ref()?;
+            Some(entry.display().to_string())
+        })
+        .unwrap_or("source.rox".to_string());
+
+    let mut logger = Log::new(log_level);
+    logger.format = error_format;
+    if let Some(log_file) = &log_file {
+        if let Err(err) = logger.log_to_file(log_file, log_prints) {
+            eprintln!("Cannot create '{}': {}", log_file, err);
+            exit(EX_CANTCREAT);
+        }
+    }
 
-    let source = fs::read_to_string("source.rox").unwrap();
+    let mut rox = Rox::with_logger(logger.clone());
+    rox.set_sandbox(sandbox);
+    rox.set_strict(strict);
+    if let Some(project) = &project {
+        rox.set_project(project);
+    }
+    if let Some(max_depth) = max_depth {
+        let mut limits = project.as_ref().map_or_else(Limits::default, |p| p.limits);
+        limits.max_call_depth = Some(max_depth);
+        rox.set_limits(limits);
+    }
 
-    let mut s = Scanner::new(source, logger);
+    if let Some(trace) = &record {
+        match TraceRecorder::create(trace, &path) {
+            Ok(recorder) => rox.set_hooks(Box::new(recorder)),
+            Err(err) => {
+                eprintln!("Cannot create '{}': {}", trace, err);
+                exit(EX_CANTCREAT);
+            }
+        }
+    }
 
-    s.scan_tokens();
+    let result = match mode {
+        Mode::Repl => exit(repl::run(&mut rox)),
+        Mode::Replay => exit(replay::run(&path)),
+        Mode::Dap => {
+            serve_dap(rox, BufReader::new(stdin()), stdout());
+            exit(0);
+        }
+        Mode::Highlight => fs::read_to_string(&path)
+            .map(|source| print!("{}", highlight(&source, highlight_format)))
+            .map_err(|err| RoxError::Io(format!("Cannot read '{}': {}", path, err))),
+        _ if emit_ast => rox
+            .parse_file(&path)
+            .map(|stmts| println!("{}", ast_to_json(&stmts))),
+        Mode::Check => rox.check_file(&path),
+        Mode::Run if watch => exit(watch_file(&mut rox, &path)),
+        Mode::Run => rox.run_file(&path).map(|_| ()),
+    };
+    if stats {
+        eprint!("{}", rox.stats());
+    }
+    // Dropping the interpreter flushes a trace being recorded, which
+    // `exit` would otherwise skip.
+    drop(rox);
+
+    match result {
+        // `check` is for CI and hooks, where a warning should fail too.
+        Ok(()) if mode == Mode::Check && logger.problems() > 0 => exit(EX_DATAERR),
+        Ok(()) => {}
+        Err(RoxError::Io(message)) => {
+            logger.error(message);
+            exit(EX_NOINPUT);
+        }
+        Err(RoxError::Scan(_)) | Err(RoxError::Parse(_)) => exit(EX_DATAERR),
+        Err(RoxError::Runtime(RuntimeError::Exit(code))) => exit(code),
+        Err(RoxError::Runtime(_)) | Err(RoxError::LimitExceeded(_)) => exit(EX_SOFTWARE),
+    }
+}
 
-    let mut parser = Parser::new(s.tokens.clone(), &logger);
+/// Runs the script, then runs it again over the same state whenever the
+/// file changes, until it calls `exit`. Errors are reported and the watch
+/// goes on, so a typo does not lose the session. Reloads only parse the
+/// parts of the file that changed.
+fn watch_file(rox: &mut Rox, path: &str) -> i32 {
+    let modified = || fs::metadata(path).and_then(|meta| meta.modified()).ok();
 
-    let stmts = parser.parse().unwrap();
+    let mut document = Document::new();
+    let mut last = modified();
+    let mut result = rox.run_file(path);
+    loop {
+        match result {
+            Err(RoxError::Runtime(RuntimeError::Exit(code))) => return code,
+            Err(RoxError::Io(message)) => eprintln!("{}", message),
+            _ => {}
+        }
 
-    let mut interpreter = Interpreter::new(logger);
+        let changed = loop {
+            thread::sleep(WATCH_INTERVAL);
+            let current = modified();
+            if current.is_some() && current != last {
+                break current;
+            }
+        };
+        last = changed;
 
-    interpreter.interpret(stmts);
+        eprintln!("Reloading {}", path);
+        result = rox.reload_document(path, &mut document);
+    }
 }
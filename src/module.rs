@@ -0,0 +1,142 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    arena::Program, error::Log, interpreter::RuntimeError, parser::Parser, resolver::analyze,
+    scanner::Scanner, value::Value,
+};
+
+pub struct Module {
+    pub name: String,
+    /// The file the module was loaded from, which identifies it.
+    pub path: PathBuf,
+    pub exports: HashMap<String, Value>,
+    /// The exports that were declared `const`, which stay constant when an
+    /// import copies them into the importing scope.
+    pub constants: HashSet<String>,
+}
+
+impl Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+#[derive(Default)]
+pub struct ModuleLoader {
+    cache: HashMap<PathBuf, Rc<Module>>,
+    loading: Vec<PathBuf>,
+    /// Where to look for imports that are not next to the importing file.
+    search_paths: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> ModuleLoader {
+        ModuleLoader::default()
+    }
+
+    pub fn set_search_paths(&mut self, paths: Vec<PathBuf>) {
+        self.search_paths = paths;
+    }
+
+    /// Resolves an import path relative to the directory of the file that is
+    /// currently executing, or the working directory for the entry script.
+    /// Failing that, each search path is tried in turn.
+    pub fn resolve(&self, import_path: &str) -> Result<PathBuf, RuntimeError> {
+        let base = match self.loading.last() {
+            Some(current) => current.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            None => PathBuf::from("."),
+        };
+
+        std::iter::once(&base)
+            .chain(&self.search_paths)
+            .find_map(|dir| fs::canonicalize(dir.join(import_path)).ok())
+            .ok_or_else(|| RuntimeError::Generic(format!("Cannot find module '{}'", import_path)))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Rc<Module>> {
+        self.cache.get(path).cloned()
+    }
+
+    pub fn insert(&mut self, path: PathBuf, module: Rc<Module>) {
+        self.cache.insert(path, module);
+    }
+
+    /// Every module loaded so far.
+    pub fn loaded(&self) -> impl Iterator<Item = &Rc<Module>> {
+        self.cache.values()
+    }
+
+    pub fn enter(&mut self, path: PathBuf) -> Result<(), RuntimeError> {
+        if let Some(pos) = self.loading.iter().position(|p| *p == path) {
+            let mut chain: Vec<String> = self.loading[pos..]
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            chain.push(path.display().to_string());
+
+            return Err(RuntimeError::Generic(format!(
+                "Circular import: {}",
+                chain.join(" -> ")
+            )));
+        }
+
+        self.loading.push(path);
+        Ok(())
+    }
+
+    /// The file currently being executed, if it was loaded from disk.
+    pub fn current(&self) -> Option<&Path> {
+        self.loading.last().map(|path| path.as_path())
+    }
+
+    /// How many scripts and modules are being run, one inside the other.
+    pub fn depth(&self) -> usize {
+        self.loading.len()
+    }
+
+    pub fn leave(&mut self) {
+        self.loading.pop();
+    }
+
+    /// Reads and compiles the module at `path`. In strict mode `globals` are
+    /// the names it may assign to without declaring them.
+    pub fn load(
+        &self,
+        path: &Path,
+        logger: Log,
+        strict: bool,
+        globals: Vec<String>,
+    ) -> Result<Program, RuntimeError> {
+        logger.debug(format!("Loading module '{}'", path.display()));
+
+        let source = fs::read_to_string(path).map_err(|err| {
+            RuntimeError::Generic(format!("Cannot read module '{}': {}", path.display(), err))
+        })?;
+
+        let file = Some(path.display().to_string());
+        let mut scanner = Scanner::new(source, file.clone(), logger.clone());
+        scanner.scan_tokens();
+
+        if scanner.had_error {
+            return Err(RuntimeError::Generic(format!(
+                "Failed to compile module '{}'",
+                path.display()
+            )));
+        }
+
+        let mut parser = Parser::new(scanner.tokens, file.clone(), &logger);
+        let compile_error =
+            |_| RuntimeError::Generic(format!("Failed to compile module '{}'", path.display()));
+
+        let mut program = parser.parse().map_err(compile_error)?;
+        analyze(&mut program, file, &logger, strict, globals).map_err(compile_error)?;
+
+        Ok(program)
+    }
+}
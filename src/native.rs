@@ -0,0 +1,1129 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    fmt::Debug,
+    io::stdin,
+    ops::RangeInclusive,
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    datetime::{format_timestamp, parse_timestamp},
+    interpreter::{Interpreter, RuntimeError},
+    value::{compare_int_number, Value, ValueIter},
+};
+
+pub type NativeFnPtr = fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>;
+
+#[derive(Clone)]
+pub struct NativeFn {
+    pub name: &'static str,
+    /// How many arguments the native accepts. Trailing optional arguments
+    /// are simply missing from the argument list.
+    pub arity: RangeInclusive<usize>,
+    pub func: NativeFnPtr,
+    /// False for natives whose result depends on the host (environment,
+    /// clock, filesystem, ...). These are refused in sandbox mode. Natives
+    /// that only wait, such as `sleep`, are deterministic; their waits count
+    /// against `Limits::timeout` instead.
+    pub deterministic: bool,
+}
+
+impl Debug for NativeFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+pub type HostFnImpl = dyn Fn(Vec<Value>) -> Result<Value, RuntimeError>;
+
+/// A function supplied by the program embedding the interpreter. Unlike a
+/// `NativeFn` it can capture state, and it is always allowed in sandbox
+/// mode since the host chose to provide it.
+pub struct HostFn {
+    pub name: String,
+    pub arity: RangeInclusive<usize>,
+    pub func: Box<HostFnImpl>,
+}
+
+impl Debug for HostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+pub fn get_natives() -> Vec<NativeFn> {
+    let natives = vec![
+        NativeFn {
+            name: "exit",
+            arity: 0..=1,
+            func: exit,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "len",
+            arity: 1..=1,
+            func: len,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "upper",
+            arity: 1..=1,
+            func: upper,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "lower",
+            arity: 1..=1,
+            func: lower,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "trim",
+            arity: 1..=1,
+            func: trim,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "split",
+            arity: 2..=2,
+            func: split,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "replace",
+            arity: 3..=3,
+            func: replace,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "contains",
+            arity: 2..=2,
+            func: contains,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "char_at",
+            arity: 2..=2,
+            func: char_at,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "chars",
+            arity: 1..=1,
+            func: chars,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "bytes",
+            arity: 1..=1,
+            func: bytes,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "codepoint",
+            arity: 2..=2,
+            func: codepoint,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "format",
+            arity: 1..=usize::MAX,
+            func: format_string,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "env",
+            arity: 1..=1,
+            func: env,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "set_env",
+            arity: 2..=2,
+            func: set_env,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "platform",
+            arity: 0..=0,
+            func: platform,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "cwd",
+            arity: 0..=0,
+            func: cwd,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "exec",
+            arity: 1..=2,
+            func: exec,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "shell",
+            arity: 1..=1,
+            func: shell,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "time",
+            arity: 0..=0,
+            func: time,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "clock",
+            arity: 0..=0,
+            func: clock,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "sleep",
+            arity: 1..=1,
+            func: sleep,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "after",
+            arity: 2..=2,
+            func: after,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "every",
+            arity: 2..=3,
+            func: every,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "time_format",
+            arity: 2..=2,
+            func: time_format,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "time_parse",
+            arity: 2..=2,
+            func: time_parse,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "input",
+            arity: 0..=1,
+            func: input,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "write",
+            arity: 0..=usize::MAX,
+            func: write,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "eprint",
+            arity: 0..=usize::MAX,
+            func: eprint,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "eval",
+            arity: 1..=1,
+            func: eval,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "type",
+            arity: 1..=1,
+            func: type_of,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "is_same",
+            arity: 2..=2,
+            func: is_same,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "clone",
+            arity: 1..=1,
+            func: clone,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "list",
+            arity: 1..=1,
+            func: list,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "iter",
+            arity: 1..=1,
+            func: iter,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "next",
+            arity: 1..=1,
+            func: next,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "zip",
+            arity: 2..=2,
+            func: zip,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "enumerate",
+            arity: 1..=1,
+            func: enumerate,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "map",
+            arity: 2..=2,
+            func: map,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "filter",
+            arity: 2..=2,
+            func: filter,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "reduce",
+            arity: 3..=3,
+            func: reduce,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "sort",
+            arity: 1..=2,
+            func: sort,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "num",
+            arity: 1..=1,
+            func: num,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "int",
+            arity: 1..=1,
+            func: int,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "float",
+            arity: 1..=1,
+            func: float,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "str",
+            arity: 1..=1,
+            func: str,
+            deterministic: true,
+        },
+    ];
+
+    let natives = [
+        natives,
+        crate::csv::natives(),
+        crate::encoding::natives(),
+        crate::filesystem::natives(),
+        crate::reflect::natives(),
+        crate::socket::natives(),
+    ]
+    .concat();
+    #[cfg(feature = "regex")]
+    let natives = [natives, crate::re::natives()].concat();
+    #[cfg(feature = "http")]
+    let natives = [natives, crate::http::natives()].concat();
+
+    natives
+}
+
+pub fn string_arg(name: &str, args: &[Value], index: usize) -> Result<Rc<str>, RuntimeError> {
+    match &args[index] {
+        Value::String(s) => Ok(s.clone()),
+        x => Err(RuntimeError::Generic(format!(
+            "{}() expects a string as argument {}, got '{}'",
+            name,
+            index + 1,
+            x
+        ))),
+    }
+}
+
+pub fn string_value(value: String) -> Value {
+    Value::String(value.into())
+}
+
+fn exit(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let code = args.first().cloned().unwrap_or(Value::Int(0));
+    match code.as_i64() {
+        Some(code) if (0..=255).contains(&code) => Err(RuntimeError::Exit(code as i32)),
+        _ => Err(RuntimeError::Generic(format!(
+            "exit() expects an integer code between 0 and 255, got '{}'",
+            code
+        ))),
+    }
+}
+
+fn len(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let len = match &args[0] {
+        Value::String(s) => s.chars().count(),
+        Value::List(items) | Value::Tuple(items) => items.len(),
+        range @ Value::Range(..) => match range.range_items() {
+            Some(items) if !items.is_empty() => {
+                let count = *items.end() as i128 - *items.start() as i128 + 1;
+                usize::try_from(count).unwrap_or(usize::MAX)
+            }
+            _ => 0,
+        },
+        x => {
+            return Err(RuntimeError::Generic(format!(
+                "len() expects a string, list, tuple or range, got a {} '{}'",
+                x.type_name(),
+                x
+            )))
+        }
+    };
+
+    Ok(Value::Int(len as i64))
+}
+
+fn upper(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(string_arg("upper", &args, 0)?.to_uppercase()))
+}
+
+fn lower(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(string_arg("lower", &args, 0)?.to_lowercase()))
+}
+
+fn trim(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(
+        string_arg("trim", &args, 0)?.trim().to_string(),
+    ))
+}
+
+fn split(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("split", &args, 0)?;
+    let sep = string_arg("split", &args, 1)?;
+
+    // An empty separator splits the string into its characters.
+    let parts: Vec<Value> = if sep.is_empty() {
+        s.chars().map(|c| string_value(c.to_string())).collect()
+    } else {
+        s.split(&*sep)
+            .map(|part| string_value(part.to_string()))
+            .collect()
+    };
+
+    Ok(Value::List(Rc::new(parts)))
+}
+
+fn replace(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("replace", &args, 0)?;
+    let from = string_arg("replace", &args, 1)?;
+    let to = string_arg("replace", &args, 2)?;
+
+    if from.is_empty() {
+        return Err(RuntimeError::Generic(
+            "replace() cannot replace an empty string".to_string(),
+        ));
+    }
+
+    Ok(string_value(s.replace(&*from, &to)))
+}
+
+fn contains(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("contains", &args, 0)?;
+    let sub = string_arg("contains", &args, 1)?;
+
+    Ok(Value::Bool(s.contains(&*sub)))
+}
+
+fn index_arg(name: &str, args: &[Value], index: usize) -> Result<usize, RuntimeError> {
+    match args[index].as_i64() {
+        Some(i) if i >= 0 => Ok(i as usize),
+        _ => Err(RuntimeError::Generic(format!(
+            "{}() expects a non-negative integer index, got '{}'",
+            name, args[index]
+        ))),
+    }
+}
+
+fn nth_char(name: &str, s: &str, index: usize) -> Result<char, RuntimeError> {
+    s.chars().nth(index).ok_or_else(|| {
+        RuntimeError::Generic(format!(
+            "{}() index {} is out of range for a string of length {}",
+            name,
+            index,
+            s.chars().count()
+        ))
+    })
+}
+
+fn char_at(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("char_at", &args, 0)?;
+    let index = index_arg("char_at", &args, 1)?;
+
+    Ok(string_value(nth_char("char_at", &s, index)?.to_string()))
+}
+
+fn chars(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("chars", &args, 0)?;
+
+    Ok(Value::List(Rc::new(
+        s.chars().map(|c| string_value(c.to_string())).collect(),
+    )))
+}
+
+/// The UTF-8 encoding of a string, one number per byte.
+fn bytes(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("bytes", &args, 0)?;
+
+    Ok(Value::List(Rc::new(
+        s.bytes().map(|b| Value::Int(b as i64)).collect(),
+    )))
+}
+
+fn codepoint(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let s = string_arg("codepoint", &args, 0)?;
+    let index = index_arg("codepoint", &args, 1)?;
+
+    Ok(Value::Int(nth_char("codepoint", &s, index)? as i64))
+}
+
+/// `format("x={}, y={:.2}", x, y)`. Each `{}` takes the next argument and
+/// `{n}` takes argument `n`, counting from zero. After a `:` comes an
+/// optional alignment (`<`, `>` or `^`), a `0` to pad numbers with zeros, a
+/// width, and a `.` followed by the number of decimals, or of characters for
+/// anything but a number. `{{` and `}}` stand for literal braces.
+fn format_string(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let template = string_arg("format", &args, 0)?;
+    let values = &args[1..];
+
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(RuntimeError::Generic(
+                                "format() found a '{' without a closing '}'".to_string(),
+                            ))
+                        }
+                    }
+                }
+
+                let (index, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let index = if index.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    index.parse().map_err(|_| {
+                        RuntimeError::Generic(format!(
+                            "format() expects an argument number in '{{{}}}'",
+                            field
+                        ))
+                    })?
+                };
+                let value = values.get(index).ok_or_else(|| {
+                    RuntimeError::Generic(format!(
+                        "format() has no argument {} for '{{{}}}', got {} arguments",
+                        index,
+                        field,
+                        values.len()
+                    ))
+                })?;
+
+                out.push_str(&format_value(value, spec)?);
+            }
+            '}' => {
+                return Err(RuntimeError::Generic(
+                    "format() found a '}' without an opening '{'; write '}}' for a literal brace"
+                        .to_string(),
+                ))
+            }
+            c => out.push(c),
+        }
+    }
+
+    Ok(string_value(out))
+}
+
+/// The largest width or precision a `format()` placeholder may ask for, so
+/// that a script cannot make the host allocate without bound.
+const MAX_FORMAT_WIDTH: usize = 1000;
+
+/// Renders one placeholder according to a spec such as `>8.2` or `05`.
+fn format_value(value: &Value, spec: &str) -> Result<String, RuntimeError> {
+    let invalid = || RuntimeError::Generic(format!("format() cannot understand '{{:{}}}'", spec));
+
+    let mut rest = spec;
+    let align = match rest.chars().next() {
+        Some(c @ ('<' | '>' | '^')) => {
+            rest = &rest[1..];
+            Some(c)
+        }
+        _ => None,
+    };
+    let zero = rest.starts_with('0');
+    if zero {
+        rest = &rest[1..];
+    }
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (
+            width,
+            Some(precision.parse::<usize>().map_err(|_| invalid())?),
+        ),
+        None => (rest, None),
+    };
+    let width = if width.is_empty() {
+        0
+    } else {
+        width.parse::<usize>().map_err(|_| invalid())?
+    };
+    if width.max(precision.unwrap_or(0)) > MAX_FORMAT_WIDTH {
+        return Err(RuntimeError::Generic(format!(
+            "format() cannot pad or round to more than {} characters in '{{:{}}}'",
+            MAX_FORMAT_WIDTH, spec
+        )));
+    }
+
+    let number = matches!(value, Value::Int(_) | Value::Number(_));
+    let text = match (value.as_f64(), precision) {
+        (Some(n), Some(precision)) => format!("{:.*}", precision, n),
+        (_, Some(precision)) => value.to_string().chars().take(precision).collect(),
+        (_, None) => value.to_string(),
+    };
+
+    let len = text.chars().count();
+    if len >= width {
+        return Ok(text);
+    }
+    let padding = width - len;
+
+    if zero && number && align.is_none() {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        return Ok(format!("{}{}{}", sign, "0".repeat(padding), digits));
+    }
+
+    let fill = if zero { "0" } else { " " };
+    let (before, after) = match align.unwrap_or(if number { '>' } else { '<' }) {
+        '<' => (0, padding),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (padding, 0),
+    };
+    Ok(format!(
+        "{}{}{}",
+        fill.repeat(before),
+        text,
+        fill.repeat(after)
+    ))
+}
+
+fn env(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let name = string_arg("env", &args, 0)?;
+
+    match std::env::var(&*name) {
+        Ok(value) => Ok(string_value(value)),
+        Err(_) => Ok(Value::Nil),
+    }
+}
+
+fn set_env(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let name = string_arg("set_env", &args, 0)?;
+    let value = string_arg("set_env", &args, 1)?;
+
+    if name.is_empty() || name.contains('=') || name.contains('\0') || value.contains('\0') {
+        return Err(RuntimeError::Generic(format!(
+            "set_env() got an invalid name or value for '{}'",
+            name
+        )));
+    }
+
+    std::env::set_var(&*name, &*value);
+
+    Ok(Value::Nil)
+}
+
+fn platform(_interpreter: &mut Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(std::env::consts::OS.to_string()))
+}
+
+fn cwd(_interpreter: &mut Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match std::env::current_dir() {
+        Ok(path) => Ok(string_value(path.display().to_string())),
+        Err(err) => Err(RuntimeError::Generic(format!(
+            "cwd() could not read the working directory: {}",
+            err
+        ))),
+    }
+}
+
+/// `exec(cmd, args)` runs a program directly, without a shell, and waits for
+/// it. Returns `(code, stdout, stderr)`, where the code is nil if the
+/// program was killed by a signal.
+fn exec(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let program = string_arg("exec", &args, 0)?;
+    let mut command = Command::new(&*program);
+    if args.len() > 1 {
+        for arg in items_arg("exec", &args, 1)? {
+            match arg {
+                Value::String(arg) => command.arg(&*arg),
+                x => {
+                    return Err(RuntimeError::Generic(format!(
+                        "exec() expects string arguments, got '{}'",
+                        x
+                    )))
+                }
+            };
+        }
+    }
+
+    run_command("exec", &program, command)
+}
+
+/// `shell("ls | wc -l")` runs a command line with the system shell and
+/// returns the same tuple as `exec`.
+fn shell(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let line = string_arg("shell", &args, 0)?;
+    let mut command = if cfg!(windows) {
+        Command::new("cmd")
+    } else {
+        Command::new("sh")
+    };
+    command.args([if cfg!(windows) { "/C" } else { "-c" }, &line]);
+
+    run_command("shell", &line, command)
+}
+
+fn run_command(name: &str, display: &str, mut command: Command) -> Result<Value, RuntimeError> {
+    let output = command.stdin(Stdio::inherit()).output().map_err(|err| {
+        RuntimeError::Generic(format!("{}() could not run '{}': {}", name, display, err))
+    })?;
+
+    let code = match output.status.code() {
+        Some(code) => Value::Int(code as i64),
+        None => Value::Nil,
+    };
+
+    Ok(Value::Tuple(Rc::new(vec![
+        code,
+        string_value(String::from_utf8_lossy(&output.stdout).into_owned()),
+        string_value(String::from_utf8_lossy(&output.stderr).into_owned()),
+    ])))
+}
+
+/// Seconds since the Unix epoch, with a fractional part.
+fn time(_interpreter: &mut Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| RuntimeError::Generic(format!("time() cannot read the clock: {}", err)))?;
+
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+/// Seconds since the first call, from a clock that never goes backwards.
+/// Only the difference between two calls is meaningful.
+fn clock(_interpreter: &mut Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    let start = START.get_or_init(Instant::now);
+    Ok(Value::Number(start.elapsed().as_secs_f64()))
+}
+
+fn seconds_arg(name: &str, args: &[Value], index: usize) -> Result<Duration, RuntimeError> {
+    match args[index].as_f64() {
+        Some(n) if n.is_finite() && n >= 0.0 => Ok(Duration::from_secs_f64(n)),
+        _ => Err(RuntimeError::Generic(format!(
+            "{}() expects a non-negative number of seconds, got '{}'",
+            name, args[index]
+        ))),
+    }
+}
+
+/// `sleep(seconds)` pauses the script. Fractions of a second are allowed.
+fn sleep(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let duration = seconds_arg("sleep", &args, 0)?;
+    interpreter.sleep(duration)?;
+
+    Ok(Value::Nil)
+}
+
+/// `after(seconds, f)` waits and then returns `f()`. Scripts run on a
+/// single thread, so the wait blocks everything else.
+fn after(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let duration = seconds_arg("after", &args, 0)?;
+    interpreter.sleep(duration)?;
+
+    interpreter.call_value(args[1].clone(), Vec::new())
+}
+
+/// `every(seconds, f, times)` calls `f()` once per interval, `times` times
+/// or forever when `times` is left out, stopping early if `f` returns false.
+/// Intervals are measured from start to start, so slow calls do not make
+/// the schedule drift. Returns how many calls were made.
+fn every(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let interval = seconds_arg("every", &args, 0)?;
+    let times = match args.get(2) {
+        None => None,
+        Some(_) => Some(index_arg("every", &args, 2)?),
+    };
+
+    let start = Instant::now();
+    let mut calls = 0;
+    while times.is_none_or(|times| calls < times) {
+        let next = interval.mul_f64((calls + 1) as f64);
+        interpreter.sleep(next.saturating_sub(start.elapsed()))?;
+
+        calls += 1;
+        if let Value::Bool(false) = interpreter.call_value(args[1].clone(), Vec::new())? {
+            break;
+        }
+    }
+
+    Ok(Value::Int(calls as i64))
+}
+
+fn timestamp_arg(name: &str, args: &[Value], index: usize) -> Result<f64, RuntimeError> {
+    match args[index].as_f64() {
+        Some(n) if n.is_finite() => Ok(n),
+        _ => Err(RuntimeError::Generic(format!(
+            "{}() expects a timestamp as argument {}, got '{}'",
+            name,
+            index + 1,
+            args[index]
+        ))),
+    }
+}
+
+/// `time_format(ts, "%Y-%m-%d")` renders a timestamp in UTC.
+fn time_format(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let timestamp = timestamp_arg("time_format", &args, 0)?;
+    let format = string_arg("time_format", &args, 1)?;
+
+    format_timestamp(timestamp, &format)
+        .map(string_value)
+        .map_err(|err| RuntimeError::Generic(format!("time_format(): {}", err)))
+}
+
+/// `time_parse("2024-01-31", "%Y-%m-%d")` reads a UTC timestamp, returning
+/// nil when the text does not match the format.
+fn time_parse(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string_arg("time_parse", &args, 0)?;
+    let format = string_arg("time_parse", &args, 1)?;
+
+    match parse_timestamp(&text, &format) {
+        Ok(Some(timestamp)) => Ok(Value::Int(timestamp as i64)),
+        Ok(None) => Ok(Value::Nil),
+        Err(err) => Err(RuntimeError::Generic(format!("time_parse(): {}", err))),
+    }
+}
+
+fn input(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let prompt = if args.is_empty() {
+        "".into()
+    } else {
+        string_arg("input", &args, 0)?
+    };
+
+    let out = interpreter.output();
+    write!(out, "{}", prompt)
+        .and_then(|_| out.flush())
+        .map_err(|err| RuntimeError::Generic(format!("input() could not write: {}", err)))?;
+
+    let mut line = String::new();
+    let read = stdin()
+        .read_line(&mut line)
+        .map_err(|err| RuntimeError::Generic(format!("input() could not read: {}", err)))?;
+
+    if read == 0 {
+        return Ok(Value::Nil);
+    }
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    Ok(string_value(line))
+}
+
+/// Joins values with spaces, the way `print` separates its arguments.
+fn join_values(args: &[Value]) -> String {
+    args.iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `write(a, b)` prints like `print a, b;` without the trailing newline.
+fn write(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let out = interpreter.output();
+    write!(out, "{}", join_values(&args))
+        .and_then(|_| out.flush())
+        .map_err(|err| RuntimeError::Generic(format!("write() could not write: {}", err)))?;
+
+    Ok(Value::Nil)
+}
+
+/// `eprint(a, b)` prints like `print a, b;` to the error stream.
+fn eprint(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    writeln!(interpreter.error_output(), "{}", join_values(&args))
+        .map_err(|err| RuntimeError::Generic(format!("eprint() could not write: {}", err)))?;
+
+    Ok(Value::Nil)
+}
+
+/// `eval(source)` runs a string of rox code and returns the value of its
+/// final expression statement, or nil.
+fn eval(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let source = string_arg("eval", &args, 0)?;
+
+    interpreter.eval_source(&source)
+}
+
+fn type_of(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(args[0].type_name().to_string()))
+}
+
+/// `is_same(a, b)` is true when `a` and `b` are one object, where `==`
+/// would also accept two equal copies.
+fn is_same(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::Bool(args[0].is_same(&args[1])))
+}
+
+/// `clone(v)` copies `v` and every string, list and tuple inside it.
+fn clone(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(args[0].deep_clone())
+}
+
+fn list(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::List(Rc::new(items_arg("list", &args, 0)?.collect())))
+}
+
+pub fn items_arg(name: &str, args: &[Value], index: usize) -> Result<ValueIter, RuntimeError> {
+    args[index].iter().ok_or_else(|| {
+        RuntimeError::Generic(format!(
+            "{}() expects a collection as argument {}, got '{}'",
+            name,
+            index + 1,
+            args[index]
+        ))
+    })
+}
+
+fn iterator_value(iter: ValueIter) -> Value {
+    Value::Iterator(Rc::new(RefCell::new(iter)))
+}
+
+fn iter(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Iterator(_) => Ok(args[0].clone()),
+        _ => Ok(iterator_value(items_arg("iter", &args, 0)?)),
+    }
+}
+
+/// Advances an iterator, returning nil once it is exhausted.
+fn next(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Iterator(iter) => Ok(iter.borrow_mut().next().unwrap_or(Value::Nil)),
+        x => Err(RuntimeError::Generic(format!(
+            "next() expects an iterator, got a {} '{}'",
+            x.type_name(),
+            x
+        ))),
+    }
+}
+
+fn zip(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let left = items_arg("zip", &args, 0)?;
+    let right = items_arg("zip", &args, 1)?;
+
+    Ok(iterator_value(ValueIter::Zip(
+        Box::new(left),
+        Box::new(right),
+    )))
+}
+
+fn enumerate(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let items = items_arg("enumerate", &args, 0)?;
+
+    Ok(iterator_value(ValueIter::Enumerate(Box::new(items), 0)))
+}
+
+fn map(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut mapped = Vec::new();
+    for item in items_arg("map", &args, 0)? {
+        mapped.push(interpreter.call_value(args[1].clone(), vec![item])?);
+    }
+
+    Ok(Value::List(Rc::new(mapped)))
+}
+
+fn filter(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut kept = Vec::new();
+    for item in items_arg("filter", &args, 0)? {
+        let keep = interpreter.call_value(args[1].clone(), vec![item.clone()])?;
+        if keep.is_truthy() {
+            kept.push(item);
+        }
+    }
+
+    Ok(Value::List(Rc::new(kept)))
+}
+
+fn reduce(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut acc = args[2].clone();
+    for item in items_arg("reduce", &args, 0)? {
+        acc = interpreter.call_value(args[1].clone(), vec![acc, item])?;
+    }
+
+    Ok(acc)
+}
+
+/// Sorts numbers or strings in ascending order, or any values with a
+/// comparator that returns a negative, zero or positive number.
+fn sort(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut items: Vec<Value> = items_arg("sort", &args, 0)?.collect();
+    let mut error = None;
+
+    items.sort_by(|a, b| {
+        if error.is_some() {
+            return Ordering::Equal;
+        }
+
+        compare(interpreter, args.get(1), a, b).unwrap_or_else(|err| {
+            error = Some(err);
+            Ordering::Equal
+        })
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(Value::List(Rc::new(items))),
+    }
+}
+
+fn compare(
+    interpreter: &mut Interpreter,
+    cmp: Option<&Value>,
+    a: &Value,
+    b: &Value,
+) -> Result<Ordering, RuntimeError> {
+    let ordering = match cmp {
+        Some(cmp) => match interpreter.call_value(cmp.clone(), vec![a.clone(), b.clone()])? {
+            Value::Int(n) => Some(n.cmp(&0)),
+            Value::Number(n) => n.partial_cmp(&0.0),
+            _ => None,
+        },
+        None => match (a, b) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Int(a), Value::Number(b)) => compare_int_number(*a, *b),
+            (Value::Number(a), Value::Int(b)) => compare_int_number(*b, *a).map(Ordering::reverse),
+            (a, b) => a
+                .as_f64()
+                .zip(b.as_f64())
+                .and_then(|(a, b)| a.partial_cmp(&b)),
+        },
+    };
+
+    ordering.ok_or_else(|| match cmp {
+        Some(_) => RuntimeError::Generic("sort() comparator must return a number".to_string()),
+        None => RuntimeError::Generic(format!(
+            "sort() can only order numbers or strings without a comparator, got '{}' and '{}'",
+            a, b
+        )),
+    })
+}
+
+/// `num("12")` is the int 12 and `num("1.5")` the number 1.5. Text that is
+/// not a number gives nil.
+fn num(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Int(n) => Ok(Value::Int(*n)),
+        Value::Number(n) => Ok(Value::Number(*n)),
+        Value::String(s) => {
+            let s = s.trim();
+            if let Ok(n) = s.parse::<i64>() {
+                return Ok(Value::Int(n));
+            }
+            match s.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+                _ => Ok(Value::Nil),
+            }
+        }
+        x => Err(RuntimeError::Generic(format!(
+            "num() expects a string, got '{}'",
+            x
+        ))),
+    }
+}
+
+/// `int(x)` drops the fractional part of a number, or reads an int from a
+/// string, giving nil when the text is not one.
+fn int(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Int(n) => Ok(Value::Int(*n)),
+        // `as` saturates, so check the range first.
+        Value::Number(n) if n.is_finite() && n.trunc().abs() < i64::MAX as f64 => {
+            Ok(Value::Int(n.trunc() as i64))
+        }
+        Value::String(s) => Ok(s.trim().parse().map_or(Value::Nil, Value::Int)),
+        x => Err(RuntimeError::Generic(format!(
+            "int() expects a finite number or a string, got '{}'",
+            x
+        ))),
+    }
+}
+
+/// `float(x)` turns an int into a number, or reads a number from a string,
+/// giving nil when the text is not one.
+fn float(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Int(n) => Ok(Value::Number(*n as f64)),
+        Value::Number(n) => Ok(Value::Number(*n)),
+        Value::String(s) => Ok(s.trim().parse().map_or(Value::Nil, Value::Number)),
+        x => Err(RuntimeError::Generic(format!(
+            "float() expects a number or a string, got '{}'",
+            x
+        ))),
+    }
+}
+
+fn str(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(string_value(args[0].to_string()))
+}
@@ -1,372 +1,937 @@
-use std::process::{exit, ExitCode};
+use std::fmt::Display;
 
 use crate::{
+    arena::{Arena, Program},
+    diagnostic::{Diagnostic, E_PARSE, E_RESOLVE, E_TYPE},
     error::Log,
     expression::Expr,
-    interpreter::Object,
-    statement::Stmt,
+    statement::{Pattern, Stmt},
+    suggest::closest,
     token::{self, Token, TokenType},
 };
 
-#[derive(Debug)]
+/// The keywords that begin a statement, which a misspelling turns into an
+/// expression statement that fails to parse.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "break", "const", "continue", "defer", "for", "if", "import", "match", "print", "pub", "throw",
+    "try", "var", "while",
+];
+
+#[derive(Debug, Clone)]
 pub enum ParseError {
-    Generic(String),
+    Generic(String, Token),
+    /// Reported by the resolver on a program that parsed successfully.
+    Resolve(String, Token),
+    /// Reported by the type checker behind `rox check`.
+    Type(String, Token),
 }
 
+impl ParseError {
+    pub fn to_diagnostic(&self, file: Option<String>) -> Diagnostic {
+        match self {
+            ParseError::Generic(message, token) => Diagnostic::error(E_PARSE, message.clone())
+                .at(token.line, token.column, token.span)
+                .in_file(file),
+            ParseError::Resolve(message, token) => Diagnostic::error(E_RESOLVE, message.clone())
+                .at(token.line, token.column, token.span)
+                .in_file(file),
+            ParseError::Type(message, token) => Diagnostic::error(E_TYPE, message.clone())
+                .at(token.line, token.column, token.span)
+                .in_file(file),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Generic(message, _token) => write!(f, "{}", message),
+            ParseError::Resolve(message, _token) => write!(f, "{}", message),
+            ParseError::Type(message, _token) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// How deeply statements, expressions and patterns may nest. Deeper input
+/// is rejected rather than overflowing the stack, here or in the passes
+/// that walk the tree afterwards.
+const MAX_NESTING: usize = 100;
+
+/// Where a top-level declaration's last token ends, as a byte offset, and
+/// how many statements it gave.
+pub(crate) type Extent = (usize, usize);
+
 pub struct Parser<'a> {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,
+    file: Option<String>,
     logger: &'a Log,
+    arena: Arena,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: Vec<Token>, logger: &'a Log) -> Parser {
+    pub fn new(tokens: Vec<Token>, file: Option<String>, logger: &'a Log) -> Parser<'a> {
+        // Doc comments are only of interest to tooling.
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| {
+                !matches!(
+                    token.token_type,
+                    TokenType::DOC_COMMENT | TokenType::COMMENT
+                )
+            })
+            .collect();
+
         Parser {
             tokens,
             current: 0,
+            depth: 0,
+            file,
             logger,
+            arena: Arena::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
+    /// Adds the nodes it parses to `arena` instead of a new one.
+    pub(crate) fn with_arena(mut self, arena: Arena) -> Parser<'a> {
+        self.arena = arena;
+        self
+    }
+
+    /// The arena holding the nodes parsed so far.
+    pub(crate) fn into_arena(self) -> Arena {
+        self.arena
+    }
+
+    pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
+        let (stmts, _) = self.parse_declarations()?;
+        let arena = std::mem::take(&mut self.arena);
+        Ok(Program { stmts, arena })
+    }
+
+    /// Like `parse`, also giving each top-level declaration's extent.
+    pub(crate) fn parse_declarations(
+        &mut self,
+    ) -> Result<(Vec<Stmt>, Vec<Extent>), Vec<ParseError>> {
         let mut statements: Vec<Stmt> = Vec::new();
+        let mut declarations = Vec::new();
+        let mut errors: Vec<ParseError> = Vec::new();
 
         while !self.is_at_end() {
-            let stmt = self.declaration();
-            statements.push(stmt);
+            let count = statements.len();
+            match self.declaration(&mut statements) {
+                Ok(()) => declarations.push((self.previous().span.end, statements.len() - count)),
+                Err(err) => {
+                    self.logger.report(&err.to_diagnostic(self.file.clone()));
+                    errors.push(err);
+                    self.synchronize();
+                }
+            }
         }
 
-        Ok(statements)
+        if errors.is_empty() {
+            Ok((statements, declarations))
+        } else {
+            Err(errors)
+        }
     }
 
-    fn declaration(&mut self) -> Stmt {
-        if self.matches(vec![TokenType::VAR]) {
-            self.var_decl()
+    /// Parses one declaration or statement onto the end of `out`. Only a
+    /// grouped `var` declaration gives more than one statement.
+    fn declaration(&mut self, out: &mut Vec<Stmt>) -> Result<(), ParseError> {
+        if self.matches(&[TokenType::PUB]) {
+            return self.pub_decl(out);
+        }
+
+        let stmt = if self.matches(&[TokenType::VAR]) {
+            return self.var_decl(out);
+        } else if self.matches(&[TokenType::CONST]) {
+            self.const_decl()?
+        } else if self.matches(&[TokenType::IMPORT]) {
+            self.import_decl()?
         } else {
-            self.statement()
+            self.statement()?
+        };
+
+        out.push(stmt);
+        Ok(())
+    }
+
+    /// Parses `var a = 1, b = 2, c;` into one `Stmt::Var` per binding. They
+    /// run in order, exactly as separate declarations would, so `b` can use
+    /// `a`.
+    fn var_decl(&mut self, out: &mut Vec<Stmt>) -> Result<(), ParseError> {
+        out.push(self.var_binding()?);
+        while self.matches(&[TokenType::COMMA]) {
+            out.push(self.var_binding()?);
         }
+
+        self.consume(
+            TokenType::SEMICOLON,
+            "Expected a ';' after variable declaration",
+        )?;
+
+        Ok(())
     }
 
-    fn var_decl(&mut self) -> Stmt {
-        let ident = self
-            .consume(TokenType::IDENTIFIER, "Expected a variable name")
-            .unwrap();
+    fn var_binding(&mut self) -> Result<Stmt, ParseError> {
+        let pattern = self.pattern()?;
+        let annotation = match pattern {
+            Pattern::Name(..) => self.type_annotation()?,
+            Pattern::Tuple(..) => None,
+        };
+
+        let initializer = if self.matches(&[TokenType::EQUAL]) {
+            Some(self.expression()?)
+        } else if let Pattern::Tuple(..) = pattern {
+            return Err(
+                self.report_error(self.peek(), "Expected '=' after the names to destructure")
+            );
+        } else {
+            None
+        };
+
+        Ok(Stmt::Var(pattern, annotation, initializer))
+    }
 
-        let initializer: Option<Expr>;
+    /// Parses a name, or a parenthesized list of patterns such as `(a, (b, c))`.
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.matches(&[TokenType::LEFT_PAREN]) {
+            let open = self.previous().clone();
+            let mut patterns = Vec::new();
 
-        if self.matches(vec![TokenType::EQUAL]) {
-            initializer = Some(self.expression());
+            while !self.check(TokenType::RIGHT_PAREN) {
+                patterns.push(self.nested(Parser::pattern)?);
+                if !self.matches(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+
+            let close = self.consume(
+                TokenType::RIGHT_PAREN,
+                "Expected a ')' after the names to destructure",
+            )?;
+            return Ok(Pattern::Tuple(open, patterns, close));
+        }
+
+        let name = self.consume(TokenType::IDENTIFIER, "Expected a variable name")?;
+        Ok(Pattern::Name(name, None))
+    }
+
+    /// Parses an optional `: type` after a declared name.
+    fn type_annotation(&mut self) -> Result<Option<Token>, ParseError> {
+        if self.matches(&[TokenType::COLON]) {
+            let name = self.consume(TokenType::IDENTIFIER, "Expected a type name after ':'")?;
+            return Ok(Some(name));
+        }
+
+        Ok(None)
+    }
+
+    /// `pub var ...;` or `pub const ...;`. Each binding of a grouped `var`
+    /// is marked on its own.
+    fn pub_decl(&mut self, out: &mut Vec<Stmt>) -> Result<(), ParseError> {
+        let keyword = self.previous().clone();
+        let start = out.len();
+        if self.matches(&[TokenType::VAR]) {
+            self.var_decl(out)?;
+        } else if self.matches(&[TokenType::CONST]) {
+            out.push(self.const_decl()?);
         } else {
-            initializer = Some(Expr::Literal(token::Literal::Nil));
+            return Err(self.report_error(self.peek(), "Expected 'var' or 'const' after 'pub'."));
         }
 
+        for stmt in &mut out[start..] {
+            let binding = std::mem::replace(stmt, Stmt::Block(Vec::new()));
+            *stmt = Stmt::Pub(keyword.clone(), self.arena.add_stmt(binding));
+        }
+        Ok(())
+    }
+
+    fn const_decl(&mut self) -> Result<Stmt, ParseError> {
+        let ident = self.consume(TokenType::IDENTIFIER, "Expected a constant name")?;
+        let annotation = self.type_annotation()?;
+        self.consume(TokenType::EQUAL, "Expected '=' after the constant name")?;
+        let initializer = self.expression()?;
+
         self.consume(
             TokenType::SEMICOLON,
-            "Expected a ';' after variable declaration",
-        )
-        .unwrap();
+            "Expected a ';' after constant declaration",
+        )?;
+
+        Ok(Stmt::Const(ident, None, annotation, initializer))
+    }
+
+    fn import_decl(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let path = self.consume(
+            TokenType::STRING,
+            "Expected a module path string after import.",
+        )?;
+
+        let alias = if self.matches(&[TokenType::AS]) {
+            Some(self.consume(TokenType::IDENTIFIER, "Expected a module name after 'as'.")?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::SEMICOLON,
+            "Expected a ';' after the import declaration.",
+        )?;
 
-        return Stmt::Var(ident, initializer);
+        Ok(Stmt::Import(keyword, path.literal.to_string(), alias))
     }
 
-    fn for_statement(&mut self) -> Stmt {
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         self.consume(
             TokenType::LEFT_PAREN,
             "Expected a '(' after the for keyword.",
-        )
-        .unwrap();
+        )?;
 
-        let mut initializer = Option::None;
+        if self.at_foreach() {
+            return self.foreach_statement(keyword, label);
+        }
+
+        let mut initializer = Vec::new();
         let mut cond = Option::None;
         let mut increment = Option::None;
 
-        if self.matches(vec![TokenType::VAR]) {
-            initializer = Some(self.var_decl());
-        } else if self.matches(vec![TokenType::SEMICOLON]) {
+        if self.matches(&[TokenType::VAR]) {
+            self.var_decl(&mut initializer)?;
+        } else if self.matches(&[TokenType::SEMICOLON]) {
         } else {
-            initializer = Some(self.expr_statement());
+            initializer.push(self.expr_statement()?);
         }
 
-        if self.matches(vec![TokenType::SEMICOLON]) {
-        } else {
-            cond = Some(self.expression());
+        if !self.check(TokenType::SEMICOLON) {
+            cond = Some(self.expression()?);
         }
 
         self.consume(
             TokenType::SEMICOLON,
             "Expected a ';' after the for loop condition.",
-        )
-        .unwrap();
+        )?;
 
         if !self.check(TokenType::RIGHT_PAREN) {
-            increment = Some(self.expression());
+            increment = Some(self.expression()?);
         }
 
         self.consume(
             TokenType::RIGHT_PAREN,
             "Expected a ')' after the for loop clauses.",
-        )
-        .unwrap();
+        )?;
 
-        let mut body = self.statement();
+        let body = self.statement()?;
 
-        body = match increment {
-            Some(inc) => {
-                let mut stmts = vec![body];
-                stmts.push(Stmt::Expression(inc));
-                Stmt::Block(stmts)
-            }
-            None => body,
+        let cond = match cond {
+            Some(expr) => expr,
+            // A missing condition loops forever. The synthesized `true` takes
+            // the keyword's position so it never points outside the source.
+            None => Expr::Literal(keyword.clone(), token::Literal::Bool(true)),
         };
+        let mut body = Stmt::While(keyword, label, cond, self.arena.add_stmt(body), increment);
 
-        body = match cond {
-            Some(expr) => Stmt::While(expr, Box::new(body)),
-            None => Stmt::While(Expr::Literal(token::Literal::Bool(true)), Box::new(body)),
-        };
+        if !initializer.is_empty() {
+            initializer.push(body);
+            body = Stmt::Block(initializer);
+        }
 
-        match initializer {
-            Some(init) => {
-                let mut stmts = vec![body];
-                stmts.insert(0, init);
-                body = Stmt::Block(stmts);
+        Ok(body)
+    }
+
+    /// Whether the tokens after `for (` are a pattern followed by `in`.
+    fn at_foreach(&self) -> bool {
+        let mut depth = 0;
+        for token in &self.tokens[self.current..] {
+            match token.token_type {
+                TokenType::LEFT_PAREN => depth += 1,
+                TokenType::RIGHT_PAREN if depth > 0 => depth -= 1,
+                TokenType::IDENTIFIER => {}
+                TokenType::COMMA if depth > 0 => {}
+                TokenType::IN => return depth == 0,
+                _ => return false,
             }
-            None => {}
         }
+        false
+    }
+
+    fn foreach_statement(
+        &mut self,
+        keyword: Token,
+        label: Option<Token>,
+    ) -> Result<Stmt, ParseError> {
+        let pattern = self.pattern()?;
+        self.consume(TokenType::IN, "Expected 'in' after the loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            "Expected a ')' after the for loop collection.",
+        )?;
+
+        let body = self.statement()?;
+        Ok(Stmt::ForEach(
+            keyword,
+            label,
+            pattern,
+            iterable,
+            self.arena.add_stmt(body),
+        ))
+    }
 
-        return body;
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        self.nested(Parser::statement_kind)
     }
 
-    fn statement(&mut self) -> Stmt {
-        if self.matches(vec![TokenType::PRINT]) {
-            return self.print_statement();
-        } else if self.matches(vec![TokenType::IF]) {
-            return self.if_statement();
-        } else if self.matches(vec![TokenType::LEFT_BRACE]) {
-            return self.block_statement();
-        } else if self.matches(vec![TokenType::WHILE]) {
-            return self.while_statement();
-        } else if self.matches(vec![TokenType::FOR]) {
-            return self.for_statement();
+    /// Parses the statement whose form the next token selects.
+    fn statement_kind(&mut self) -> Result<Stmt, ParseError> {
+        if self.check(TokenType::IDENTIFIER) && self.check_next(TokenType::COLON) {
+            self.labeled_statement()
+        } else if self.matches(&[TokenType::PRINT]) {
+            self.print_statement()
+        } else if self.matches(&[TokenType::IF]) {
+            self.if_statement()
+        } else if self.matches(&[TokenType::LEFT_BRACE]) {
+            self.block_statement()
+        } else if self.matches(&[TokenType::WHILE]) {
+            self.while_statement(None)
+        } else if self.matches(&[TokenType::FOR]) {
+            self.for_statement(None)
+        } else if self.matches(&[TokenType::BREAK]) {
+            self.break_statement()
+        } else if self.matches(&[TokenType::CONTINUE]) {
+            self.continue_statement()
+        } else if self.matches(&[TokenType::TRY]) {
+            self.try_statement()
+        } else if self.matches(&[TokenType::THROW]) {
+            self.throw_statement()
+        } else if self.matches(&[TokenType::DEFER]) {
+            self.defer_statement()
+        } else if self.matches(&[TokenType::MATCH]) {
+            self.match_statement()
         } else {
-            return self.expr_statement();
+            let start = self.peek().clone();
+            self.expr_statement()
+                .map_err(|err| suggest_keyword(&start, err))
         }
     }
 
-    fn if_statement(&mut self) -> Stmt {
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         self.consume(
             TokenType::LEFT_PAREN,
             "Expected a '(' after the if statement.",
-        )
-        .unwrap();
-        let condition = self.expression();
+        )?;
+        let condition = self.expression()?;
         self.consume(
             TokenType::RIGHT_PAREN,
             "Expected a ')' after the if condition.",
-        )
-        .unwrap();
+        )?;
 
-        let then_branch = self.statement();
-        let else_branch = match self.matches(vec![TokenType::ELSE]) {
-            true => Some(self.statement()),
+        let then_branch = self.statement()?;
+        let else_branch = match self.matches(&[TokenType::ELSE]) {
+            true => Some(self.statement()?),
             false => None,
         };
 
-        return Stmt::If(condition, Box::new(then_branch), Box::new(else_branch));
+        Ok(Stmt::If(
+            keyword,
+            condition,
+            self.arena.add_stmt(then_branch),
+            else_branch.map(|stmt| self.arena.add_stmt(stmt)),
+        ))
     }
 
-    fn block_statement(&mut self) -> Stmt {
+    fn block_statement(&mut self) -> Result<Stmt, ParseError> {
         let mut statements = Vec::new();
 
         while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
-            statements.push(self.declaration());
+            self.declaration(&mut statements)?;
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expected '}' after the block.")?;
+
+        Ok(Stmt::Block(statements))
+    }
+
+    fn block(&mut self, message: &str) -> Result<Vec<Stmt>, ParseError> {
+        self.consume(TokenType::LEFT_BRACE, message)?;
+
+        match self.block_statement()? {
+            Stmt::Block(statements) => Ok(statements),
+            _ => unreachable!(),
+        }
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let try_keyword = self.previous().clone();
+        let try_block = self.block("Expected '{' after the try keyword.")?;
+
+        let mut catch_clause = None;
+        let mut finally_block = None;
+
+        if self.matches(&[TokenType::CATCH]) {
+            self.consume(
+                TokenType::LEFT_PAREN,
+                "Expected a '(' after the catch keyword.",
+            )?;
+            let ident = self.consume(
+                TokenType::IDENTIFIER,
+                "Expected an exception variable name.",
+            )?;
+            self.consume(
+                TokenType::RIGHT_PAREN,
+                "Expected a ')' after the exception variable.",
+            )?;
+            let catch_block = self.block("Expected '{' before the catch body.")?;
+            catch_clause = Some((ident, catch_block));
+        }
+
+        if self.matches(&[TokenType::FINALLY]) {
+            finally_block = Some(self.block("Expected '{' after the finally keyword.")?);
+        }
+
+        if catch_clause.is_none() && finally_block.is_none() {
+            return Err(self.report_error(
+                &try_keyword,
+                "Expected a catch or finally clause after the try block.",
+            ));
+        }
+
+        Ok(Stmt::Try(
+            try_keyword,
+            try_block,
+            catch_clause,
+            finally_block,
+        ))
+    }
+
+    fn match_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(
+            TokenType::LEFT_PAREN,
+            "Expected a '(' after the match keyword.",
+        )?;
+        let subject = self.expression()?;
+        self.consume(
+            TokenType::RIGHT_PAREN,
+            "Expected a ')' after the match subject.",
+        )?;
+        self.consume(TokenType::LEFT_BRACE, "Expected '{' before the match arms.")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            if self.matches(&[TokenType::ELSE]) {
+                if default.is_some() {
+                    return Err(
+                        self.report_error(self.previous(), "A match can only have one else arm.")
+                    );
+                }
+                self.consume(TokenType::ARROW, "Expected '->' after else.")?;
+                default = Some(self.statement()?);
+                continue;
+            }
+
+            let mut patterns = vec![self.expression()?];
+            while self.matches(&[TokenType::COMMA]) {
+                patterns.push(self.expression()?);
+            }
+            self.consume(TokenType::ARROW, "Expected '->' after the match pattern.")?;
+            arms.push((patterns, self.statement()?));
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expected '}' after the match arms.")?;
+
+        Ok(Stmt::Match(
+            keyword,
+            subject,
+            arms,
+            default.map(|stmt| self.arena.add_stmt(stmt)),
+        ))
+    }
+
+    /// `name: while ...` or `name: for ...`, a loop that `break name;` and
+    /// `continue name;` can target from inside nested loops.
+    fn labeled_statement(&mut self) -> Result<Stmt, ParseError> {
+        let label = self.advance().clone();
+        self.advance();
+        if self.matches(&[TokenType::WHILE]) {
+            self.while_statement(Some(label))
+        } else if self.matches(&[TokenType::FOR]) {
+            self.for_statement(Some(label))
+        } else {
+            Err(self.report_error(self.peek(), "Expected a loop after the label."))
+        }
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let label = self.loop_label();
+        self.consume(TokenType::SEMICOLON, "Expected ';' after 'break'.")?;
+        Ok(Stmt::Break(keyword, label))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let label = self.loop_label();
+        self.consume(TokenType::SEMICOLON, "Expected ';' after 'continue'.")?;
+        Ok(Stmt::Continue(keyword, label))
+    }
+
+    fn loop_label(&mut self) -> Option<Token> {
+        if self.matches(&[TokenType::IDENTIFIER]) {
+            return Some(self.previous().clone());
         }
+        None
+    }
 
-        self.consume(TokenType::RIGHT_BRACE, "Expected '}' after the block.")
-            .unwrap();
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expected ';' after the thrown value.")?;
+        Ok(Stmt::Throw(keyword, value))
+    }
 
-        return Stmt::Block(statements);
+    fn defer_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let stmt = self.statement()?;
+        Ok(Stmt::Defer(keyword, self.arena.add_stmt(stmt)))
     }
 
-    fn while_statement(&mut self) -> Stmt {
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         self.consume(
             TokenType::LEFT_PAREN,
             "Expected a '(' after the while keyword.",
-        )
-        .unwrap();
-        let cond = self.expression();
+        )?;
+        let cond = self.expression()?;
         self.consume(
             TokenType::RIGHT_PAREN,
             "Expected a ')' after the condition.",
-        )
-        .unwrap();
-
-        let block = self.statement();
-        return Stmt::While(cond, Box::new(block));
+        )?;
+
+        let block = self.statement()?;
+        Ok(Stmt::While(
+            keyword,
+            label,
+            cond,
+            self.arena.add_stmt(block),
+            None,
+        ))
     }
 
-    fn print_statement(&mut self) -> Stmt {
-        let value = self.expression();
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let mut values = Vec::new();
+        if !self.check(TokenType::SEMICOLON) {
+            values.push(self.expression()?);
+            while self.matches(&[TokenType::COMMA]) {
+                values.push(self.expression()?);
+            }
+        }
         self.consume(
             TokenType::SEMICOLON,
             "Expected ';' after the print statement.",
-        )
-        .unwrap();
-        return Stmt::Print(value);
+        )?;
+        Ok(Stmt::Print(keyword, values))
     }
 
-    fn expr_statement(&mut self) -> Stmt {
-        let expr = self.expression();
-        self.consume(TokenType::SEMICOLON, "Expected ';' after the expression.")
-            .unwrap();
-        return Stmt::Expression(expr);
+    fn expr_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expected ';' after the expression.")?;
+        Ok(Stmt::Expression(expr))
     }
 
-    fn expression(&mut self) -> Expr {
-        return self.assignment();
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.nested(Parser::assignment)
     }
 
-    fn assignment(&mut self) -> Expr {
-        let expr = self.or();
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.or()?;
 
-        if self.matches(vec![TokenType::EQUAL]) {
-            let equals = self.previous();
-            let value = self.assignment();
+        if self.matches(&[TokenType::EQUAL]) {
+            let equals = self.previous().clone();
+            let value = self.expression()?;
 
             match expr {
-                Expr::Var(token) => Expr::Assign(token, Box::new(value)),
-                _ => {
-                    self.report_error(equals, "Invalid assignment target");
-                    panic!();
-                }
+                Expr::Var(token, _) => Ok(Expr::Assign(token, self.arena.add_expr(value), None)),
+                _ => Err(self.report_error(&equals, "Invalid assignment target")),
             }
         } else {
-            expr
+            Ok(expr)
         }
     }
 
-    fn or(&mut self) -> Expr {
-        let mut expr = self.and();
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.coalesce()?;
 
-        while self.matches(vec![TokenType::OR]) {
-            let op = self.previous();
-            let right = self.and();
-            expr = Expr::Logical(Box::new(expr), op, Box::new(right))
+        while self.matches(&[TokenType::OR]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.coalesce()?;
+            expr = Expr::Logical(self.arena.add_expr(expr), op, self.arena.add_expr(right))
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn and(&mut self) -> Expr {
-        let mut expr = self.equality();
+    fn coalesce(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
 
-        while self.matches(vec![TokenType::AND]) {
-            let op = self.previous();
-            let right = self.equality();
-            expr = Expr::Logical(Box::new(expr), op, Box::new(right))
+        while self.matches(&[TokenType::QUESTION_QUESTION]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.and()?;
+            expr = Expr::Logical(self.arena.add_expr(expr), op, self.arena.add_expr(right))
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality()?;
 
-        while self.matches(vec![TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
-            let op = self.previous();
-            let right = self.comparison();
-            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+        while self.matches(&[TokenType::AND]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.equality()?;
+            expr = Expr::Logical(self.arena.add_expr(expr), op, self.arena.add_expr(right))
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.comparison()?;
 
-        while self.matches(vec![
+        while self.matches(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.comparison()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.range()?;
+
+        while self.matches(&[
             TokenType::GREATER,
             TokenType::GREATER_EQUAL,
             TokenType::LESS,
             TokenType::LESS_EQUAL,
+            TokenType::IS,
         ]) {
-            let op = self.previous();
-            let right = self.term();
-            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.range()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn range(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.bit_or()?;
+
+        if self.matches(&[TokenType::DOT_DOT, TokenType::DOT_DOT_EQUAL]) {
+            let op = self.previous().clone();
+            let right = self.bit_or()?;
+            return Ok(Expr::Binary(
+                self.arena.add_expr(expr),
+                op,
+                self.arena.add_expr(right),
+            ));
+        }
+
+        Ok(expr)
+    }
 
-        while self.matches(vec![TokenType::MINUS, TokenType::PLUS]) {
-            let op = self.previous();
-            let right = self.factor();
-            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+    fn bit_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.matches(&[TokenType::PIPE]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.bit_xor()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn bit_xor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_and()?;
 
-        while self.matches(vec![TokenType::STAR, TokenType::SLASH]) {
-            let op = self.previous();
-            let right = self.unary();
-            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+        while self.matches(&[TokenType::CARET]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.bit_and()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr {
-        if self.matches(vec![TokenType::BANG, TokenType::MINUS]) {
-            let op = self.previous();
-            let right = self.unary();
-            return Expr::Unary(op, Box::new(right));
+    fn bit_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.shift()?;
+
+        while self.matches(&[TokenType::AMPERSAND]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.shift()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
         }
 
-        return self.primary();
+        Ok(expr)
     }
 
-    fn primary(&mut self) -> Expr {
-        if self.matches(vec![TokenType::FALSE]) {
-            return Expr::Literal(token::Literal::Bool(false));
-        } else if self.matches(vec![TokenType::TRUE]) {
-            return Expr::Literal(token::Literal::Bool(true));
-        } else if self.matches(vec![TokenType::NIL]) {
-            return Expr::Literal(token::Literal::Nil);
-        } else if self.matches(vec![TokenType::NUMBER, TokenType::STRING]) {
-            return Expr::Literal(self.previous().literal);
-        } else if self.matches(vec![TokenType::LEFT_PAREN]) {
-            let expr = self.expression();
+    fn shift(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
 
-            match self.consume(TokenType::RIGHT_PAREN, "Expected ')' after expression.") {
-                Ok(_) => {}
-                Err(parse_error) => match parse_error {
-                    ParseError::Generic(error_message) => {
-                        self.logger.error(error_message);
-                        exit(1);
-                    }
-                    _ => {}
-                },
-            };
-            return Expr::Grouping(Box::new(expr));
-        } else if self.matches(vec![TokenType::IDENTIFIER]) {
-            Expr::Var(self.previous())
-        } else {
-            let err = self.report_error(self.peek(), "Expected expression.");
-            match err {
-                ParseError::Generic(error_message) => {
-                    self.logger.error(error_message);
-                    exit(1);
+        while self.matches(&[TokenType::LESS_LESS, TokenType::GREATER_GREATER]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.term()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.matches(&[TokenType::MINUS, TokenType::PLUS]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.factor()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.matches(&[TokenType::STAR, TokenType::SLASH]) {
+            let op = self.previous().clone();
+            self.link()?;
+            let right = self.unary()?;
+            expr = Expr::Binary(self.arena.add_expr(expr), op, self.arena.add_expr(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.matches(&[TokenType::BANG, TokenType::MINUS, TokenType::TILDE]) {
+            let op = self.previous().clone();
+            let right = self.nested(Parser::unary)?;
+            return Ok(Expr::Unary(op, self.arena.add_expr(right)));
+        }
+
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.matches(&[TokenType::LEFT_PAREN]) {
+                self.link()?;
+                expr = self.finish_call(expr)?;
+            } else if self.matches(&[TokenType::DOT]) {
+                self.link()?;
+                let name =
+                    self.consume(TokenType::IDENTIFIER, "Expected a member name after '.'.")?;
+                expr = Expr::Get(self.arena.add_expr(expr), name);
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut args = Vec::new();
+
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                if self.matches(&[TokenType::DOT_DOT_DOT]) {
+                    let op = self.previous().clone();
+                    let expr = self.expression()?;
+                    args.push(Expr::Spread(op, self.arena.add_expr(expr)));
+                } else {
+                    args.push(self.expression()?);
                 }
-                _ => {
-                    panic!("Unhandled ParseError Arm");
+
+                if !self.matches(&[TokenType::COMMA]) {
+                    break;
                 }
-            };
+            }
+        }
+
+        let paren = self.consume(
+            TokenType::RIGHT_PAREN,
+            "Expected ')' after the call arguments.",
+        )?;
+
+        Ok(Expr::Call(self.arena.add_expr(callee), paren, args))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.matches(&[TokenType::FALSE]) {
+            Ok(Expr::Literal(
+                self.previous().clone(),
+                token::Literal::Bool(false),
+            ))
+        } else if self.matches(&[TokenType::TRUE]) {
+            Ok(Expr::Literal(
+                self.previous().clone(),
+                token::Literal::Bool(true),
+            ))
+        } else if self.matches(&[TokenType::NIL]) {
+            Ok(Expr::Literal(self.previous().clone(), token::Literal::Nil))
+        } else if self.matches(&[TokenType::NUMBER, TokenType::STRING]) {
+            let token = self.previous().clone();
+            let literal = token.literal.clone();
+            Ok(Expr::Literal(token, literal))
+        } else if self.matches(&[TokenType::LEFT_PAREN]) {
+            let open = self.previous().clone();
+            if self.matches(&[TokenType::RIGHT_PAREN]) {
+                return Ok(Expr::Tuple(open, Vec::new(), self.previous().clone()));
+            }
+
+            let expr = self.expression()?;
+            if !self.matches(&[TokenType::COMMA]) {
+                self.consume(TokenType::RIGHT_PAREN, "Expected ')' after expression.")?;
+                return Ok(Expr::Grouping(self.arena.add_expr(expr)));
+            }
+
+            // A comma makes this a tuple; a trailing comma is allowed.
+            let mut items = vec![expr];
+            while !self.check(TokenType::RIGHT_PAREN) {
+                items.push(self.expression()?);
+                if !self.matches(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+
+            let close = self.consume(TokenType::RIGHT_PAREN, "Expected ')' after tuple items.")?;
+            Ok(Expr::Tuple(open, items, close))
+        } else if self.matches(&[TokenType::IDENTIFIER]) {
+            Ok(Expr::Var(self.previous().clone(), None))
+        } else {
+            Err(self.report_error(self.peek(), "Expected expression."))
         }
     }
 
@@ -382,11 +947,20 @@ impl<'a> Parser<'a> {
                 TokenType::CLASS => return,
                 TokenType::FUN => return,
                 TokenType::VAR => return,
+                TokenType::CONST => return,
+                TokenType::PUB => return,
                 TokenType::FOR => return,
                 TokenType::IF => return,
                 TokenType::WHILE => return,
                 TokenType::PRINT => return,
                 TokenType::RETURN => return,
+                TokenType::TRY => return,
+                TokenType::THROW => return,
+                TokenType::DEFER => return,
+                TokenType::BREAK => return,
+                TokenType::CONTINUE => return,
+                TokenType::IMPORT => return,
+                TokenType::MATCH => return,
                 _ => {
                     self.advance();
                 }
@@ -394,22 +968,51 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Runs `parse` one level deeper, failing once `MAX_NESTING` is reached.
+    fn nested<T>(
+        &mut self,
+        parse: fn(&mut Parser<'a>) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.report_error(self.peek(), "Nesting is too deep."));
+        }
+
+        // Restoring the saved depth also releases the links `link` added
+        // below this level, including on the error paths.
+        let depth = self.depth;
+        self.depth += 1;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Counts one more link of an operator or call chain, which deepens the
+    /// tree as much as nesting does, towards `MAX_NESTING`.
+    fn link(&mut self) -> Result<(), ParseError> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.report_error(self.previous(), "Nesting is too deep."));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
     fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParseError> {
         if self.check(token_type) {
-            return Ok(self.advance());
+            return Ok(self.advance().clone());
         }
 
-        return Err(self.report_error(self.peek(), message));
+        Err(self.report_error(self.peek(), message))
     }
 
-    fn matches(&mut self, token_types: Vec<TokenType>) -> bool {
-        for t in token_types {
+    fn matches(&mut self, token_types: &[TokenType]) -> bool {
+        for &t in token_types {
             if self.check(t) {
                 self.advance();
                 return true;
             }
         }
-        return false;
+        false
     }
 
     fn check(&self, token_type: TokenType) -> bool {
@@ -417,40 +1020,65 @@ impl<'a> Parser<'a> {
             return false;
         }
 
-        return self.peek().token_type == token_type;
+        self.peek().token_type == token_type
     }
 
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
         }
 
-        return self.previous();
+        self.previous()
+    }
+
+    /// Whether the token after the current one has type `token_type`.
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
     }
 
     fn is_at_end(&self) -> bool {
-        return self.peek().token_type == TokenType::EOF;
+        self.peek().token_type == TokenType::EOF
     }
 
-    fn peek(&self) -> Token {
-        return self.tokens[self.current].clone();
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
     }
 
-    fn previous(&mut self) -> Token {
-        return self.tokens[self.current - 1].clone();
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
     }
 
-    fn report_error(&self, token: Token, message: &str) -> ParseError {
+    fn report_error(&self, token: &Token, message: &str) -> ParseError {
         if token.token_type == TokenType::EOF {
-            ParseError::Generic(format!(
-                "Parse error at line {} at end, {}",
-                token.line, message
-            ))
+            let message = format!("Parse error at line {} at end, {}", token.line, message);
+            ParseError::Generic(message, token.clone())
         } else {
-            ParseError::Generic(format!(
+            let message = format!(
                 "Parse error at line {} at '{}', {}",
                 token.line, token.lexeme, message
-            ))
+            );
+            ParseError::Generic(message, token.clone())
+        }
+    }
+}
+
+/// Adds a hint to an error in a statement that starts with a misspelled
+/// keyword, such as `whle (x) {}`.
+fn suggest_keyword(start: &Token, err: ParseError) -> ParseError {
+    if start.token_type != TokenType::IDENTIFIER {
+        return err;
+    }
+
+    match (
+        closest(&start.lexeme, STATEMENT_KEYWORDS.iter().copied()),
+        err,
+    ) {
+        (Some(keyword), ParseError::Generic(message, token)) => {
+            ParseError::Generic(format!("{} Did you mean '{}'?", message, keyword), token)
         }
+        (_, err) => err,
     }
 }
@@ -0,0 +1,300 @@
+//! Turning a parsed program back into source text.
+//!
+//! The printer adds no parentheses of its own: the parser keeps every
+//! group as an `Expr::Grouping`, so printing the tree as it is and parsing
+//! the result gives the same tree back. Comments and the original layout
+//! are not part of the tree and are lost.
+
+use crate::{
+    arena::{Arena, Program},
+    expression::Expr,
+    statement::{Pattern, Stmt},
+    token::{Literal, TokenType},
+};
+
+/// Renders `program` as source, one statement per line and nested
+/// statements indented by four spaces.
+pub fn ast_to_source(program: &Program) -> String {
+    stmts_to_source(&program.arena, &program.stmts)
+}
+
+/// Like `ast_to_source`, for some statements whose children are in `arena`.
+pub(crate) fn stmts_to_source(arena: &Arena, stmts: &[Stmt]) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+        arena,
+    };
+    for stmt in stmts {
+        printer.stmt(stmt);
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+struct Printer<'a> {
+    out: String,
+    indent: usize,
+    arena: &'a Arena,
+}
+
+impl Printer<'_> {
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let arena = self.arena;
+        match stmt {
+            Stmt::Print(_, values) => {
+                self.write("print");
+                if !values.is_empty() {
+                    self.write(" ");
+                    self.write(&list_source(self.arena, values));
+                }
+                self.write(";");
+            }
+            Stmt::Expression(expr) => {
+                self.write(&expr_source(self.arena, expr));
+                self.write(";");
+            }
+            Stmt::Var(pattern, annotation, initializer) => {
+                self.write("var ");
+                self.write(&pattern_source(pattern));
+                if let Some(annotation) = annotation {
+                    self.write(&format!(": {}", annotation.lexeme));
+                }
+                if let Some(initializer) = initializer {
+                    self.write(&format!(" = {}", expr_source(self.arena, initializer)));
+                }
+                self.write(";");
+            }
+            Stmt::Const(name, _, annotation, initializer) => {
+                self.write(&format!("const {}", name.lexeme));
+                if let Some(annotation) = annotation {
+                    self.write(&format!(": {}", annotation.lexeme));
+                }
+                self.write(&format!(" = {};", expr_source(self.arena, initializer)));
+            }
+            Stmt::Block(stmts) => self.block(stmts),
+            Stmt::If(_, condition, then_branch, else_branch) => {
+                self.write(&format!("if ({})", expr_source(self.arena, condition)));
+                self.body(&arena[*then_branch]);
+
+                if let Some(else_branch) = else_branch {
+                    let else_branch = &arena[*else_branch];
+                    match &arena[*then_branch] {
+                        Stmt::Block(_) => self.write(" else"),
+                        _ => {
+                            self.newline();
+                            self.write("else");
+                        }
+                    }
+                    match else_branch {
+                        Stmt::If(..) => {
+                            self.write(" ");
+                            self.stmt(else_branch);
+                        }
+                        _ => self.body(else_branch),
+                    }
+                }
+            }
+            Stmt::While(keyword, label, condition, body, increment) => {
+                if let Some(label) = label {
+                    self.write(&format!("{}: ", label.lexeme));
+                }
+
+                if keyword.token_type == TokenType::FOR || increment.is_some() {
+                    // A `for` without a condition loops on a `true` that
+                    // the parser made up at the keyword.
+                    let condition = match condition {
+                        Expr::Literal(token, _) if token.token_type == TokenType::FOR => {
+                            String::new()
+                        }
+                        condition => format!(" {}", expr_source(self.arena, condition)),
+                    };
+                    let increment = match increment {
+                        Some(increment) => format!(" {}", expr_source(self.arena, increment)),
+                        None => String::new(),
+                    };
+                    self.write(&format!("for (;{};{})", condition, increment));
+                } else {
+                    self.write(&format!("while ({})", expr_source(self.arena, condition)));
+                }
+                self.body(&arena[*body]);
+            }
+            Stmt::ForEach(_, label, pattern, iterable, body) => {
+                if let Some(label) = label {
+                    self.write(&format!("{}: ", label.lexeme));
+                }
+                self.write(&format!(
+                    "for ({} in {})",
+                    pattern_source(pattern),
+                    expr_source(self.arena, iterable)
+                ));
+                self.body(&arena[*body]);
+            }
+            Stmt::Break(_, label) => self.jump("break", label.as_ref().map(|l| &l.lexeme)),
+            Stmt::Continue(_, label) => self.jump("continue", label.as_ref().map(|l| &l.lexeme)),
+            Stmt::Throw(_, value) => {
+                self.write(&format!("throw {};", expr_source(self.arena, value)))
+            }
+            Stmt::Defer(_, stmt) => {
+                self.write("defer");
+                self.body(&arena[*stmt]);
+            }
+            Stmt::Try(_, try_block, catch_clause, finally_block) => {
+                self.write("try ");
+                self.block(try_block);
+                if let Some((name, catch_block)) = catch_clause {
+                    self.write(&format!(" catch ({}) ", name.lexeme));
+                    self.block(catch_block);
+                }
+                if let Some(finally_block) = finally_block {
+                    self.write(" finally ");
+                    self.block(finally_block);
+                }
+            }
+            Stmt::Import(_, path, alias) => {
+                self.write(&format!("import {}", string_source(path)));
+                if let Some(alias) = alias {
+                    self.write(&format!(" as {}", alias.lexeme));
+                }
+                self.write(";");
+            }
+            Stmt::Pub(_, stmt) => {
+                self.write("pub ");
+                self.stmt(&arena[*stmt]);
+            }
+            Stmt::Match(_, subject, arms, default) => {
+                self.write(&format!("match ({}) {{", expr_source(self.arena, subject)));
+                self.indent += 1;
+                for (patterns, body) in arms.iter() {
+                    self.newline();
+                    self.write(&format!("{} -> ", list_source(self.arena, patterns)));
+                    self.stmt(body);
+                }
+                if let Some(default) = default {
+                    self.newline();
+                    self.write("else -> ");
+                    self.stmt(&arena[*default]);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        self.write("{");
+        self.indent += 1;
+        for stmt in stmts {
+            self.newline();
+            self.stmt(stmt);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    /// The body of an `if`, loop or `defer`: a block on the same line, any
+    /// other statement on the next line, indented.
+    fn body(&mut self, stmt: &Stmt) {
+        if let Stmt::Block(stmts) = stmt {
+            self.write(" ");
+            self.block(stmts);
+            return;
+        }
+
+        self.indent += 1;
+        self.newline();
+        self.stmt(stmt);
+        self.indent -= 1;
+    }
+
+    fn jump(&mut self, keyword: &str, label: Option<&String>) {
+        self.write(keyword);
+        if let Some(label) = label {
+            self.write(&format!(" {}", label));
+        }
+        self.write(";");
+    }
+}
+
+fn expr_source(arena: &Arena, expr: &Expr) -> String {
+    match expr {
+        Expr::Unary(op, right) => format!("{}{}", op.lexeme, expr_source(arena, &arena[*right])),
+        Expr::Binary(left, op, right) | Expr::Logical(left, op, right) => {
+            format!(
+                "{} {} {}",
+                expr_source(arena, &arena[*left]),
+                op.lexeme,
+                expr_source(arena, &arena[*right])
+            )
+        }
+        Expr::Grouping(expr) => format!("({})", expr_source(arena, &arena[*expr])),
+        Expr::Literal(_, literal) => literal_source(literal),
+        Expr::Var(name, _) => name.lexeme.clone(),
+        Expr::Assign(name, value, _) => {
+            format!("{} = {}", name.lexeme, expr_source(arena, &arena[*value]))
+        }
+        Expr::Call(callee, _, args) => format!(
+            "{}({})",
+            expr_source(arena, &arena[*callee]),
+            list_source(arena, args)
+        ),
+        Expr::Get(object, name) => {
+            format!("{}.{}", expr_source(arena, &arena[*object]), name.lexeme)
+        }
+        Expr::Tuple(_, items, _) => match items.as_slice() {
+            [item] => format!("({},)", expr_source(arena, item)),
+            items => format!("({})", list_source(arena, items)),
+        },
+        Expr::Spread(_, expr) => format!("...{}", expr_source(arena, &arena[*expr])),
+    }
+}
+
+fn list_source(arena: &Arena, exprs: &[Expr]) -> String {
+    let items: Vec<String> = exprs.iter().map(|expr| expr_source(arena, expr)).collect();
+    items.join(", ")
+}
+
+fn pattern_source(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Name(name, _) => name.lexeme.clone(),
+        Pattern::Tuple(_, patterns, _) => {
+            let items: Vec<String> = patterns.iter().map(pattern_source).collect();
+            format!("({})", items.join(", "))
+        }
+    }
+}
+
+fn literal_source(literal: &Literal) -> String {
+    match literal {
+        Literal::Int(n) => n.to_string(),
+        // The shortest form that reads back as the same number, always with
+        // a decimal point or an exponent so it stays a number and not an int.
+        Literal::Number(n) => format!("{:?}", n),
+        Literal::String(s) => string_source(s),
+        Literal::Bool(b) => b.to_string(),
+        Literal::Nil => "nil".to_string(),
+    }
+}
+
+/// Strings have no escapes, so one holding a `"` is written with triple
+/// quotes. One holding `"""` or ending in `"` has no source form at all.
+fn string_source(s: &str) -> String {
+    if s.contains('"') {
+        format!("\"\"\"{}\"\"\"", s)
+    } else {
+        format!("\"{}\"", s)
+    }
+}
@@ -0,0 +1,310 @@
+//! `rox.toml`, the manifest that makes a directory a project:
+//!
+//! ```toml
+//! [project]
+//! entry = "src/main.rox"
+//! paths = ["lib", "vendor"]
+//!
+//! [features]
+//! sandbox = true
+//! strict = true
+//!
+//! [limits]
+//! max_statements = 1000000
+//! max_call_depth = 200
+//! timeout_ms = 5000
+//! ```
+//!
+//! Only the part of TOML these keys need is understood: tables, and values
+//! that are strings, integers, booleans or single-line arrays of strings.
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{limits::Limits, suggest::closest};
+
+/// The name of the manifest file.
+pub const MANIFEST: &str = "rox.toml";
+
+/// The settings in a `rox.toml`, with its paths made absolute.
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// The directory holding the manifest.
+    pub root: PathBuf,
+    /// The script `rox` runs when it is not given one.
+    pub entry: Option<PathBuf>,
+    /// Directories searched, in order, for imports that are not found
+    /// relative to the importing file.
+    pub paths: Vec<PathBuf>,
+    pub sandbox: bool,
+    pub strict: bool,
+    pub limits: Limits,
+}
+
+#[derive(Debug)]
+enum TomlValue {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<String>),
+}
+
+impl TomlValue {
+    fn kind(&self) -> &'static str {
+        match self {
+            TomlValue::String(_) => "a string",
+            TomlValue::Integer(_) => "an integer",
+            TomlValue::Bool(_) => "a boolean",
+            TomlValue::Array(_) => "an array",
+        }
+    }
+}
+
+/// Every key a manifest may set, by table.
+const KEYS: &[(&str, &[&str])] = &[
+    ("project", &["entry", "paths"]),
+    ("features", &["sandbox", "strict"]),
+    (
+        "limits",
+        &[
+            "max_statements",
+            "max_call_depth",
+            "max_heap_objects",
+            "timeout_ms",
+        ],
+    ),
+];
+
+impl Project {
+    /// Looks for a manifest in `dir` and each directory above it, and loads
+    /// the first one found.
+    pub fn find<P: AsRef<Path>>(dir: P) -> Result<Option<Project>, String> {
+        for dir in dir.as_ref().ancestors() {
+            let manifest = dir.join(MANIFEST);
+            if manifest.is_file() {
+                return Project::load(manifest).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `find`, starting from the working directory.
+    pub fn find_current() -> Result<Option<Project>, String> {
+        let dir = env::current_dir()
+            .map_err(|err| format!("Cannot read the working directory: {}", err))?;
+        Project::find(dir)
+    }
+
+    /// Reads the manifest at `path`. Paths in it are relative to its
+    /// directory.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Project, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read '{}': {}", path.display(), err))?;
+        let root = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let root = root
+            .canonicalize()
+            .map_err(|err| format!("Cannot open '{}': {}", root.display(), err))?;
+
+        Project::parse(&text, root).map_err(|message| format!("{}: {}", path.display(), message))
+    }
+
+    /// Builds a project from the text of a manifest in `root`.
+    pub fn parse(text: &str, root: PathBuf) -> Result<Project, String> {
+        let mut project = Project {
+            root,
+            ..Project::default()
+        };
+
+        for ((table, key), (line, value)) in parse_toml(text)? {
+            let at = |message: String| format!("line {}: {}", line, message);
+            let expected = |kind: &str| at(format!("'{}' must be {}", key, kind));
+            match (table.as_str(), key.as_str(), value) {
+                ("project", "entry", TomlValue::String(entry)) => {
+                    project.entry = Some(project.root.join(entry));
+                }
+                ("project", "paths", TomlValue::Array(paths)) => {
+                    project.paths = paths.iter().map(|dir| project.root.join(dir)).collect();
+                }
+                ("features", "sandbox", TomlValue::Bool(sandbox)) => project.sandbox = sandbox,
+                ("features", "strict", TomlValue::Bool(strict)) => project.strict = strict,
+                ("limits", _, TomlValue::Integer(n)) if n < 0 => {
+                    return Err(expected("zero or more"));
+                }
+                ("limits", "max_statements", TomlValue::Integer(n)) => {
+                    project.limits.max_statements = Some(n as u64);
+                }
+                ("limits", "max_call_depth", TomlValue::Integer(n)) => {
+                    project.limits.max_call_depth = Some(n as usize);
+                }
+                ("limits", "max_heap_objects", TomlValue::Integer(n)) => {
+                    project.limits.max_heap_objects = Some(n as usize);
+                }
+                ("limits", "timeout_ms", TomlValue::Integer(n)) => {
+                    project.limits.timeout = Some(Duration::from_millis(n as u64));
+                }
+                ("project", "entry", value) => {
+                    return Err(expected(&format!("a string, not {}", value.kind())));
+                }
+                ("project", "paths", value) => {
+                    return Err(expected(&format!("an array, not {}", value.kind())));
+                }
+                ("features", _, value) => {
+                    return Err(expected(&format!("a boolean, not {}", value.kind())));
+                }
+                (_, _, value) => {
+                    return Err(expected(&format!("an integer, not {}", value.kind())));
+                }
+            }
+        }
+
+        Ok(project)
+    }
+}
+
+/// The values in `text` by table and key, with the line each was set on.
+type Entries = Vec<((String, String), (usize, TomlValue))>;
+
+fn parse_toml(text: &str) -> Result<Entries, String> {
+    let mut entries: Entries = Vec::new();
+    let mut seen = HashMap::new();
+    let mut table: Option<(&str, &[&str])> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let at = |message: String| format!("line {}: {}", number, message);
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| at("Expected ']' after the table name".to_string()))?
+                .trim();
+            table = match KEYS.iter().find(|(table, _)| *table == name) {
+                Some(&table) => Some(table),
+                None => {
+                    let hint = closest(name, KEYS.iter().map(|(table, _)| *table))
+                        .map(|table| format!(". Did you mean '{}'?", table))
+                        .unwrap_or_default();
+                    return Err(at(format!("Unknown table '{}'{}", name, hint)));
+                }
+            };
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| at("Expected 'key = value'".to_string()))?;
+        let key = key.trim();
+        let Some((table, keys)) = table else {
+            return Err(at(format!("'{}' must be inside a table", key)));
+        };
+        if !keys.contains(&key) {
+            let hint = closest(key, keys.iter().copied())
+                .map(|key| format!(". Did you mean '{}'?", key))
+                .unwrap_or_default();
+            return Err(at(format!("Unknown key '{}' in [{}]{}", key, table, hint)));
+        }
+        if let Some(first) = seen.insert((table, key), number) {
+            return Err(at(format!("'{}' was already set on line {}", key, first)));
+        }
+
+        let value = parse_value(value.trim()).map_err(at)?;
+        entries.push(((table.to_string(), key.to_string()), (number, value)));
+    }
+
+    Ok(entries)
+}
+
+/// `line` without a `#` comment, leaving any `#` inside a string alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<TomlValue, String> {
+    match text {
+        "true" => return Ok(TomlValue::Bool(true)),
+        "false" => return Ok(TomlValue::Bool(false)),
+        _ => {}
+    }
+
+    if text.starts_with('"') {
+        let (string, rest) = parse_string(text)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("Unexpected '{}' after the string", rest.trim()));
+        }
+        return Ok(TomlValue::String(string));
+    }
+
+    if let Some(inner) = text.strip_prefix('[') {
+        let mut rest = inner.trim_start();
+        let mut items = Vec::new();
+        loop {
+            if let Some(after) = rest.strip_prefix(']') {
+                if !after.trim().is_empty() {
+                    return Err(format!("Unexpected '{}' after the array", after.trim()));
+                }
+                return Ok(TomlValue::Array(items));
+            }
+            if !rest.starts_with('"') {
+                return Err("Arrays may only hold strings".to_string());
+            }
+            let (item, after) = parse_string(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after.trim_start();
+            } else if !rest.starts_with(']') {
+                return Err("Expected ',' or ']' in the array".to_string());
+            }
+        }
+    }
+
+    text.replace('_', "")
+        .parse()
+        .map(TomlValue::Integer)
+        .map_err(|_| format!("Invalid value '{}'", text))
+}
+
+/// Reads the basic string at the start of `text`, returning it unescaped
+/// along with whatever follows its closing quote.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let mut string = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, &text[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => string.push('\n'),
+                Some((_, 't')) => string.push('\t'),
+                Some((_, '"')) => string.push('"'),
+                Some((_, '\\')) => string.push('\\'),
+                Some((_, c)) => return Err(format!("Unknown escape '\\{}'", c)),
+                None => break,
+            },
+            c => string.push(c),
+        }
+    }
+    Err("Unterminated string".to_string())
+}
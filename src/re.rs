@@ -0,0 +1,109 @@
+use std::rc::Rc;
+
+use regex::Regex;
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Regular expression natives, using the syntax of the `regex` crate.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "re_match",
+            arity: 2..=2,
+            func: re_match,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "re_find_all",
+            arity: 2..=2,
+            func: re_find_all,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "re_replace",
+            arity: 3..=3,
+            func: re_replace,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "re_split",
+            arity: 2..=2,
+            func: re_split,
+            deterministic: true,
+        },
+    ]
+}
+
+fn pattern_arg(name: &str, args: &[Value]) -> Result<Regex, RuntimeError> {
+    let pattern = string_arg(name, args, 0)?;
+    Regex::new(&pattern)
+        .map_err(|err| RuntimeError::Generic(format!("{}() got an invalid pattern: {}", name, err)))
+}
+
+fn list_value(items: Vec<Value>) -> Value {
+    Value::List(Rc::new(items))
+}
+
+/// `re_match(pattern, s)` returns the first match as a list holding the
+/// whole match followed by each capture group, with nil for groups that did
+/// not take part. Returns nil when the pattern does not match at all.
+fn re_match(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let regex = pattern_arg("re_match", &args)?;
+    let s = string_arg("re_match", &args, 1)?;
+
+    let Some(captures) = regex.captures(&s) else {
+        return Ok(Value::Nil);
+    };
+
+    Ok(list_value(
+        captures
+            .iter()
+            .map(|group| match group {
+                Some(group) => string_value(group.as_str().to_string()),
+                None => Value::Nil,
+            })
+            .collect(),
+    ))
+}
+
+/// `re_find_all(pattern, s)` returns every non-overlapping match.
+fn re_find_all(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let regex = pattern_arg("re_find_all", &args)?;
+    let s = string_arg("re_find_all", &args, 1)?;
+
+    Ok(list_value(
+        regex
+            .find_iter(&s)
+            .map(|found| string_value(found.as_str().to_string()))
+            .collect(),
+    ))
+}
+
+/// `re_replace(pattern, s, replacement)` replaces every match. `$1` or
+/// `${name}` in the replacement stand for a capture group, `$$` for a `$`.
+fn re_replace(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let regex = pattern_arg("re_replace", &args)?;
+    let s = string_arg("re_replace", &args, 1)?;
+    let replacement = string_arg("re_replace", &args, 2)?;
+
+    Ok(string_value(
+        regex.replace_all(&s, &*replacement).into_owned(),
+    ))
+}
+
+/// `re_split(pattern, s)` splits `s` at every match.
+fn re_split(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let regex = pattern_arg("re_split", &args)?;
+    let s = string_arg("re_split", &args, 1)?;
+
+    Ok(list_value(
+        regex
+            .split(&s)
+            .map(|part| string_value(part.to_string()))
+            .collect(),
+    ))
+}
@@ -0,0 +1,164 @@
+use std::rc::Rc;
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    module::Module,
+    native::{string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// Natives that look inside values at runtime. Modules are the only values
+/// with named members, so they are what `fields` and `get_field` work on.
+/// Their exports are read-only, so `set_field` always fails.
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "fields",
+            arity: 1..=1,
+            func: fields,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "methods",
+            arity: 1..=1,
+            func: methods,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "get_field",
+            arity: 2..=2,
+            func: get_field,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "set_field",
+            arity: 3..=3,
+            func: set_field,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "arity",
+            arity: 1..=1,
+            func: arity,
+            deterministic: true,
+        },
+        NativeFn {
+            name: "name",
+            arity: 1..=1,
+            func: name,
+            deterministic: true,
+        },
+    ]
+}
+
+fn module_arg<'a>(
+    name: &str,
+    args: &'a [Value],
+    index: usize,
+) -> Result<&'a Rc<Module>, RuntimeError> {
+    match &args[index] {
+        Value::Module(module) => Ok(module),
+        x => Err(RuntimeError::Generic(format!(
+            "{}() expects a module as argument {}, got '{}'",
+            name,
+            index + 1,
+            x
+        ))),
+    }
+}
+
+/// The names of a module's members for which `keep` holds, sorted.
+fn member_names(module: &Module, keep: impl Fn(&Value) -> bool) -> Value {
+    let mut names: Vec<&String> = module
+        .exports
+        .iter()
+        .filter(|(_, value)| keep(value))
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+
+    Value::List(Rc::new(
+        names
+            .into_iter()
+            .map(|name| string_value(name.clone()))
+            .collect(),
+    ))
+}
+
+/// `fields(module)` lists the names of everything the module exports.
+fn fields(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let module = module_arg("fields", &args, 0)?;
+    Ok(member_names(module, |_| true))
+}
+
+/// `methods(module)` lists the names of the exports that can be called.
+fn methods(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let module = module_arg("methods", &args, 0)?;
+    Ok(member_names(module, |value| {
+        matches!(value, Value::Native(_) | Value::Host(_))
+    }))
+}
+
+/// `get_field(module, "name")` is `module.name` with the name chosen at
+/// runtime.
+fn get_field(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let module = module_arg("get_field", &args, 0)?;
+    let field = string_arg("get_field", &args, 1)?;
+
+    module.exports.get(&*field).cloned().ok_or_else(|| {
+        RuntimeError::Generic(format!(
+            "Module '{}' has no member '{}'",
+            module.name, field
+        ))
+    })
+}
+
+/// `set_field(module, "name", value)` would be `module.name = value`, which
+/// is never allowed, so it explains that rather than failing as unknown.
+fn set_field(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let module = module_arg("set_field", &args, 0)?;
+    let field = string_arg("set_field", &args, 1)?;
+
+    Err(RuntimeError::Generic(format!(
+        "Cannot set '{}' on module '{}', module exports are read-only",
+        field, module.name
+    )))
+}
+
+/// `arity(fn)` is how many arguments a function takes, or a `(min, max)`
+/// tuple for one with optional arguments, where `max` is nil if there is
+/// no limit.
+fn arity(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let range = match &args[0] {
+        Value::Native(native) => &native.arity,
+        Value::Host(host) => &host.arity,
+        x => {
+            return Err(RuntimeError::Generic(format!(
+                "arity() expects a function, got '{}'",
+                x
+            )))
+        }
+    };
+
+    let min = Value::Int(*range.start() as i64);
+    if range.start() == range.end() {
+        return Ok(min);
+    }
+    // Natives that take any number of arguments have no maximum.
+    let max = i64::try_from(*range.end()).map_or(Value::Nil, Value::Int);
+    Ok(Value::Tuple(Rc::new(vec![min, max])))
+}
+
+/// `name(value)` is the name a function or module was defined or imported
+/// under.
+fn name(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Native(native) => Ok(string_value(native.name.to_string())),
+        Value::Host(host) => Ok(string_value(host.name.clone())),
+        Value::Module(module) => Ok(string_value(module.name.clone())),
+        x => Err(RuntimeError::Generic(format!(
+            "name() expects a function or module, got '{}'",
+            x
+        ))),
+    }
+}
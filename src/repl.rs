@@ -0,0 +1,399 @@
+use std::{
+    env, fs,
+    io::{stdin, stdout, BufRead, IsTerminal, Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use rox_rust::{is_incomplete, keywords, Rox, RoxError, RuntimeError, Value};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// How many lines of history are kept between sessions.
+const HISTORY_LIMIT: usize = 1000;
+
+/// Runs statements typed at the prompt in one session, echoing the value of
+/// a trailing expression. Input with unclosed brackets, strings or comments
+/// is continued on the next line. A line starting with `:` is a command:
+/// `:save file` and `:load file` write and read a snapshot of the session.
+/// Returns the process exit code.
+pub fn run(rox: &mut Rox) -> i32 {
+    let mut editor = LineEditor::new(history_path());
+    let mut source = String::new();
+
+    let code = loop {
+        let prompt = if source.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        let mut words: Vec<String> = keywords().map(str::to_string).collect();
+        words.extend(rox.names());
+
+        match editor.read_line(prompt, &words) {
+            Input::Line(line) if source.is_empty() && line.trim_start().starts_with(':') => {
+                command(rox, line.trim());
+                continue;
+            }
+            Input::Line(line) => {
+                source.push_str(&line);
+                source.push('\n');
+            }
+            Input::Cancel => {
+                source.clear();
+                continue;
+            }
+            Input::Eof => break 0,
+        }
+
+        if is_incomplete(&source) {
+            continue;
+        }
+
+        let input = std::mem::take(&mut source);
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        // Errors have already been reported by the time eval returns.
+        match rox.eval(&input) {
+            Ok(Value::Nil) => {}
+            Ok(value) => println!("{}", value),
+            Err(RoxError::Runtime(RuntimeError::Exit(code))) => break code,
+            Err(_) => {}
+        }
+    };
+
+    editor.save_history();
+    code
+}
+
+fn command(rox: &mut Rox, line: &str) {
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+
+    let result = match (name, argument) {
+        (":save", path) if !path.is_empty() => rox.save_snapshot(path),
+        (":load", path) if !path.is_empty() => rox.load_snapshot(path),
+        (":save" | ":load", _) => {
+            eprintln!("Usage: {} <file>", name);
+            return;
+        }
+        _ => {
+            eprintln!(
+                "Unknown command '{}'. Try :save <file> or :load <file>.",
+                name
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+/// `$ROX_HISTORY`, or `.rox_history` in the home directory.
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("ROX_HISTORY") {
+        return Some(PathBuf::from(path));
+    }
+
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rox_history"))
+}
+
+enum Input {
+    Line(String),
+    /// Ctrl-C: drop the line and anything continued from earlier lines.
+    Cancel,
+    Eof,
+}
+
+/// A minimal line editor: cursor movement, history recall with the arrow
+/// keys, Tab completion and Ctrl-C to cancel. It puts the terminal into raw
+/// mode with `stty` while a line is being read, and falls back to plain
+/// buffered input when stdin is not a terminal or `stty` is unavailable.
+struct LineEditor {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+    interactive: bool,
+}
+
+impl LineEditor {
+    fn new(history_path: Option<PathBuf>) -> LineEditor {
+        let history = history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        LineEditor {
+            history,
+            history_path,
+            interactive: stdin().is_terminal(),
+        }
+    }
+
+    fn save_history(&self) {
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if !self.interactive {
+            return;
+        }
+
+        let start = self.history.len().saturating_sub(HISTORY_LIMIT);
+        let mut text = self.history[start..].join("\n");
+        text.push('\n');
+
+        // Losing history is not worth interrupting the session over.
+        let _ = fs::write(path, text);
+    }
+
+    /// Reads one line, completing identifiers from `words` on Tab.
+    fn read_line(&mut self, prompt: &str, words: &[String]) -> Input {
+        if !self.interactive {
+            return read_buffered_line();
+        }
+
+        print!("{}", prompt);
+        let _ = stdout().flush();
+
+        let Some(saved) = enter_raw_mode() else {
+            return read_buffered_line();
+        };
+        let input = self.edit(prompt, words);
+        leave_raw_mode(&saved);
+
+        if let Input::Line(line) = &input {
+            if !line.trim().is_empty() && self.history.last() != Some(line) {
+                self.history.push(line.clone());
+            }
+        }
+
+        input
+    }
+
+    fn edit(&mut self, prompt: &str, words: &[String]) -> Input {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        let mut history_index = self.history.len();
+        let mut draft = Vec::new();
+
+        loop {
+            let Some(byte) = read_byte() else {
+                return Input::Eof;
+            };
+
+            match byte {
+                b'\r' | b'\n' => {
+                    println!();
+                    return Input::Line(line.into_iter().collect());
+                }
+                // Ctrl-C
+                3 => {
+                    println!("^C");
+                    return Input::Cancel;
+                }
+                // Ctrl-D ends the session on an empty line and deletes
+                // forwards otherwise.
+                4 if line.is_empty() => {
+                    println!();
+                    return Input::Eof;
+                }
+                4 if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                // Backspace
+                8 | 127 if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                b'\t' => cursor = complete(&mut line, cursor, words),
+                // Ctrl-A and Ctrl-E
+                1 => cursor = 0,
+                5 => cursor = line.len(),
+                27 => match read_escape() {
+                    Some(b'A') if history_index > 0 => {
+                        if history_index == self.history.len() {
+                            draft = line;
+                        }
+                        history_index -= 1;
+                        line = self.history[history_index].chars().collect();
+                        cursor = line.len();
+                    }
+                    Some(b'B') if history_index < self.history.len() => {
+                        history_index += 1;
+                        line = match self.history.get(history_index) {
+                            Some(entry) => entry.chars().collect(),
+                            None => std::mem::take(&mut draft),
+                        };
+                        cursor = line.len();
+                    }
+                    Some(b'C') if cursor < line.len() => cursor += 1,
+                    Some(b'D') if cursor > 0 => cursor -= 1,
+                    Some(b'H') => cursor = 0,
+                    Some(b'F') => cursor = line.len(),
+                    Some(b'~') if cursor < line.len() => {
+                        line.remove(cursor);
+                    }
+                    _ => {}
+                },
+                byte if byte >= 0x20 => {
+                    if let Some(c) = read_char(byte) {
+                        line.insert(cursor, c);
+                        cursor += 1;
+                    }
+                }
+                _ => {}
+            }
+
+            redraw(prompt, &line, cursor);
+        }
+    }
+}
+
+/// Completes the identifier before the cursor as far as every matching word
+/// agrees, listing the candidates when that adds nothing. Returns the new
+/// cursor position.
+fn complete(line: &mut Vec<char>, cursor: usize, words: &[String]) -> usize {
+    let start = line[..cursor]
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(0, |index| index + 1);
+    let prefix: String = line[start..cursor].iter().collect();
+    if prefix.is_empty() || (start > 0 && line[start - 1] == '.') {
+        return cursor;
+    }
+
+    let mut candidates: Vec<&str> = words
+        .iter()
+        .map(String::as_str)
+        .filter(|word| word.starts_with(&prefix))
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let Some(first) = candidates.first() else {
+        return cursor;
+    };
+    let common = candidates.iter().fold(first.to_string(), |common, word| {
+        common
+            .chars()
+            .zip(word.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    });
+
+    let added: Vec<char> = common.chars().skip(prefix.chars().count()).collect();
+    if added.is_empty() && candidates.len() > 1 {
+        println!("\n{}", candidates.join("  "));
+        return cursor;
+    }
+
+    let inserted = added.len();
+    line.splice(cursor..cursor, added);
+    cursor + inserted
+}
+
+fn read_buffered_line() -> Input {
+    let mut line = String::new();
+    match stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => Input::Eof,
+        Ok(_) => {
+            let len = line.trim_end_matches(['\r', '\n']).len();
+            line.truncate(len);
+            Input::Line(line)
+        }
+    }
+}
+
+fn read_byte() -> Option<u8> {
+    let mut byte = [0];
+    match stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+/// Reads the rest of an escape sequence and returns the byte that names the
+/// key: `A` to `D` for the arrows, `H` and `F` for Home and End, and `~`
+/// for Delete.
+fn read_escape() -> Option<u8> {
+    match read_byte()? {
+        b'[' | b'O' => {}
+        _ => return None,
+    }
+
+    let byte = read_byte()?;
+    if !byte.is_ascii_digit() {
+        return Some(byte);
+    }
+
+    // `ESC [ n ~` keys: 1 and 7 are Home, 4 and 8 are End, 3 is Delete.
+    let mut last = byte;
+    while last != b'~' {
+        last = read_byte()?;
+    }
+    match byte {
+        b'1' | b'7' => Some(b'H'),
+        b'4' | b'8' => Some(b'F'),
+        b'3' => Some(b'~'),
+        _ => None,
+    }
+}
+
+/// Decodes a UTF-8 character whose first byte has already been read.
+fn read_char(first: u8) -> Option<char> {
+    let len = match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return None,
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        bytes.push(read_byte()?);
+    }
+
+    std::str::from_utf8(&bytes).ok()?.chars().next()
+}
+
+fn redraw(prompt: &str, line: &[char], cursor: usize) {
+    let text: String = line.iter().collect();
+    print!("\r{}{}\x1b[K", prompt, text);
+    if cursor < line.len() {
+        print!("\x1b[{}D", line.len() - cursor);
+    }
+    let _ = stdout().flush();
+}
+
+/// Turns off line buffering, echo and signal keys so every key press can be
+/// handled, returning the settings to restore afterwards.
+fn enter_raw_mode() -> Option<String> {
+    let saved = Command::new("stty")
+        .arg("-g")
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    Command::new("stty")
+        .args(["-icanon", "-echo", "-isig", "min", "1"])
+        .status()
+        .ok()
+        .filter(|status| status.success())?;
+
+    Some(String::from_utf8_lossy(&saved.stdout).trim().to_string())
+}
+
+fn leave_raw_mode(saved: &str) {
+    let _ = Command::new("stty").arg(saved).status();
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    io::{stdin, stdout, BufRead, Write},
+};
+
+use rox_rust::Trace;
+
+const HELP: &str = "n: next step, b: previous step, g N: go to step N, \
+                    v: variables, q: quit. Enter repeats the last move.";
+
+/// Steps forwards and backwards through a trace written by `--record`,
+/// showing each statement's source line, what it wrote and called, and the
+/// value of every variable at that point. Returns the process exit code.
+pub fn run(path: &str) -> i32 {
+    let trace = match Trace::read(path) {
+        Ok(trace) => trace,
+        Err(message) => {
+            eprintln!("{}", message);
+            return super::EX_DATAERR;
+        }
+    };
+    if trace.steps.is_empty() {
+        println!("The trace has no statements.");
+        return 0;
+    }
+
+    // Without the script the viewer still works, just without source text.
+    let source = fs::read_to_string(&trace.script).unwrap_or_default();
+    let lines: Vec<&str> = source.lines().collect();
+
+    println!("{}", HELP);
+    let mut current = 0;
+    let mut last_move = 1;
+    show(&trace, &lines, current);
+
+    loop {
+        print!("replay> ");
+        let _ = stdout().flush();
+
+        let mut input = String::new();
+        match stdin().lock().read_line(&mut input) {
+            Ok(0) | Err(_) => return 0,
+            Ok(_) => {}
+        }
+
+        let mut words = input.split_whitespace();
+        let target = match (words.next(), words.next()) {
+            (None, _) => current.saturating_add_signed(last_move),
+            (Some("n"), _) => {
+                last_move = 1;
+                current + 1
+            }
+            (Some("b"), _) => {
+                last_move = -1;
+                current.saturating_sub(1)
+            }
+            (Some("g"), Some(step)) => match step.parse::<usize>() {
+                Ok(step) if step >= 1 => step - 1,
+                _ => {
+                    println!("Steps are numbered from 1.");
+                    continue;
+                }
+            },
+            (Some("v"), _) => {
+                for (name, value) in trace.variables_at(current) {
+                    println!("  {} = {}", name, value);
+                }
+                continue;
+            }
+            (Some("q"), _) => return 0,
+            _ => {
+                println!("{}", HELP);
+                continue;
+            }
+        };
+
+        if target >= trace.steps.len() {
+            println!("That is past the end of the trace.");
+            continue;
+        }
+        current = target;
+        show(&trace, &lines, current);
+    }
+}
+
+fn show(trace: &Trace, lines: &[&str], index: usize) {
+    let step = &trace.steps[index];
+    let text = usize::try_from(step.line - 1)
+        .ok()
+        .and_then(|line| lines.get(line))
+        .map_or("", |text| text.trim());
+
+    println!(
+        "step {}/{}  line {}: {}",
+        index + 1,
+        trace.steps.len(),
+        step.line,
+        text
+    );
+    for (name, value) in step.writes.iter() {
+        println!("  {} = {}", name, value);
+    }
+    if !step.calls.is_empty() {
+        println!("  calls {}", step.calls.join(", "));
+    }
+}
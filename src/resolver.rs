@@ -0,0 +1,863 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    arena::{Arena, ExprId, Program, StmtId},
+    diagnostic::{
+        Diagnostic, W_CONSTANT_CONDITION, W_REDECLARED_VARIABLE, W_SHADOWED_VARIABLE,
+        W_UNREACHABLE_CODE, W_UNUSED_EXPRESSION, W_UNUSED_VARIABLE,
+    },
+    error::Log,
+    expression::{Expr, Local},
+    parser::ParseError,
+    statement::{Pattern, Stmt},
+    token::{Literal, Span, Token, TokenType},
+    typecheck::TypeChecker,
+    value::Value,
+    visitor::{ExprVisitorMut, StmtVisitorMut},
+};
+
+/// What the resolver knows about a declared name.
+struct Binding {
+    name: Token,
+    constant: bool,
+    read: bool,
+    /// Whether a value has been given to it in code seen so far.
+    assigned: bool,
+    /// Where a local lives in its scope. Top-level names, and names that
+    /// only an import defines, have none and are looked up by name.
+    slot: Option<usize>,
+}
+
+#[derive(Default)]
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    slots: usize,
+}
+
+/// A static pass over the parsed program that catches mistakes before any
+/// code runs, such as assigning to a constant. It also warns about code
+/// that is likely wrong but still runs, such as unreachable statements.
+///
+/// Locals are given slots as they are declared, and every reference to one
+/// is annotated with how many scopes out it lives and at which slot, so the
+/// interpreter can find it without looking the name up.
+///
+/// In strict mode, turned on by the host or by a `"use strict";` first
+/// statement, warnings are errors and a few more mistakes are caught:
+/// assigning to a name that was never declared and reading a variable
+/// before it is given a value.
+pub struct Resolver<'a> {
+    scopes: Vec<Scope>,
+    /// The labels of the enclosing loops, innermost last.
+    loops: Vec<Option<String>>,
+    errors: Vec<ParseError>,
+    file: Option<String>,
+    logger: &'a Log,
+    strict: bool,
+    /// Names defined before this code runs, such as natives and earlier
+    /// top-level variables, which strict mode lets it assign to.
+    globals: HashSet<String>,
+    /// Set by an import without an alias, whose names cannot be known here.
+    imports_names: bool,
+    /// Set for the statement about to be resolved when its value is kept:
+    /// the last one of a program, whose value `eval` returns, and the
+    /// `"use strict";` pragma.
+    keeps_value: bool,
+    /// The index of the scope the code's top level is resolved in, which is
+    /// only past the first when it runs inside blocks, as `eval` does.
+    top: usize,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(file: Option<String>, logger: &'a Log) -> Resolver<'a> {
+        Resolver {
+            scopes: vec![Scope::default()],
+            loops: Vec::new(),
+            errors: Vec::new(),
+            file,
+            logger,
+            strict: false,
+            globals: HashSet::new(),
+            imports_names: false,
+            keeps_value: false,
+            top: 0,
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> Resolver<'a> {
+        self.strict = strict;
+        self
+    }
+
+    /// Tells strict mode about names defined outside the code, so assigning
+    /// to them is allowed.
+    pub fn with_globals(mut self, names: Vec<String>) -> Resolver<'a> {
+        self.globals.extend(names);
+        self
+    }
+
+    /// Resolves the code as if it ran inside blocks, which declared the
+    /// given locals. Each scope lists its names, slots and whether they are
+    /// constants, outermost first; the last is the one the code runs in,
+    /// and its own declarations go there by name.
+    pub fn within(mut self, scopes: Vec<Vec<(String, usize, bool)>>) -> Resolver<'a> {
+        self.scopes.clear();
+        for locals in scopes {
+            let mut scope = Scope::default();
+            for (name, slot, constant) in locals {
+                let token = Token::new(
+                    TokenType::IDENTIFIER,
+                    name.clone(),
+                    0,
+                    0,
+                    Span::default(),
+                    Literal::Nil,
+                );
+                let binding = Binding {
+                    name: token,
+                    constant,
+                    read: true,
+                    assigned: true,
+                    slot: Some(slot),
+                };
+                scope.bindings.insert(name, binding);
+            }
+            self.scopes.push(scope);
+        }
+        if self.scopes.is_empty() {
+            self.scopes.push(Scope::default());
+        }
+        self.top = self.scopes.len() - 1;
+        self
+    }
+
+    /// Whether strict mode is on, by the host's choice or the code's.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn resolve(&mut self, program: &mut Program) -> Result<(), Vec<ParseError>> {
+        if strict_pragma(&program.stmts) {
+            self.strict = true;
+        }
+        self.resolve_stmts(&mut program.arena, &mut program.stmts);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn resolve_stmts(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        let mut reported = false;
+        let top_level = self.scopes.len() == self.top + 1;
+        let pragma = strict_pragma(stmts);
+
+        for i in 0..stmts.len() {
+            let jump = if i > 0 {
+                jumps_away(arena, &stmts[i - 1])
+            } else {
+                None
+            };
+            if let (false, Some(keyword)) = (reported, jump) {
+                let stmt = &stmts[i];
+                if let (Some(token), Some(span)) = (stmt.first_token(arena), stmt.span(arena)) {
+                    self.warn(
+                        token,
+                        span,
+                        W_UNREACHABLE_CODE,
+                        format!("Unreachable code after '{}'.", keyword),
+                        None,
+                    );
+                    reported = true;
+                }
+            }
+            self.keeps_value = top_level && (i + 1 == stmts.len() || (i == 0 && pragma));
+            self.resolve_stmt(arena, &mut stmts[i]);
+        }
+    }
+
+    fn resolve_stmt(&mut self, arena: &mut Arena, stmt: &mut Stmt) {
+        self.check_dropped(arena, stmt);
+        stmt.accept_mut(arena, self);
+    }
+
+    fn resolve_stmt_id(&mut self, arena: &mut Arena, stmt: StmtId) {
+        self.check_dropped(arena, &arena[stmt]);
+        arena.accept_stmt_mut(stmt, self);
+    }
+
+    fn resolve_expr(&mut self, arena: &mut Arena, expr: &mut Expr) {
+        expr.accept_mut(arena, self);
+    }
+
+    fn resolve_expr_id(&mut self, arena: &mut Arena, expr: ExprId) {
+        arena.accept_expr_mut(expr, self);
+    }
+
+    fn resolve_block(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        self.begin_scope();
+        self.resolve_stmts(arena, stmts);
+        self.end_scope();
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn end_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            let mut unread: Vec<Binding> =
+                scope.bindings.into_values().filter(|b| !b.read).collect();
+            unread.sort_by_key(|b| b.name.span.start);
+
+            for binding in unread {
+                self.warn_unread(&binding);
+            }
+        }
+    }
+
+    /// Warns about a local that is never read. Globals are skipped because
+    /// importing modules may read them, and `_`-prefixed names opt out.
+    fn warn_unread(&mut self, binding: &Binding) {
+        let name = &binding.name;
+        if name.lexeme.starts_with('_') {
+            return;
+        }
+
+        self.warn(
+            name,
+            name.span,
+            W_UNUSED_VARIABLE,
+            format!("Variable '{}' is never read.", name.lexeme),
+            None,
+        );
+    }
+
+    fn resolve_loop_body(&mut self, arena: &mut Arena, label: Option<&Token>, body: StmtId) {
+        if let Some(label) = label {
+            if self
+                .loops
+                .iter()
+                .flatten()
+                .any(|name| *name == label.lexeme)
+            {
+                self.error(
+                    label,
+                    format!("Loop label '{}' is already in use.", label.lexeme),
+                );
+            }
+        }
+        self.loops.push(label.map(|label| label.lexeme.clone()));
+        self.resolve_stmt_id(arena, body);
+        self.loops.pop();
+    }
+
+    /// Reports a `break` or `continue` that has no loop to leave.
+    fn check_jump(&mut self, keyword: &Token, label: Option<&Token>) {
+        match label {
+            None if self.loops.is_empty() => {
+                self.error(keyword, format!("'{}' outside a loop.", keyword.lexeme));
+            }
+            Some(label)
+                if !self
+                    .loops
+                    .iter()
+                    .flatten()
+                    .any(|name| *name == label.lexeme) =>
+            {
+                self.error(
+                    label,
+                    format!("No enclosing loop labeled '{}'.", label.lexeme),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Warns when a condition is a literal. A true loop condition is allowed,
+    /// as `while (true)` is the usual way to write an endless loop.
+    fn check_condition(&mut self, arena: &Arena, condition: &Expr, is_loop: bool) {
+        let mut inner = condition;
+        while let Expr::Grouping(expr) = inner {
+            inner = &arena[*expr];
+        }
+
+        if let Expr::Literal(token, literal) = inner {
+            let truthy = Value::from(literal).is_truthy();
+            if is_loop && truthy {
+                return;
+            }
+
+            self.warn(
+                token,
+                condition.span(arena),
+                W_CONSTANT_CONDITION,
+                format!("Condition is always {}.", truthy),
+                None,
+            );
+        }
+    }
+
+    /// Warns about an expression statement whose value is thrown away,
+    /// unless it is one the resolver was told is kept.
+    fn check_dropped(&mut self, arena: &Arena, stmt: &Stmt) {
+        let keeps_value = std::mem::take(&mut self.keeps_value);
+        if let (Stmt::Expression(expr), false) = (stmt, keeps_value) {
+            self.check_unused(arena, expr);
+        }
+    }
+
+    /// Warns about an expression statement that only computes a value and
+    /// throws it away, such as `a == b;` written for `a = b;`.
+    fn check_unused(&mut self, arena: &Arena, expr: &Expr) {
+        if has_effects(arena, expr) {
+            return;
+        }
+
+        let mut inner = expr;
+        while let Expr::Grouping(expr) = inner {
+            inner = &arena[*expr];
+        }
+        let message = match inner {
+            Expr::Binary(_, op, _) if op.token_type == TokenType::EQUAL_EQUAL => {
+                "Result of '==' is unused. Did you mean '='?".to_string()
+            }
+            _ => "Expression result is unused.".to_string(),
+        };
+        self.warn(
+            expr.first_token(arena),
+            expr.span(arena),
+            W_UNUSED_EXPRESSION,
+            message,
+            None,
+        );
+    }
+
+    fn mark_read(&mut self, name: &str) {
+        if let Some(binding) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.get_mut(name))
+        {
+            binding.read = true;
+        }
+    }
+
+    /// In strict mode, reports reading a variable that was declared without
+    /// a value and not assigned since. Only the first such read is reported.
+    fn check_assigned(&mut self, name: &Token) {
+        if !self.strict {
+            return;
+        }
+
+        if let Some(binding) = self.lookup_mut(&name.lexeme).filter(|b| !b.assigned) {
+            binding.assigned = true;
+            self.error(
+                name,
+                format!("Variable '{}' is read before it is assigned.", name.lexeme),
+            );
+        }
+    }
+
+    /// Declares the names in `pattern` and records the slots they get.
+    fn declare_pattern(&mut self, pattern: &mut Pattern, assigned: bool) {
+        match pattern {
+            Pattern::Name(name, slot) => *slot = self.declare(name, false, assigned),
+            Pattern::Tuple(_open, patterns, _close) => {
+                for pattern in patterns {
+                    self.declare_pattern(pattern, assigned);
+                }
+            }
+        }
+    }
+
+    /// Declares `name` in the innermost scope and returns its slot, or
+    /// `None` at the top level. Redeclaring a local reuses its slot.
+    fn declare(&mut self, name: &Token, constant: bool, assigned: bool) -> Option<usize> {
+        if self.scopes.len() == self.top + 1 {
+            self.bind(name, constant, assigned, None);
+            return None;
+        }
+
+        let outer = &self.scopes[..self.scopes.len() - 1];
+        if let Some(shadowed) = outer
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(&name.lexeme))
+        {
+            let shadowed = shadowed.name.clone();
+            self.warn(
+                name,
+                name.span,
+                W_SHADOWED_VARIABLE,
+                format!(
+                    "Variable '{}' shadows the one declared at line {}.",
+                    name.lexeme, shadowed.line
+                ),
+                Some(&shadowed),
+            );
+        }
+
+        let scope = self.scopes.last_mut().unwrap();
+        let slot = match scope.bindings.get(&name.lexeme).and_then(|b| b.slot) {
+            Some(slot) => slot,
+            None => {
+                scope.slots += 1;
+                scope.slots - 1
+            }
+        };
+
+        self.bind(name, constant, assigned, Some(slot));
+        Some(slot)
+    }
+
+    fn bind(&mut self, name: &Token, constant: bool, assigned: bool, slot: Option<usize>) {
+        let scope = self.scopes.last_mut().unwrap();
+
+        if scope.bindings.get(&name.lexeme).is_some_and(|b| b.constant) {
+            self.error(
+                name,
+                format!("Cannot redeclare constant '{}'.", name.lexeme),
+            );
+            return;
+        }
+
+        let binding = Binding {
+            name: name.clone(),
+            constant,
+            read: false,
+            assigned,
+            slot,
+        };
+        let Some(previous) = scope.bindings.insert(name.lexeme.clone(), binding) else {
+            return;
+        };
+
+        self.warn(
+            name,
+            name.span,
+            W_REDECLARED_VARIABLE,
+            format!(
+                "Variable '{}' is already declared in this scope at line {}.",
+                name.lexeme, previous.name.line
+            ),
+            Some(&previous.name),
+        );
+        if !previous.read && self.scopes.len() > self.top + 1 {
+            self.warn_unread(&previous);
+        }
+    }
+
+    /// The innermost declaration of `name`, if it was declared in code the
+    /// resolver has seen.
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.get_mut(name))
+    }
+
+    /// Where `name` lives at runtime, if it is a local with a slot.
+    fn resolve_local(&self, name: &str) -> Option<Local> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| scope.bindings.get(name).map(|b| (depth, b)))
+            .and_then(|(depth, binding)| binding.slot.map(|slot| Local { depth, slot }))
+    }
+
+    /// Reports a warning, or an error in strict mode. `earlier` is a
+    /// declaration the warning is about, shown as a related location.
+    fn warn(
+        &mut self,
+        token: &Token,
+        span: Span,
+        code: &'static str,
+        message: String,
+        earlier: Option<&Token>,
+    ) {
+        if self.strict {
+            self.error(token, message);
+            return;
+        }
+
+        let message = format!("Warning at line {}: {}", token.line, message);
+        let mut diagnostic = Diagnostic::warning(code, message)
+            .at(token.line, token.column, span)
+            .in_file(self.file.clone());
+        if let Some(earlier) = earlier {
+            diagnostic = diagnostic.related(
+                format!("'{}' is declared here.", earlier.lexeme),
+                earlier.line,
+                earlier.column,
+                earlier.span,
+            );
+        }
+        self.logger.report(&diagnostic);
+    }
+
+    fn error(&mut self, token: &Token, message: String) {
+        let message = format!("Resolve error at line {}: {}", token.line, message);
+        let err = ParseError::Resolve(message, token.clone());
+        self.logger.report(&err.to_diagnostic(self.file.clone()));
+        self.errors.push(err);
+    }
+}
+
+impl ExprVisitorMut<()> for Resolver<'_> {
+    fn visit_unary_expr(&mut self, arena: &mut Arena, _op: &mut Token, right: ExprId) {
+        self.resolve_expr_id(arena, right);
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        _op: &mut Token,
+        right: ExprId,
+    ) {
+        self.resolve_expr_id(arena, left);
+        self.resolve_expr_id(arena, right);
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        _op: &mut Token,
+        right: ExprId,
+    ) {
+        self.resolve_expr_id(arena, left);
+        self.resolve_expr_id(arena, right);
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &mut Arena, inner: ExprId) {
+        self.resolve_expr_id(arena, inner);
+    }
+
+    fn visit_literal_expr(
+        &mut self,
+        _arena: &mut Arena,
+        _token: &mut Token,
+        _literal: &mut Literal,
+    ) {
+    }
+
+    fn visit_var_expr(&mut self, _arena: &mut Arena, name: &mut Token, local: &mut Option<Local>) {
+        self.mark_read(&name.lexeme);
+        self.check_assigned(name);
+        *local = self.resolve_local(&name.lexeme);
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        value: ExprId,
+        local: &mut Option<Local>,
+    ) {
+        self.resolve_expr_id(arena, value);
+        match self.lookup(&name.lexeme) {
+            Some(binding) if binding.constant => self.error(
+                name,
+                format!("Cannot assign to constant '{}'.", name.lexeme),
+            ),
+            None if self.strict && !self.imports_names && !self.globals.contains(&name.lexeme) => {
+                self.error(
+                    name,
+                    format!("Cannot assign to undeclared variable '{}'.", name.lexeme),
+                )
+            }
+            _ => {}
+        }
+        if let Some(binding) = self.lookup_mut(&name.lexeme) {
+            binding.assigned = true;
+        }
+        *local = self.resolve_local(&name.lexeme);
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        arena: &mut Arena,
+        callee: ExprId,
+        _paren: &mut Token,
+        args: &mut [Expr],
+    ) {
+        self.resolve_expr_id(arena, callee);
+        for arg in args {
+            self.resolve_expr(arena, arg);
+        }
+    }
+
+    fn visit_get_expr(&mut self, arena: &mut Arena, object: ExprId, _name: &mut Token) {
+        self.resolve_expr_id(arena, object);
+    }
+
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &mut Arena,
+        _open: &mut Token,
+        items: &mut [Expr],
+        _close: &mut Token,
+    ) {
+        for item in items {
+            self.resolve_expr(arena, item);
+        }
+    }
+
+    fn visit_spread_expr(&mut self, arena: &mut Arena, _op: &mut Token, expr: ExprId) {
+        self.resolve_expr_id(arena, expr);
+    }
+}
+
+impl StmtVisitorMut<()> for Resolver<'_> {
+    fn visit_print_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, values: &mut [Expr]) {
+        for expr in values {
+            self.resolve_expr(arena, expr);
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &mut Arena, expr: &mut Expr) {
+        self.resolve_expr(arena, expr);
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &mut Arena,
+        pattern: &mut Pattern,
+        _annotation: Option<&mut Token>,
+        initializer: Option<&mut Expr>,
+    ) {
+        let assigned = initializer.is_some();
+        if let Some(initializer) = initializer {
+            self.resolve_expr(arena, initializer);
+        }
+        self.declare_pattern(pattern, assigned);
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        slot: &mut Option<usize>,
+        _annotation: Option<&mut Token>,
+        initializer: &mut Expr,
+    ) {
+        self.resolve_expr(arena, initializer);
+        *slot = self.declare(name, true, true);
+    }
+
+    fn visit_block_stmt(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        self.resolve_block(arena, stmts);
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        condition: &mut Expr,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) {
+        self.check_condition(arena, condition, false);
+        self.resolve_expr(arena, condition);
+        self.resolve_stmt_id(arena, then_branch);
+        if let Some(else_branch) = else_branch {
+            self.resolve_stmt_id(arena, else_branch);
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        label: Option<&mut Token>,
+        condition: &mut Expr,
+        body: StmtId,
+        increment: Option<&mut Expr>,
+    ) {
+        self.check_condition(arena, condition, true);
+        self.resolve_expr(arena, condition);
+        self.resolve_loop_body(arena, label.as_deref(), body);
+        if let Some(increment) = increment {
+            self.resolve_expr(arena, increment);
+        }
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        label: Option<&mut Token>,
+        pattern: &mut Pattern,
+        iterable: &mut Expr,
+        body: StmtId,
+    ) {
+        self.resolve_expr(arena, iterable);
+        self.begin_scope();
+        self.declare_pattern(pattern, true);
+        self.resolve_loop_body(arena, label.as_deref(), body);
+        self.end_scope();
+    }
+
+    fn visit_break_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        self.check_jump(keyword, label.as_deref());
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        self.check_jump(keyword, label.as_deref());
+    }
+
+    fn visit_throw_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, value: &mut Expr) {
+        self.resolve_expr(arena, value);
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, stmt: StmtId) {
+        self.resolve_stmt_id(arena, stmt);
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        try_block: &mut [Stmt],
+        catch_clause: Option<(&mut Token, &mut [Stmt])>,
+        finally_block: Option<&mut [Stmt]>,
+    ) {
+        self.resolve_block(arena, try_block);
+        if let Some((name, catch_block)) = catch_clause {
+            // The caught value always takes the first slot of its scope.
+            self.begin_scope();
+            self.declare(name, false, true);
+            self.mark_read(&name.lexeme);
+            self.resolve_block(arena, catch_block);
+            self.end_scope();
+        }
+        if let Some(finally_block) = finally_block {
+            self.resolve_block(arena, finally_block);
+        }
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        _keyword: &mut Token,
+        _path: &str,
+        alias: Option<&mut Token>,
+    ) {
+        match alias {
+            Some(alias) => self.bind(alias, false, true, None),
+            None => self.imports_names = true,
+        }
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) {
+        if self.scopes.len() > self.top + 1 {
+            self.error(
+                keyword,
+                "'pub' is only allowed at the top level of a file.".to_string(),
+            );
+        }
+        self.resolve_stmt_id(arena, stmt);
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        subject: &mut Expr,
+        arms: &mut [(Vec<Expr>, Stmt)],
+        default: Option<StmtId>,
+    ) {
+        self.resolve_expr(arena, subject);
+        for (patterns, body) in arms {
+            for pattern in patterns {
+                self.resolve_expr(arena, pattern);
+            }
+            self.resolve_stmt(arena, body);
+        }
+        if let Some(default) = default {
+            self.resolve_stmt_id(arena, default);
+        }
+    }
+}
+
+/// Resolves a parsed program. In strict mode it is type checked as well,
+/// which catches comparisons between values of different types.
+pub(crate) fn analyze(
+    program: &mut Program,
+    file: Option<String>,
+    logger: &Log,
+    strict: bool,
+    globals: Vec<String>,
+) -> Result<(), Vec<ParseError>> {
+    let mut resolver = Resolver::new(file.clone(), logger)
+        .strict(strict)
+        .with_globals(globals);
+    resolver.resolve(program)?;
+
+    if resolver.is_strict() {
+        TypeChecker::new(file, logger).strict(true).check(program)?;
+    }
+    Ok(())
+}
+
+/// Whether evaluating `expr` may do more than produce a value: assign,
+/// call a function, or spread into a call.
+fn has_effects(arena: &Arena, expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_, _) | Expr::Var(_, _) => false,
+        Expr::Grouping(expr) | Expr::Unary(_, expr) | Expr::Get(expr, _) => {
+            has_effects(arena, &arena[*expr])
+        }
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+            has_effects(arena, &arena[*left]) || has_effects(arena, &arena[*right])
+        }
+        Expr::Tuple(_, items, _) => items.iter().any(|item| has_effects(arena, item)),
+        Expr::Assign(_, _, _) | Expr::Call(_, _, _) | Expr::Spread(_, _) => true,
+    }
+}
+
+/// Whether `stmts` opt into strict mode by starting with `"use strict";`.
+pub(crate) fn strict_pragma(stmts: &[Stmt]) -> bool {
+    matches!(
+        stmts.first(),
+        Some(Stmt::Expression(Expr::Literal(_, Literal::String(s)))) if &**s == "use strict"
+    )
+}
+
+/// The keyword that always leaves `stmt` early, if running it always ends in
+/// a thrown exception, a `break` or a `continue`.
+fn jumps_away(arena: &Arena, stmt: &Stmt) -> Option<&'static str> {
+    match stmt {
+        Stmt::Throw(_, _) => Some("throw"),
+        Stmt::Break(_, _) => Some("break"),
+        Stmt::Continue(_, _) => Some("continue"),
+        Stmt::Block(stmts) => stmts.iter().find_map(|stmt| jumps_away(arena, stmt)),
+        Stmt::If(_, _, then_branch, Some(else_branch)) => {
+            jumps_away(arena, &arena[*else_branch]).and(jumps_away(arena, &arena[*then_branch]))
+        }
+        _ => None,
+    }
+}
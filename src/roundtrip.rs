@@ -0,0 +1,571 @@
+//! Checking that printing a program and parsing the result gives the same
+//! program back, over random programs built from fuzzer input.
+//!
+//! The generator only builds trees the parser could have produced: an
+//! operand that binds more loosely than its operator is wrapped in an
+//! `Expr::Grouping`, and an `if` without an `else` never comes right before
+//! an `else` it would take. Any difference the check finds is therefore a
+//! place where the printer and the grammar disagree.
+
+use crate::{
+    arena::{same_stmt, Arena, ExprId, Program, StmtId},
+    error::{Log, LogLevel},
+    expression::Expr,
+    parser::Parser,
+    printer::{ast_to_source, stmts_to_source},
+    scanner::Scanner,
+    statement::{Pattern, Stmt},
+    token::{Literal, Span, Token, TokenType},
+};
+
+/// How deeply generated statements and expressions nest, well inside the
+/// parser's own limit.
+const MAX_DEPTH: usize = 6;
+
+const NAMES: &[&str] = &["a", "b", "count", "item", "total", "x"];
+const LABELS: &[&str] = &["inner", "outer"];
+const TYPES: &[&str] = &["bool", "int", "list", "number", "string"];
+const STRINGS: &[&str] = &["", "hello", "two words", "say \"hi\" twice", "line\nbreak"];
+const MODULES: &[&str] = &["lib.rox", "util/strings.rox"];
+
+// Precedence levels, loosest first, named after the parser method that
+// handles each one.
+const ASSIGNMENT: usize = 0;
+const OR: usize = 1;
+const COALESCE: usize = 2;
+const AND: usize = 3;
+const EQUALITY: usize = 4;
+const COMPARISON: usize = 5;
+const RANGE: usize = 6;
+const BIT_OR: usize = 7;
+const BIT_XOR: usize = 8;
+const BIT_AND: usize = 9;
+const SHIFT: usize = 10;
+const TERM: usize = 11;
+const FACTOR: usize = 12;
+const UNARY: usize = 13;
+const CALL: usize = 14;
+const PRIMARY: usize = 15;
+
+/// Builds a random program, making every choice from the next byte of
+/// `data`. Once the bytes run out every choice is the first one, which
+/// always ends the program, so any input gives a finite tree.
+pub fn random_ast(data: &[u8]) -> Program {
+    let mut generator = Generator {
+        data,
+        position: 0,
+        depth: 0,
+        arena: Arena::new(),
+    };
+    let stmts = generator.declarations(true);
+    Program {
+        stmts,
+        arena: generator.arena,
+    }
+}
+
+/// Prints `program`, parses the result and compares the two trees, ignoring
+/// where tokens are. On a mismatch the error shows the printed source and
+/// the first statement that changed.
+pub fn check_round_trip(program: &Program) -> Result<(), String> {
+    let source = ast_to_source(program);
+
+    let mut scanner = Scanner::quiet(source.clone());
+    scanner.scan_tokens();
+    if scanner.had_error {
+        let messages: Vec<&str> = scanner.errors.iter().map(|e| e.message.as_str()).collect();
+        return Err(format!(
+            "The printed program does not scan: {}\n{}",
+            messages.join("; "),
+            source
+        ));
+    }
+
+    let logger = Log::new(LogLevel::Off);
+    let reparsed = Parser::new(scanner.tokens, None, &logger)
+        .parse()
+        .map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            format!(
+                "The printed program does not parse: {}\n{}",
+                messages.join("; "),
+                source
+            )
+        })?;
+
+    if reparsed == *program {
+        return Ok(());
+    }
+
+    let (stmts, arena) = (&program.stmts, &program.arena);
+    let index = stmts
+        .iter()
+        .zip(reparsed.stmts.iter())
+        .position(|(before, after)| !same_stmt(arena, before, &reparsed.arena, after))
+        .unwrap_or(stmts.len().min(reparsed.stmts.len()));
+    let printed = |program: &Program| match program.stmts.get(index) {
+        Some(stmt) => stmts_to_source(&program.arena, std::slice::from_ref(stmt)),
+        None => "(nothing)\n".to_string(),
+    };
+    Err(format!(
+        "Statement {} reads back differently.\nPrinted:\n{}Read back:\n{}In:\n{}",
+        index + 1,
+        printed(program),
+        printed(&reparsed),
+        source
+    ))
+}
+
+struct Generator<'a> {
+    data: &'a [u8],
+    position: usize,
+    depth: usize,
+    arena: Arena,
+}
+
+impl Generator<'_> {
+    /// A number below `options`, or 0 once the input runs out.
+    fn choose(&mut self, options: usize) -> usize {
+        let byte = self.data.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte as usize % options
+    }
+
+    fn pick<'s>(&mut self, options: &[&'s str]) -> &'s str {
+        options[self.choose(options.len())]
+    }
+
+    fn deep(&self) -> bool {
+        self.depth >= MAX_DEPTH
+    }
+
+    fn deeper<T>(&mut self, build: impl FnOnce(&mut Self) -> T) -> T {
+        self.depth += 1;
+        let built = build(self);
+        self.depth -= 1;
+        built
+    }
+
+    fn add_stmt(&mut self, stmt: Stmt) -> StmtId {
+        self.arena.add_stmt(stmt)
+    }
+
+    fn add_expr(&mut self, expr: Expr) -> ExprId {
+        self.arena.add_expr(expr)
+    }
+
+    fn name(&mut self) -> Token {
+        let name = self.pick(NAMES);
+        token(TokenType::IDENTIFIER, name)
+    }
+
+    fn label(&mut self) -> Option<Token> {
+        match self.choose(3) {
+            0 => None,
+            _ => Some(token(TokenType::IDENTIFIER, self.pick(LABELS))),
+        }
+    }
+
+    fn declarations(&mut self, top_level: bool) -> Vec<Stmt> {
+        let count = match self.deep() {
+            true => 0,
+            false => self.choose(if top_level { 8 } else { 4 }),
+        };
+        (0..count).map(|_| self.declaration(top_level)).collect()
+    }
+
+    fn declaration(&mut self, top_level: bool) -> Stmt {
+        match self.choose(5) {
+            1 => self.var_decl(),
+            2 => self.const_decl(),
+            3 if top_level => {
+                let path = self.pick(MODULES).to_string();
+                let alias = match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.name()),
+                };
+                Stmt::Import(token(TokenType::IMPORT, "import"), path, alias)
+            }
+            4 if top_level => {
+                let decl = match self.choose(2) {
+                    0 => self.var_decl(),
+                    _ => self.const_decl(),
+                };
+                Stmt::Pub(token(TokenType::PUB, "pub"), self.add_stmt(decl))
+            }
+            _ => self.statement(false),
+        }
+    }
+
+    fn var_decl(&mut self) -> Stmt {
+        let pattern = self.pattern();
+        match pattern {
+            Pattern::Name(..) => {
+                let annotation = self.annotation();
+                let initializer = match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.expr(ASSIGNMENT)),
+                };
+                Stmt::Var(pattern, annotation, initializer)
+            }
+            Pattern::Tuple(..) => Stmt::Var(pattern, None, Some(self.expr(ASSIGNMENT))),
+        }
+    }
+
+    fn const_decl(&mut self) -> Stmt {
+        let name = self.name();
+        let annotation = self.annotation();
+        Stmt::Const(name, None, annotation, self.expr(ASSIGNMENT))
+    }
+
+    fn annotation(&mut self) -> Option<Token> {
+        match self.choose(3) {
+            0 => None,
+            _ => Some(token(TokenType::IDENTIFIER, self.pick(TYPES))),
+        }
+    }
+
+    fn pattern(&mut self) -> Pattern {
+        if self.deep() || self.choose(4) != 3 {
+            return Pattern::Name(self.name(), None);
+        }
+
+        let count = self.choose(4);
+        let patterns = self.deeper(|g| (0..count).map(|_| g.pattern()).collect());
+        Pattern::Tuple(
+            token(TokenType::LEFT_PAREN, "("),
+            patterns,
+            token(TokenType::RIGHT_PAREN, ")"),
+        )
+    }
+
+    /// A statement that is not a declaration. A `closed` statement must not
+    /// end in an `if` without an `else`, because an `else` comes next.
+    fn statement(&mut self, closed: bool) -> Stmt {
+        if self.deep() {
+            return Stmt::Expression(self.expr(ASSIGNMENT));
+        }
+        self.deeper(|g| g.statement_kind(closed))
+    }
+
+    fn statement_kind(&mut self, closed: bool) -> Stmt {
+        match self.choose(13) {
+            1 => Stmt::Expression(self.expr(ASSIGNMENT)),
+            2 => Stmt::Block(self.declarations(false)),
+            3 => {
+                let condition = self.expr(ASSIGNMENT);
+                if closed || self.choose(2) == 1 {
+                    let then_branch = self.statement(true);
+                    let else_branch = self.statement(closed);
+                    Stmt::If(
+                        token(TokenType::IF, "if"),
+                        condition,
+                        self.add_stmt(then_branch),
+                        Some(self.add_stmt(else_branch)),
+                    )
+                } else {
+                    let then_branch = self.statement(false);
+                    Stmt::If(
+                        token(TokenType::IF, "if"),
+                        condition,
+                        self.add_stmt(then_branch),
+                        None,
+                    )
+                }
+            }
+            4 => {
+                let label = self.label();
+                let condition = self.expr(ASSIGNMENT);
+                let body = self.statement(closed);
+                Stmt::While(
+                    token(TokenType::WHILE, "while"),
+                    label,
+                    condition,
+                    self.add_stmt(body),
+                    None,
+                )
+            }
+            5 => {
+                let keyword = token(TokenType::FOR, "for");
+                let label = self.label();
+                let condition = match self.choose(2) {
+                    0 => Expr::Literal(keyword.clone(), Literal::Bool(true)),
+                    _ => self.expr(ASSIGNMENT),
+                };
+                let increment = match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.expr(ASSIGNMENT)),
+                };
+                let body = self.statement(closed);
+                Stmt::While(keyword, label, condition, self.add_stmt(body), increment)
+            }
+            6 => {
+                let label = self.label();
+                let pattern = self.pattern();
+                let iterable = self.expr(ASSIGNMENT);
+                let body = self.statement(closed);
+                Stmt::ForEach(
+                    token(TokenType::FOR, "for"),
+                    label,
+                    pattern,
+                    iterable,
+                    self.add_stmt(body),
+                )
+            }
+            7 => Stmt::Break(token(TokenType::BREAK, "break"), self.label()),
+            8 => Stmt::Continue(token(TokenType::CONTINUE, "continue"), self.label()),
+            9 => Stmt::Throw(token(TokenType::THROW, "throw"), self.expr(ASSIGNMENT)),
+            10 => {
+                let stmt = self.statement(closed);
+                Stmt::Defer(token(TokenType::DEFER, "defer"), self.add_stmt(stmt))
+            }
+            11 => {
+                let try_block = self.declarations(false);
+                let (catch, finally) = match self.choose(3) {
+                    0 => (true, false),
+                    1 => (false, true),
+                    _ => (true, true),
+                };
+                let catch_clause = match catch {
+                    true => Some((self.name(), self.declarations(false))),
+                    false => None,
+                };
+                let finally_block = match finally {
+                    true => Some(self.declarations(false)),
+                    false => None,
+                };
+                Stmt::Try(
+                    token(TokenType::TRY, "try"),
+                    try_block,
+                    catch_clause,
+                    finally_block,
+                )
+            }
+            12 => {
+                let subject = self.expr(ASSIGNMENT);
+                let has_default = self.choose(2) == 1;
+                let arms = (0..self.choose(4))
+                    .map(|_| {
+                        let patterns = (0..=self.choose(2))
+                            .map(|_| self.expr(ASSIGNMENT))
+                            .collect();
+                        (patterns, self.statement(has_default))
+                    })
+                    .collect();
+                let default = match has_default {
+                    true => {
+                        let stmt = self.statement(false);
+                        Some(self.add_stmt(stmt))
+                    }
+                    false => None,
+                };
+                Stmt::Match(token(TokenType::MATCH, "match"), subject, arms, default)
+            }
+            _ => {
+                let values = (0..self.choose(3)).map(|_| self.expr(ASSIGNMENT)).collect();
+                Stmt::Print(token(TokenType::PRINT, "print"), values)
+            }
+        }
+    }
+
+    /// An expression that binds at least as tightly as `level`.
+    fn expr(&mut self, level: usize) -> Expr {
+        if self.deep() {
+            return self.literal();
+        }
+
+        // Counting down from the tightest level makes a missing byte pick
+        // a plain literal.
+        let form = PRIMARY - self.choose(PRIMARY - level + 1);
+        self.deeper(|g| g.expr_at(form))
+    }
+
+    fn expr_at(&mut self, level: usize) -> Expr {
+        let binary = |g: &mut Self, operators: &[(TokenType, &str)]| {
+            let left = g.expr(level);
+            let (token_type, lexeme) = operators[g.choose(operators.len())];
+            let right = g.expr(level + 1);
+            Expr::Binary(
+                g.add_expr(left),
+                token(token_type, lexeme),
+                g.add_expr(right),
+            )
+        };
+        let logical = |g: &mut Self, token_type: TokenType, lexeme: &str| {
+            let left = g.expr(level);
+            let right = g.expr(level + 1);
+            Expr::Logical(
+                g.add_expr(left),
+                token(token_type, lexeme),
+                g.add_expr(right),
+            )
+        };
+
+        match level {
+            ASSIGNMENT => {
+                let name = self.name();
+                let value = self.expr(ASSIGNMENT);
+                Expr::Assign(name, self.add_expr(value), None)
+            }
+            OR => logical(self, TokenType::OR, "or"),
+            COALESCE => logical(self, TokenType::QUESTION_QUESTION, "??"),
+            AND => logical(self, TokenType::AND, "and"),
+            EQUALITY => binary(
+                self,
+                &[
+                    (TokenType::BANG_EQUAL, "!="),
+                    (TokenType::EQUAL_EQUAL, "=="),
+                ],
+            ),
+            COMPARISON => binary(
+                self,
+                &[
+                    (TokenType::GREATER, ">"),
+                    (TokenType::GREATER_EQUAL, ">="),
+                    (TokenType::LESS, "<"),
+                    (TokenType::LESS_EQUAL, "<="),
+                    (TokenType::IS, "is"),
+                ],
+            ),
+            // Ranges do not chain, so both sides bind more tightly.
+            RANGE => {
+                let start = self.expr(BIT_OR);
+                let op = match self.choose(2) {
+                    0 => token(TokenType::DOT_DOT, ".."),
+                    _ => token(TokenType::DOT_DOT_EQUAL, "..="),
+                };
+                let end = self.expr(BIT_OR);
+                Expr::Binary(self.add_expr(start), op, self.add_expr(end))
+            }
+            BIT_OR => binary(self, &[(TokenType::PIPE, "|")]),
+            BIT_XOR => binary(self, &[(TokenType::CARET, "^")]),
+            BIT_AND => binary(self, &[(TokenType::AMPERSAND, "&")]),
+            SHIFT => binary(
+                self,
+                &[
+                    (TokenType::LESS_LESS, "<<"),
+                    (TokenType::GREATER_GREATER, ">>"),
+                ],
+            ),
+            TERM => binary(self, &[(TokenType::MINUS, "-"), (TokenType::PLUS, "+")]),
+            FACTOR => binary(self, &[(TokenType::STAR, "*"), (TokenType::SLASH, "/")]),
+            UNARY => {
+                let op = match self.choose(3) {
+                    0 => token(TokenType::BANG, "!"),
+                    1 => token(TokenType::MINUS, "-"),
+                    _ => token(TokenType::TILDE, "~"),
+                };
+                let right = self.expr(UNARY);
+                Expr::Unary(op, self.add_expr(right))
+            }
+            CALL => {
+                let callee = self.expr(CALL);
+                if self.choose(2) == 0 {
+                    let object = self.add_expr(callee);
+                    return Expr::Get(object, self.name());
+                }
+
+                let args = (0..self.choose(4))
+                    .map(|_| match self.choose(4) {
+                        3 => {
+                            let expr = self.expr(ASSIGNMENT);
+                            Expr::Spread(token(TokenType::DOT_DOT_DOT, "..."), self.add_expr(expr))
+                        }
+                        _ => self.expr(ASSIGNMENT),
+                    })
+                    .collect();
+                Expr::Call(
+                    self.add_expr(callee),
+                    token(TokenType::RIGHT_PAREN, ")"),
+                    args,
+                )
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Expr {
+        match self.choose(4) {
+            1 => Expr::Var(self.name(), None),
+            2 => {
+                let inner = self.expr(ASSIGNMENT);
+                Expr::Grouping(self.add_expr(inner))
+            }
+            3 => {
+                let items = (0..self.choose(4)).map(|_| self.expr(ASSIGNMENT)).collect();
+                Expr::Tuple(
+                    token(TokenType::LEFT_PAREN, "("),
+                    items,
+                    token(TokenType::RIGHT_PAREN, ")"),
+                )
+            }
+            _ => self.literal(),
+        }
+    }
+
+    fn literal(&mut self) -> Expr {
+        let (token_type, lexeme, literal) = match self.choose(6) {
+            1 => {
+                let n = self.choose(256) as f64 / 8.0;
+                (TokenType::NUMBER, "", Literal::Number(n))
+            }
+            2 => {
+                let s = self.pick(STRINGS);
+                (TokenType::STRING, "", Literal::String(s.into()))
+            }
+            3 => (TokenType::TRUE, "true", Literal::Bool(true)),
+            4 => (TokenType::FALSE, "false", Literal::Bool(false)),
+            5 => (TokenType::NIL, "nil", Literal::Nil),
+            _ => {
+                let n = self.choose(256) as i64;
+                (TokenType::NUMBER, "", Literal::Int(n))
+            }
+        };
+
+        // Keyword literals carry no value on their token, only on the node.
+        let value = match token_type {
+            TokenType::NUMBER | TokenType::STRING => literal.clone(),
+            _ => Literal::Nil,
+        };
+        let token = Token::new(token_type, lexeme.to_string(), 1, 1, Span::default(), value);
+        Expr::Literal(token, literal)
+    }
+}
+
+fn token(token_type: TokenType, lexeme: &str) -> Token {
+    Token::new(
+        token_type,
+        lexeme.to_string(),
+        1,
+        1,
+        Span::default(),
+        Literal::Nil,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_round_trip, random_ast};
+
+    /// How many programs the test builds, each from its own bytes.
+    const CASES: usize = 1000;
+
+    /// Random programs from a fixed seed, so a failure can be reproduced.
+    #[test]
+    fn printed_programs_parse_back() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next_byte = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        };
+
+        for case in 0..CASES {
+            let data: Vec<u8> = (0..256).map(|_| next_byte()).collect();
+            if let Err(message) = check_round_trip(&random_ast(&data)) {
+                panic!("case {}: {}", case, message);
+            }
+        }
+    }
+}
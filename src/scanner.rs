@@ -1,7 +1,11 @@
 use crate::{
-    error::Log,
-    keywords::get_keywords,
-    token::{self, Literal, Token, TokenType},
+    diagnostic::{
+        Diagnostic, E_INTEGER_TOO_LARGE, E_INVALID_NUMBER, E_UNEXPECTED_CHARACTER,
+        E_UNTERMINATED_COMMENT, E_UNTERMINATED_STRING,
+    },
+    error::{Log, LogLevel},
+    keywords::keyword,
+    token::{Literal, Span, Token, TokenType},
 };
 
 pub struct Scanner {
@@ -10,146 +14,301 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: i32,
-
+    line_start: usize,
+    /// A byte offset on the current line and its column, so columns are
+    /// counted in characters without rescanning the line for every token.
+    column_mark: (usize, i32),
+    start_line: i32,
+    start_column: i32,
+    pub had_error: bool,
+    /// Every lexical error found so far, reported or not. Scanning carries
+    /// on past them so that one run finds them all.
+    pub errors: Vec<Diagnostic>,
+    /// Set when the source ends inside a string or block comment.
+    unterminated: bool,
+    quiet: bool,
+
+    file: Option<String>,
     logger: Log,
 }
 
+/// Whether `source` stops partway through a string, a block comment or a
+/// bracketed construct, so that a REPL should read another line before
+/// running it.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::quiet(source.to_string());
+    scanner.scan_tokens();
+
+    if scanner.unterminated {
+        return true;
+    }
+
+    let mut depth = 0;
+    for token in &scanner.tokens {
+        match token.token_type {
+            TokenType::LEFT_PAREN | TokenType::LEFT_BRACE => depth += 1,
+            TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACE => depth -= 1,
+            _ => {}
+        }
+        // A stray closing bracket can never be completed; let it be reported.
+        if depth < 0 {
+            return false;
+        }
+    }
+
+    depth > 0
+}
+
 impl Scanner {
-    pub fn new(source: String, logger: Log) -> Scanner {
+    pub fn new(source: String, file: Option<String>, logger: Log) -> Scanner {
         Scanner {
             source,
             tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            column_mark: (0, 1),
+            start_line: 1,
+            start_column: 1,
+            had_error: false,
+            errors: Vec::new(),
+            unterminated: false,
+            quiet: false,
+            file,
             logger,
         }
     }
 
+    /// A scanner that never reports errors, for tools that only want
+    /// whatever tokens can be found.
+    pub fn quiet(source: String) -> Scanner {
+        Scanner {
+            quiet: true,
+            ..Scanner::new(source, None, Log::new(LogLevel::Error))
+        }
+    }
+
     pub fn scan_tokens(&mut self) {
-        while !self.is_at_end() {
+        self.scan_until(self.source.len());
+
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column_at(self.start);
+        let eof = self.make_token(TokenType::EOF, "".to_string(), Literal::Nil);
+        self.tokens.push(eof);
+    }
+
+    /// Scans `source[start..end]` only, as if scanning had reached `start`
+    /// on line `line`. The EOF token is only added when `end` is the end
+    /// of the source. A token that crosses `end` is scanned to its own end,
+    /// so `current` then lies past `end`.
+    pub(crate) fn scan_range(&mut self, start: usize, end: usize, line: i32) {
+        self.current = start;
+        self.line = line;
+        self.line_start = self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+
+        if end == self.source.len() {
+            self.scan_tokens();
+        } else {
+            self.scan_until(end);
+        }
+    }
+
+    /// Whether the last scan ended exactly at `end`, without errors and
+    /// outside any string or comment.
+    pub(crate) fn ended_cleanly_at(&self, end: usize) -> bool {
+        !self.had_error && !self.unterminated && self.current == end
+    }
+
+    fn scan_until(&mut self, end: usize) {
+        // A leading `#!` line lets scripts be run directly from the shell.
+        if self.current == 0 && self.source.starts_with("#!") {
+            while self.peek() != '\n' && !self.is_at_end() {
+                self.advance();
+            }
+            let lexeme = self.source[..self.current].to_string();
+            let shebang = self.make_token(TokenType::COMMENT, lexeme, Literal::Nil);
+            self.tokens.push(shebang);
+        }
+
+        while self.current < end && !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column_at(self.start);
             self.scan();
         }
+    }
 
-        self.tokens.push(Token::new(
-            TokenType::EOF,
-            "".to_string(),
-            self.line,
-            Literal::Nil,
-        ));
+    /// The column of byte `offset` on the current line, counting characters
+    /// rather than bytes.
+    fn column_at(&mut self, offset: usize) -> i32 {
+        let (mut from, mut column) = self.column_mark;
+        if from < self.line_start || from > offset {
+            (from, column) = (self.line_start, 1);
+        }
+        column += self.source[from..offset].chars().count() as i32;
+        self.column_mark = (offset, column);
+        column
+    }
+
+    /// Builds a token positioned at the lexeme currently being scanned.
+    fn make_token(&self, token_type: TokenType, lexeme: String, literal: Literal) -> Token {
+        Token::new(
+            token_type,
+            lexeme,
+            self.start_line,
+            self.start_column,
+            Span {
+                start: self.start,
+                end: self.current,
+            },
+            literal,
+        )
     }
 
     fn scan(&mut self) {
         let c = self.advance();
 
-        let token_type = self.get_token_type(c.clone());
+        let token_type = self.get_token_type(c);
 
-        match token_type {
-            None => return,
-            Some(token_type) => {
-                let s = self.start as usize;
-                let e = self.current as usize;
+        if let Some(token_type) = token_type {
+            let s = self.start;
+            let e = self.current;
 
-                let lexeme = &self.source[s..e].to_string();
+            let lexeme = &self.source[s..e].to_string();
 
-                let new_token = Token::new(token_type, lexeme.to_owned(), self.line, Literal::Nil);
+            let new_token = self.make_token(token_type, lexeme.to_owned(), Literal::Nil);
 
-                self.tokens.push(new_token);
-            }
+            self.tokens.push(new_token);
         }
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current as usize >= self.source.len();
+        self.current >= self.source.len()
     }
 
-    fn advance(&mut self) -> String {
-        let c = self
-            .source
-            .chars()
-            .nth(self.current as usize)
-            .unwrap()
-            .to_string();
-
-        self.current += 1;
-        return c;
+    /// The unscanned part of the source. `current` is a byte offset that
+    /// always sits on a character boundary.
+    fn rest(&self) -> &str {
+        &self.source[self.current..]
     }
 
-    fn peek(&self) -> String {
-        if self.is_at_end() {
-            return "\0".to_string();
-        }
+    fn advance(&mut self) -> char {
+        let c = self.rest().chars().next().unwrap();
+
+        self.current += c.len_utf8();
+        c
+    }
 
-        self.source
-            .chars()
-            .nth(self.current as usize)
-            .unwrap()
-            .to_string()
+    fn peek(&self) -> char {
+        self.rest().chars().next().unwrap_or('\0')
     }
 
-    fn get_token_type(&mut self, character: String) -> Option<TokenType> {
-        match character.as_str() {
-            "(" => Some(TokenType::LEFT_PAREN),
-            ")" => Some(TokenType::RIGHT_PAREN),
-            "{" => Some(TokenType::LEFT_BRACE),
-            "}" => Some(TokenType::RIGHT_BRACE),
-            "," => Some(TokenType::COMMA),
-            "." => Some(TokenType::DOT),
-            "-" => Some(TokenType::MINUS),
-            "+" => Some(TokenType::PLUS),
-            ";" => Some(TokenType::SEMICOLON),
-            "*" => Some(TokenType::STAR),
-            "!" => {
-                if self.match_char("=".to_string()) {
+    fn get_token_type(&mut self, character: char) -> Option<TokenType> {
+        match character {
+            '(' => Some(TokenType::LEFT_PAREN),
+            ')' => Some(TokenType::RIGHT_PAREN),
+            '{' => Some(TokenType::LEFT_BRACE),
+            '}' => Some(TokenType::RIGHT_BRACE),
+            ',' => Some(TokenType::COMMA),
+            '.' => {
+                if self.match_char('.') {
+                    if self.match_char('=') {
+                        Some(TokenType::DOT_DOT_EQUAL)
+                    } else if self.match_char('.') {
+                        Some(TokenType::DOT_DOT_DOT)
+                    } else {
+                        Some(TokenType::DOT_DOT)
+                    }
+                } else {
+                    Some(TokenType::DOT)
+                }
+            }
+            '-' => {
+                if self.match_char('>') {
+                    Some(TokenType::ARROW)
+                } else {
+                    Some(TokenType::MINUS)
+                }
+            }
+            '+' => Some(TokenType::PLUS),
+            ';' => Some(TokenType::SEMICOLON),
+            ':' => Some(TokenType::COLON),
+            '*' => Some(TokenType::STAR),
+            '&' => Some(TokenType::AMPERSAND),
+            '|' => Some(TokenType::PIPE),
+            '^' => Some(TokenType::CARET),
+            '~' => Some(TokenType::TILDE),
+            '!' => {
+                if self.match_char('=') {
                     Some(TokenType::BANG_EQUAL)
                 } else {
                     Some(TokenType::BANG)
                 }
             }
-            "=" => {
-                if self.match_char("=".to_string()) {
+            '=' => {
+                if self.match_char('=') {
                     Some(TokenType::EQUAL_EQUAL)
                 } else {
                     Some(TokenType::EQUAL)
                 }
             }
-            "<" => {
-                if self.match_char("=".to_string()) {
+            '<' => {
+                if self.match_char('=') {
                     Some(TokenType::LESS_EQUAL)
+                } else if self.match_char('<') {
+                    Some(TokenType::LESS_LESS)
                 } else {
                     Some(TokenType::LESS)
                 }
             }
-            ">" => {
-                if self.match_char("=".to_string()) {
+            '>' => {
+                if self.match_char('=') {
                     Some(TokenType::GREATER_EQUAL)
+                } else if self.match_char('>') {
+                    Some(TokenType::GREATER_GREATER)
                 } else {
                     Some(TokenType::GREATER)
                 }
             }
-            "/" => {
-                if self.match_char("/".to_string()) {
-                    while self.peek() != "\n".to_string() && !self.is_at_end() {
+            '/' => {
+                if self.match_char('/') {
+                    // `///` starts a doc comment, but `////` is a plain one.
+                    let doc = self.peek() == '/' && self.peek_next() != '/';
+
+                    while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
 
-                    None
-                } else if self.match_char("*".to_string()) {
+                    if doc {
+                        self.add_doc_comment();
+                        None
+                    } else {
+                        Some(TokenType::COMMENT)
+                    }
+                } else if self.match_char('*') {
                     self.parse_block_comments();
-                    None
+                    Some(TokenType::COMMENT)
                 } else {
                     Some(TokenType::SLASH)
                 }
             }
-            " " => None,
-            "\r" => None,
-            "\t" => None,
-            "\n" => {
+            ' ' => None,
+            '\r' => None,
+            '\t' => None,
+            '\n' => {
                 self.line += 1;
+                self.line_start = self.current;
                 None
             }
-            "\"" => self.parse_string(),
+            '"' => self.parse_string(),
+            '?' if self.match_char('?') => Some(TokenType::QUESTION_QUESTION),
+
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.parse_string()
+            }
 
             x => {
                 if self.is_digit(x) {
@@ -157,165 +316,299 @@ impl Scanner {
                     return None;
                 }
 
-                if self.is_alphanumeric(x) {
+                if self.is_alpha(x) {
                     self.parse_identifier();
                     return None;
                 }
 
-                self.logger.error(format!(
+                let message = format!(
                     "Syntax Error: Unidentified character '{}' at line {}",
-                    self.source
-                        .chars()
-                        .nth((self.current - 1) as usize)
-                        .unwrap(),
-                    self.line
-                ));
-                panic!();
+                    x, self.line
+                );
+                self.report_error(E_UNEXPECTED_CHARACTER, message);
+                None
             }
         }
     }
 
-    fn match_char(&mut self, expected: String) -> bool {
+    fn report_error(&mut self, code: &'static str, message: String) {
+        let span = Span {
+            start: self.start,
+            end: self.current,
+        };
+
+        let diagnostic = Diagnostic::error(code, message)
+            .at(self.start_line, self.start_column, span)
+            .in_file(self.file.clone());
+
+        if !self.quiet {
+            self.logger.report(&diagnostic);
+        }
+        self.errors.push(diagnostic);
+        self.had_error = true;
+    }
+
+    fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
-            return false;
+            false
         } else {
             let c = self.peek();
 
             if c != expected {
-                return false;
+                false
             } else {
-                self.current += 1;
-                return true;
+                self.current += c.len_utf8();
+                true
             }
         }
     }
 
+    /// Scans a string literal after its opening quote. `"""` strings may
+    /// contain unescaped `"` characters and span lines. Backslashes are
+    /// never special, so the `r` prefix of a raw string changes nothing
+    /// yet; it is reserved so that `r"..."` keeps meaning "no escapes" once
+    /// plain strings get them.
     fn parse_string(&mut self) -> Option<TokenType> {
-        while self.peek() != "\"".to_string() && !self.is_at_end() {
-            if self.peek() == "\n".to_string() {
+        let delimiter = if self.peek() == '"' && self.peek_next() == '"' {
+            self.advance();
+            self.advance();
+            "\"\"\""
+        } else {
+            "\""
+        };
+        let content_start = self.current;
+
+        while !self.rest().starts_with(delimiter) && !self.is_at_end() {
+            if self.peek() == '\n' {
                 self.line += 1;
+                self.line_start = self.current + 1;
             }
             self.advance();
         }
 
         if self.is_at_end() {
-            self.logger
-                .error("Syntax Error: Unterminated string".to_string());
+            self.unterminated = true;
+            let message = format!(
+                "Syntax Error: Unterminated string starting at line {}",
+                self.start_line
+            );
+            self.report_error(E_UNTERMINATED_STRING, message);
             return None;
         }
 
-        self.advance();
-
-        let str_val = self.source[self.start + 1..self.current - 1].to_owned();
+        let str_val = self.source[content_start..self.current].to_owned();
+        self.current += delimiter.len();
 
-        let new_token = Token::new(
+        let new_token = self.make_token(
             TokenType::STRING,
-            "".to_string(),
-            self.line,
-            Literal::String(str_val),
+            self.source[self.start..self.current].to_string(),
+            Literal::String(str_val.into()),
         );
 
         self.tokens.push(new_token);
 
-        return None;
+        None
     }
 
-    fn is_digit(&self, x: &str) -> bool {
-        return x.to_string().parse::<i32>().is_ok();
+    fn is_digit(&self, x: char) -> bool {
+        x.is_ascii_digit()
     }
 
-    fn peek_next(&self) -> String {
-        if self.is_at_end() || self.current + 1 >= self.source.len() {
-            return "\0".to_string();
-        }
-
-        self.source
-            .chars()
-            .nth(self.current + 1)
-            .unwrap()
-            .to_string()
+    fn peek_next(&self) -> char {
+        let mut chars = self.rest().chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
+    /// Scans `12`, `0xff`, `0o755` and `0b1010` as ints, and `12.5` and
+    /// `1e9` as numbers. A `_` may separate digits, as in `1_000_000`.
     fn parse_number(&mut self) {
-        while self.is_digit(&self.peek()) {
+        let radix = match (&self.source[self.start..self.current], self.peek()) {
+            ("0", 'x' | 'X') => 16,
+            ("0", 'o' | 'O') => 8,
+            ("0", 'b' | 'B') => 2,
+            _ => 10,
+        };
+
+        let literal = if radix != 10 {
             self.advance();
+            let digits = self.take_digits(radix, false);
+            if self.is_alphanumeric(self.peek()) {
+                while self.is_alphanumeric(self.peek()) {
+                    self.advance();
+                }
+                Err((
+                    E_INVALID_NUMBER,
+                    "has a digit that is not valid in its base",
+                ))
+            } else {
+                self.radix_literal(digits, radix)
+            }
+        } else {
+            self.decimal_literal()
+        };
+
+        let literal = match literal {
+            Ok(literal) => literal,
+            Err((code, problem)) => {
+                let message = format!(
+                    "Syntax Error: Number literal {} {} at line {}",
+                    &self.source[self.start..self.current],
+                    problem,
+                    self.start_line
+                );
+                self.report_error(code, message);
+                return;
+            }
+        };
+
+        let lexeme = self.source[self.start..self.current].to_string();
+        let new_token = self.make_token(TokenType::NUMBER, lexeme, literal);
+
+        self.tokens.push(new_token);
+    }
+
+    fn radix_literal(
+        &self,
+        digits: Option<String>,
+        radix: u32,
+    ) -> Result<Literal, (&'static str, &'static str)> {
+        match digits {
+            Some(digits) if !digits.is_empty() => i64::from_str_radix(&digits, radix)
+                .map(Literal::Int)
+                .map_err(|_| (E_INTEGER_TOO_LARGE, "does not fit in 64 bits")),
+            Some(_) => Err((E_INVALID_NUMBER, "has no digits after its prefix")),
+            None => Err((E_INVALID_NUMBER, "has a '_' that is not between digits")),
         }
+    }
+
+    /// Scans the rest of a decimal literal, whose first digit has been read.
+    fn decimal_literal(&mut self) -> Result<Literal, (&'static str, &'static str)> {
+        let misplaced = (E_INVALID_NUMBER, "has a '_' that is not between digits");
 
-        if self.peek() == ".".to_string() && self.is_digit(&self.peek_next()) {
+        let mut text = self.source[self.start..self.current].to_string();
+        text += &self.take_digits(10, true).ok_or(misplaced)?;
+
+        let mut float = false;
+        if self.peek() == '.' && self.is_digit(self.peek_next()) {
+            float = true;
             self.advance();
+            text.push('.');
+            text += &self.take_digits(10, false).ok_or(misplaced)?;
+        }
 
-            while self.is_digit(&self.peek()) {
-                self.advance();
+        // Only take an exponent that has digits, so `2else` still scans as
+        // a number and a keyword.
+        let exponent_digit = match self.peek_next() {
+            '+' | '-' => self.rest().chars().nth(2).unwrap_or('\0'),
+            c => c,
+        };
+        if matches!(self.peek(), 'e' | 'E') && self.is_digit(exponent_digit) {
+            float = true;
+            self.advance();
+            text.push('e');
+            if matches!(self.peek(), '+' | '-') {
+                text.push(self.advance());
             }
+            text += &self.take_digits(10, false).ok_or(misplaced)?;
         }
 
-        let number_val = self.source[self.start..self.current]
-            .to_owned()
-            .parse::<f64>()
-            .unwrap();
-
-        let new_token = Token::new(
-            TokenType::NUMBER,
-            "".to_string(),
-            self.line,
-            Literal::Number(number_val),
-        );
+        if !float {
+            return text
+                .parse::<i64>()
+                .map(Literal::Int)
+                .map_err(|_| (E_INTEGER_TOO_LARGE, "does not fit in 64 bits"));
+        }
 
-        self.tokens.push(new_token);
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Literal::Number(n)),
+            _ => Err((E_INVALID_NUMBER, "is too large for a number")),
+        }
     }
 
-    fn is_alpha(&self, x: &str) -> bool {
-        let utf8_code = x.bytes().next().unwrap();
+    /// Consumes digits in `radix` along with `_` separators and returns the
+    /// digits alone. `after_digit` says whether a digit was read just before.
+    /// Returns `None` if a separator is not between two digits.
+    fn take_digits(&mut self, radix: u32, after_digit: bool) -> Option<String> {
+        let mut digits = String::new();
+        let mut after_digit = after_digit;
+        let mut misplaced = false;
+
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            let c = self.advance();
+            if c == '_' {
+                misplaced |= !after_digit;
+            } else {
+                digits.push(c);
+            }
+            after_digit = c != '_';
+        }
 
-        if (utf8_code > 64 && utf8_code < 65)
-            || (utf8_code > 96 && utf8_code < 123)
-            || utf8_code == 95
-        {
-            return true;
-        } else {
-            return false;
+        if misplaced || !after_digit && self.source[self.start..self.current].ends_with('_') {
+            return None;
         }
+        Some(digits)
     }
 
-    fn is_alphanumeric(&self, x: &str) -> bool {
-        self.is_alpha(x) || self.is_digit(x)
+    /// Whether `x` can start an identifier: any Unicode letter or `_`.
+    fn is_alpha(&self, x: char) -> bool {
+        x.is_alphabetic() || x == '_'
     }
 
-    fn parse_identifier(&mut self) {
-        let keywords = get_keywords();
+    /// Whether `x` can continue an identifier, which also allows digits.
+    fn is_alphanumeric(&self, x: char) -> bool {
+        x.is_alphanumeric() || x == '_'
+    }
 
-        while self.is_alphanumeric(&self.peek()) {
+    fn parse_identifier(&mut self) {
+        while self.is_alphanumeric(self.peek()) {
             self.advance();
         }
 
         let lexeme = &self.source[self.start..self.current];
+        let token_type = keyword(lexeme).unwrap_or(TokenType::IDENTIFIER);
 
-        let mut token_type = keywords.get(lexeme);
+        let new_token = self.make_token(token_type, lexeme.to_string(), Literal::Nil);
 
-        if token_type.is_none() {
-            token_type = Some(&TokenType::IDENTIFIER);
-        }
+        self.tokens.push(new_token);
+    }
 
-        let new_token = Token::new(
-            token_type.unwrap().to_owned(),
-            lexeme.to_string(),
-            self.line,
-            Literal::Nil,
-        );
+    fn add_doc_comment(&mut self) {
+        let lexeme = self.source[self.start..self.current].to_string();
+        let text = lexeme[3..].strip_prefix(' ').unwrap_or(&lexeme[3..]);
+        let text = text.trim_end_matches('\r').into();
 
-        self.tokens.push(new_token);
+        let token = self.make_token(TokenType::DOC_COMMENT, lexeme, Literal::String(text));
+        self.tokens.push(token);
     }
 
+    /// Skips a `/* ... */` comment, which may contain nested block comments.
     fn parse_block_comments(&mut self) {
-        while self.peek() != "*" && self.peek_next() != "/" {
-            let ch = self.advance();
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.unterminated = true;
+                let message = format!(
+                    "Syntax Error: Unterminated block comment starting at line {}",
+                    self.start_line
+                );
+                self.report_error(E_UNTERMINATED_COMMENT, message);
+                return;
+            }
 
-            if ch == "\n" {
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                self.advance();
+                depth -= 1;
+            } else if self.advance() == '\n' {
                 self.line += 1;
+                self.line_start = self.current;
             }
         }
-
-        self.advance();
-        self.advance();
     }
 }
@@ -0,0 +1,273 @@
+//! Saving the top-level state of a session and restoring it later.
+//!
+//! A snapshot is a JSON document with the top-level bindings of the running
+//! script, the globals a prelude or the host added, and every module loaded
+//! so far, so restoring one never runs a module again. Values are copied:
+//! two bindings that shared a list hold two equal lists once restored.
+//! Natives and host functions are saved by name and looked up again on
+//! restore. Iterators and sockets cannot be saved.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::{
+    ast_json::{node, object, Json, JsonReader},
+    diagnostic::json_string,
+    module::Module,
+    value::Value,
+};
+
+/// Bumped whenever the shape of the JSON below changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// One named binding and whether it was declared with `const`.
+pub struct Binding {
+    pub name: String,
+    pub value: Value,
+    pub constant: bool,
+}
+
+pub struct Snapshot {
+    /// Globals other than the natives.
+    pub globals: Vec<Binding>,
+    /// The top-level bindings of the running script.
+    pub bindings: Vec<Binding>,
+    pub modules: Vec<Rc<Module>>,
+}
+
+pub fn encode(snapshot: &Snapshot) -> Result<String, String> {
+    let mut sorted: Vec<&Rc<Module>> = snapshot.modules.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut modules = Vec::new();
+    for module in sorted {
+        let exports: Vec<Binding> = module
+            .exports
+            .iter()
+            .map(|(name, value)| Binding {
+                name: name.clone(),
+                value: value.clone(),
+                constant: module.constants.contains(name),
+            })
+            .collect();
+
+        modules.push(object(&[
+            ("name", json_string(&module.name)),
+            ("path", json_string(&module.path.display().to_string())),
+            ("exports", bindings_json(&exports)?),
+        ]));
+    }
+
+    Ok(object(&[
+        ("version", SNAPSHOT_VERSION.to_string()),
+        ("globals", bindings_json(&snapshot.globals)?),
+        ("bindings", bindings_json(&snapshot.bindings)?),
+        ("modules", format!("[{}]", modules.join(","))),
+    ]))
+}
+
+/// Sorted by name, so saving the same state twice gives the same file.
+fn bindings_json(bindings: &[Binding]) -> Result<String, String> {
+    let mut sorted: Vec<&Binding> = bindings.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut items = Vec::new();
+    for binding in sorted {
+        let value = value_json(&binding.value)
+            .map_err(|message| format!("Cannot save '{}': {}", binding.name, message))?;
+        items.push(object(&[
+            ("name", json_string(&binding.name)),
+            ("constant", binding.constant.to_string()),
+            ("value", value),
+        ]));
+    }
+    Ok(format!("[{}]", items.join(",")))
+}
+
+fn value_json(value: &Value) -> Result<String, String> {
+    let json = match value {
+        Value::Nil => node("Nil", &[]),
+        Value::Bool(b) => node("Bool", &[("value", b.to_string())]),
+        Value::Int(n) => node("Int", &[("value", n.to_string())]),
+        // NaN and the infinities have no JSON number, so they are written as
+        // the strings `f64` parses back.
+        Value::Number(n) if n.is_finite() => node("Number", &[("value", format!("{:?}", n))]),
+        Value::Number(n) => node("Number", &[("value", json_string(&n.to_string()))]),
+        Value::String(s) => node("String", &[("value", json_string(s))]),
+        Value::Native(native) => node("Native", &[("name", json_string(native.name))]),
+        Value::Host(host) => node("Native", &[("name", json_string(&host.name))]),
+        Value::Module(module) => node(
+            "Module",
+            &[("path", json_string(&module.path.display().to_string()))],
+        ),
+        Value::List(items) => node("List", &[("items", values_json(items)?)]),
+        Value::Tuple(items) => node("Tuple", &[("items", values_json(items)?)]),
+        Value::Range(start, end, inclusive) => node(
+            if *inclusive {
+                "RangeInclusive"
+            } else {
+                "Range"
+            },
+            &[("start", start.to_string()), ("end", end.to_string())],
+        ),
+        Value::Iterator(_) | Value::Socket(_) => {
+            return Err(format!(
+                "a value of type {} cannot be saved",
+                value.type_name()
+            ))
+        }
+    };
+    Ok(json)
+}
+
+fn values_json(values: &[Value]) -> Result<String, String> {
+    let items = values
+        .iter()
+        .map(value_json)
+        .collect::<Result<Vec<String>, String>>()?;
+    Ok(format!("[{}]", items.join(",")))
+}
+
+/// Reads a snapshot written by `encode`. `native` finds the function a
+/// saved native or host function name refers to now.
+pub fn decode(json: &str, native: &dyn Fn(&str) -> Option<Value>) -> Result<Snapshot, String> {
+    let document = JsonReader::new(json).document()?;
+
+    let version = document.get("version")?.as_number()?;
+    if version != SNAPSHOT_VERSION as f64 {
+        return Err(format!(
+            "Unsupported snapshot version {} (expected {})",
+            version, SNAPSHOT_VERSION
+        ));
+    }
+
+    let mut decoder = Decoder {
+        native,
+        saved_modules: HashMap::new(),
+        modules: HashMap::new(),
+        loading: HashSet::new(),
+    };
+    for module in document.get("modules")?.as_array()? {
+        let path = module.get("path")?.as_str()?.to_string();
+        decoder.saved_modules.insert(path, module);
+    }
+
+    let paths: Vec<String> = decoder.saved_modules.keys().cloned().collect();
+    let mut modules = Vec::new();
+    for path in paths {
+        modules.push(decoder.module(&path)?);
+    }
+
+    Ok(Snapshot {
+        globals: decoder.bindings(document.get("globals")?)?,
+        bindings: decoder.bindings(document.get("bindings")?)?,
+        modules,
+    })
+}
+
+struct Decoder<'a> {
+    native: &'a dyn Fn(&str) -> Option<Value>,
+    saved_modules: HashMap<String, &'a Json>,
+    /// Modules already rebuilt, so every reference to one shares it.
+    modules: HashMap<String, Rc<Module>>,
+    /// Modules being rebuilt, to reject a file that refers back to one.
+    loading: HashSet<String>,
+}
+
+impl Decoder<'_> {
+    fn module(&mut self, path: &str) -> Result<Rc<Module>, String> {
+        if let Some(module) = self.modules.get(path) {
+            return Ok(module.clone());
+        }
+        let saved = *self
+            .saved_modules
+            .get(path)
+            .ok_or_else(|| format!("Missing module '{}'", path))?;
+        if !self.loading.insert(path.to_string()) {
+            return Err(format!("Module '{}' refers to itself", path));
+        }
+
+        let bindings = self.bindings(saved.get("exports")?)?;
+        let constants = bindings
+            .iter()
+            .filter(|binding| binding.constant)
+            .map(|binding| binding.name.clone())
+            .collect();
+        let exports = bindings
+            .into_iter()
+            .map(|binding| (binding.name, binding.value))
+            .collect();
+        let module = Rc::new(Module {
+            name: saved.get("name")?.as_str()?.to_string(),
+            path: PathBuf::from(path),
+            exports,
+            constants,
+        });
+
+        self.loading.remove(path);
+        self.modules.insert(path.to_string(), module.clone());
+        Ok(module)
+    }
+
+    fn bindings(&mut self, json: &Json) -> Result<Vec<Binding>, String> {
+        let mut bindings = Vec::new();
+        for binding in json.as_array()? {
+            bindings.push(Binding {
+                name: binding.get("name")?.as_str()?.to_string(),
+                value: self.value(binding.get("value")?)?,
+                constant: matches!(binding.get("constant")?, Json::Bool(true)),
+            });
+        }
+        Ok(bindings)
+    }
+
+    fn value(&mut self, json: &Json) -> Result<Value, String> {
+        let value = match json.get("kind")?.as_str()? {
+            "Nil" => Value::Nil,
+            "Bool" => match json.get("value")? {
+                Json::Bool(b) => Value::Bool(*b),
+                _ => return Err("Expected a bool".to_string()),
+            },
+            "Int" => Value::Int(integer(json.get("value")?)?),
+            "Number" => match json.get("value")? {
+                Json::String(text) => Value::Number(
+                    text.parse()
+                        .map_err(|_| format!("Invalid number '{}'", text))?,
+                ),
+                number => Value::Number(number.as_number()?),
+            },
+            "String" => Value::String(json.get("value")?.as_str()?.into()),
+            "Native" => {
+                let name = json.get("name")?.as_str()?;
+                (self.native)(name).ok_or_else(|| format!("Unknown native '{}'", name))?
+            }
+            "Module" => Value::Module(self.module(json.get("path")?.as_str()?)?),
+            "List" => Value::List(Rc::new(self.values(json.get("items")?)?)),
+            "Tuple" => Value::Tuple(Rc::new(self.values(json.get("items")?)?)),
+            kind @ ("Range" | "RangeInclusive") => Value::Range(
+                integer(json.get("start")?)?,
+                integer(json.get("end")?)?,
+                kind == "RangeInclusive",
+            ),
+            kind => return Err(format!("Unknown value kind '{}'", kind)),
+        };
+        Ok(value)
+    }
+
+    fn values(&mut self, json: &Json) -> Result<Vec<Value>, String> {
+        json.as_array()?
+            .iter()
+            .map(|item| self.value(item))
+            .collect()
+    }
+}
+
+fn integer(json: &Json) -> Result<i64, String> {
+    match json {
+        Json::Integer(n) => Ok(*n),
+        _ => Err("Expected an integer".to_string()),
+    }
+}
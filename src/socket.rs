@@ -0,0 +1,295 @@
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream, UdpSocket},
+    rc::Rc,
+};
+
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    native::{string_arg, string_value, NativeFn},
+    value::Value,
+};
+
+/// How many bytes `recv` and `recv_from` read when no limit is given.
+const DEFAULT_RECV_BYTES: usize = 64 * 1024;
+
+/// An open network socket, shared by every copy of the value that holds it.
+/// `close` drops the OS socket; using the value afterwards is an error.
+pub struct Socket {
+    kind: &'static str,
+    /// The local or remote address, for display.
+    address: String,
+    inner: RefCell<Option<Inner>>,
+}
+
+enum Inner {
+    Stream(TcpStream),
+    Listener(TcpListener),
+    Datagram(UdpSocket),
+}
+
+impl Socket {
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl Debug for Socket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} {}>", self.kind, self.address)
+    }
+}
+
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "tcp_connect",
+            arity: 1..=1,
+            func: tcp_connect,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "tcp_listen",
+            arity: 1..=1,
+            func: tcp_listen,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "tcp_accept",
+            arity: 1..=1,
+            func: tcp_accept,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "udp_bind",
+            arity: 1..=1,
+            func: udp_bind,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "send",
+            arity: 2..=2,
+            func: send,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "recv",
+            arity: 1..=2,
+            func: recv,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "send_to",
+            arity: 3..=3,
+            func: send_to,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "recv_from",
+            arity: 1..=2,
+            func: recv_from,
+            deterministic: false,
+        },
+        NativeFn {
+            name: "close",
+            arity: 1..=1,
+            func: close,
+            deterministic: false,
+        },
+    ]
+}
+
+fn socket_value(kind: &'static str, address: String, inner: Inner) -> Value {
+    Value::Socket(Rc::new(Socket {
+        kind,
+        address,
+        inner: RefCell::new(Some(inner)),
+    }))
+}
+
+fn io_error(name: &str, err: std::io::Error) -> RuntimeError {
+    RuntimeError::Generic(format!("{}() failed: {}", name, err))
+}
+
+fn socket_arg(name: &str, args: &[Value], index: usize) -> Result<Rc<Socket>, RuntimeError> {
+    match &args[index] {
+        Value::Socket(socket) => Ok(socket.clone()),
+        x => Err(RuntimeError::Generic(format!(
+            "{}() expects a socket as argument {}, got '{}'",
+            name,
+            index + 1,
+            x
+        ))),
+    }
+}
+
+fn size_arg(name: &str, args: &[Value], index: usize) -> Result<usize, RuntimeError> {
+    let Some(x) = args.get(index) else {
+        return Ok(DEFAULT_RECV_BYTES);
+    };
+
+    match x.as_i64() {
+        Some(n) if n >= 1 => Ok(n as usize),
+        _ => Err(RuntimeError::Generic(format!(
+            "{}() expects a positive byte count, got '{}'",
+            name, x
+        ))),
+    }
+}
+
+/// Runs `f` on the OS socket behind `socket`, failing if it has been closed.
+fn with_inner<T>(
+    name: &str,
+    socket: &Socket,
+    f: impl FnOnce(&mut Inner) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    match socket.inner.borrow_mut().as_mut() {
+        Some(inner) => f(inner),
+        None => Err(RuntimeError::Generic(format!(
+            "{}() cannot use a closed socket",
+            name
+        ))),
+    }
+}
+
+fn wrong_kind(name: &str, socket: &Socket) -> RuntimeError {
+    RuntimeError::Generic(format!("{}() cannot be used on a {}", name, socket.kind))
+}
+
+/// `tcp_connect("host:port")` opens a connection.
+fn tcp_connect(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let address = string_arg("tcp_connect", &args, 0)?;
+    let stream = TcpStream::connect(&*address).map_err(|err| io_error("tcp_connect", err))?;
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| address.to_string(), |addr| addr.to_string());
+
+    Ok(socket_value("tcp stream", peer, Inner::Stream(stream)))
+}
+
+/// `tcp_listen("host:port")` starts listening for connections. Port 0
+/// picks a free port.
+fn tcp_listen(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let address = string_arg("tcp_listen", &args, 0)?;
+    let listener = TcpListener::bind(&*address).map_err(|err| io_error("tcp_listen", err))?;
+    let local = listener
+        .local_addr()
+        .map_or_else(|_| address.to_string(), |addr| addr.to_string());
+
+    Ok(socket_value(
+        "tcp listener",
+        local,
+        Inner::Listener(listener),
+    ))
+}
+
+/// `tcp_accept(listener)` waits for a connection and returns the new stream
+/// together with the peer's address.
+fn tcp_accept(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("tcp_accept", &args, 0)?;
+    let (stream, peer) = with_inner("tcp_accept", &socket, |inner| match inner {
+        Inner::Listener(listener) => listener.accept().map_err(|err| io_error("tcp_accept", err)),
+        _ => Err(wrong_kind("tcp_accept", &socket)),
+    })?;
+
+    Ok(Value::Tuple(Rc::new(vec![
+        socket_value("tcp stream", peer.to_string(), Inner::Stream(stream)),
+        string_value(peer.to_string()),
+    ])))
+}
+
+/// `udp_bind("host:port")` opens a UDP socket for `send_to` and `recv_from`.
+fn udp_bind(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let address = string_arg("udp_bind", &args, 0)?;
+    let socket = UdpSocket::bind(&*address).map_err(|err| io_error("udp_bind", err))?;
+    let local = socket
+        .local_addr()
+        .map_or_else(|_| address.to_string(), |addr| addr.to_string());
+
+    Ok(socket_value("udp socket", local, Inner::Datagram(socket)))
+}
+
+/// `send(stream, data)` writes all of `data` and returns the number of bytes.
+fn send(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("send", &args, 0)?;
+    let data = string_arg("send", &args, 1)?;
+
+    with_inner("send", &socket, |inner| match inner {
+        Inner::Stream(stream) => stream
+            .write_all(data.as_bytes())
+            .map_err(|err| io_error("send", err)),
+        _ => Err(wrong_kind("send", &socket)),
+    })?;
+
+    Ok(Value::Int(data.len() as i64))
+}
+
+/// `recv(stream, max)` waits for data and returns up to `max` bytes of it as
+/// a string, or nil once the peer has closed the connection.
+fn recv(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("recv", &args, 0)?;
+    let mut buffer = vec![0; size_arg("recv", &args, 1)?];
+
+    let read = with_inner("recv", &socket, |inner| match inner {
+        Inner::Stream(stream) => stream
+            .read(&mut buffer)
+            .map_err(|err| io_error("recv", err)),
+        _ => Err(wrong_kind("recv", &socket)),
+    })?;
+
+    if read == 0 {
+        return Ok(Value::Nil);
+    }
+    Ok(string_value(
+        String::from_utf8_lossy(&buffer[..read]).into_owned(),
+    ))
+}
+
+/// `send_to(udp, data, "host:port")` sends one datagram.
+fn send_to(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("send_to", &args, 0)?;
+    let data = string_arg("send_to", &args, 1)?;
+    let address = string_arg("send_to", &args, 2)?;
+
+    let sent = with_inner("send_to", &socket, |inner| match inner {
+        Inner::Datagram(udp) => udp
+            .send_to(data.as_bytes(), &*address)
+            .map_err(|err| io_error("send_to", err)),
+        _ => Err(wrong_kind("send_to", &socket)),
+    })?;
+
+    Ok(Value::Int(sent as i64))
+}
+
+/// `recv_from(udp, max)` waits for one datagram and returns its contents
+/// together with the sender's address.
+fn recv_from(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("recv_from", &args, 0)?;
+    let mut buffer = vec![0; size_arg("recv_from", &args, 1)?];
+
+    let (read, sender) = with_inner("recv_from", &socket, |inner| match inner {
+        Inner::Datagram(udp) => udp
+            .recv_from(&mut buffer)
+            .map_err(|err| io_error("recv_from", err)),
+        _ => Err(wrong_kind("recv_from", &socket)),
+    })?;
+
+    Ok(Value::Tuple(Rc::new(vec![
+        string_value(String::from_utf8_lossy(&buffer[..read]).into_owned()),
+        string_value(sender.to_string()),
+    ])))
+}
+
+/// `close(socket)` releases the socket. Closing it twice does nothing.
+fn close(_interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let socket = socket_arg("close", &args, 0)?;
+    socket.inner.borrow_mut().take();
+
+    Ok(Value::Nil)
+}
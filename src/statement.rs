@@ -1,17 +1,319 @@
 use crate::{
+    arena::{Arena, StmtId},
     expression::Expr,
-    interpreter::Object,
-    token::{Literal, Token},
+    token::{Span, Token},
+    visitor::{StmtVisitor, StmtVisitorMut},
 };
 
+/// The names a declaration binds: a single name, or a parenthesized tuple
+/// of patterns that destructures a tuple value. A name's slot is filled in
+/// by the resolver for locals; top-level names have none.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Name(Token, Option<usize>),
+    Tuple(Token, Vec<Pattern>, Token),
+}
+
+impl Pattern {
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Pattern::Name(name, _) => name,
+            Pattern::Tuple(paren, _, _) => paren,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Name(name, _) => name.span,
+            Pattern::Tuple(open, _, close) => open.span.to(close.span),
+        }
+    }
+
+    /// Every name bound by this pattern, in source order.
+    pub fn names(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Name(name, _) => vec![name],
+            Pattern::Tuple(_, patterns, _) => patterns.iter().flat_map(Pattern::names).collect(),
+        }
+    }
+}
+
+/// A statement. Like expressions, nested statements live in the program's
+/// `Arena`, while blocks and match arms keep their statements inline.
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Print(Expr),
+    /// `print a, b;`, which writes its values separated by spaces.
+    Print(Token, Vec<Expr>),
     Expression(Expr),
-    Var(Token, Option<Expr>),
+    Var(Pattern, Option<Token>, Option<Expr>),
+    /// `const name: annotation = value;`, with the slot the resolver gave
+    /// a local constant.
+    Const(Token, Option<usize>, Option<Token>, Expr),
     Block(Vec<Stmt>),
-    If(Expr, Box<Stmt>, Box<Option<Stmt>>),
-    While(Expr, Box<Stmt>),
+    If(Token, Expr, StmtId, Option<StmtId>),
+    /// `label: while (cond) body`. A desugared `for` keeps its increment
+    /// separate so `continue` still runs it.
+    While(Token, Option<Token>, Expr, StmtId, Option<Expr>),
+    ForEach(Token, Option<Token>, Pattern, Expr, StmtId),
+    /// `break label;`, where the label is optional.
+    Break(Token, Option<Token>),
+    /// `continue label;`, where the label is optional.
+    Continue(Token, Option<Token>),
+    Throw(Token, Expr),
+    Defer(Token, StmtId),
+    Try(
+        Token,
+        Vec<Stmt>,
+        Option<(Token, Vec<Stmt>)>,
+        Option<Vec<Stmt>>,
+    ),
+    Import(Token, String, Option<Token>),
+    /// `pub var` or `pub const` at the top of a module, marking the binding
+    /// as one that importers can see.
+    Pub(Token, StmtId),
+    Match(Token, Expr, Vec<(Vec<Expr>, Stmt)>, Option<StmtId>),
 }
 
-pub struct Void;
+impl Stmt {
+    /// The token this statement starts with. Only an empty block has none.
+    pub fn first_token<'a>(&'a self, arena: &'a Arena) -> Option<&'a Token> {
+        match self {
+            Stmt::Print(keyword, _) => Some(keyword),
+            Stmt::Expression(expr) => Some(expr.first_token(arena)),
+            Stmt::Var(pattern, _, _) => Some(pattern.first_token()),
+            Stmt::Const(token, _, _, _) => Some(token),
+            Stmt::Block(stmts) => stmts.iter().find_map(|stmt| stmt.first_token(arena)),
+            Stmt::If(keyword, _, _, _) => Some(keyword),
+            Stmt::While(keyword, label, _, _, _) => Some(label.as_ref().unwrap_or(keyword)),
+            Stmt::ForEach(keyword, label, _, _, _) => Some(label.as_ref().unwrap_or(keyword)),
+            Stmt::Break(keyword, _) => Some(keyword),
+            Stmt::Continue(keyword, _) => Some(keyword),
+            Stmt::Throw(keyword, _) => Some(keyword),
+            Stmt::Defer(keyword, _) => Some(keyword),
+            Stmt::Try(keyword, _, _, _) => Some(keyword),
+            Stmt::Import(keyword, _, _) => Some(keyword),
+            Stmt::Pub(keyword, _) => Some(keyword),
+            Stmt::Match(keyword, _, _, _) => Some(keyword),
+        }
+    }
+
+    /// The line this statement starts on. Only an empty block has none.
+    pub fn line(&self, arena: &Arena) -> Option<i32> {
+        self.first_token(arena).map(|token| token.line)
+    }
+
+    /// The source range covered by this statement's tokens.
+    pub fn span(&self, arena: &Arena) -> Option<Span> {
+        let nested = |id: &StmtId| arena[*id].span(arena);
+        match self {
+            Stmt::Print(keyword, exprs) => match exprs.last() {
+                Some(expr) => Some(keyword.span.to(expr.span(arena))),
+                None => Some(keyword.span),
+            },
+            Stmt::Expression(expr) => Some(expr.span(arena)),
+            Stmt::Var(pattern, _, initializer) => match initializer {
+                Some(expr) => Some(pattern.span().to(expr.span(arena))),
+                None => Some(pattern.span()),
+            },
+            Stmt::Const(token, _, _, initializer) => Some(token.span.to(initializer.span(arena))),
+            Stmt::Block(stmts) => block_span(arena, stmts),
+            Stmt::If(keyword, _, then_branch, else_branch) => {
+                let mut span = keyword.span;
+                if let Some(then_span) = nested(then_branch) {
+                    span = span.to(then_span);
+                }
+                if let Some(else_span) = else_branch.as_ref().and_then(nested) {
+                    span = span.to(else_span);
+                }
+                Some(span)
+            }
+            Stmt::While(keyword, label, cond, body, _) => {
+                let start = label.as_ref().unwrap_or(keyword).span;
+                let span = start.to(cond.span(arena));
+                Some(nested(body).map_or(span, |body_span| span.to(body_span)))
+            }
+            Stmt::ForEach(keyword, label, _, iterable, body) => {
+                let start = label.as_ref().unwrap_or(keyword).span;
+                let span = start.to(iterable.span(arena));
+                Some(nested(body).map_or(span, |body_span| span.to(body_span)))
+            }
+            Stmt::Break(keyword, label) | Stmt::Continue(keyword, label) => match label {
+                Some(label) => Some(keyword.span.to(label.span)),
+                None => Some(keyword.span),
+            },
+            Stmt::Throw(keyword, expr) => Some(keyword.span.to(expr.span(arena))),
+            Stmt::Defer(keyword, stmt) => {
+                Some(nested(stmt).map_or(keyword.span, |span| keyword.span.to(span)))
+            }
+            Stmt::Try(keyword, try_block, catch_clause, finally_block) => {
+                let mut span = keyword.span;
+                for block in [
+                    Some(try_block),
+                    catch_clause.as_ref().map(|(_, block)| block),
+                    finally_block.as_ref(),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    if let Some(block_span) = block_span(arena, block) {
+                        span = span.to(block_span);
+                    }
+                }
+                Some(span)
+            }
+            Stmt::Import(keyword, _, alias) => match alias {
+                Some(alias) => Some(keyword.span.to(alias.span)),
+                None => Some(keyword.span),
+            },
+            Stmt::Pub(keyword, stmt) => {
+                Some(nested(stmt).map_or(keyword.span, |span| keyword.span.to(span)))
+            }
+            Stmt::Match(keyword, subject, arms, default) => {
+                let mut span = keyword.span.to(subject.span(arena));
+                for (_, body) in arms.iter() {
+                    if let Some(body_span) = body.span(arena) {
+                        span = span.to(body_span);
+                    }
+                }
+                if let Some(default_span) = default.as_ref().and_then(nested) {
+                    span = span.to(default_span);
+                }
+                Some(span)
+            }
+        }
+    }
+
+    /// Calls the method of `visitor` for this kind of statement, with the
+    /// children looked up in `arena`.
+    pub fn accept<R, V: StmtVisitor<R> + ?Sized>(&self, arena: &Arena, visitor: &mut V) -> R {
+        match self {
+            Stmt::Print(keyword, values) => visitor.visit_print_stmt(arena, keyword, values),
+            Stmt::Expression(expr) => visitor.visit_expression_stmt(arena, expr),
+            Stmt::Var(pattern, annotation, initializer) => {
+                visitor.visit_var_stmt(arena, pattern, annotation.as_ref(), initializer.as_ref())
+            }
+            Stmt::Const(name, slot, annotation, initializer) => {
+                visitor.visit_const_stmt(arena, name, *slot, annotation.as_ref(), initializer)
+            }
+            Stmt::Block(stmts) => visitor.visit_block_stmt(arena, stmts),
+            Stmt::If(keyword, condition, then_branch, else_branch) => visitor.visit_if_stmt(
+                arena,
+                keyword,
+                condition,
+                &arena[*then_branch],
+                else_branch.map(|id| &arena[id]),
+            ),
+            Stmt::While(keyword, label, condition, body, increment) => visitor.visit_while_stmt(
+                arena,
+                keyword,
+                label.as_ref(),
+                condition,
+                &arena[*body],
+                increment.as_ref(),
+            ),
+            Stmt::ForEach(keyword, label, pattern, iterable, body) => visitor.visit_for_each_stmt(
+                arena,
+                keyword,
+                label.as_ref(),
+                pattern,
+                iterable,
+                &arena[*body],
+            ),
+            Stmt::Break(keyword, label) => visitor.visit_break_stmt(arena, keyword, label.as_ref()),
+            Stmt::Continue(keyword, label) => {
+                visitor.visit_continue_stmt(arena, keyword, label.as_ref())
+            }
+            Stmt::Throw(keyword, value) => visitor.visit_throw_stmt(arena, keyword, value),
+            Stmt::Defer(keyword, stmt) => visitor.visit_defer_stmt(arena, keyword, &arena[*stmt]),
+            Stmt::Try(keyword, try_block, catch_clause, finally_block) => visitor.visit_try_stmt(
+                arena,
+                keyword,
+                try_block,
+                catch_clause
+                    .as_ref()
+                    .map(|(name, block)| (name, block.as_slice())),
+                finally_block.as_deref(),
+            ),
+            Stmt::Import(keyword, path, alias) => {
+                visitor.visit_import_stmt(arena, keyword, path, alias.as_ref())
+            }
+            Stmt::Pub(keyword, stmt) => visitor.visit_pub_stmt(arena, keyword, &arena[*stmt]),
+            Stmt::Match(keyword, subject, arms, default) => visitor.visit_match_stmt(
+                arena,
+                keyword,
+                subject,
+                arms,
+                default.map(|id| &arena[id]),
+            ),
+        }
+    }
+
+    /// Like `accept`, for visitors that modify the tree. This statement
+    /// must not be in `arena` itself; `Arena::accept_stmt_mut` visits one
+    /// that is.
+    pub fn accept_mut<R, V: StmtVisitorMut<R> + ?Sized>(
+        &mut self,
+        arena: &mut Arena,
+        visitor: &mut V,
+    ) -> R {
+        match self {
+            Stmt::Print(keyword, values) => visitor.visit_print_stmt(arena, keyword, values),
+            Stmt::Expression(expr) => visitor.visit_expression_stmt(arena, expr),
+            Stmt::Var(pattern, annotation, initializer) => {
+                visitor.visit_var_stmt(arena, pattern, annotation.as_mut(), initializer.as_mut())
+            }
+            Stmt::Const(name, slot, annotation, initializer) => {
+                visitor.visit_const_stmt(arena, name, slot, annotation.as_mut(), initializer)
+            }
+            Stmt::Block(stmts) => visitor.visit_block_stmt(arena, stmts),
+            Stmt::If(keyword, condition, then_branch, else_branch) => {
+                visitor.visit_if_stmt(arena, keyword, condition, *then_branch, *else_branch)
+            }
+            Stmt::While(keyword, label, condition, body, increment) => visitor.visit_while_stmt(
+                arena,
+                keyword,
+                label.as_mut(),
+                condition,
+                *body,
+                increment.as_mut(),
+            ),
+            Stmt::ForEach(keyword, label, pattern, iterable, body) => visitor.visit_for_each_stmt(
+                arena,
+                keyword,
+                label.as_mut(),
+                pattern,
+                iterable,
+                *body,
+            ),
+            Stmt::Break(keyword, label) => visitor.visit_break_stmt(arena, keyword, label.as_mut()),
+            Stmt::Continue(keyword, label) => {
+                visitor.visit_continue_stmt(arena, keyword, label.as_mut())
+            }
+            Stmt::Throw(keyword, value) => visitor.visit_throw_stmt(arena, keyword, value),
+            Stmt::Defer(keyword, stmt) => visitor.visit_defer_stmt(arena, keyword, *stmt),
+            Stmt::Try(keyword, try_block, catch_clause, finally_block) => visitor.visit_try_stmt(
+                arena,
+                keyword,
+                try_block,
+                catch_clause
+                    .as_mut()
+                    .map(|(name, block)| (name, block.as_mut_slice())),
+                finally_block.as_deref_mut(),
+            ),
+            Stmt::Import(keyword, path, alias) => {
+                visitor.visit_import_stmt(arena, keyword, path, alias.as_mut())
+            }
+            Stmt::Pub(keyword, stmt) => visitor.visit_pub_stmt(arena, keyword, *stmt),
+            Stmt::Match(keyword, subject, arms, default) => {
+                visitor.visit_match_stmt(arena, keyword, subject, arms, *default)
+            }
+        }
+    }
+}
+
+fn block_span(arena: &Arena, stmts: &[Stmt]) -> Option<Span> {
+    stmts
+        .iter()
+        .filter_map(|stmt| stmt.span(arena))
+        .reduce(|acc, span| acc.to(span))
+}
@@ -0,0 +1,26 @@
+use std::fmt::Display;
+
+/// Counts of the work an interpreter has done since it was created, for
+/// tuning scripts and spotting interpreter regressions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub statements: u64,
+    pub expressions: u64,
+    /// Variable reads and assignments, local or global.
+    pub lookups: u64,
+    /// Strings, lists and tuples created at runtime, counting each item of
+    /// a list or tuple as well, as the heap object limit does.
+    pub allocations: u64,
+    /// The most block scopes that were open at once.
+    pub peak_scope_depth: usize,
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "statements executed    {}", self.statements)?;
+        writeln!(f, "expressions evaluated  {}", self.expressions)?;
+        writeln!(f, "variable lookups       {}", self.lookups)?;
+        writeln!(f, "allocations            {}", self.allocations)?;
+        writeln!(f, "peak scope depth       {}", self.peak_scope_depth)
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq)]
+use std::rc::Rc;
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenType {
     // Single-character tokens.
     LEFT_PAREN,
@@ -12,8 +15,13 @@ pub enum TokenType {
     MINUS,
     PLUS,
     SEMICOLON,
+    COLON,
     SLASH,
     STAR,
+    AMPERSAND,
+    PIPE,
+    CARET,
+    TILDE,
 
     // One or two character tokens.
     BANG,
@@ -24,11 +32,22 @@ pub enum TokenType {
     GREATER_EQUAL,
     LESS,
     LESS_EQUAL,
+    LESS_LESS,
+    GREATER_GREATER,
+    ARROW,
+    DOT_DOT,
+    DOT_DOT_EQUAL,
+    DOT_DOT_DOT,
+    QUESTION_QUESTION,
 
     // Literals.
     IDENTIFIER,
     STRING,
     NUMBER,
+    /// A `///` comment, kept for tooling. The parser ignores it.
+    DOC_COMMENT,
+    /// Any other comment, or a leading `#!` line. The parser ignores it.
+    COMMENT,
 
     // Keywords.
     AND,
@@ -47,30 +66,156 @@ pub enum TokenType {
     TRUE,
     VAR,
     WHILE,
+    TRY,
+    CATCH,
+    FINALLY,
+    THROW,
+    IMPORT,
+    AS,
+    IS,
+    MATCH,
+    IN,
+    CONST,
+    DEFER,
+    BREAK,
+    CONTINUE,
+    PUB,
 
     EOF,
 }
 
-#[derive(Debug, Clone)]
+impl TokenType {
+    /// Looks up a token type by its variant name, as written by the AST
+    /// JSON export. New variants must be added here too.
+    pub fn from_name(name: &str) -> Option<TokenType> {
+        let token_type = match name {
+            "LEFT_PAREN" => TokenType::LEFT_PAREN,
+            "RIGHT_PAREN" => TokenType::RIGHT_PAREN,
+            "LEFT_BRACE" => TokenType::LEFT_BRACE,
+            "RIGHT_BRACE" => TokenType::RIGHT_BRACE,
+            "COMMA" => TokenType::COMMA,
+            "DOT" => TokenType::DOT,
+            "MINUS" => TokenType::MINUS,
+            "PLUS" => TokenType::PLUS,
+            "SEMICOLON" => TokenType::SEMICOLON,
+            "COLON" => TokenType::COLON,
+            "SLASH" => TokenType::SLASH,
+            "STAR" => TokenType::STAR,
+            "AMPERSAND" => TokenType::AMPERSAND,
+            "PIPE" => TokenType::PIPE,
+            "CARET" => TokenType::CARET,
+            "TILDE" => TokenType::TILDE,
+            "BANG" => TokenType::BANG,
+            "BANG_EQUAL" => TokenType::BANG_EQUAL,
+            "EQUAL" => TokenType::EQUAL,
+            "EQUAL_EQUAL" => TokenType::EQUAL_EQUAL,
+            "GREATER" => TokenType::GREATER,
+            "GREATER_EQUAL" => TokenType::GREATER_EQUAL,
+            "LESS" => TokenType::LESS,
+            "LESS_EQUAL" => TokenType::LESS_EQUAL,
+            "LESS_LESS" => TokenType::LESS_LESS,
+            "GREATER_GREATER" => TokenType::GREATER_GREATER,
+            "ARROW" => TokenType::ARROW,
+            "DOT_DOT" => TokenType::DOT_DOT,
+            "DOT_DOT_EQUAL" => TokenType::DOT_DOT_EQUAL,
+            "DOT_DOT_DOT" => TokenType::DOT_DOT_DOT,
+            "QUESTION_QUESTION" => TokenType::QUESTION_QUESTION,
+            "IDENTIFIER" => TokenType::IDENTIFIER,
+            "STRING" => TokenType::STRING,
+            "NUMBER" => TokenType::NUMBER,
+            "DOC_COMMENT" => TokenType::DOC_COMMENT,
+            "COMMENT" => TokenType::COMMENT,
+            "AND" => TokenType::AND,
+            "CLASS" => TokenType::CLASS,
+            "ELSE" => TokenType::ELSE,
+            "FALSE" => TokenType::FALSE,
+            "FUN" => TokenType::FUN,
+            "FOR" => TokenType::FOR,
+            "IF" => TokenType::IF,
+            "NIL" => TokenType::NIL,
+            "OR" => TokenType::OR,
+            "PRINT" => TokenType::PRINT,
+            "RETURN" => TokenType::RETURN,
+            "SUPER" => TokenType::SUPER,
+            "THIS" => TokenType::THIS,
+            "TRUE" => TokenType::TRUE,
+            "VAR" => TokenType::VAR,
+            "WHILE" => TokenType::WHILE,
+            "TRY" => TokenType::TRY,
+            "CATCH" => TokenType::CATCH,
+            "FINALLY" => TokenType::FINALLY,
+            "THROW" => TokenType::THROW,
+            "IMPORT" => TokenType::IMPORT,
+            "AS" => TokenType::AS,
+            "IS" => TokenType::IS,
+            "MATCH" => TokenType::MATCH,
+            "IN" => TokenType::IN,
+            "CONST" => TokenType::CONST,
+            "DEFER" => TokenType::DEFER,
+            "BREAK" => TokenType::BREAK,
+            "CONTINUE" => TokenType::CONTINUE,
+            "PUB" => TokenType::PUB,
+            "EOF" => TokenType::EOF,
+            _ => return None,
+        };
+        Some(token_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
+    Int(i64),
     Number(f64),
-    String(String),
+    String(Rc<str>),
     Nil,
     Bool(bool),
 }
 
+/// A half-open range of offsets into the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: i32,
+    pub column: i32,
+    pub span: Span,
     pub literal: Literal,
 }
 
+/// Tokens are equal when their type, lexeme and literal are; where they
+/// appear in the source is ignored, so a program reads the same however it
+/// is laid out. Numbers and strings compare by value alone, so `0xff` and
+/// `255` are the same token.
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        let literal = matches!(self.token_type, TokenType::NUMBER | TokenType::STRING);
+        self.token_type == other.token_type
+            && (literal || self.lexeme == other.lexeme)
+            && self.literal == other.literal
+    }
+}
+
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Number(n) => write!(f, "{}", n),
+            Literal::Int(n) => write!(f, "{}", n),
+            Literal::Number(n) => write!(f, "{:?}", n),
             Literal::String(s) => write!(f, "{}", s),
             Literal::Bool(b) => write!(f, "{}", b),
             Literal::Nil => write!(f, "nil"),
@@ -81,7 +226,7 @@ impl Display for Literal {
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.literal {
-            Literal::Number(_n) => Display::fmt(&self.literal, f),
+            Literal::Int(_) | Literal::Number(_) => Display::fmt(&self.literal, f),
             Literal::Nil => match self.token_type {
                 TokenType::PLUS => write!(f, "+"),
                 TokenType::STAR => write!(f, "*"),
@@ -101,11 +246,20 @@ impl Display for Token {
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: i32, literal: Literal) -> Token {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        line: i32,
+        column: i32,
+        span: Span,
+        literal: Literal,
+    ) -> Token {
         Token {
             token_type,
             lexeme,
             line,
+            column,
+            span,
             literal,
         }
     }
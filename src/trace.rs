@@ -0,0 +1,174 @@
+//! Recording an execution to a file and reading it back for replay.
+//!
+//! A trace has one JSON object per line. It starts with a `Start` event
+//! naming the script, followed by a `Statement` event before each statement
+//! runs and a `Write` or `Call` event for every variable write and function
+//! call that statement makes. Values are stored as they print, so anything
+//! can be recorded, including sockets and iterators.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    arena::Arena,
+    ast_json::{node, JsonReader},
+    diagnostic::json_string,
+    hooks::InterpreterHooks,
+    statement::Stmt,
+    value::Value,
+};
+
+/// Bumped whenever the shape of the events changes.
+pub const TRACE_VERSION: u32 = 1;
+
+/// Hooks that append every statement, variable write and call to a trace
+/// file. The file is flushed when the recorder is dropped.
+pub struct TraceRecorder {
+    out: BufWriter<File>,
+}
+
+impl TraceRecorder {
+    /// Creates the trace file at `path` for a run of `script`.
+    pub fn create<P: AsRef<Path>>(path: P, script: &str) -> std::io::Result<TraceRecorder> {
+        let mut recorder = TraceRecorder {
+            out: BufWriter::new(File::create(path)?),
+        };
+        recorder.event(node(
+            "Start",
+            &[
+                ("version", TRACE_VERSION.to_string()),
+                ("script", json_string(script)),
+            ],
+        ));
+        Ok(recorder)
+    }
+
+    // Hooks cannot fail, and a trace cut short by a full disk is still
+    // worth replaying, so write errors are ignored.
+    fn event(&mut self, event: String) {
+        let _ = writeln!(self.out, "{}", event);
+    }
+}
+
+impl InterpreterHooks for TraceRecorder {
+    fn on_statement(&mut self, arena: &Arena, stmt: &Stmt, line: i32) {
+        // A block would only repeat the position of its first statement.
+        if let Stmt::Block(_) = stmt {
+            return;
+        }
+
+        let column = stmt.first_token(arena).map_or(0, |token| token.column);
+        self.event(node(
+            "Statement",
+            &[("line", line.to_string()), ("column", column.to_string())],
+        ));
+    }
+
+    fn on_call(&mut self, name: &str) {
+        self.event(node("Call", &[("name", json_string(name))]));
+    }
+
+    fn on_var_write(&mut self, name: &str, value: &Value) {
+        self.event(node(
+            "Write",
+            &[
+                ("name", json_string(name)),
+                ("value", json_string(&value.to_string())),
+            ],
+        ));
+    }
+}
+
+/// One executed statement and what it did.
+#[derive(Debug, Clone, Default)]
+pub struct TraceStep {
+    pub line: i32,
+    pub column: i32,
+    /// Variables written, with the values as they print, in order.
+    pub writes: Vec<(String, String)>,
+    /// Functions called, in order.
+    pub calls: Vec<String>,
+}
+
+/// A trace read back from a file written by `TraceRecorder`.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    /// The script that was recorded, as it was given on the command line.
+    pub script: String,
+    pub steps: Vec<TraceStep>,
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Trace, String> {
+        let mut trace = Trace::default();
+
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            trace
+                .apply(line)
+                .map_err(|message| format!("Line {} of the trace: {}", index + 1, message))?;
+        }
+
+        Ok(trace)
+    }
+
+    fn apply(&mut self, line: &str) -> Result<(), String> {
+        let event = JsonReader::new(line).document()?;
+        let kind = event.get("kind")?.as_str()?;
+
+        match (kind, self.steps.last_mut()) {
+            ("Start", _) => {
+                let version = event.get("version")?.as_number()?;
+                if version != TRACE_VERSION as f64 {
+                    return Err(format!(
+                        "Unsupported trace version {} (expected {})",
+                        version, TRACE_VERSION
+                    ));
+                }
+                self.script = event.get("script")?.as_str()?.to_string();
+            }
+            ("Statement", _) => self.steps.push(TraceStep {
+                line: event.get("line")?.as_number()? as i32,
+                column: event.get("column")?.as_number()? as i32,
+                ..TraceStep::default()
+            }),
+            ("Write", Some(step)) => step.writes.push((
+                event.get("name")?.as_str()?.to_string(),
+                event.get("value")?.as_str()?.to_string(),
+            )),
+            ("Call", Some(step)) => step.calls.push(event.get("name")?.as_str()?.to_string()),
+            ("Write" | "Call", None) => {
+                return Err(format!("'{}' before the first statement", kind))
+            }
+            (kind, _) => return Err(format!("Unknown event '{}'", kind)),
+        }
+
+        Ok(())
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Trace, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read '{}': {}", path.display(), err))?;
+        Trace::parse(&text)
+    }
+
+    /// The last value written to each variable up to and including `step`,
+    /// sorted by name. Variables are told apart by name only, so a local
+    /// that shadows a global shows up as the same variable.
+    pub fn variables_at(&self, step: usize) -> BTreeMap<&str, &str> {
+        let mut variables = BTreeMap::new();
+        for step in self.steps.iter().take(step + 1) {
+            for (name, value) in step.writes.iter() {
+                variables.insert(name.as_str(), value.as_str());
+            }
+        }
+        variables
+    }
+}
@@ -0,0 +1,453 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    arena::{Arena, Program},
+    error::Log,
+    expression::Expr,
+    parser::ParseError,
+    statement::{Pattern, Stmt},
+    token::{Literal, Token, TokenType},
+};
+
+/// The static type of an expression. `Any` is used wherever the checker
+/// cannot tell, so unannotated code is never rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Number,
+    String,
+    Bool,
+    Nil,
+    List,
+    Tuple,
+    Range,
+    Iterator,
+    Function,
+    Module,
+    Socket,
+    Any,
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "number" => Some(Type::Number),
+            "string" => Some(Type::String),
+            "bool" => Some(Type::Bool),
+            "nil" => Some(Type::Nil),
+            "list" => Some(Type::List),
+            "tuple" => Some(Type::Tuple),
+            "range" => Some(Type::Range),
+            "iterator" => Some(Type::Iterator),
+            "function" => Some(Type::Function),
+            "module" => Some(Type::Module),
+            "socket" => Some(Type::Socket),
+            "any" => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    /// Whether a value of type `other` may be stored where `self` is expected.
+    /// An int is accepted wherever a number is.
+    fn accepts(self, other: Type) -> bool {
+        self == Type::Any
+            || other == Type::Any
+            || self == other
+            || (self == Type::Number && other == Type::Int)
+    }
+
+    /// The type of `+`, `-`, `*` or `/` on these operands: an int when both
+    /// are ints, and a number once either is a number.
+    fn arithmetic(left: Type, right: Type) -> Type {
+        match (left, right) {
+            (Type::Int, Type::Int) => Type::Int,
+            (Type::Any, _) | (_, Type::Any) => Type::Any,
+            _ => Type::Number,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Type::Int => "int",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Bool => "bool",
+            Type::Nil => "nil",
+            Type::List => "list",
+            Type::Tuple => "tuple",
+            Type::Range => "range",
+            Type::Iterator => "iterator",
+            Type::Function => "function",
+            Type::Module => "module",
+            Type::Socket => "socket",
+            Type::Any => "any",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Validates type annotations such as `var x: number = 5;` without running
+/// the program. Annotations have no effect at runtime. In strict mode it
+/// also rejects comparing values whose types are known to differ.
+pub struct TypeChecker<'a> {
+    scopes: Vec<HashMap<String, Type>>,
+    errors: Vec<ParseError>,
+    file: Option<String>,
+    logger: &'a Log,
+    strict: bool,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(file: Option<String>, logger: &'a Log) -> TypeChecker<'a> {
+        TypeChecker {
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+            file,
+            logger,
+            strict: false,
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> TypeChecker<'a> {
+        self.strict = strict;
+        self
+    }
+
+    pub fn check(&mut self, program: &Program) -> Result<(), Vec<ParseError>> {
+        for stmt in &program.stmts {
+            self.check_stmt(&program.arena, stmt);
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn check_stmt(&mut self, arena: &Arena, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.infer(arena, expr);
+            }
+            Stmt::Print(_keyword, exprs) => {
+                for expr in exprs {
+                    self.infer(arena, expr);
+                }
+            }
+            Stmt::Var(Pattern::Name(name, _), annotation, initializer) => {
+                let declared = self.annotated_type(annotation);
+                if let Some(initializer) = initializer {
+                    let actual = self.infer(arena, initializer);
+                    self.expect_assignable(name, declared, actual);
+                }
+                self.declare(name, declared);
+            }
+            Stmt::Var(pattern, _annotation, initializer) => {
+                if let Some(initializer) = initializer {
+                    let actual = self.infer(arena, initializer);
+                    self.expect_tuple(pattern.first_token(), actual);
+                }
+                self.declare_pattern(pattern, Type::Any);
+            }
+            Stmt::Const(name, _, annotation, initializer) => {
+                let declared = self.annotated_type(annotation);
+                let actual = self.infer(arena, initializer);
+                self.expect_assignable(name, declared, actual);
+                self.declare(name, declared);
+            }
+            Stmt::Block(stmts) => self.check_block(arena, stmts),
+            Stmt::If(_keyword, condition, then_branch, else_branch) => {
+                self.infer(arena, condition);
+                self.check_stmt(arena, &arena[*then_branch]);
+                if let Some(else_branch) = else_branch {
+                    self.check_stmt(arena, &arena[*else_branch]);
+                }
+            }
+            Stmt::While(_keyword, _label, condition, body, increment) => {
+                self.infer(arena, condition);
+                self.check_stmt(arena, &arena[*body]);
+                if let Some(increment) = increment {
+                    self.infer(arena, increment);
+                }
+            }
+            Stmt::ForEach(keyword, _label, pattern, iterable, body) => {
+                let item = match self.infer(arena, iterable) {
+                    Type::String => Type::String,
+                    Type::Range => Type::Int,
+                    Type::List | Type::Tuple | Type::Iterator | Type::Any => Type::Any,
+                    other => {
+                        self.error(keyword, format!("Cannot iterate over a {}.", other));
+                        Type::Any
+                    }
+                };
+                if let Pattern::Tuple(..) = pattern {
+                    self.expect_tuple(pattern.first_token(), item);
+                }
+
+                self.scopes.push(HashMap::new());
+                self.declare_pattern(pattern, item);
+                self.check_stmt(arena, &arena[*body]);
+                self.scopes.pop();
+            }
+            Stmt::Break(_keyword, _label) | Stmt::Continue(_keyword, _label) => {}
+            Stmt::Throw(_keyword, expr) => {
+                self.infer(arena, expr);
+            }
+            Stmt::Defer(_keyword, stmt) => self.check_stmt(arena, &arena[*stmt]),
+            Stmt::Try(_keyword, try_block, catch_clause, finally_block) => {
+                self.check_block(arena, try_block);
+                if let Some((name, catch_block)) = catch_clause {
+                    self.scopes.push(HashMap::new());
+                    self.declare(name, Type::Any);
+                    self.check_block(arena, catch_block);
+                    self.scopes.pop();
+                }
+                if let Some(finally_block) = finally_block {
+                    self.check_block(arena, finally_block);
+                }
+            }
+            Stmt::Import(_keyword, _path, alias) => {
+                if let Some(alias) = alias {
+                    self.declare(alias, Type::Module);
+                }
+            }
+            Stmt::Pub(_keyword, stmt) => self.check_stmt(arena, &arena[*stmt]),
+            Stmt::Match(_keyword, subject, arms, default) => {
+                self.infer(arena, subject);
+                for (patterns, body) in arms {
+                    for pattern in patterns {
+                        self.infer(arena, pattern);
+                    }
+                    self.check_stmt(arena, body);
+                }
+                if let Some(default) = default {
+                    self.check_stmt(arena, &arena[*default]);
+                }
+            }
+        }
+    }
+
+    fn check_block(&mut self, arena: &Arena, stmts: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.check_stmt(arena, stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn infer(&mut self, arena: &Arena, expr: &Expr) -> Type {
+        match expr {
+            Expr::Literal(_token, literal) => match literal {
+                Literal::Int(_) => Type::Int,
+                Literal::Number(_) => Type::Number,
+                Literal::String(_) => Type::String,
+                Literal::Bool(_) => Type::Bool,
+                Literal::Nil => Type::Nil,
+            },
+            Expr::Grouping(inner) => self.infer(arena, &arena[*inner]),
+            Expr::Unary(op, right) => {
+                let right = self.infer(arena, &arena[*right]);
+                match op.token_type {
+                    TokenType::MINUS => {
+                        self.expect_number(op, right);
+                        Type::arithmetic(right, right)
+                    }
+                    TokenType::TILDE => {
+                        self.expect_int(op, right);
+                        Type::Int
+                    }
+                    _ => Type::Bool,
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                let left = self.infer(arena, &arena[*left]);
+                let right = self.infer(arena, &arena[*right]);
+                match op.token_type {
+                    TokenType::PLUS | TokenType::MINUS | TokenType::STAR | TokenType::SLASH => {
+                        self.expect_number(op, left);
+                        self.expect_number(op, right);
+                        Type::arithmetic(left, right)
+                    }
+                    TokenType::AMPERSAND
+                    | TokenType::PIPE
+                    | TokenType::CARET
+                    | TokenType::LESS_LESS
+                    | TokenType::GREATER_GREATER => {
+                        self.expect_int(op, left);
+                        self.expect_int(op, right);
+                        Type::Int
+                    }
+                    TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL => {
+                        self.expect_number(op, left);
+                        self.expect_number(op, right);
+                        Type::Range
+                    }
+                    TokenType::EQUAL_EQUAL
+                    | TokenType::BANG_EQUAL
+                    | TokenType::GREATER
+                    | TokenType::GREATER_EQUAL
+                    | TokenType::LESS
+                    | TokenType::LESS_EQUAL => {
+                        // Anything may be checked against nil for equality.
+                        let nil_check = matches!(
+                            op.token_type,
+                            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL
+                        ) && (left == Type::Nil || right == Type::Nil);
+                        if self.strict && !nil_check && !left.accepts(right) && !right.accepts(left)
+                        {
+                            self.error(
+                                op,
+                                format!(
+                                    "Comparing values of different types, {} and {}.",
+                                    left, right
+                                ),
+                            );
+                        }
+                        Type::Bool
+                    }
+                    _ => Type::Bool,
+                }
+            }
+            Expr::Logical(left, op, right) => {
+                let left = self.infer(arena, &arena[*left]);
+                let right = self.infer(arena, &arena[*right]);
+                if op.token_type == TokenType::QUESTION_QUESTION && left == Type::Nil {
+                    right
+                } else if left == right {
+                    left
+                } else {
+                    Type::Any
+                }
+            }
+            Expr::Var(name, _) => self.lookup(&name.lexeme),
+            Expr::Assign(name, value, _) => {
+                let actual = self.infer(arena, &arena[*value]);
+                let declared = self.lookup(&name.lexeme);
+                self.expect_assignable(name, declared, actual);
+                actual
+            }
+            Expr::Call(callee, _paren, args) => {
+                self.infer(arena, &arena[*callee]);
+                for arg in args {
+                    self.infer(arena, arg);
+                }
+                Type::Any
+            }
+            Expr::Get(object, _name) => {
+                self.infer(arena, &arena[*object]);
+                Type::Any
+            }
+            Expr::Tuple(_open, items, _close) => {
+                for item in items {
+                    self.infer(arena, item);
+                }
+                Type::Tuple
+            }
+            Expr::Spread(op, expr) => {
+                let actual = self.infer(arena, &arena[*expr]);
+                if !matches!(
+                    actual,
+                    Type::List
+                        | Type::Tuple
+                        | Type::String
+                        | Type::Range
+                        | Type::Iterator
+                        | Type::Any
+                ) {
+                    self.error(op, format!("Cannot spread a {}.", actual));
+                }
+                Type::Any
+            }
+        }
+    }
+
+    fn annotated_type(&mut self, annotation: &Option<Token>) -> Type {
+        match annotation {
+            Some(name) => match Type::from_name(&name.lexeme) {
+                Some(ty) => ty,
+                None => {
+                    self.error(name, format!("Unknown type '{}'.", name.lexeme));
+                    Type::Any
+                }
+            },
+            None => Type::Any,
+        }
+    }
+
+    fn expect_assignable(&mut self, name: &Token, declared: Type, actual: Type) {
+        if !declared.accepts(actual) {
+            self.error(
+                name,
+                format!(
+                    "'{}' is declared as {} but is given a {}.",
+                    name.lexeme, declared, actual
+                ),
+            );
+        }
+    }
+
+    fn expect_number(&mut self, op: &Token, actual: Type) {
+        if !Type::Number.accepts(actual) {
+            self.error(
+                op,
+                format!(
+                    "Operator '{}' expects numbers, got a {}.",
+                    op.lexeme, actual
+                ),
+            );
+        }
+    }
+
+    fn expect_int(&mut self, op: &Token, actual: Type) {
+        if !Type::Int.accepts(actual) {
+            self.error(
+                op,
+                format!("Operator '{}' expects ints, got a {}.", op.lexeme, actual),
+            );
+        }
+    }
+
+    fn expect_tuple(&mut self, token: &Token, actual: Type) {
+        if !Type::Tuple.accepts(actual) {
+            self.error(token, format!("Cannot destructure a {}.", actual));
+        }
+    }
+
+    /// Declares a lone name with `ty`; names inside a tuple pattern are untyped.
+    fn declare_pattern(&mut self, pattern: &Pattern, ty: Type) {
+        match pattern {
+            Pattern::Name(name, _) => self.declare(name, ty),
+            Pattern::Tuple(..) => {
+                for name in pattern.names() {
+                    self.declare(name, Type::Any);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, ty: Type) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.clone(), ty);
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .unwrap_or(Type::Any)
+    }
+
+    fn error(&mut self, token: &Token, message: String) {
+        let message = format!("Type error at line {}: {}", token.line, message);
+        let err = ParseError::Type(message, token.clone());
+        self.logger.report(&err.to_diagnostic(self.file.clone()));
+        self.errors.push(err);
+    }
+}
@@ -0,0 +1,303 @@
+use std::{cell::RefCell, cmp::Ordering, fmt::Display, ops::RangeInclusive, rc::Rc};
+
+use crate::{
+    module::Module,
+    native::{HostFn, NativeFn},
+    socket::Socket,
+    token::Literal,
+};
+
+/// A runtime value. Cloning one never copies string or list contents.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    /// A 64-bit integer. Arithmetic on two ints stays exact and fails
+    /// rather than wrapping on overflow.
+    Int(i64),
+    /// A double-precision float.
+    Number(f64),
+    String(Rc<str>),
+    Native(NativeFn),
+    /// A function registered by the embedding program.
+    Host(Rc<HostFn>),
+    Module(Rc<Module>),
+    List(Rc<Vec<Value>>),
+    /// A fixed-size group of values, such as `(1, "a")`.
+    Tuple(Rc<Vec<Value>>),
+    /// Integers from `start` up to `end`, which is included when the flag
+    /// is set, as for `start..=end`.
+    Range(i64, i64, bool),
+    /// A lazy, shared cursor over a collection. Iterating it consumes it.
+    Iterator(Rc<RefCell<ValueIter>>),
+    /// A network connection, listener or UDP socket, opened by a native.
+    Socket(Rc<Socket>),
+}
+
+impl Value {
+    /// Value equality, as used by `==`, `!=` and `match`. Nil, bools,
+    /// numbers, strings and ranges compare by value, and lists and tuples
+    /// item by item, since none of them can change. Functions, modules,
+    /// iterators and sockets are equal only to themselves. An int equals a
+    /// number with the same value; values of other different types are
+    /// never equal.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Int(a), Value::Number(b)) | (Value::Number(b), Value::Int(a)) => {
+                int_equals_number(*a, *b)
+            }
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Native(a), Value::Native(b)) => a.name == b.name,
+            (Value::Host(a), Value::Host(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                Rc::ptr_eq(a, b)
+                    || (a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b)))
+            }
+            (Value::Range(..), Value::Range(..)) => self.range_items() == other.range_items(),
+            (Value::Iterator(a), Value::Iterator(b)) => Rc::ptr_eq(a, b),
+            (Value::Socket(a), Value::Socket(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Identity, for `is_same`: strings, lists and tuples are the same only
+    /// when they are one object, not merely equal, while values that live
+    /// on no heap, such as numbers and ranges, are the same when equal.
+    pub fn is_same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                Rc::ptr_eq(a, b)
+            }
+            (Value::Int(_), Value::Number(_)) | (Value::Number(_), Value::Int(_)) => false,
+            _ => self.equals(other),
+        }
+    }
+
+    /// A copy that shares no strings, lists or tuples with this value, at
+    /// any depth. Functions, modules, iterators and sockets are shared, as
+    /// there is no meaningful copy of them.
+    pub fn deep_clone(&self) -> Value {
+        match self {
+            Value::String(s) => Value::String(Rc::from(&**s)),
+            Value::List(items) => {
+                Value::List(Rc::new(items.iter().map(Value::deep_clone).collect()))
+            }
+            Value::Tuple(items) => {
+                Value::Tuple(Rc::new(items.iter().map(Value::deep_clone).collect()))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Whether the value counts as true in a condition, for `!`, `and` and
+    /// `or`. As in Lox, only `nil` and `false` are false; `0`, `""` and
+    /// empty collections are all true.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The value of an int or a number as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The value of an int, or of a number without a fractional part that
+    /// fits in an int.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    /// An iterator over the elements of a collection, or `None` when the
+    /// value cannot be iterated.
+    pub fn iter(&self) -> Option<ValueIter> {
+        match self {
+            Value::List(items) | Value::Tuple(items) => Some(ValueIter::List(items.clone(), 0)),
+            Value::String(s) => Some(ValueIter::Chars(s.clone(), 0)),
+            Value::Range(..) => self.range_items().map(ValueIter::Range),
+            Value::Iterator(iter) => Some(ValueIter::Shared(iter.clone())),
+            _ => None,
+        }
+    }
+
+    /// The first and last int of a range, or `None` for other values. An
+    /// empty range gives an empty `RangeInclusive`.
+    pub(crate) fn range_items(&self) -> Option<RangeInclusive<i64>> {
+        match *self {
+            Value::Range(start, end, true) => Some(start..=end),
+            Value::Range(start, end, false) => match end.checked_sub(1) {
+                Some(last) => Some(start..=last),
+                // Nothing comes before i64::MIN.
+                None => Some(RangeInclusive::new(1, 0)),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Native(_) | Value::Host(_) => "function",
+            Value::Module(_) => "module",
+            Value::List(_) => "list",
+            Value::Tuple(_) => "tuple",
+            Value::Range(..) => "range",
+            Value::Iterator(_) => "iterator",
+            Value::Socket(_) => "socket",
+        }
+    }
+}
+
+/// Whether `n` is exactly `int`.
+fn int_equals_number(int: i64, n: f64) -> bool {
+    compare_int_number(int, n) == Some(Ordering::Equal)
+}
+
+/// How `int` orders against `n`, or `None` when `n` is NaN. Converting the
+/// int to a float would round ints past 2^53, so the number's whole part is
+/// converted instead when it is in range, and its fractional part breaks
+/// a tie.
+pub(crate) fn compare_int_number(int: i64, n: f64) -> Option<Ordering> {
+    // -2^63 is i64::MIN, while 2^63 is one past i64::MAX.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if n.is_nan() {
+        None
+    } else if n >= LIMIT {
+        Some(Ordering::Less)
+    } else if n < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let fract = n.fract();
+        Some(
+            int.cmp(&(n.trunc() as i64))
+                .then(0.0_f64.partial_cmp(&fract)?),
+        )
+    }
+}
+
+/// The iteration protocol behind `for (item in collection)`.
+#[derive(Debug)]
+pub enum ValueIter {
+    List(Rc<Vec<Value>>, usize),
+    Chars(Rc<str>, usize),
+    Range(RangeInclusive<i64>),
+    /// Advances an iterator value, so progress is visible to every holder.
+    Shared(Rc<RefCell<ValueIter>>),
+    /// Pairs up items until either side runs out.
+    Zip(Box<ValueIter>, Box<ValueIter>),
+    /// Pairs each item with its position.
+    Enumerate(Box<ValueIter>, usize),
+}
+
+impl Iterator for ValueIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            ValueIter::List(items, index) => {
+                let item = items.get(*index)?.clone();
+                *index += 1;
+                Some(item)
+            }
+            // The index is a byte offset, advanced one character at a time.
+            ValueIter::Chars(s, offset) => {
+                let c = s[*offset..].chars().next()?;
+                *offset += c.len_utf8();
+                Some(Value::String(c.to_string().into()))
+            }
+            ValueIter::Range(range) => range.next().map(Value::Int),
+            ValueIter::Shared(iter) => iter.borrow_mut().next(),
+            ValueIter::Zip(left, right) => {
+                let pair = vec![left.next()?, right.next()?];
+                Some(Value::Tuple(Rc::new(pair)))
+            }
+            ValueIter::Enumerate(items, index) => {
+                let pair = vec![Value::Int(*index as i64), items.next()?];
+                *index += 1;
+                Some(Value::Tuple(Rc::new(pair)))
+            }
+        }
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Int(n) => Value::Int(*n),
+            Literal::Number(n) => Value::Number(*n),
+            Literal::String(s) => Value::String(s.clone()),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Int(x) => write!(f, "{}", x),
+            // Always with a decimal point or exponent, so `7.0` never looks
+            // like the int `7`.
+            Value::Number(x) => write!(f, "{:?}", x),
+            Value::Nil => write!(f, "nil"),
+            Value::String(x) => write!(f, "{}", x),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::Host(host) => write!(f, "<native fn {}>", host.name),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_nested(item, f)?;
+                }
+                write!(f, "]")
+            }
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_nested(item, f)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Range(start, end, false) => write!(f, "{}..{}", start, end),
+            Value::Range(start, end, true) => write!(f, "{}..={}", start, end),
+            Value::Iterator(_) => write!(f, "<iterator>"),
+            Value::Socket(socket) => write!(f, "<{} {}>", socket.kind(), socket.address()),
+        }
+    }
+}
+
+/// Writes a value that sits inside a collection. Strings are quoted there,
+/// so that `[1, "1"]` does not print the same as `[1, 1]`.
+fn fmt_nested(value: &Value, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "\"{}\"", s),
+        value => write!(f, "{}", value),
+    }
+}
@@ -0,0 +1,1276 @@
+//! Visitor traits for walking the AST without matching on every variant.
+//!
+//! `ExprVisitor` and `StmtVisitor` have one method per variant, which
+//! receives the arena the program's nodes live in and the variant's
+//! fields, with nested nodes already looked up. `Expr::accept` and
+//! `Stmt::accept` call the right one. A visitor chooses what each node
+//! produces: the interpreter evaluates to values, a linter might return
+//! nothing and collect its findings as it goes. The `Mut` twins hand out
+//! mutable children, for passes like the resolver that annotate the tree;
+//! nested nodes come as ids, to visit with `Arena::accept_expr_mut` and
+//! `Arena::accept_stmt_mut`.
+//!
+//! Visitors that return nothing and only care about some of the variants
+//! implement `Walk` or `WalkMut` instead: every method defaults to visiting
+//! the node's children. `walk_expr` and `walk_stmt`, and their `_mut`
+//! twins, visit the children of a whole node in the same way.
+
+use crate::{
+    arena::{Arena, ExprId, StmtId},
+    expression::{Expr, Local},
+    statement::{Pattern, Stmt},
+    token::{Literal, Token},
+};
+
+pub trait ExprVisitor<R> {
+    fn visit_unary_expr(&mut self, arena: &Arena, op: &Token, right: &Expr) -> R;
+    fn visit_binary_expr(&mut self, arena: &Arena, left: &Expr, op: &Token, right: &Expr) -> R;
+    fn visit_logical_expr(&mut self, arena: &Arena, left: &Expr, op: &Token, right: &Expr) -> R;
+    fn visit_grouping_expr(&mut self, arena: &Arena, inner: &Expr) -> R;
+    fn visit_literal_expr(&mut self, arena: &Arena, token: &Token, literal: &Literal) -> R;
+    fn visit_var_expr(&mut self, arena: &Arena, name: &Token, local: Option<Local>) -> R;
+    fn visit_assign_expr(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        value: &Expr,
+        local: Option<Local>,
+    ) -> R;
+    fn visit_call_expr(&mut self, arena: &Arena, callee: &Expr, paren: &Token, args: &[Expr]) -> R;
+    fn visit_get_expr(&mut self, arena: &Arena, object: &Expr, name: &Token) -> R;
+    fn visit_tuple_expr(&mut self, arena: &Arena, open: &Token, items: &[Expr], close: &Token)
+        -> R;
+    fn visit_spread_expr(&mut self, arena: &Arena, op: &Token, expr: &Expr) -> R;
+}
+
+pub trait StmtVisitor<R> {
+    fn visit_print_stmt(&mut self, arena: &Arena, keyword: &Token, values: &[Expr]) -> R;
+    fn visit_expression_stmt(&mut self, arena: &Arena, expr: &Expr) -> R;
+    fn visit_var_stmt(
+        &mut self,
+        arena: &Arena,
+        pattern: &Pattern,
+        annotation: Option<&Token>,
+        initializer: Option<&Expr>,
+    ) -> R;
+    fn visit_const_stmt(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        slot: Option<usize>,
+        annotation: Option<&Token>,
+        initializer: &Expr,
+    ) -> R;
+    fn visit_block_stmt(&mut self, arena: &Arena, stmts: &[Stmt]) -> R;
+    fn visit_if_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> R;
+    fn visit_while_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> R;
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> R;
+    fn visit_break_stmt(&mut self, arena: &Arena, keyword: &Token, label: Option<&Token>) -> R;
+    fn visit_continue_stmt(&mut self, arena: &Arena, keyword: &Token, label: Option<&Token>) -> R;
+    fn visit_throw_stmt(&mut self, arena: &Arena, keyword: &Token, value: &Expr) -> R;
+    fn visit_defer_stmt(&mut self, arena: &Arena, keyword: &Token, stmt: &Stmt) -> R;
+    fn visit_try_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        try_block: &[Stmt],
+        catch_clause: Option<(&Token, &[Stmt])>,
+        finally_block: Option<&[Stmt]>,
+    ) -> R;
+    fn visit_import_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        path: &str,
+        alias: Option<&Token>,
+    ) -> R;
+    fn visit_pub_stmt(&mut self, arena: &Arena, keyword: &Token, stmt: &Stmt) -> R;
+    fn visit_match_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        subject: &Expr,
+        arms: &[(Vec<Expr>, Stmt)],
+        default: Option<&Stmt>,
+    ) -> R;
+}
+
+/// Like `ExprVisitor`, with mutable access to the children.
+pub trait ExprVisitorMut<R> {
+    fn visit_unary_expr(&mut self, arena: &mut Arena, op: &mut Token, right: ExprId) -> R;
+    fn visit_binary_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) -> R;
+    fn visit_logical_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) -> R;
+    fn visit_grouping_expr(&mut self, arena: &mut Arena, inner: ExprId) -> R;
+    fn visit_literal_expr(
+        &mut self,
+        arena: &mut Arena,
+        token: &mut Token,
+        literal: &mut Literal,
+    ) -> R;
+    fn visit_var_expr(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        local: &mut Option<Local>,
+    ) -> R;
+    fn visit_assign_expr(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        value: ExprId,
+        local: &mut Option<Local>,
+    ) -> R;
+    fn visit_call_expr(
+        &mut self,
+        arena: &mut Arena,
+        callee: ExprId,
+        paren: &mut Token,
+        args: &mut [Expr],
+    ) -> R;
+    fn visit_get_expr(&mut self, arena: &mut Arena, object: ExprId, name: &mut Token) -> R;
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &mut Arena,
+        open: &mut Token,
+        items: &mut [Expr],
+        close: &mut Token,
+    ) -> R;
+    fn visit_spread_expr(&mut self, arena: &mut Arena, op: &mut Token, expr: ExprId) -> R;
+}
+
+/// Like `StmtVisitor`, with mutable access to the children.
+pub trait StmtVisitorMut<R> {
+    fn visit_print_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        values: &mut [Expr],
+    ) -> R;
+    fn visit_expression_stmt(&mut self, arena: &mut Arena, expr: &mut Expr) -> R;
+    fn visit_var_stmt(
+        &mut self,
+        arena: &mut Arena,
+        pattern: &mut Pattern,
+        annotation: Option<&mut Token>,
+        initializer: Option<&mut Expr>,
+    ) -> R;
+    fn visit_const_stmt(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        slot: &mut Option<usize>,
+        annotation: Option<&mut Token>,
+        initializer: &mut Expr,
+    ) -> R;
+    fn visit_block_stmt(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) -> R;
+    fn visit_if_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        condition: &mut Expr,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) -> R;
+    fn visit_while_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        condition: &mut Expr,
+        body: StmtId,
+        increment: Option<&mut Expr>,
+    ) -> R;
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        pattern: &mut Pattern,
+        iterable: &mut Expr,
+        body: StmtId,
+    ) -> R;
+    fn visit_break_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) -> R;
+    fn visit_continue_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) -> R;
+    fn visit_throw_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, value: &mut Expr) -> R;
+    fn visit_defer_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) -> R;
+    fn visit_try_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        try_block: &mut [Stmt],
+        catch_clause: Option<(&mut Token, &mut [Stmt])>,
+        finally_block: Option<&mut [Stmt]>,
+    ) -> R;
+    fn visit_import_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        path: &str,
+        alias: Option<&mut Token>,
+    ) -> R;
+    fn visit_pub_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) -> R;
+    fn visit_match_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        subject: &mut Expr,
+        arms: &mut [(Vec<Expr>, Stmt)],
+        default: Option<StmtId>,
+    ) -> R;
+}
+
+/// A visitor that returns nothing and, unless told otherwise, walks into
+/// every child. A linter or a search only implements the methods for the
+/// variants it cares about, and calls `Expr::accept`, `Stmt::accept` or
+/// `walk_expr`/`walk_stmt` to go on into the children of those. Anything
+/// that implements `Walk` is an `ExprVisitor<()>` and a `StmtVisitor<()>`.
+pub trait Walk {
+    fn visit_unary_expr(&mut self, arena: &Arena, _op: &Token, right: &Expr) {
+        right.accept(arena, self);
+    }
+
+    fn visit_binary_expr(&mut self, arena: &Arena, left: &Expr, _op: &Token, right: &Expr) {
+        left.accept(arena, self);
+        right.accept(arena, self);
+    }
+
+    fn visit_logical_expr(&mut self, arena: &Arena, left: &Expr, _op: &Token, right: &Expr) {
+        left.accept(arena, self);
+        right.accept(arena, self);
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &Arena, inner: &Expr) {
+        inner.accept(arena, self);
+    }
+
+    fn visit_literal_expr(&mut self, _arena: &Arena, _token: &Token, _literal: &Literal) {}
+
+    fn visit_var_expr(&mut self, _arena: &Arena, _name: &Token, _local: Option<Local>) {}
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &Arena,
+        _name: &Token,
+        value: &Expr,
+        _local: Option<Local>,
+    ) {
+        value.accept(arena, self);
+    }
+
+    fn visit_call_expr(&mut self, arena: &Arena, callee: &Expr, _paren: &Token, args: &[Expr]) {
+        callee.accept(arena, self);
+        args.iter().for_each(|arg| arg.accept(arena, self));
+    }
+
+    fn visit_get_expr(&mut self, arena: &Arena, object: &Expr, _name: &Token) {
+        object.accept(arena, self);
+    }
+
+    fn visit_tuple_expr(&mut self, arena: &Arena, _open: &Token, items: &[Expr], _close: &Token) {
+        items.iter().for_each(|item| item.accept(arena, self));
+    }
+
+    fn visit_spread_expr(&mut self, arena: &Arena, _op: &Token, expr: &Expr) {
+        expr.accept(arena, self);
+    }
+
+    fn visit_print_stmt(&mut self, arena: &Arena, _keyword: &Token, values: &[Expr]) {
+        values.iter().for_each(|value| value.accept(arena, self));
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &Arena, expr: &Expr) {
+        expr.accept(arena, self);
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &Arena,
+        _pattern: &Pattern,
+        _annotation: Option<&Token>,
+        initializer: Option<&Expr>,
+    ) {
+        if let Some(initializer) = initializer {
+            initializer.accept(arena, self);
+        }
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &Arena,
+        _name: &Token,
+        _slot: Option<usize>,
+        _annotation: Option<&Token>,
+        initializer: &Expr,
+    ) {
+        initializer.accept(arena, self);
+    }
+
+    fn visit_block_stmt(&mut self, arena: &Arena, stmts: &[Stmt]) {
+        stmts.iter().for_each(|stmt| stmt.accept(arena, self));
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        condition.accept(arena, self);
+        then_branch.accept(arena, self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(arena, self);
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        _label: Option<&Token>,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) {
+        condition.accept(arena, self);
+        body.accept(arena, self);
+        if let Some(increment) = increment {
+            increment.accept(arena, self);
+        }
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        _label: Option<&Token>,
+        _pattern: &Pattern,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        iterable.accept(arena, self);
+        body.accept(arena, self);
+    }
+
+    fn visit_break_stmt(&mut self, _arena: &Arena, _keyword: &Token, _label: Option<&Token>) {}
+
+    fn visit_continue_stmt(&mut self, _arena: &Arena, _keyword: &Token, _label: Option<&Token>) {}
+
+    fn visit_throw_stmt(&mut self, arena: &Arena, _keyword: &Token, value: &Expr) {
+        value.accept(arena, self);
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &Arena, _keyword: &Token, stmt: &Stmt) {
+        stmt.accept(arena, self);
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        try_block: &[Stmt],
+        catch_clause: Option<(&Token, &[Stmt])>,
+        finally_block: Option<&[Stmt]>,
+    ) {
+        self.visit_block_stmt(arena, try_block);
+        if let Some((_, catch_block)) = catch_clause {
+            self.visit_block_stmt(arena, catch_block);
+        }
+        if let Some(finally_block) = finally_block {
+            self.visit_block_stmt(arena, finally_block);
+        }
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _arena: &Arena,
+        _keyword: &Token,
+        _path: &str,
+        _alias: Option<&Token>,
+    ) {
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &Arena, _keyword: &Token, stmt: &Stmt) {
+        stmt.accept(arena, self);
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &Arena,
+        _keyword: &Token,
+        subject: &Expr,
+        arms: &[(Vec<Expr>, Stmt)],
+        default: Option<&Stmt>,
+    ) {
+        subject.accept(arena, self);
+        for (patterns, body) in arms {
+            patterns
+                .iter()
+                .for_each(|pattern| pattern.accept(arena, self));
+            body.accept(arena, self);
+        }
+        if let Some(default) = default {
+            default.accept(arena, self);
+        }
+    }
+}
+
+impl<W: Walk + ?Sized> ExprVisitor<()> for W {
+    fn visit_unary_expr(&mut self, arena: &Arena, op: &Token, right: &Expr) {
+        Walk::visit_unary_expr(self, arena, op, right)
+    }
+
+    fn visit_binary_expr(&mut self, arena: &Arena, left: &Expr, op: &Token, right: &Expr) {
+        Walk::visit_binary_expr(self, arena, left, op, right)
+    }
+
+    fn visit_logical_expr(&mut self, arena: &Arena, left: &Expr, op: &Token, right: &Expr) {
+        Walk::visit_logical_expr(self, arena, left, op, right)
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &Arena, inner: &Expr) {
+        Walk::visit_grouping_expr(self, arena, inner)
+    }
+
+    fn visit_literal_expr(&mut self, arena: &Arena, token: &Token, literal: &Literal) {
+        Walk::visit_literal_expr(self, arena, token, literal)
+    }
+
+    fn visit_var_expr(&mut self, arena: &Arena, name: &Token, local: Option<Local>) {
+        Walk::visit_var_expr(self, arena, name, local)
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        value: &Expr,
+        local: Option<Local>,
+    ) {
+        Walk::visit_assign_expr(self, arena, name, value, local)
+    }
+
+    fn visit_call_expr(&mut self, arena: &Arena, callee: &Expr, paren: &Token, args: &[Expr]) {
+        Walk::visit_call_expr(self, arena, callee, paren, args)
+    }
+
+    fn visit_get_expr(&mut self, arena: &Arena, object: &Expr, name: &Token) {
+        Walk::visit_get_expr(self, arena, object, name)
+    }
+
+    fn visit_tuple_expr(&mut self, arena: &Arena, open: &Token, items: &[Expr], close: &Token) {
+        Walk::visit_tuple_expr(self, arena, open, items, close)
+    }
+
+    fn visit_spread_expr(&mut self, arena: &Arena, op: &Token, expr: &Expr) {
+        Walk::visit_spread_expr(self, arena, op, expr)
+    }
+}
+
+impl<W: Walk + ?Sized> StmtVisitor<()> for W {
+    fn visit_print_stmt(&mut self, arena: &Arena, keyword: &Token, values: &[Expr]) {
+        Walk::visit_print_stmt(self, arena, keyword, values)
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &Arena, expr: &Expr) {
+        Walk::visit_expression_stmt(self, arena, expr)
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &Arena,
+        pattern: &Pattern,
+        annotation: Option<&Token>,
+        initializer: Option<&Expr>,
+    ) {
+        Walk::visit_var_stmt(self, arena, pattern, annotation, initializer)
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &Arena,
+        name: &Token,
+        slot: Option<usize>,
+        annotation: Option<&Token>,
+        initializer: &Expr,
+    ) {
+        Walk::visit_const_stmt(self, arena, name, slot, annotation, initializer)
+    }
+
+    fn visit_block_stmt(&mut self, arena: &Arena, stmts: &[Stmt]) {
+        Walk::visit_block_stmt(self, arena, stmts)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        Walk::visit_if_stmt(self, arena, keyword, condition, then_branch, else_branch)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) {
+        Walk::visit_while_stmt(self, arena, keyword, label, condition, body, increment)
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        label: Option<&Token>,
+        pattern: &Pattern,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        Walk::visit_for_each_stmt(self, arena, keyword, label, pattern, iterable, body)
+    }
+
+    fn visit_break_stmt(&mut self, arena: &Arena, keyword: &Token, label: Option<&Token>) {
+        Walk::visit_break_stmt(self, arena, keyword, label)
+    }
+
+    fn visit_continue_stmt(&mut self, arena: &Arena, keyword: &Token, label: Option<&Token>) {
+        Walk::visit_continue_stmt(self, arena, keyword, label)
+    }
+
+    fn visit_throw_stmt(&mut self, arena: &Arena, keyword: &Token, value: &Expr) {
+        Walk::visit_throw_stmt(self, arena, keyword, value)
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &Arena, keyword: &Token, stmt: &Stmt) {
+        Walk::visit_defer_stmt(self, arena, keyword, stmt)
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        try_block: &[Stmt],
+        catch_clause: Option<(&Token, &[Stmt])>,
+        finally_block: Option<&[Stmt]>,
+    ) {
+        Walk::visit_try_stmt(self, arena, keyword, try_block, catch_clause, finally_block)
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        path: &str,
+        alias: Option<&Token>,
+    ) {
+        Walk::visit_import_stmt(self, arena, keyword, path, alias)
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &Arena, keyword: &Token, stmt: &Stmt) {
+        Walk::visit_pub_stmt(self, arena, keyword, stmt)
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &Arena,
+        keyword: &Token,
+        subject: &Expr,
+        arms: &[(Vec<Expr>, Stmt)],
+        default: Option<&Stmt>,
+    ) {
+        Walk::visit_match_stmt(self, arena, keyword, subject, arms, default)
+    }
+}
+
+/// Like `Walk`, for the `Mut` visitors.
+pub trait WalkMut {
+    fn visit_unary_expr(&mut self, arena: &mut Arena, _op: &mut Token, right: ExprId) {
+        arena.accept_expr_mut(right, self);
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        _op: &mut Token,
+        right: ExprId,
+    ) {
+        arena.accept_expr_mut(left, self);
+        arena.accept_expr_mut(right, self);
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        _op: &mut Token,
+        right: ExprId,
+    ) {
+        arena.accept_expr_mut(left, self);
+        arena.accept_expr_mut(right, self);
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &mut Arena, inner: ExprId) {
+        arena.accept_expr_mut(inner, self);
+    }
+
+    fn visit_literal_expr(
+        &mut self,
+        _arena: &mut Arena,
+        _token: &mut Token,
+        _literal: &mut Literal,
+    ) {
+    }
+
+    fn visit_var_expr(
+        &mut self,
+        _arena: &mut Arena,
+        _name: &mut Token,
+        _local: &mut Option<Local>,
+    ) {
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &mut Arena,
+        _name: &mut Token,
+        value: ExprId,
+        _local: &mut Option<Local>,
+    ) {
+        arena.accept_expr_mut(value, self);
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        arena: &mut Arena,
+        callee: ExprId,
+        _paren: &mut Token,
+        args: &mut [Expr],
+    ) {
+        arena.accept_expr_mut(callee, self);
+        args.iter_mut().for_each(|arg| arg.accept_mut(arena, self));
+    }
+
+    fn visit_get_expr(&mut self, arena: &mut Arena, object: ExprId, _name: &mut Token) {
+        arena.accept_expr_mut(object, self);
+    }
+
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &mut Arena,
+        _open: &mut Token,
+        items: &mut [Expr],
+        _close: &mut Token,
+    ) {
+        items
+            .iter_mut()
+            .for_each(|item| item.accept_mut(arena, self));
+    }
+
+    fn visit_spread_expr(&mut self, arena: &mut Arena, _op: &mut Token, expr: ExprId) {
+        arena.accept_expr_mut(expr, self);
+    }
+
+    fn visit_print_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, values: &mut [Expr]) {
+        values
+            .iter_mut()
+            .for_each(|value| value.accept_mut(arena, self));
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &mut Arena, expr: &mut Expr) {
+        expr.accept_mut(arena, self);
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _pattern: &mut Pattern,
+        _annotation: Option<&mut Token>,
+        initializer: Option<&mut Expr>,
+    ) {
+        if let Some(initializer) = initializer {
+            initializer.accept_mut(arena, self);
+        }
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _name: &mut Token,
+        _slot: &mut Option<usize>,
+        _annotation: Option<&mut Token>,
+        initializer: &mut Expr,
+    ) {
+        initializer.accept_mut(arena, self);
+    }
+
+    fn visit_block_stmt(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        stmts
+            .iter_mut()
+            .for_each(|stmt| stmt.accept_mut(arena, self));
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        condition: &mut Expr,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) {
+        condition.accept_mut(arena, self);
+        arena.accept_stmt_mut(then_branch, self);
+        if let Some(else_branch) = else_branch {
+            arena.accept_stmt_mut(else_branch, self);
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        _label: Option<&mut Token>,
+        condition: &mut Expr,
+        body: StmtId,
+        increment: Option<&mut Expr>,
+    ) {
+        condition.accept_mut(arena, self);
+        arena.accept_stmt_mut(body, self);
+        if let Some(increment) = increment {
+            increment.accept_mut(arena, self);
+        }
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        _label: Option<&mut Token>,
+        _pattern: &mut Pattern,
+        iterable: &mut Expr,
+        body: StmtId,
+    ) {
+        iterable.accept_mut(arena, self);
+        arena.accept_stmt_mut(body, self);
+    }
+
+    fn visit_break_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        _keyword: &mut Token,
+        _label: Option<&mut Token>,
+    ) {
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        _keyword: &mut Token,
+        _label: Option<&mut Token>,
+    ) {
+    }
+
+    fn visit_throw_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, value: &mut Expr) {
+        value.accept_mut(arena, self);
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, stmt: StmtId) {
+        arena.accept_stmt_mut(stmt, self);
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        try_block: &mut [Stmt],
+        catch_clause: Option<(&mut Token, &mut [Stmt])>,
+        finally_block: Option<&mut [Stmt]>,
+    ) {
+        self.visit_block_stmt(arena, try_block);
+        if let Some((_, catch_block)) = catch_clause {
+            self.visit_block_stmt(arena, catch_block);
+        }
+        if let Some(finally_block) = finally_block {
+            self.visit_block_stmt(arena, finally_block);
+        }
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _arena: &mut Arena,
+        _keyword: &mut Token,
+        _path: &str,
+        _alias: Option<&mut Token>,
+    ) {
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &mut Arena, _keyword: &mut Token, stmt: StmtId) {
+        arena.accept_stmt_mut(stmt, self);
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &mut Arena,
+        _keyword: &mut Token,
+        subject: &mut Expr,
+        arms: &mut [(Vec<Expr>, Stmt)],
+        default: Option<StmtId>,
+    ) {
+        subject.accept_mut(arena, self);
+        for (patterns, body) in arms {
+            patterns
+                .iter_mut()
+                .for_each(|pattern| pattern.accept_mut(arena, self));
+            body.accept_mut(arena, self);
+        }
+        if let Some(default) = default {
+            arena.accept_stmt_mut(default, self);
+        }
+    }
+}
+
+impl<W: WalkMut + ?Sized> ExprVisitorMut<()> for W {
+    fn visit_unary_expr(&mut self, arena: &mut Arena, op: &mut Token, right: ExprId) {
+        WalkMut::visit_unary_expr(self, arena, op, right)
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) {
+        WalkMut::visit_binary_expr(self, arena, left, op, right)
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        arena: &mut Arena,
+        left: ExprId,
+        op: &mut Token,
+        right: ExprId,
+    ) {
+        WalkMut::visit_logical_expr(self, arena, left, op, right)
+    }
+
+    fn visit_grouping_expr(&mut self, arena: &mut Arena, inner: ExprId) {
+        WalkMut::visit_grouping_expr(self, arena, inner)
+    }
+
+    fn visit_literal_expr(&mut self, arena: &mut Arena, token: &mut Token, literal: &mut Literal) {
+        WalkMut::visit_literal_expr(self, arena, token, literal)
+    }
+
+    fn visit_var_expr(&mut self, arena: &mut Arena, name: &mut Token, local: &mut Option<Local>) {
+        WalkMut::visit_var_expr(self, arena, name, local)
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        value: ExprId,
+        local: &mut Option<Local>,
+    ) {
+        WalkMut::visit_assign_expr(self, arena, name, value, local)
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        arena: &mut Arena,
+        callee: ExprId,
+        paren: &mut Token,
+        args: &mut [Expr],
+    ) {
+        WalkMut::visit_call_expr(self, arena, callee, paren, args)
+    }
+
+    fn visit_get_expr(&mut self, arena: &mut Arena, object: ExprId, name: &mut Token) {
+        WalkMut::visit_get_expr(self, arena, object, name)
+    }
+
+    fn visit_tuple_expr(
+        &mut self,
+        arena: &mut Arena,
+        open: &mut Token,
+        items: &mut [Expr],
+        close: &mut Token,
+    ) {
+        WalkMut::visit_tuple_expr(self, arena, open, items, close)
+    }
+
+    fn visit_spread_expr(&mut self, arena: &mut Arena, op: &mut Token, expr: ExprId) {
+        WalkMut::visit_spread_expr(self, arena, op, expr)
+    }
+}
+
+impl<W: WalkMut + ?Sized> StmtVisitorMut<()> for W {
+    fn visit_print_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, values: &mut [Expr]) {
+        WalkMut::visit_print_stmt(self, arena, keyword, values)
+    }
+
+    fn visit_expression_stmt(&mut self, arena: &mut Arena, expr: &mut Expr) {
+        WalkMut::visit_expression_stmt(self, arena, expr)
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        arena: &mut Arena,
+        pattern: &mut Pattern,
+        annotation: Option<&mut Token>,
+        initializer: Option<&mut Expr>,
+    ) {
+        WalkMut::visit_var_stmt(self, arena, pattern, annotation, initializer)
+    }
+
+    fn visit_const_stmt(
+        &mut self,
+        arena: &mut Arena,
+        name: &mut Token,
+        slot: &mut Option<usize>,
+        annotation: Option<&mut Token>,
+        initializer: &mut Expr,
+    ) {
+        WalkMut::visit_const_stmt(self, arena, name, slot, annotation, initializer)
+    }
+
+    fn visit_block_stmt(&mut self, arena: &mut Arena, stmts: &mut [Stmt]) {
+        WalkMut::visit_block_stmt(self, arena, stmts)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        condition: &mut Expr,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) {
+        WalkMut::visit_if_stmt(self, arena, keyword, condition, then_branch, else_branch)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        condition: &mut Expr,
+        body: StmtId,
+        increment: Option<&mut Expr>,
+    ) {
+        WalkMut::visit_while_stmt(self, arena, keyword, label, condition, body, increment)
+    }
+
+    fn visit_for_each_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+        pattern: &mut Pattern,
+        iterable: &mut Expr,
+        body: StmtId,
+    ) {
+        WalkMut::visit_for_each_stmt(self, arena, keyword, label, pattern, iterable, body)
+    }
+
+    fn visit_break_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        WalkMut::visit_break_stmt(self, arena, keyword, label)
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        label: Option<&mut Token>,
+    ) {
+        WalkMut::visit_continue_stmt(self, arena, keyword, label)
+    }
+
+    fn visit_throw_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, value: &mut Expr) {
+        WalkMut::visit_throw_stmt(self, arena, keyword, value)
+    }
+
+    fn visit_defer_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) {
+        WalkMut::visit_defer_stmt(self, arena, keyword, stmt)
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        try_block: &mut [Stmt],
+        catch_clause: Option<(&mut Token, &mut [Stmt])>,
+        finally_block: Option<&mut [Stmt]>,
+    ) {
+        WalkMut::visit_try_stmt(self, arena, keyword, try_block, catch_clause, finally_block)
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        path: &str,
+        alias: Option<&mut Token>,
+    ) {
+        WalkMut::visit_import_stmt(self, arena, keyword, path, alias)
+    }
+
+    fn visit_pub_stmt(&mut self, arena: &mut Arena, keyword: &mut Token, stmt: StmtId) {
+        WalkMut::visit_pub_stmt(self, arena, keyword, stmt)
+    }
+
+    fn visit_match_stmt(
+        &mut self,
+        arena: &mut Arena,
+        keyword: &mut Token,
+        subject: &mut Expr,
+        arms: &mut [(Vec<Expr>, Stmt)],
+        default: Option<StmtId>,
+    ) {
+        WalkMut::visit_match_stmt(self, arena, keyword, subject, arms, default)
+    }
+}
+
+/// Visits the expressions directly inside `expr`, in source order.
+pub fn walk_expr<V: ExprVisitor<()> + ?Sized>(visitor: &mut V, arena: &Arena, expr: &Expr) {
+    match expr {
+        Expr::Literal(_, _) | Expr::Var(_, _) => {}
+        Expr::Unary(_, inner)
+        | Expr::Grouping(inner)
+        | Expr::Assign(_, inner, _)
+        | Expr::Get(inner, _)
+        | Expr::Spread(_, inner) => arena[*inner].accept(arena, visitor),
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+            arena[*left].accept(arena, visitor);
+            arena[*right].accept(arena, visitor);
+        }
+        Expr::Call(callee, _, args) => {
+            arena[*callee].accept(arena, visitor);
+            args.iter().for_each(|arg| arg.accept(arena, visitor));
+        }
+        Expr::Tuple(_, items, _) => items.iter().for_each(|item| item.accept(arena, visitor)),
+    }
+}
+
+/// Visits the statements and expressions directly inside `stmt`, in
+/// source order.
+pub fn walk_stmt<V: StmtVisitor<()> + ExprVisitor<()> + ?Sized>(
+    visitor: &mut V,
+    arena: &Arena,
+    stmt: &Stmt,
+) {
+    match stmt {
+        Stmt::Print(_, exprs) => exprs.iter().for_each(|expr| expr.accept(arena, visitor)),
+        Stmt::Expression(expr) | Stmt::Const(_, _, _, expr) | Stmt::Throw(_, expr) => {
+            expr.accept(arena, visitor)
+        }
+        Stmt::Var(_, _, initializer) => {
+            if let Some(initializer) = initializer {
+                initializer.accept(arena, visitor);
+            }
+        }
+        Stmt::Block(stmts) => stmts.iter().for_each(|stmt| stmt.accept(arena, visitor)),
+        Stmt::If(_, condition, then_branch, else_branch) => {
+            condition.accept(arena, visitor);
+            arena[*then_branch].accept(arena, visitor);
+            if let Some(else_branch) = else_branch {
+                arena[*else_branch].accept(arena, visitor);
+            }
+        }
+        Stmt::While(_, _, condition, body, increment) => {
+            condition.accept(arena, visitor);
+            arena[*body].accept(arena, visitor);
+            if let Some(increment) = increment {
+                increment.accept(arena, visitor);
+            }
+        }
+        Stmt::ForEach(_, _, _, iterable, body) => {
+            iterable.accept(arena, visitor);
+            arena[*body].accept(arena, visitor);
+        }
+        Stmt::Break(_, _) | Stmt::Continue(_, _) | Stmt::Import(_, _, _) => {}
+        Stmt::Defer(_, stmt) | Stmt::Pub(_, stmt) => arena[*stmt].accept(arena, visitor),
+        Stmt::Try(_, try_block, catch_clause, finally_block) => {
+            try_block
+                .iter()
+                .for_each(|stmt| stmt.accept(arena, visitor));
+            if let Some((_, catch_block)) = catch_clause {
+                catch_block
+                    .iter()
+                    .for_each(|stmt| stmt.accept(arena, visitor));
+            }
+            if let Some(finally_block) = finally_block {
+                finally_block
+                    .iter()
+                    .for_each(|stmt| stmt.accept(arena, visitor));
+            }
+        }
+        Stmt::Match(_, subject, arms, default) => {
+            subject.accept(arena, visitor);
+            for (patterns, body) in arms {
+                patterns
+                    .iter()
+                    .for_each(|pattern| pattern.accept(arena, visitor));
+                body.accept(arena, visitor);
+            }
+            if let Some(default) = default {
+                arena[*default].accept(arena, visitor);
+            }
+        }
+    }
+}
+
+/// Like `walk_expr`, for the `Mut` visitors. `expr` must not be in `arena`,
+/// as `Arena::accept_expr_mut` takes nodes out of it while they are visited.
+pub fn walk_expr_mut<V: ExprVisitorMut<()> + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    expr: &mut Expr,
+) {
+    match expr {
+        Expr::Literal(_, _) | Expr::Var(_, _) => {}
+        Expr::Unary(_, inner)
+        | Expr::Grouping(inner)
+        | Expr::Assign(_, inner, _)
+        | Expr::Get(inner, _)
+        | Expr::Spread(_, inner) => arena.accept_expr_mut(*inner, visitor),
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+            arena.accept_expr_mut(*left, visitor);
+            arena.accept_expr_mut(*right, visitor);
+        }
+        Expr::Call(callee, _, args) => {
+            arena.accept_expr_mut(*callee, visitor);
+            args.iter_mut()
+                .for_each(|arg| arg.accept_mut(arena, visitor));
+        }
+        Expr::Tuple(_, items, _) => items
+            .iter_mut()
+            .for_each(|item| item.accept_mut(arena, visitor)),
+    }
+}
+
+/// Like `walk_stmt`, for the `Mut` visitors.
+pub fn walk_stmt_mut<V: StmtVisitorMut<()> + ExprVisitorMut<()> + ?Sized>(
+    visitor: &mut V,
+    arena: &mut Arena,
+    stmt: &mut Stmt,
+) {
+    match stmt {
+        Stmt::Print(_, exprs) => exprs
+            .iter_mut()
+            .for_each(|expr| expr.accept_mut(arena, visitor)),
+        Stmt::Expression(expr) | Stmt::Const(_, _, _, expr) | Stmt::Throw(_, expr) => {
+            expr.accept_mut(arena, visitor)
+        }
+        Stmt::Var(_, _, initializer) => {
+            if let Some(initializer) = initializer {
+                initializer.accept_mut(arena, visitor);
+            }
+        }
+        Stmt::Block(stmts) => stmts
+            .iter_mut()
+            .for_each(|stmt| stmt.accept_mut(arena, visitor)),
+        Stmt::If(_, condition, then_branch, else_branch) => {
+            condition.accept_mut(arena, visitor);
+            arena.accept_stmt_mut(*then_branch, visitor);
+            if let Some(else_branch) = else_branch {
+                arena.accept_stmt_mut(*else_branch, visitor);
+            }
+        }
+        Stmt::While(_, _, condition, body, increment) => {
+            condition.accept_mut(arena, visitor);
+            arena.accept_stmt_mut(*body, visitor);
+            if let Some(increment) = increment {
+                increment.accept_mut(arena, visitor);
+            }
+        }
+        Stmt::ForEach(_, _, _, iterable, body) => {
+            iterable.accept_mut(arena, visitor);
+            arena.accept_stmt_mut(*body, visitor);
+        }
+        Stmt::Break(_, _) | Stmt::Continue(_, _) | Stmt::Import(_, _, _) => {}
+        Stmt::Defer(_, stmt) | Stmt::Pub(_, stmt) => arena.accept_stmt_mut(*stmt, visitor),
+        Stmt::Try(_, try_block, catch_clause, finally_block) => {
+            let catch_block = catch_clause.as_mut().map(|(_, block)| block);
+            for block in [Some(try_block), catch_block, finally_block.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                block
+                    .iter_mut()
+                    .for_each(|stmt| stmt.accept_mut(arena, visitor));
+            }
+        }
+        Stmt::Match(_, subject, arms, default) => {
+            subject.accept_mut(arena, visitor);
+            for (patterns, body) in arms {
+                patterns
+                    .iter_mut()
+                    .for_each(|pattern| pattern.accept_mut(arena, visitor));
+                body.accept_mut(arena, visitor);
+            }
+            if let Some(default) = default {
+                arena.accept_stmt_mut(*default, visitor);
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    error::{ErrorFormat, Log, LogLevel},
+    Rox,
+};
+
+/// What `run` hands back to JavaScript: everything the script printed, and
+/// the error reports, one per line, if it failed.
+#[wasm_bindgen]
+pub struct RunResult {
+    output: String,
+    errors: String,
+}
+
+#[wasm_bindgen]
+impl RunResult {
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        self.output.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> String {
+        self.errors.clone()
+    }
+}
+
+/// Runs `source` in a fresh sandboxed interpreter. If `on_print` is given
+/// it is called with each complete line of output as it is printed, so a
+/// page can show progress before the script finishes.
+#[wasm_bindgen]
+pub fn run(source: &str, on_print: Option<Function>) -> RunResult {
+    let out = Sink::new(on_print);
+    let err = Sink::new(None);
+
+    let mut rox = Rox::with_logger(Log {
+        color: false,
+        format: ErrorFormat::Text,
+        ..Log::new(LogLevel::Warning)
+    });
+    rox.set_sandbox(true);
+    rox.set_output(Box::new(out.clone()), Box::new(err.clone()));
+
+    let result = rox.eval(source);
+    out.clone().flush_lines(true);
+
+    // Runtime errors are written to the error sink as they happen; scan and
+    // parse errors only come back in the result.
+    let mut errors = err.text();
+    if let Err(error) = result {
+        if errors.is_empty() {
+            errors = format!("{}\n", error);
+        }
+    }
+
+    RunResult {
+        output: out.text(),
+        errors,
+    }
+}
+
+/// A `Write` that collects text and forwards whole lines to a callback.
+#[derive(Clone)]
+struct Sink {
+    state: Rc<RefCell<SinkState>>,
+}
+
+struct SinkState {
+    text: String,
+    /// How much of `text` has been passed to the callback.
+    forwarded: usize,
+    callback: Option<Function>,
+}
+
+impl Sink {
+    fn new(callback: Option<Function>) -> Sink {
+        Sink {
+            state: Rc::new(RefCell::new(SinkState {
+                text: String::new(),
+                forwarded: 0,
+                callback,
+            })),
+        }
+    }
+
+    fn text(&self) -> String {
+        self.state.borrow().text.clone()
+    }
+
+    /// Passes complete lines to the callback, or everything not yet passed
+    /// on when `all` is set.
+    fn flush_lines(&mut self, all: bool) {
+        let mut state = self.state.borrow_mut();
+        let end = if all {
+            state.text.len()
+        } else {
+            match state.text.rfind('\n') {
+                Some(newline) => newline + 1,
+                None => return,
+            }
+        };
+        if end <= state.forwarded {
+            return;
+        }
+
+        let chunk = state.text[state.forwarded..end].to_string();
+        state.forwarded = end;
+        if let Some(callback) = &state.callback {
+            // An exception thrown by the callback is not the script's fault.
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&chunk));
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.state
+            .borrow_mut()
+            .text
+            .push_str(&String::from_utf8_lossy(buf));
+        self.flush_lines(false);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flush_lines(true);
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn bitwise_operators_work_on_ints() {
+    let out = output("print 12 & 10, 12 | 10, 12 ^ 10, ~12, ~-1;");
+    assert_eq!(out, "8 14 6 -13 0\n");
+}
+
+#[test]
+fn shifts_move_bits_and_keep_the_sign() {
+    let out = output("print 1 << 4, 1 << 63, 256 >> 4, -16 >> 2, -1 >> 63;");
+    assert_eq!(out, "16 -9223372036854775808 16 -4 -1\n");
+}
+
+#[test]
+fn precedence_runs_from_shifts_down_to_or() {
+    // Shifts bind looser than arithmetic, then &, ^ and | in turn, and all
+    // of them tighter than comparisons.
+    let out = output("print 1 + 1 << 2, 1 | 2 ^ 3 & 6, 6 & 3 == 2, 1 << 2 < 5, 0..1 << 2;");
+    assert_eq!(out, "8 1 true true 0..4\n");
+}
+
+#[test]
+fn operators_of_one_level_group_to_the_left() {
+    assert_eq!(output("print 256 >> 2 >> 1, 7 ^ 1 ^ 2;"), "32 4\n");
+}
+
+#[test]
+fn literals_in_other_bases_make_masks_readable() {
+    assert_eq!(
+        output("var flags = 0b0101;\nprint flags & 0b0100 != 0, flags | 0xf0, flags ^ 0o7;"),
+        "true 245 2\n"
+    );
+}
+
+#[test]
+fn numbers_and_other_values_are_rejected() {
+    for source in [
+        "print 1.5 & 1;",
+        "print 1 | \"x\";",
+        "print true ^ 1;",
+        "print ~1.0;",
+    ] {
+        let (result, _) = run(source);
+        assert!(result.is_err(), "{} ran", source);
+    }
+
+    let (result, _) = run("print 2.0 << 1;");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("which only works on ints"), "{}", err);
+}
+
+#[test]
+fn shift_amounts_outside_0_to_63_are_errors() {
+    for source in ["print 1 << 64;", "print 1 >> -1;"] {
+        let (result, _) = run(source);
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("between 0 and 63"), "{}: {}", source, err);
+    }
+}
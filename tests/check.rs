@@ -0,0 +1,36 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::temp_dir;
+
+/// Runs `rox check` on a script holding `source`, returning its exit code.
+fn check(name: &str, source: &str, args: &[&str]) -> Option<i32> {
+    let script = temp_dir(name).join("main.rox");
+    fs::write(&script, source).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_rox_rust"))
+        .arg("check")
+        .args(args)
+        .arg(&script)
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn check_passes_clean_code() {
+    assert_eq!(check("clean", "print 1;\n", &[]), Some(0));
+}
+
+#[test]
+fn check_fails_on_warnings() {
+    assert_eq!(check("warns", "{ var unused = 1; }\n", &[]), Some(65));
+}
+
+#[test]
+fn check_fails_on_warnings_the_log_level_hides() {
+    let code = check("hidden", "{ var unused = 1; }\n", &["--log-level=error"]);
+    assert_eq!(code, Some(65));
+}
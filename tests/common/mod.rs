@@ -0,0 +1,67 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    rc::Rc,
+};
+
+use rox_rust::{Log, LogLevel, Rox, RoxError, Value};
+
+/// An output sink the test keeps a handle to after the interpreter takes it.
+#[derive(Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    pub fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An interpreter that reports nothing and prints into the returned buffer.
+pub fn quiet_rox() -> (Rox, Output) {
+    let mut rox = Rox::with_logger(Log::new(LogLevel::Off));
+    let out = Output::default();
+    rox.set_output(Box::new(out.clone()), Box::new(Output::default()));
+    (rox, out)
+}
+
+/// Runs `source` and returns its result along with what it printed.
+pub fn run(source: &str) -> (Result<Value, RoxError>, String) {
+    let (mut rox, out) = quiet_rox();
+    let result = rox.eval(source);
+    (result, out.text())
+}
+
+/// Runs `source` and returns what it printed, failing the test on errors.
+pub fn output(source: &str) -> String {
+    let (result, out) = run(source);
+    if let Err(err) = result {
+        panic!("{} failed: {}", source, err);
+    }
+    out
+}
+
+/// A fresh directory for the files one test needs.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rox-test-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use common::temp_dir;
+
+/// An editor talking to `rox dap` over its stdin and stdout.
+struct Client {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    seq: u32,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rox_rust"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+
+        Client {
+            child,
+            input,
+            output,
+            seq: 0,
+        }
+    }
+
+    /// Starts a session on `script`, stopping at `breakpoints`, and returns
+    /// once the script is running.
+    fn launch(script: &Path, stop_on_entry: bool, breakpoints: &[i32]) -> Client {
+        let mut client = Client::start();
+        client.request("initialize", "{}");
+        client.expect("\"event\":\"initialized\"");
+
+        let path = script.display().to_string();
+        client.request(
+            "launch",
+            &format!(
+                "{{\"program\":\"{}\",\"stopOnEntry\":{}}}",
+                path, stop_on_entry
+            ),
+        );
+        let lines: Vec<String> = breakpoints
+            .iter()
+            .map(|line| format!("{{\"line\":{}}}", line))
+            .collect();
+        client.request(
+            "setBreakpoints",
+            &format!(
+                "{{\"source\":{{\"path\":\"{}\"}},\"breakpoints\":[{}]}}",
+                path,
+                lines.join(",")
+            ),
+        );
+        client.request("configurationDone", "{}");
+        client.expect("\"command\":\"configurationDone\"");
+        client
+    }
+
+    fn request(&mut self, command: &str, arguments: &str) {
+        self.seq += 1;
+        let body = format!(
+            "{{\"seq\":{},\"type\":\"request\",\"command\":\"{}\",\"arguments\":{}}}",
+            self.seq, command, arguments
+        );
+        write!(self.input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    /// The next message from the server.
+    fn message(&mut self) -> String {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            assert_ne!(self.output.read_line(&mut header).unwrap(), 0, "closed");
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+
+        let mut body = vec![0; length.unwrap()];
+        self.output.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    /// Reads messages up to the first that contains `text`, and returns it.
+    fn expect(&mut self, text: &str) -> String {
+        loop {
+            let message = self.message();
+            if message.contains(text) {
+                return message;
+            }
+        }
+    }
+
+    /// Sends `command` and returns its response.
+    fn ask(&mut self, command: &str, arguments: &str) -> String {
+        self.request(command, arguments);
+        self.expect(&format!("\"command\":\"{}\"", command))
+    }
+
+    fn disconnect(mut self) {
+        self.request("disconnect", "{}");
+        self.expect("\"command\":\"disconnect\"");
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn script(name: &str, source: &str) -> std::path::PathBuf {
+    let path = temp_dir(name).join("main.rox");
+    fs::write(&path, source).unwrap();
+    path.canonicalize().unwrap()
+}
+
+#[test]
+fn breakpoints_stop_the_script_and_show_its_variables() {
+    let path = script("dap-breakpoint", "var a = 1;\nvar b = \"two\";\nprint a;\n");
+    let mut client = Client::launch(&path, false, &[3]);
+
+    let stopped = client.expect("\"event\":\"stopped\"");
+    assert!(stopped.contains("\"reason\":\"breakpoint\""), "{}", stopped);
+
+    let trace = client.ask("stackTrace", "{\"threadId\":1}");
+    assert!(trace.contains("\"line\":3"), "{}", trace);
+    assert!(trace.contains("\"name\":\"main.rox\""), "{}", trace);
+
+    let globals = client.ask("variables", "{\"variablesReference\":2}");
+    assert!(
+        globals.contains("\"name\":\"a\",\"value\":\"1\""),
+        "{}",
+        globals
+    );
+    assert!(
+        globals.contains("\"name\":\"b\",\"value\":\"\\\"two\\\"\""),
+        "{}",
+        globals
+    );
+
+    client.ask("continue", "{\"threadId\":1}");
+    let output = client.expect("\"event\":\"output\"");
+    assert!(output.contains("\"output\":\"1\\n\""), "{}", output);
+    let exited = client.expect("\"event\":\"exited\"");
+    assert!(exited.contains("\"exitCode\":0"), "{}", exited);
+
+    client.disconnect();
+}
+
+#[test]
+fn next_steps_one_line_at_a_time() {
+    let path = script("dap-step", "var a = 1;\nvar b = 2;\nvar c = a + b;\n");
+    let mut client = Client::launch(&path, true, &[]);
+
+    let stopped = client.expect("\"event\":\"stopped\"");
+    assert!(stopped.contains("\"reason\":\"entry\""), "{}", stopped);
+    assert!(client
+        .ask("stackTrace", "{\"threadId\":1}")
+        .contains("\"line\":1"));
+
+    client.ask("next", "{\"threadId\":1}");
+    let stopped = client.expect("\"event\":\"stopped\"");
+    assert!(stopped.contains("\"reason\":\"step\""), "{}", stopped);
+    assert!(client
+        .ask("stackTrace", "{\"threadId\":1}")
+        .contains("\"line\":2"));
+
+    client.ask("continue", "{\"threadId\":1}");
+    client.expect("\"event\":\"terminated\"");
+    client.disconnect();
+}
+
+#[test]
+fn evaluate_shows_variables_in_scope_only() {
+    let path = script("dap-evaluate", "var n = 41;\nn = n + 1;\nprint n;\n");
+    let mut client = Client::launch(&path, false, &[3]);
+    client.expect("\"event\":\"stopped\"");
+
+    let found = client.ask("evaluate", "{\"expression\":\"n\"}");
+    assert!(found.contains("\"success\":true"), "{}", found);
+    assert!(found.contains("\"result\":\"42\""), "{}", found);
+
+    let missing = client.ask("evaluate", "{\"expression\":\"n + 1\"}");
+    assert!(missing.contains("\"success\":false"), "{}", missing);
+
+    client.ask("continue", "{\"threadId\":1}");
+    client.expect("\"event\":\"terminated\"");
+    client.disconnect();
+}
+
+#[test]
+fn runtime_errors_are_shown_and_end_with_a_failing_exit_code() {
+    let path = script("dap-error", "print 1 + \"x\";\n");
+    let mut client = Client::launch(&path, false, &[]);
+
+    let error = client.expect("\"category\":\"stderr\"");
+    assert!(error.contains("Cannot apply +"), "{}", error);
+    let exited = client.expect("\"event\":\"exited\"");
+    assert!(exited.contains("\"exitCode\":1"), "{}", exited);
+
+    client.disconnect();
+}
+
+#[test]
+fn requests_that_need_a_stopped_script_fail_before_it_runs() {
+    let mut client = Client::start();
+    client.request("initialize", "{}");
+    client.expect("\"event\":\"initialized\"");
+
+    let trace = client.ask("stackTrace", "{\"threadId\":1}");
+    assert!(trace.contains("\"success\":false"), "{}", trace);
+    let unknown = client.ask("restartFrame", "{}");
+    assert!(
+        unknown.contains("Unsupported request 'restartFrame'"),
+        "{}",
+        unknown
+    );
+
+    client.disconnect();
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn deferred_statements_run_newest_first_when_the_block_ends() {
+    let out = output("{\n    defer print 1;\n    defer print 2;\n    print 0;\n}\nprint 3;\n");
+    assert_eq!(out, "0\n2\n1\n3\n");
+}
+
+#[test]
+fn each_block_runs_only_its_own_deferred_statements() {
+    let out = output(
+        "{\n    defer print \"outer\";\n    {\n        defer print \"inner\";\n    }\n    print \"between\";\n}\n",
+    );
+    assert_eq!(out, "inner\nbetween\nouter\n");
+}
+
+#[test]
+fn deferred_statements_see_variables_as_they_are_when_they_run() {
+    let out = output("{\n    var a = 1;\n    defer print a;\n    a = 2;\n}\n");
+    assert_eq!(out, "2\n");
+}
+
+#[test]
+fn top_level_deferred_statements_run_after_the_script() {
+    let out = output("defer print \"last\";\nprint \"first\";\n");
+    assert_eq!(out, "first\nlast\n");
+}
+
+#[test]
+fn deferred_statements_run_when_an_exception_leaves_the_block() {
+    let out = output(
+        "try {\n    defer print \"cleanup\";\n    throw \"boom\";\n} catch (e) {\n    print e;\n}\n",
+    );
+    assert_eq!(out, "cleanup\nboom\n");
+}
+
+#[test]
+fn deferred_statements_run_when_break_leaves_the_loop() {
+    let out = output(
+        "while (true) {\n    defer print \"left\";\n    break;\n    print \"unreachable\";\n}\nprint \"after\";\n",
+    );
+    assert_eq!(out, "left\nafter\n");
+}
+
+#[test]
+fn each_loop_iteration_runs_its_deferred_statements() {
+    let out = output("for (x in 0..3) {\n    defer print x;\n    print x * 10;\n}\n");
+    assert_eq!(out, "0\n0\n10\n1\n20\n2\n");
+}
+
+#[test]
+fn an_error_in_deferred_code_replaces_the_block_result() {
+    let (result, out) = run("{\n    defer throw \"second\";\n    throw \"first\";\n}\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("second"), "{}", err);
+    assert_eq!(out, "");
+}
+
+#[test]
+fn exit_skips_deferred_statements() {
+    let (result, out) = run("{\n    defer print \"skipped\";\n    exit(3);\n}\n");
+    assert!(result.is_err());
+    assert_eq!(out, "");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::Output;
+use rox_rust::{ErrorFormat, Log, LogLevel, Rox};
+
+/// Runs `source` and returns the runtime error it reports, rendered in
+/// `format`.
+fn reported(source: &str, format: ErrorFormat) -> String {
+    let mut log = Log::new(LogLevel::Off);
+    log.color = false;
+    log.format = format;
+
+    let mut rox = Rox::with_logger(log);
+    let err = Output::default();
+    rox.set_output(Box::new(Output::default()), Box::new(err.clone()));
+    assert!(rox.eval(source).is_err(), "{} ran", source);
+    err.text()
+}
+
+#[test]
+fn text_errors_start_with_their_line_and_column() {
+    let text = reported("var a = 1;\nprint a + \"x\";\n", ErrorFormat::Text);
+    assert_eq!(text, "2:9: Runtime Error: Cannot apply + to '1' and 'x'\n");
+}
+
+#[test]
+fn operator_errors_point_at_the_operator() {
+    let json = reported("var a = 1;\nprint a + \"x\";\n", ErrorFormat::Json);
+    assert!(json.contains("\"line\":2,\"column\":9"), "{}", json);
+    assert!(
+        json.contains("\"span\":{\"start\":19,\"end\":20}"),
+        "{}",
+        json
+    );
+
+    let text = reported("print 1 + -\"x\";\n", ErrorFormat::Text);
+    assert!(text.starts_with("1:11: "), "{}", text);
+}
+
+#[test]
+fn call_errors_point_at_the_callee() {
+    let text = reported("print 1, len(1, 2);\n", ErrorFormat::Text);
+    assert!(text.starts_with("1:10: "), "{}", text);
+
+    let text = reported("var n = 1;\nprint 2 + n();\n", ErrorFormat::Text);
+    assert!(text.starts_with("2:11: "), "{}", text);
+}
+
+#[test]
+fn operand_errors_keep_their_own_position() {
+    let text = reported("print 1 + (2 < \"x\");\n", ErrorFormat::Text);
+    assert!(text.starts_with("1:14: "), "{}", text);
+}
+
+#[test]
+fn parse_errors_quote_literal_tokens() {
+    let (result, _) = common::run("print 1 2;");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("at '2'"), "{}", err);
+
+    let (result, _) = common::run("print \"a\" \"b\";");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("at '\"b\"'"), "{}", err);
+}
+
+#[test]
+fn for_loop_conditions_report_their_own_position_on_later_iterations() {
+    let source = "for (var i = 0; i < 2; i = \"s\") {\n    print i;\n}\n";
+    let text = reported(source, ErrorFormat::Text);
+    assert!(text.starts_with("1:19: "), "{}", text);
+}
+
+#[test]
+fn for_loop_increments_report_their_own_position() {
+    let source = "for (var i = 0; i < 2;\n     i = i + \"x\") {\n    print i;\n}\n";
+    let text = reported(source, ErrorFormat::Text);
+    assert!(text.starts_with("2:12: "), "{}", text);
+}
+
+#[test]
+fn for_in_loops_point_at_what_they_iterate() {
+    let source = "var n = 0;\nfor (x in 0..3) {\n    n = n + x;\n}\nfor (x in n) print x;\n";
+    let text = reported(source, ErrorFormat::Text);
+    assert!(text.starts_with("5:11: "), "{}", text);
+}
+
+#[test]
+fn errors_after_a_multi_line_string_report_the_lines_it_spans() {
+    let source = "var s = \"\"\"one\ntwo \"quoted\"\nthree\"\"\";\nprint s + 1;\n";
+    let text = reported(source, ErrorFormat::Text);
+    assert!(text.starts_with("4:9: "), "{}", text);
+
+    let (result, _) = common::run("var s = \"\"\"a\nb\nc\"\"\"; print s;\nprint missing;\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("at line 4"), "{}", err);
+}
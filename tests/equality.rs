@@ -0,0 +1,56 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn ints_equal_numbers_with_the_same_value() {
+    assert_eq!(
+        output("print 3 == 3.0, 3.0 == 3, 3 == 3.5, -0 == -0.0;"),
+        "true true false true\n"
+    );
+}
+
+#[test]
+fn ints_past_two_to_the_53_compare_exactly_with_numbers() {
+    let out = output(
+        "print 9007199254740993 == 9007199254740992.0, 9007199254740992 == 9007199254740992.0;",
+    );
+    assert_eq!(out, "false true\n");
+}
+
+#[test]
+fn numbers_out_of_int_range_equal_no_int() {
+    let out = output("print 9223372036854775807 == 9223372036854775808.0, -9223372036854775807 - 1 == -9223372036854775808.0;");
+    assert_eq!(out, "false true\n");
+}
+
+#[test]
+fn ints_past_two_to_the_53_order_exactly_with_numbers() {
+    let out = output(
+        "print 9007199254740993 > 9007199254740992.0, 9007199254740993 >= 9007199254740992.0, 9007199254740992.0 < 9007199254740993;",
+    );
+    assert_eq!(out, "true true true\n");
+}
+
+#[test]
+fn ordering_agrees_with_equality_between_ints_and_numbers() {
+    let out = output("print 3 >= 3.0, 3 > 3.0, 3 < 3.5, -3 > -3.5, 3.5 > 3;");
+    assert_eq!(out, "true false true true true\n");
+}
+
+#[test]
+fn ints_are_unordered_with_nan() {
+    let out = output(
+        "var big = 1000000000000000000000000000000.0;
+        var inf = big * big * big * big * big * big * big * big * big * big * big;
+        var nan = inf - inf;
+        print 0 < nan, 0 >= nan, nan > 0, nan <= 0;",
+    );
+    assert_eq!(out, "false false false false\n");
+}
+
+#[test]
+fn numbers_out_of_int_range_order_past_every_int() {
+    let out = output("print 9223372036854775807 >= 9223372036854775808.0, -9223372036854775807 - 1 >= -9223372036854775808.0;");
+    assert_eq!(out, "false true\n");
+}
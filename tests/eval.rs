@@ -0,0 +1,41 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn eval_sees_the_locals_of_its_block() {
+    assert_eq!(
+        output(r#"{ var local = 42; print eval("local"); }"#),
+        "42\n"
+    );
+}
+
+#[test]
+fn eval_sees_locals_of_enclosing_blocks_and_loops() {
+    let out = output(
+        r#"
+        { var a = 1; { var b = 2; print eval("a + b"); } }
+        for (var i = 0; i < 2; i = i + 1) { print eval("i"); }
+        "#,
+    );
+    assert_eq!(out, "3\n0\n1\n");
+}
+
+#[test]
+fn eval_assigns_to_locals() {
+    let out = output(r#"{ var a = 1; { eval("a = 10;"); } print a; }"#);
+    assert_eq!(out, "10\n");
+}
+
+#[test]
+fn eval_declarations_stay_in_the_block() {
+    let out = output(r#"{ eval("var c = 3;"); print eval("c"); }"#);
+    assert_eq!(out, "3\n");
+}
+
+#[test]
+fn eval_cannot_assign_to_a_local_constant() {
+    let (result, _) = run(r#"{ const k = 1; eval("k = 2;"); }"#);
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Cannot assign to constant 'k'"), "{}", err);
+}
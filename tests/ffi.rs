@@ -0,0 +1,43 @@
+//! The C API, called the way a C host would call it.
+
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_char, c_void, CStr, CString};
+
+// Links the library, which no Rust item here refers to.
+extern crate rox_rust;
+
+extern "C" {
+    fn rox_new() -> *mut c_void;
+    fn rox_eval(rox: *mut c_void, source: *const c_char) -> i32;
+    fn rox_get_string_result(rox: *const c_void) -> *const c_char;
+    fn rox_free(rox: *mut c_void);
+}
+
+const ROX_STATUS_OK: i32 = 0;
+const ROX_STATUS_RUNTIME_ERROR: i32 = 2;
+
+fn eval(source: &str) -> (i32, String) {
+    let source = CString::new(source).unwrap();
+    unsafe {
+        let rox = rox_new();
+        let status = rox_eval(rox, source.as_ptr());
+        let result = CStr::from_ptr(rox_get_string_result(rox))
+            .to_string_lossy()
+            .into_owned();
+        rox_free(rox);
+        (status, result)
+    }
+}
+
+#[test]
+fn eval_returns_the_final_value() {
+    assert_eq!(eval("1 + 2;"), (ROX_STATUS_OK, "3".to_string()));
+}
+
+#[test]
+fn a_result_holding_nul_is_an_error() {
+    let (status, result) = eval(r#"hex_decode("00");"#);
+    assert_eq!(status, ROX_STATUS_RUNTIME_ERROR);
+    assert!(result.contains("NUL"), "{}", result);
+}
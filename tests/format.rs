@@ -0,0 +1,31 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn format_pads_and_rounds() {
+    assert_eq!(
+        output(r#"print format("[{:>6.2}] [{:<4}] [{:03}]", 1.5, "ab", 7);"#),
+        "[  1.50] [ab  ] [007]\n"
+    );
+}
+
+#[test]
+fn format_allows_the_largest_width_and_precision() {
+    let out = output(r#"print len(format("{:>1000}", 1)), len(format("{:.1000}", 1.5));"#);
+    assert_eq!(out, "1000 1002\n");
+}
+
+#[test]
+fn format_rejects_a_huge_precision() {
+    let (result, _) = run(r#"format("{:.99999999999}", 1.5);"#);
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("more than 1000 characters"), "{}", err);
+}
+
+#[test]
+fn format_rejects_a_huge_width() {
+    let (result, _) = run(r#"format("{:>99999999999}", 1);"#);
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("more than 1000 characters"), "{}", err);
+}
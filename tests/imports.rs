@@ -0,0 +1,38 @@
+mod common;
+
+use std::fs;
+
+use common::{quiet_rox, temp_dir};
+
+#[test]
+fn unaliased_import_keeps_exported_constants_constant() {
+    let dir = temp_dir("import-const");
+    fs::write(dir.join("util.rox"), "pub const K = 3;\npub var v = 1;\n").unwrap();
+    fs::write(
+        dir.join("main.rox"),
+        "import \"util.rox\";\nv = 2;\nprint v;\nK = 4;\nprint K;\n",
+    )
+    .unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    let err = rox.run_file(dir.join("main.rox")).unwrap_err();
+
+    assert_eq!(out.text(), "2\n");
+    assert!(err.to_string().contains("constant 'K'"), "{}", err);
+}
+
+#[test]
+fn aliased_import_reads_exports_through_the_module() {
+    let dir = temp_dir("import-alias");
+    fs::write(dir.join("util.rox"), "pub const K = 3;\n").unwrap();
+    fs::write(
+        dir.join("main.rox"),
+        "import \"util.rox\" as u;\nprint u.K;\n",
+    )
+    .unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.run_file(dir.join("main.rox")).unwrap();
+
+    assert_eq!(out.text(), "3\n");
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::quiet_rox;
+use rox_rust::{ast_to_json, Document, Rox};
+
+/// The tokens and tree of `document`, positions included.
+fn snapshot(document: &Document) -> (String, String) {
+    (
+        format!("{:?}", document.tokens()),
+        ast_to_json(document.program()),
+    )
+}
+
+/// Parses `source` into a fresh document, or returns the error it reports.
+fn from_scratch(rox: &Rox, source: &str) -> Result<(String, String), String> {
+    let mut document = Document::new();
+    rox.reparse(&mut document, source)
+        .map(|_| ())
+        .map_err(|err| err.to_string())?;
+    Ok(snapshot(&document))
+}
+
+/// Feeds `versions` to one document in turn, checking after each that it
+/// holds the same tokens and tree as a document parsed from scratch.
+fn assert_edits_match(versions: &[&str]) -> Document {
+    let (rox, _) = quiet_rox();
+    let mut document = Document::new();
+    for (i, version) in versions.iter().enumerate() {
+        let edited = rox
+            .reparse(&mut document, version)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+            .map(|_| snapshot(&document));
+        assert_eq!(
+            edited,
+            from_scratch(&rox, version),
+            "version {}:\n{}",
+            i,
+            version
+        );
+    }
+    document
+}
+
+#[test]
+fn inserted_lines_move_the_declarations_after_them() {
+    assert_edits_match(&[
+        "var a = 1;\nvar b = a + 2;\nprint a, b;\n",
+        "var a = 1;\n\n\nvar b = a + 2;\nprint a, b;\n",
+        "var a = 1;\nvar x = 3;\n\nvar b = a + 2;\nprint a, b;\n",
+        "var a = 1;\nvar b = a + 2;\nprint a, b;\n",
+    ]);
+}
+
+#[test]
+fn edits_on_a_shared_line_keep_the_columns_after_them() {
+    assert_edits_match(&[
+        "var a = 1; var b = 2;\nprint a + b;\n",
+        "var a = 100; var b = 2;\nprint a + b;\n",
+        "var a = 1; var b = 2; var c = 3;\nprint a + b + c;\n",
+    ]);
+}
+
+#[test]
+fn edits_inside_nested_statements_shift_everything_after_them() {
+    assert_edits_match(&[
+        "if (true) {\n    while (false) print 1;\n} else print 2;\nfor (x in 0..2) { print x; }\nmatch (1) { 1 -> print \"one\"; else -> print 0; }\n",
+        "if (true) {\n    while (false) {\n        print 1;\n    }\n} else print 2;\nfor (x in 0..2) { print x; }\nmatch (1) { 1 -> print \"one\"; else -> print 0; }\n",
+        "if (true) {\n    while (false) {\n        print 1;\n    }\n} else print 2;\n\nfor (x in 0..2) { print x; }\ntry { throw 1; } catch (e) { print e; } finally { print 2; }\n",
+    ]);
+}
+
+#[test]
+fn multi_line_strings_and_comments_keep_later_lines() {
+    assert_edits_match(&[
+        "var s = \"\"\"one\ntwo\"\"\";\nprint s;\n",
+        "var s = \"\"\"one\ntwo\nthree\"\"\";\nprint s;\n",
+        "/* a\nlong\ncomment */\nvar s = \"\"\"one\ntwo\nthree\"\"\";\nprint s;\n",
+        "/* a comment */\nvar s = \"\"\"one\ntwo\nthree\"\"\";\nprint s;\n",
+    ]);
+}
+
+#[test]
+fn a_failed_parse_is_reported_and_the_next_version_parses() {
+    assert_edits_match(&[
+        "var a = 1;\n{\n    print a;\n}\nprint 2;\n",
+        "var a = 1;\n{\n    print a;\n\nprint 2;\n",
+        "var a = 1;\n{\n    print a;\n}\nprint 3;\n",
+        "var a = 1 +;\nprint 3;\n",
+        "var a = 1;\nprint 3;\n",
+    ]);
+}
+
+#[test]
+fn edits_inside_the_last_declaration_reach_the_end_of_the_file() {
+    assert_edits_match(&[
+        "var a = 1;\nprint a;\n",
+        "var a = 1;\nprint a + 1;\n",
+        "var a = 1;\nprint a + 1;\n// the end\n",
+        "var a = 1;\nprint a + 1;\nprint a;",
+        "var a = 1;\nprint a + 1;\nprint a",
+        "var a = 1;\nprint a + 1;\nprint a;",
+    ]);
+}
+
+#[test]
+fn repeated_edits_do_not_grow_the_tree_without_bound() {
+    let mut versions: Vec<String> = Vec::new();
+    for i in 0..50 {
+        versions.push(format!(
+            "var a = ({} + 1) * 2;\nprint (a, a + 1, a * 2);\nvar b = a;\n",
+            i
+        ));
+    }
+    let versions: Vec<&str> = versions.iter().map(String::as_str).collect();
+    let document = assert_edits_match(&versions);
+
+    let (rox, _) = quiet_rox();
+    let mut fresh = Document::new();
+    rox.reparse(&mut fresh, versions[versions.len() - 1])
+        .unwrap();
+    assert!(document.program().arena.len() <= 2 * fresh.program().arena.len());
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn break_with_a_label_leaves_the_outer_loop() {
+    let source = "outer: for (i in 0..3) {\n    for (j in 0..3) {\n        if (j == 1) break outer;\n        print i, j;\n    }\n}\nprint \"done\";\n";
+    assert_eq!(output(source), "0 0\ndone\n");
+}
+
+#[test]
+fn continue_with_a_label_moves_on_to_the_next_outer_iteration() {
+    let source = "outer: for (i in 0..3) {\n    for (j in 0..3) {\n        if (j == 1) continue outer;\n        print i, j;\n    }\n    print \"unreachable\";\n}\n";
+    assert_eq!(output(source), "0 0\n1 0\n2 0\n");
+}
+
+#[test]
+fn unlabeled_break_and_continue_target_the_innermost_loop() {
+    let source = "outer: for (i in 0..2) {\n    for (j in 0..3) {\n        if (j == 0) continue;\n        if (j == 2) break;\n        print i, j;\n    }\n}\n";
+    assert_eq!(output(source), "0 1\n1 1\n");
+}
+
+#[test]
+fn continue_with_a_label_runs_the_outer_increment() {
+    let source = "outer: for (var i = 0; i < 3; i = i + 1) {\n    var j = 0;\n    while (true) {\n        j = j + 1;\n        if (j > 1) continue outer;\n    }\n}\nprint \"done\";\n";
+    assert_eq!(output(source), "done\n");
+}
+
+#[test]
+fn labels_can_be_reused_by_sibling_loops() {
+    let source =
+        "l: for (i in 0..2) { break l; }\nl: while (true) { print \"second\"; break l; }\n";
+    assert_eq!(output(source), "second\n");
+}
+
+#[test]
+fn deferred_statements_of_the_loops_left_still_run() {
+    let source = "outer: while (true) {\n    defer print \"outer\";\n    while (true) {\n        defer print \"inner\";\n        break outer;\n    }\n}\n";
+    assert_eq!(output(source), "inner\nouter\n");
+}
+
+#[test]
+fn an_unknown_label_is_an_error() {
+    let (result, out) = run("outer: while (true) {\n    break inner;\n}\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("inner"), "{}", err);
+    assert_eq!(out, "");
+}
+
+#[test]
+fn a_label_must_name_a_loop() {
+    let (result, _) = run("here: print 1;\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Expected a loop after the label"), "{}", err);
+}
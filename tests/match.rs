@@ -0,0 +1,55 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn the_first_matching_arm_runs() {
+    let source = "for (x in 0..4) {\n    match (x) {\n        0 -> print \"zero\";\n        1, 2 -> print \"small\";\n        2 -> print \"never\";\n        else -> print \"other\";\n    }\n}\n";
+    assert_eq!(output(source), "zero\nsmall\nsmall\nother\n");
+}
+
+#[test]
+fn patterns_compare_by_value_across_types() {
+    let source = "match (\"x\") {\n    1 -> print 1;\n    \"x\" -> print \"string\";\n}\nmatch (2.0) {\n    2 -> print \"int\";\n}\nmatch ((1, \"a\")) {\n    (1, \"a\") -> print \"tuple\";\n}\n";
+    assert_eq!(output(source), "string\nint\ntuple\n");
+}
+
+#[test]
+fn no_arm_runs_without_a_match_or_an_else() {
+    assert_eq!(
+        output("match (5) {\n    1 -> print 1;\n}\nprint \"done\";\n"),
+        "done\n"
+    );
+}
+
+#[test]
+fn patterns_are_expressions_evaluated_in_order_until_one_matches() {
+    let source = "var n = 3;\nmatch (4) {\n    n -> print \"n\";\n    n + 1 -> print \"n + 1\";\n    1 / 0 -> print \"not evaluated\";\n}\n";
+    assert_eq!(output(source), "n + 1\n");
+}
+
+#[test]
+fn the_subject_is_evaluated_once() {
+    let source = "var it = iter(0..10);\nmatch (next(it)) {\n    5 -> print 5;\n    6 -> print 6;\n    else -> print \"else\";\n}\nprint next(it);\n";
+    assert_eq!(output(source), "else\n1\n");
+}
+
+#[test]
+fn arms_can_be_blocks() {
+    let source = "match (1) {\n    1 -> {\n        var a = \"block\";\n        print a;\n    }\n    else -> print \"else\";\n}\n";
+    assert_eq!(output(source), "block\n");
+}
+
+#[test]
+fn a_second_else_arm_is_a_parse_error() {
+    let (result, _) = run("match (1) {\n    else -> print 1;\n    else -> print 2;\n}\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("only have one else arm"), "{}", err);
+}
+
+#[test]
+fn a_missing_arrow_is_a_parse_error() {
+    let (result, _) = run("match (1) {\n    1 print 1;\n}\n");
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Expected '->'"), "{}", err);
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::temp_dir;
+
+/// Runs `rox` with `args` in `dir`, returning its exit code and stdout.
+fn rox(dir: &Path, args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rox_rust"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (output.status.code(), stdout)
+}
+
+#[test]
+fn running_without_a_script_runs_the_project_entry() {
+    let dir = temp_dir("project-entry");
+    fs::write(dir.join("rox.toml"), "[project]\nentry = \"main.rox\"\n").unwrap();
+    fs::write(dir.join("main.rox"), "print \"entry\";\n").unwrap();
+
+    assert_eq!(rox(&dir, &[]), (Some(0), "entry\n".to_string()));
+}
+
+#[test]
+fn a_named_script_ignores_the_manifest_above_it() {
+    let dir = temp_dir("project-named");
+    fs::write(dir.join("rox.toml"), "[features]\nsandbox = true\n").unwrap();
+    fs::write(dir.join("main.rox"), "print clock() > 0;\n").unwrap();
+
+    assert_eq!(rox(&dir, &["main.rox"]), (Some(0), "true\n".to_string()));
+}
+
+#[test]
+fn a_broken_manifest_only_stops_project_runs() {
+    let dir = temp_dir("project-broken");
+    fs::write(dir.join("rox.toml"), "not toml\n").unwrap();
+    fs::write(dir.join("main.rox"), "print 1;\n").unwrap();
+
+    assert_eq!(rox(&dir, &["main.rox"]).0, Some(0));
+    assert_eq!(rox(&dir, &["highlight", "main.rox"]).0, Some(0));
+    assert_eq!(rox(&dir, &[]).0, Some(78));
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn inclusive_ranges_reach_the_largest_int() {
+    let out = output(
+        "for (x in 9223372036854775806..=9223372036854775807) print x;
+        print len(9223372036854775806..=9223372036854775807);",
+    );
+    assert_eq!(out, "9223372036854775806\n9223372036854775807\n2\n");
+}
+
+#[test]
+fn inclusive_ranges_include_their_end() {
+    let out = output(
+        "for (x in 1..=3) print x;
+        print 1..=3, len(1..=3), len(3..=1), 1..=2 == 1..3;",
+    );
+    assert_eq!(out, "1\n2\n3\n1..=3 3 0 true\n");
+}
+
+#[test]
+fn exclusive_ranges_from_the_smallest_int_are_empty() {
+    let out = output(
+        "var min = -9223372036854775807 - 1;
+        print len(min..min), len(0..min);
+        for (x in min..min) print x;",
+    );
+    assert_eq!(out, "0 0\n");
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::fs;
+
+use common::{output, quiet_rox, temp_dir};
+
+/// Runs `main` next to a module `shapes.rox` and returns what it printed,
+/// or the error it stopped with.
+fn with_module(name: &str, main: &str) -> Result<String, String> {
+    let dir = temp_dir(name);
+    fs::write(
+        dir.join("shapes.rox"),
+        "pub const sides = 4;\npub var label = \"square\";\npub var shout = upper;\n",
+    )
+    .unwrap();
+    fs::write(dir.join("main.rox"), main).unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.run_file(dir.join("main.rox"))
+        .map(|_| out.text())
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn fields_lists_every_export_sorted() {
+    let out = with_module(
+        "reflect-fields",
+        "import \"shapes.rox\" as s;\nprint fields(s);\n",
+    );
+    assert_eq!(out.unwrap(), "[\"label\", \"shout\", \"sides\"]\n");
+}
+
+#[test]
+fn methods_lists_only_the_callable_exports() {
+    let out = with_module(
+        "reflect-methods",
+        "import \"shapes.rox\" as s;\nprint methods(s);\n",
+    );
+    assert_eq!(out.unwrap(), "[\"shout\"]\n");
+}
+
+#[test]
+fn get_field_reads_an_export_named_at_runtime() {
+    let out = with_module(
+        "reflect-get",
+        "import \"shapes.rox\" as s;\nfor (f in fields(s)) print f, get_field(s, f);\n",
+    );
+    assert_eq!(
+        out.unwrap(),
+        "label square\nshout <native fn upper>\nsides 4\n"
+    );
+
+    let err = with_module(
+        "reflect-get-missing",
+        "import \"shapes.rox\" as s;\nget_field(s, \"colour\");\n",
+    );
+    assert!(err.unwrap_err().contains("has no member 'colour'"));
+}
+
+#[test]
+fn set_field_reports_that_exports_are_read_only() {
+    let err = with_module(
+        "reflect-set",
+        "import \"shapes.rox\" as s;\nset_field(s, \"sides\", 3);\n",
+    )
+    .unwrap_err();
+    assert!(err.contains("module exports are read-only"), "{}", err);
+    assert!(err.contains("'sides'"), "{}", err);
+
+    let out = with_module(
+        "reflect-set-catch",
+        "import \"shapes.rox\" as s;\ntry {\n    set_field(s, \"label\", \"x\");\n} catch (e) {\n    print \"caught\";\n}\nprint s.label;\n",
+    );
+    assert_eq!(out.unwrap(), "caught\nsquare\n");
+}
+
+#[test]
+fn set_field_checks_its_arguments_first() {
+    let (mut rox, _) = quiet_rox();
+    let err = rox.eval("set_field(1, \"a\", 2);").unwrap_err().to_string();
+    assert!(err.contains("expects a module as argument 1"), "{}", err);
+}
+
+#[test]
+fn arity_is_a_count_or_a_range() {
+    assert_eq!(
+        output("print arity(upper), arity(every), arity(format);"),
+        "1 (2, 3) (1, nil)\n"
+    );
+}
+
+#[test]
+fn name_gives_the_name_of_functions_and_modules() {
+    assert_eq!(output("var f = upper;\nprint name(f);"), "upper\n");
+
+    let out = with_module(
+        "reflect-name",
+        "import \"shapes.rox\" as s;\nprint name(s), name(s.shout);\n",
+    );
+    assert_eq!(out.unwrap(), "shapes.rox upper\n");
+
+    let (mut rox, _) = quiet_rox();
+    let err = rox.eval("name(1);").unwrap_err().to_string();
+    assert!(err.contains("expects a function or module"), "{}", err);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{output, temp_dir};
+
+/// The JSON diagnostics `rox check` reports for `source`, one per line.
+fn diagnostics(name: &str, source: &str) -> Vec<String> {
+    let script = temp_dir(name).join("main.rox");
+    fs::write(&script, source).unwrap();
+
+    let result = Command::new(env!("CARGO_BIN_EXE_rox_rust"))
+        .args(["check", "--error-format=json"])
+        .arg(&script)
+        .output()
+        .unwrap();
+    String::from_utf8(result.stderr)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// The diagnostics with `code`.
+fn with_code<'a>(diagnostics: &'a [String], code: &str) -> Vec<&'a String> {
+    let code = format!("\"code\":\"{}\"", code);
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.contains(&code))
+        .collect()
+}
+
+#[test]
+fn an_inner_declaration_warns_that_it_shadows_the_outer_one() {
+    let source =
+        "{\n    var a = 1;\n    {\n        var a = 2;\n        print a;\n    }\n    print a;\n}\n";
+    let diagnostics = diagnostics("shadow-inner", source);
+    let shadowed = with_code(&diagnostics, "W2104");
+
+    assert_eq!(shadowed.len(), 1, "{:?}", diagnostics);
+    let warning = shadowed[0];
+    assert!(
+        warning.contains("\"line\":4,\"column\":13,\"span\":{\"start\":35,\"end\":36}"),
+        "{}",
+        warning
+    );
+    assert!(
+        warning.contains(
+            "\"related\":[{\"message\":\"'a' is declared here.\",\"line\":2,\"column\":9,\"span\":{\"start\":10,\"end\":11}}]"
+        ),
+        "{}",
+        warning
+    );
+}
+
+#[test]
+fn a_second_declaration_in_one_scope_warns_with_both_places() {
+    let source = "{\n    var a = 1;\n    print a;\n    var a = 2;\n    print a;\n}\n";
+    let diagnostics = diagnostics("shadow-redeclared", source);
+    let redeclared = with_code(&diagnostics, "W2105");
+
+    assert_eq!(redeclared.len(), 1, "{:?}", diagnostics);
+    let warning = redeclared[0];
+    assert!(warning.contains("\"line\":4,\"column\":9"), "{}", warning);
+    assert!(
+        warning.contains(
+            "\"related\":[{\"message\":\"'a' is declared here.\",\"line\":2,\"column\":9"
+        ),
+        "{}",
+        warning
+    );
+    assert!(
+        with_code(&diagnostics, "W2104").is_empty(),
+        "{:?}",
+        diagnostics
+    );
+}
+
+#[test]
+fn redeclared_constants_are_errors_rather_than_warnings() {
+    let source = "{\n    const k = 1;\n    print k;\n    var k = 2;\n    print k;\n}\n";
+    let diagnostics = diagnostics("shadow-const", source);
+
+    assert!(
+        with_code(&diagnostics, "W2105").is_empty(),
+        "{:?}",
+        diagnostics
+    );
+    assert!(
+        diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.contains("Cannot redeclare constant 'k'")),
+        "{:?}",
+        diagnostics
+    );
+}
+
+#[test]
+fn sibling_blocks_and_distinct_names_do_not_warn() {
+    let source =
+        "{\n    var a = 1;\n    print a;\n}\n{\n    var a = 2;\n    var b = a;\n    print b;\n}\n";
+    assert_eq!(diagnostics("shadow-none", source), Vec::<String>::new());
+}
+
+#[test]
+fn shadowing_only_warns_and_the_inner_binding_wins() {
+    let source =
+        "{\n    var a = 1;\n    {\n        var a = 2;\n        print a;\n    }\n    print a;\n}\n";
+    assert_eq!(output(source), "2\n1\n");
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::fs;
+
+use common::{quiet_rox, temp_dir};
+use rox_rust::{Rox, Value};
+
+/// Runs `setup` in one session, restores its snapshot into a fresh one and
+/// returns what `check` prints there.
+fn restored(setup: &str, check: &str) -> String {
+    let (mut saved, _) = quiet_rox();
+    saved.eval(setup).unwrap();
+    let snapshot = saved.snapshot().unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.restore(&snapshot).unwrap();
+    rox.eval(check).unwrap();
+    out.text()
+}
+
+#[test]
+fn values_of_every_saveable_type_survive_a_round_trip() {
+    let setup = "var inf = 1e308 * 10.0;\n\
+                 var nan = inf - inf;\n\
+                 var values = (nil, true, 3, 2.5, \"s\", list((1, 2)), 1..3, 1..=3, inf, -inf);\n\
+                 var f = len;\n";
+    let check = "print values;\nprint f(\"abc\");\nprint nan == nan;\n";
+
+    let out = restored(setup, check);
+    assert_eq!(
+        out,
+        "(nil, true, 3, 2.5, \"s\", [1, 2], 1..3, 1..=3, inf, -inf)\n3\nfalse\n"
+    );
+}
+
+#[test]
+fn constants_stay_constant_after_a_restore() {
+    let (mut saved, _) = quiet_rox();
+    saved.eval("const k = 1;\nvar v = 2;\n").unwrap();
+    let snapshot = saved.snapshot().unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.restore(&snapshot).unwrap();
+    rox.eval("v = 3;\nprint k, v;\n").unwrap();
+    let err = rox.eval("k = 4;").unwrap_err();
+
+    assert_eq!(out.text(), "1 3\n");
+    assert!(err.to_string().contains("constant 'k'"), "{}", err);
+}
+
+#[test]
+fn restoring_keeps_bindings_the_snapshot_does_not_mention() {
+    let (mut saved, _) = quiet_rox();
+    saved.eval("var a = 1;").unwrap();
+    let snapshot = saved.snapshot().unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.eval("var a = 0;\nvar b = 2;").unwrap();
+    rox.restore(&snapshot).unwrap();
+    rox.eval("print a, b;").unwrap();
+
+    assert_eq!(out.text(), "1 2\n");
+}
+
+#[test]
+fn restored_modules_are_not_run_again() {
+    let dir = temp_dir("snapshot-modules");
+    fs::write(dir.join("util.rox"), "print \"loading\";\npub var v = 1;\n").unwrap();
+    fs::write(dir.join("main.rox"), "import \"util.rox\";\nprint v;\n").unwrap();
+
+    let (mut saved, saved_out) = quiet_rox();
+    saved.run_file(dir.join("main.rox")).unwrap();
+    assert_eq!(saved_out.text(), "loading\n1\n");
+    let snapshot = saved.snapshot().unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.restore(&snapshot).unwrap();
+    rox.run_file(dir.join("main.rox")).unwrap();
+
+    assert_eq!(out.text(), "1\n");
+}
+
+#[test]
+fn host_functions_are_looked_up_by_name_on_restore() {
+    let define = |rox: &mut Rox| {
+        rox.define_native("twice", 1..=1, |args| match args[0] {
+            Value::Int(n) => Ok(Value::Int(n * 2)),
+            _ => Ok(Value::Nil),
+        })
+        .unwrap();
+    };
+
+    let (mut saved, _) = quiet_rox();
+    define(&mut saved);
+    saved.eval("var f = twice;").unwrap();
+    let snapshot = saved.snapshot().unwrap();
+
+    let (mut missing, _) = quiet_rox();
+    let err = missing.restore(&snapshot).unwrap_err();
+    assert!(err.to_string().contains("twice"), "{}", err);
+
+    let (mut rox, out) = quiet_rox();
+    define(&mut rox);
+    rox.restore(&snapshot).unwrap();
+    rox.eval("print f(4);").unwrap();
+    assert_eq!(out.text(), "8\n");
+}
+
+#[test]
+fn iterators_cannot_be_saved() {
+    let (mut rox, _) = quiet_rox();
+    rox.eval("var it = iter(0..3);").unwrap();
+
+    let err = rox.snapshot().unwrap_err();
+    assert!(err.to_string().contains("Cannot save 'it'"), "{}", err);
+}
+
+#[test]
+fn snapshots_round_trip_through_a_file() {
+    let path = temp_dir("snapshot-file").join("session.json");
+
+    let (mut saved, _) = quiet_rox();
+    saved.eval("var greeting = \"hi\";").unwrap();
+    saved.save_snapshot(&path).unwrap();
+
+    let (mut rox, out) = quiet_rox();
+    rox.load_snapshot(&path).unwrap();
+    rox.eval("print greeting;").unwrap();
+
+    assert_eq!(out.text(), "hi\n");
+}
+
+#[test]
+fn snapshots_from_another_version_are_refused() {
+    let (mut saved, _) = quiet_rox();
+    saved.eval("var a = 1;").unwrap();
+    let snapshot = saved.snapshot().unwrap();
+    let changed = snapshot.replacen("\"version\":1", "\"version\":99", 1);
+    assert_ne!(snapshot, changed);
+
+    let (mut rox, _) = quiet_rox();
+    let err = rox.restore(&changed).unwrap_err();
+    assert!(
+        err.to_string().contains("Cannot restore snapshot"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn strict_rejects_comparing_variables_of_different_types() {
+    let (result, _) = run(r#""use strict"; var a = 1; var b = "s"; print a == b;"#);
+    let err = result.unwrap_err().to_string();
+    assert!(
+        err.contains("Comparing values of different types"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn strict_allows_ints_with_numbers_and_anything_with_nil() {
+    let out = output(r#""use strict"; var a = 1; var b = 1.0; var c = nil; print a == b, a != c;"#);
+    assert_eq!(out, "true true\n");
+}
+
+#[test]
+fn comparing_different_types_is_fine_outside_strict_mode() {
+    assert_eq!(
+        output(r#"var a = 1; var b = "s"; print a == b;"#),
+        "false\n"
+    );
+}
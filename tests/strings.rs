@@ -0,0 +1,15 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn triple_quoted_strings_keep_their_newlines_and_quotes() {
+    let out = output("print \"\"\"one\n\"two\"\nthree\"\"\";");
+    assert_eq!(out, "one\n\"two\"\nthree\n");
+}
+
+#[test]
+fn raw_strings_read_the_same_as_plain_ones_for_now() {
+    let out = output("print r\"a\\nb\" == \"a\\nb\", r\"\"\"x\"y\"\"\";");
+    assert_eq!(out, "true x\"y\n");
+}
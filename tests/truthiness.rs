@@ -0,0 +1,52 @@
+//! Only `nil` and `false` are false in a condition; everything else,
+//! including `0`, `""` and empty collections, is true. Every construct
+//! that tests a condition agrees.
+
+mod common;
+
+use common::output;
+
+#[test]
+fn not_treats_only_nil_and_false_as_false() {
+    let out = output(r#"print !nil, !false, !true, !0, !0.0, !"", !list(""), !(1, 2);"#);
+    assert_eq!(out, "true true false false false false false false\n");
+}
+
+#[test]
+fn if_takes_zero_and_empty_strings_as_true() {
+    let out = output(
+        r#"
+        var zero = 0;
+        var empty = "";
+        var none = nil;
+        if (zero) print "zero"; else print "no";
+        if (empty) print "empty"; else print "no";
+        if (none) print "nil"; else print "no";
+        "#,
+    );
+    assert_eq!(out, "zero\nempty\nno\n");
+}
+
+#[test]
+fn while_stops_only_on_nil_or_false() {
+    let out = output(
+        r#"
+        var n = 0;
+        while (n) { print n; n = false; }
+        var s = "";
+        while (s) { print "empty"; s = nil; }
+        "#,
+    );
+    assert_eq!(out, "0\nempty\n");
+}
+
+#[test]
+fn and_and_or_short_circuit_on_truthiness() {
+    let out = output(
+        r#"
+        print nil or "a", false or "b", 0 or "c", "" or "d";
+        print nil and "a", false and "b", 0 and "c", "" and "d";
+        "#,
+    );
+    assert_eq!(out, "a b 0 \nnil false c d\n");
+}
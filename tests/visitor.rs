@@ -0,0 +1,66 @@
+mod common;
+
+use common::quiet_rox;
+use rox_rust::{
+    ast_to_source, walk_stmt, walk_stmt_mut, Arena, Expr, Local, Program, Token, Walk, WalkMut,
+};
+
+/// Counts calls, anywhere in a program.
+#[derive(Default)]
+struct Calls(usize);
+
+impl Walk for Calls {
+    fn visit_call_expr(&mut self, arena: &Arena, callee: &Expr, _paren: &Token, args: &[Expr]) {
+        self.0 += 1;
+        callee.accept(arena, self);
+        args.iter().for_each(|arg| arg.accept(arena, self));
+    }
+}
+
+/// Renames every use of a variable.
+struct Rename(&'static str, &'static str);
+
+impl WalkMut for Rename {
+    fn visit_var_expr(&mut self, _arena: &mut Arena, name: &mut Token, _local: &mut Option<Local>) {
+        if name.lexeme == self.0 {
+            name.lexeme = self.1.to_string();
+        }
+    }
+}
+
+#[test]
+fn a_walk_only_handles_the_variants_it_cares_about() {
+    let (rox, _) = quiet_rox();
+    let program = rox
+        .parse(
+            "var x = len(upper(\"a\"));
+            if (x > 0) { print str(x); } else { while (false) print len(\"b\"); }
+            match (x) { 1 -> print x; else -> print min(x, 2); }",
+        )
+        .unwrap();
+
+    let mut calls = Calls::default();
+    for stmt in &program.stmts {
+        walk_stmt(&mut calls, &program.arena, stmt);
+    }
+    assert_eq!(calls.0, 5);
+}
+
+#[test]
+fn a_mutable_walk_reaches_every_nested_node() {
+    let (rox, _) = quiet_rox();
+    let mut program = rox
+        .parse("var a = 1;\nfor (x in 0..2) { if (x > 0) print a + x; }\ndefer print (a, -a);\n")
+        .unwrap();
+
+    let mut rename = Rename("a", "b");
+    let Program { stmts, arena } = &mut program;
+    for stmt in stmts {
+        walk_stmt_mut(&mut rename, arena, stmt);
+    }
+
+    assert_eq!(
+        ast_to_source(&program),
+        "var a = 1;\nfor (x in 0 .. 2) {\n    if (x > 0)\n        print b + x;\n}\ndefer\n    print (b, -b);\n"
+    );
+}
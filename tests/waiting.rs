@@ -0,0 +1,56 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::quiet_rox;
+use rox_rust::{Limits, Rox, RoxError, Value};
+
+/// A sandboxed interpreter whose runs may take at most `timeout`, with a
+/// `ping()` that returns 1 to wait for.
+fn sandboxed(timeout: Duration) -> (Rox, common::Output) {
+    let (mut rox, out) = quiet_rox();
+    rox.define_native("ping", 0..=0, |_| Ok(Value::Int(1)))
+        .unwrap();
+    rox.set_sandbox(true);
+    rox.set_limits(Limits {
+        timeout: Some(timeout),
+        ..Limits::default()
+    });
+    (rox, out)
+}
+
+#[test]
+fn waiting_natives_run_in_the_sandbox() {
+    let (mut rox, out) = sandboxed(Duration::from_secs(10));
+    rox.eval("sleep(0.001);\nprint after(0.001, ping);\nprint every(0.001, ping, 3);\n")
+        .unwrap();
+    assert_eq!(out.text(), "1\n3\n");
+}
+
+#[test]
+fn every_wait_counts_against_the_timeout() {
+    for source in ["sleep(5);", "after(5, ping);", "every(0.01, ping);"] {
+        let (mut rox, _) = sandboxed(Duration::from_millis(50));
+        let started = Instant::now();
+        let err = rox.eval(source).unwrap_err();
+
+        assert!(
+            matches!(err, RoxError::LimitExceeded(_)),
+            "{}: {}",
+            source,
+            err
+        );
+        assert!(started.elapsed() < Duration::from_secs(2), "{}", source);
+    }
+}
+
+#[test]
+fn clock_reading_natives_are_still_refused() {
+    let (mut rox, _) = sandboxed(Duration::from_secs(10));
+    let err = rox.eval("print clock();").unwrap_err();
+    assert!(
+        err.to_string().contains("not available in sandbox mode"),
+        "{}",
+        err
+    );
+}